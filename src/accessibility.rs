@@ -0,0 +1,82 @@
+use anyhow::Result;
+use rustyline::error::ReadlineError;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn prompt_line(prompt: &str) -> io::Result<Option<String>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(input.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+pub fn readline(prompt: &str) -> Result<String, ReadlineError> {
+    match prompt_line(prompt)? {
+        Some(line) => Ok(line),
+        None => Err(ReadlineError::Eof),
+    }
+}
+
+pub fn pick(options: &[String], shortcuts: &[(char, Option<usize>)]) -> Result<Option<usize>> {
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}. {}", i + 1, option);
+    }
+
+    loop {
+        let Some(input) = prompt_line(&format!("Choose 1-{} (c to cancel): ", options.len()))?
+        else {
+            return Ok(None);
+        };
+        let input = input.trim();
+
+        if let Ok(n) = input.parse::<usize>() {
+            if (1..=options.len()).contains(&n) {
+                return Ok(Some(n - 1));
+            }
+        }
+        let mut chars = input.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if let Some((_, option)) = shortcuts.iter().find(|(key, _)| *key == c) {
+                return Ok(*option);
+            }
+            if c == 'c' {
+                return Ok(None);
+            }
+        }
+        println!("Please enter a number between 1 and {}.", options.len());
+    }
+}
+
+pub fn edit(current: &str) -> Result<String> {
+    println!("Current SQL:\n{}\n", current);
+    println!("Enter the new SQL, ending with a line containing only a period. A period alone keeps the current SQL.");
+
+    let mut lines = Vec::new();
+    while let Some(line) = prompt_line("")? {
+        if line.trim() == "." {
+            break;
+        }
+        lines.push(line);
+    }
+
+    let sql = lines.join("\n");
+    if sql.trim().is_empty() {
+        Ok(current.to_string())
+    } else {
+        Ok(sql)
+    }
+}
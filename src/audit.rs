@@ -0,0 +1,98 @@
+use crate::psql::PsqlConnection;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    pub user: String,
+    pub db_user: String,
+    pub database: String,
+    pub question: String,
+    pub sql: String,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Value>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        psql: &PsqlConnection,
+        question: &str,
+        sql: &str,
+        success: bool,
+        error: &str,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| psql.user.clone());
+
+        Self {
+            timestamp,
+            user,
+            db_user: psql.user.clone(),
+            database: psql.database.clone(),
+            question: question.to_string(),
+            sql: sql.to_string(),
+            success,
+            error: (!success && !error.trim().is_empty()).then(|| error.trim().to_string()),
+            plan: None,
+        }
+    }
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn for_database(psql: &PsqlConnection) -> Result<Self> {
+        let path = dirs::data_dir()
+            .context("Could not determine data directory")?
+            .join("psqlm")
+            .join("audit")
+            .join(format!("{}.jsonl", psql.storage_key()));
+        Ok(Self { path })
+    }
+
+    pub fn append(&self, entry: &AuditEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open audit log: {:?}", self.path))?;
+        file.write_all(format!("{}\n", serde_json::to_string(entry)?).as_bytes())?;
+
+        Ok(())
+    }
+}
+
+pub fn record(
+    psql: &PsqlConnection,
+    explain: bool,
+    question: &str,
+    sql: &str,
+    success: bool,
+    error: &str,
+) -> Result<()> {
+    let mut entry = AuditEntry::new(psql, question, sql, success, error);
+    if explain && success {
+        entry.plan = psql.explain_json(sql, false).ok();
+    }
+    AuditLog::for_database(psql)?.append(&entry)
+}
@@ -0,0 +1,126 @@
+use crate::config::{Auth, AuthMode};
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_REFRESH_MINUTES: u64 = 10;
+
+#[derive(Debug)]
+enum Source {
+    RdsIam { region: Option<String> },
+    Command(String),
+}
+
+#[derive(Debug)]
+pub struct TokenSource {
+    source: Source,
+    host: String,
+    port: String,
+    user: String,
+    database: String,
+    refresh: Duration,
+    cached: Mutex<Option<(String, Instant)>>,
+}
+
+impl TokenSource {
+    pub fn from_config(
+        auth: &Auth,
+        host: &str,
+        port: &str,
+        user: &str,
+        database: &str,
+    ) -> Result<Option<Self>> {
+        let source = match auth.mode {
+            AuthMode::Password => return Ok(None),
+            AuthMode::RdsIam => Source::RdsIam {
+                region: auth.region.clone(),
+            },
+            AuthMode::Command => Source::Command(
+                auth.command
+                    .clone()
+                    .context("auth.command must be set when auth.mode is \"command\"")?,
+            ),
+        };
+        Ok(Some(Self {
+            source,
+            host: host.to_string(),
+            port: port.to_string(),
+            user: user.to_string(),
+            database: database.to_string(),
+            refresh: Duration::from_secs(
+                auth.refresh_minutes.unwrap_or(DEFAULT_REFRESH_MINUTES) * 60,
+            ),
+            cached: Mutex::new(None),
+        }))
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self.source {
+            Source::RdsIam { .. } => "an RDS IAM auth token",
+            Source::Command(_) => "a password from auth.command",
+        }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = match &self.source {
+            Source::RdsIam { region } => {
+                let mut command = Command::new("aws");
+                command
+                    .args(["rds", "generate-db-auth-token"])
+                    .args(["--hostname", &self.host])
+                    .args(["--port", &self.port])
+                    .args(["--username", &self.user]);
+                if let Some(region) = region {
+                    command.args(["--region", region]);
+                }
+                command
+            }
+            Source::Command(script) => {
+                let mut command = Command::new("sh");
+                command.args(["-c", script]);
+                command
+            }
+        };
+        command
+            .env("PSQLM_HOST", &self.host)
+            .env("PSQLM_PORT", &self.port)
+            .env("PSQLM_USER", &self.user)
+            .env("PSQLM_DATABASE", &self.database);
+        command
+    }
+
+    fn generate(&self) -> Result<String> {
+        let output = self
+            .command()
+            .output()
+            .with_context(|| format!("Failed to generate {}", self.describe()))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to generate {}: {}",
+                self.describe(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if token.is_empty() {
+            anyhow::bail!(
+                "Failed to generate {}: the command printed nothing",
+                self.describe()
+            );
+        }
+        Ok(token)
+    }
+
+    pub fn token(&self) -> Result<String> {
+        let mut cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((token, generated)) = cached.as_ref() {
+            if generated.elapsed() < self.refresh {
+                return Ok(token.clone());
+            }
+        }
+        let token = self.generate()?;
+        *cached = Some((token.clone(), Instant::now()));
+        Ok(token)
+    }
+}
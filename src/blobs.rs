@@ -0,0 +1,165 @@
+use crate::psql::PsqlConnection;
+use crate::result::ResultSet;
+use crate::units::format_bytes;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+pub const LARGE_BLOB_BYTES: usize = 1024;
+
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "png"),
+    (b"\xff\xd8\xff", "jpg"),
+    (b"GIF8", "gif"),
+    (b"%PDF", "pdf"),
+    (b"PK\x03\x04", "zip"),
+    (b"\x1f\x8b", "gz"),
+];
+
+fn hex_digits(value: &str) -> Option<&str> {
+    let digits = value.strip_prefix("\\x")?;
+    (digits.len() % 2 == 0 && digits.bytes().all(|b| b.is_ascii_hexdigit())).then_some(digits)
+}
+
+pub fn decode_bytea(value: &str) -> Option<Vec<u8>> {
+    let digits = hex_digits(value)?;
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn large_size(value: &str) -> Option<usize> {
+    if value.len() <= 2 + 2 * LARGE_BLOB_BYTES {
+        return None;
+    }
+    hex_digits(value).map(|digits| digits.len() / 2)
+}
+
+pub fn placeholder(value: &str) -> Option<String> {
+    large_size(value).map(|size| format!("<binary, {}>", format_bytes(size as u64)))
+}
+
+fn large_columns(result: &ResultSet) -> Vec<&str> {
+    result
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            result
+                .rows
+                .iter()
+                .any(|row| row[*i].as_deref().and_then(large_size).is_some())
+        })
+        .map(|(_, name)| name.as_str())
+        .collect()
+}
+
+pub fn hint(result: &ResultSet) -> Option<String> {
+    match large_columns(result).as_slice() {
+        [] => None,
+        [column] => Some(format!(
+            "{} holds binary data; \\save-blob {} <path> writes it to files.",
+            column, column
+        )),
+        columns => Some(format!(
+            "{} hold binary data; \\save-blob <column> <path> writes them to files.",
+            columns.join(", ")
+        )),
+    }
+}
+
+fn extension(bytes: &[u8]) -> &'static str {
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+        .map_or("bin", |(_, extension)| extension)
+}
+
+fn write_value(
+    psql: &PsqlConnection,
+    value: &str,
+    path: PathBuf,
+    add_extension: bool,
+    large_object: bool,
+) -> Result<PathBuf> {
+    if large_object {
+        let oid = value
+            .parse::<u32>()
+            .with_context(|| format!("{} is not a large object OID", value))?;
+        let path = match add_extension {
+            true => path.with_extension("bin"),
+            false => path,
+        };
+        psql.lo_export(oid, &path)?;
+        return Ok(path);
+    }
+    let bytes = decode_bytea(value).unwrap_or_else(|| value.as_bytes().to_vec());
+    let path = match add_extension {
+        true => path.with_extension(extension(&bytes)),
+        false => path,
+    };
+    std::fs::write(&path, &bytes).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(path)
+}
+
+// Only `oid` and `lo` columns hold large object references; other integers are plain values.
+fn is_large_object(psql: &PsqlConnection, sql: Option<&str>, column: &str) -> Result<bool> {
+    let Some(sql) = sql else {
+        return Ok(false);
+    };
+    Ok(psql
+        .describe(sql)?
+        .iter()
+        .any(|(name, data_type)| name == column && matches!(data_type.as_str(), "oid" | "lo")))
+}
+
+pub fn save(
+    psql: &PsqlConnection,
+    result: &ResultSet,
+    sql: Option<&str>,
+    column: &str,
+    path: &str,
+) -> Result<Vec<PathBuf>> {
+    let index = result.column_index(column).with_context(|| {
+        format!(
+            "Unknown column: {} (available: {})",
+            column,
+            result.columns.join(", ")
+        )
+    })?;
+    let values: Vec<(usize, &str)> = result
+        .rows
+        .iter()
+        .enumerate()
+        .filter_map(|(i, row)| row[index].as_deref().map(|value| (i + 1, value)))
+        .collect();
+
+    let large_object = is_large_object(psql, sql, &result.columns[index])?;
+    let path = Path::new(path);
+    match values.as_slice() {
+        [] => anyhow::bail!("{} is NULL in every row", result.columns[index]),
+        [(_, value)] if !path.is_dir() => Ok(vec![write_value(
+            psql,
+            value,
+            path.to_path_buf(),
+            false,
+            large_object,
+        )?]),
+        values => {
+            std::fs::create_dir_all(path)
+                .with_context(|| format!("Failed to create {:?}", path))?;
+            values
+                .iter()
+                .map(|(row, value)| {
+                    write_value(
+                        psql,
+                        value,
+                        path.join(format!("row-{}", row)),
+                        true,
+                        large_object,
+                    )
+                })
+                .collect()
+        }
+    }
+}
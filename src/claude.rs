@@ -1,75 +1,293 @@
+use crate::config::{RateLimit, StreamDisplay};
+use crate::feedback::{self, Feedback};
+use crate::hints::Hints;
+use crate::llm::{ApiRequest, LlmProvider, Message, ModelInfo};
+use crate::metrics;
+use crate::named_results;
+use crate::naming;
+use crate::provider_error::ProviderError;
+use crate::rate_limit::RateLimiter;
+use crate::report::Entry;
+use crate::result::ResultSet;
 use crate::schema::Schema;
-use anyhow::{Context, Result};
-use futures_util::StreamExt;
+use crate::spinner::Spinner;
+use anyhow::Result;
+use crossterm::terminal;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io::{self, Write};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 
-const API_URL: &str = "https://api.anthropic.com/v1/messages";
-const MODEL: &str = "claude-sonnet-4-20250514";
+pub const MODEL: &str = "claude-sonnet-4-20250514";
+pub const FAST_MODEL: &str = "claude-haiku-4-5";
+pub const DEFAULT_TIMEOUT_SECS: u64 = 120;
+const TIMEOUT_ATTEMPTS: u32 = 2;
+const PROVIDER_RETRIES: u32 = 2;
+const FOLLOW_UP_COUNT: usize = 3;
+const FOLLOW_UP_SAMPLE_ROWS: usize = 10;
+
+static ROW_COUNT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^\((\d+) rows?\)$").unwrap());
+static LIST_MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(\d+[.)]|[-*])\s+").unwrap());
 
 const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const DIM: &str = "\x1b[2m";
 const RESET: &str = "\x1b[0m";
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversationTurn {
     pub question: String,
     pub sql: String,
     pub result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_change: Option<String>,
+    #[serde(default)]
+    pub approved: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct Client {
-    api_key: String,
-    http: reqwest::Client,
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+    fast_model: String,
+    hints: Hints,
+    rate_limiter: RateLimiter,
+    language: Option<String>,
+    timeout: Duration,
+    persona: Option<String>,
+    recent_queries: Option<String>,
+    time_zone: Option<String>,
+    view_definitions: bool,
     pub history: Vec<ConversationTurn>,
+    pub stream_display: StreamDisplay,
+    pub feedback: Vec<Feedback>,
+    pub named_results: BTreeMap<String, Entry>,
+    strict: bool,
 }
 
-#[derive(Debug, Serialize)]
-struct ApiRequest {
-    model: &'static str,
-    max_tokens: u32,
-    system: String,
-    messages: Vec<Message>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    stream: Option<bool>,
+fn with_schema_change(change: Option<&str>, question: &str) -> String {
+    match change {
+        Some(change) => format!(
+            "[Schema changed since the previous query; do not reuse dropped or renamed tables and columns from earlier SQL]\n{}\n{}",
+            change, question
+        ),
+        None => question.to_string(),
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Message {
-    role: String,
-    content: String,
-}
+impl Client {
+    pub fn new(
+        provider: Arc<dyn LlmProvider>,
+        stream_display: StreamDisplay,
+        strict: bool,
+    ) -> Self {
+        Self {
+            model: provider.default_model().to_string(),
+            fast_model: provider.default_fast_model().to_string(),
+            provider,
+            hints: Hints::default(),
+            rate_limiter: RateLimiter::new(RateLimit::default()),
+            language: None,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            persona: None,
+            recent_queries: None,
+            time_zone: None,
+            view_definitions: false,
+            history: Vec::new(),
+            stream_display,
+            feedback: Vec::new(),
+            named_results: BTreeMap::new(),
+            strict,
+        }
+    }
 
-#[derive(Debug, Deserialize)]
-struct StreamEvent {
-    #[serde(rename = "type")]
-    event_type: String,
-    delta: Option<Delta>,
-}
+    pub fn with_model(self, model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            ..self
+        }
+    }
 
-#[derive(Debug, Deserialize)]
-struct Delta {
-    text: Option<String>,
-}
+    pub fn model(&self) -> &str {
+        &self.model
+    }
 
-impl Client {
-    pub fn new(api_key: &str) -> Self {
+    pub fn set_model(&mut self, model: &str) {
+        self.model = model.to_string();
+    }
+
+    pub fn with_fast_model(self, fast_model: &str) -> Self {
         Self {
-            api_key: api_key.to_string(),
-            http: reqwest::Client::new(),
-            history: Vec::new(),
+            fast_model: fast_model.to_string(),
+            ..self
+        }
+    }
+
+    pub fn request_model(&self) -> &str {
+        if self.hints.fast {
+            &self.fast_model
+        } else {
+            &self.model
+        }
+    }
+
+    pub fn hints(&self) -> Hints {
+        self.hints
+    }
+
+    pub fn set_hints(&mut self, hints: Hints) {
+        self.hints = hints;
+    }
+
+    pub fn with_rate_limit(self, limits: RateLimit) -> Self {
+        Self {
+            rate_limiter: RateLimiter::new(limits),
+            ..self
+        }
+    }
+
+    pub fn with_language(self, language: &str) -> Self {
+        Self {
+            language: Some(language.to_string()),
+            ..self
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    pub fn set_persona(&mut self, guidance: Option<String>) {
+        self.persona = guidance;
+    }
+
+    pub fn set_recent_queries(&mut self, digest: Option<String>) {
+        self.recent_queries = digest;
+    }
+
+    pub fn set_time_zone(&mut self, zone: Option<String>) {
+        self.time_zone = zone;
+    }
+
+    pub fn set_view_definitions(&mut self, enabled: bool) {
+        self.view_definitions = enabled;
+    }
+
+    pub fn time_zone(&self) -> Option<&str> {
+        self.time_zone.as_deref()
+    }
+
+    pub fn shares_data(&self) -> bool {
+        !self.strict
+    }
+
+    pub async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.provider.list_models().await
+    }
+
+    pub async fn validate_model(&self) -> Result<()> {
+        let Ok(models) = self.list_models().await else {
+            return Ok(());
+        };
+        if models.is_empty() || models.iter().any(|m| m.id == self.model) {
+            return Ok(());
+        }
+
+        let available: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+        anyhow::bail!(
+            "Model '{}' is not available from the API; it may have been deprecated or retired.\n\
+             Set `model` in config.toml to one of: {}",
+            self.model,
+            available.join(", ")
+        )
+    }
+
+    fn redact_error(&self, error: &str) -> String {
+        if self.shares_data() {
+            return error.to_string();
+        }
+
+        error
+            .lines()
+            .filter(|line| {
+                let line = line.trim_start();
+                line.starts_with("ERROR:")
+                    || line.starts_with("HINT:")
+                    || line.starts_with("LINE ")
+                    || line.starts_with('^')
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn unshared_rows(&self) -> usize {
+        self.history
+            .iter()
+            .filter(|turn| !turn.approved)
+            .filter_map(|turn| turn.result.as_deref())
+            .flat_map(|result| ROW_COUNT.captures_iter(result))
+            .filter_map(|captures| captures[1].parse::<usize>().ok())
+            .sum()
+    }
+
+    pub fn approve_results(&mut self) {
+        for turn in &mut self.history {
+            turn.approved = true;
+        }
+    }
+
+    pub fn withhold_results(&mut self) {
+        for turn in self.history.iter_mut().filter(|turn| !turn.approved) {
+            turn.result = None;
+            turn.approved = true;
         }
     }
 
     pub fn add_to_history(&mut self, question: String, sql: String, result: Option<String>) {
-        self.history.push(ConversationTurn { question, sql, result });
+        let result = result.filter(|_| self.shares_data());
+        self.history.push(ConversationTurn {
+            question,
+            sql,
+            result,
+            schema_change: None,
+            approved: false,
+        });
         if self.history.len() > 10 {
             self.history.remove(0);
         }
     }
 
-    fn system_prompt(schema: &Schema) -> String {
-        format!(
+    pub fn note_schema_change(&mut self, changes: &[String]) {
+        let Some(turn) = self.history.last_mut() else {
+            return;
+        };
+
+        let note = turn.schema_change.get_or_insert_with(String::new);
+        for change in changes {
+            note.push_str(change);
+            note.push('\n');
+        }
+    }
+
+    fn language_instructions(&self) -> String {
+        match &self.language {
+            Some(language) => format!(
+                r#"
+LANGUAGE:
+- The user may write in {language}; interpret questions in that language
+- Write any prose (explanations, answers, summaries) in {language}
+- SQL stays standard PostgreSQL: keep keywords and table/column names exactly as in the schema
+"#
+            ),
+            None => String::new(),
+        }
+    }
+
+    fn system_prompt(&self, schema: &Schema) -> String {
+        let mut prompt = format!(
             r#"You are a PostgreSQL expert assistant. Your job is to convert natural language questions into SQL queries.
 
 Given the database schema below, generate a PostgreSQL query that answers the user's question.
@@ -79,83 +297,477 @@ IMPORTANT:
 - Do not include explanations, markdown formatting, or code blocks
 - The query should be ready to execute directly
 - Use proper PostgreSQL syntax
+- Never supply values for GENERATED ALWAYS columns in INSERT or UPDATE; leave them out so PostgreSQL fills them in
+- Leave out GENERATED BY DEFAULT identity columns too, unless the user gives their values
 
 Database Schema:
 {}
 "#,
             schema.to_prompt_string()
-        )
-    }
+        );
+        if let Some(naming) = naming::to_prompt_string(schema) {
+            prompt.push_str(&naming);
+        }
+        if let Some(definitions) = schema.view_definitions().filter(|_| self.view_definitions) {
+            prompt.push_str(&definitions);
+        }
+        prompt.push_str(&self.language_instructions());
+        if let Some(persona) = &self.persona {
+            prompt.push_str(&format!("\nPERSONA:\n{}\n", persona));
+        }
+        if let Some(digest) = &self.recent_queries {
+            prompt.push_str(&format!(
+                "\nRECENT QUERIES:\nStatements this user has run, busiest first, from pg_stat_statements (constants appear as $1, $2, ...). When the question refers to one of them, such as \"that slow report query\", work from its text.\n{}",
+                digest
+            ));
+        }
+        if let Some(zone) = &self.time_zone {
+            prompt.push_str(&format!(
+                "\nTIME ZONE:\nCompute calendar boundaries such as today, yesterday and last week in {zone}, not the session time zone: compare timestamp with time zone columns against values like date_trunc('day', now() AT TIME ZONE '{zone}') AT TIME ZONE '{zone}'\n",
+                zone = zone
+            ));
+        }
 
-    async fn stream_response(&self, request: ApiRequest) -> Result<String> {
-        let response = self
-            .http
-            .post(API_URL)
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send request to Claude API")?;
+        let exemplars = feedback::to_prompt_string(&self.feedback);
+        if !exemplars.is_empty() {
+            prompt.push('\n');
+            prompt.push_str(&exemplars);
+        }
+
+        prompt
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("Claude API error ({}): {}", status, body);
+    async fn stream_text(
+        &self,
+        request: ApiRequest,
+        activity: &str,
+        echo: Option<&str>,
+    ) -> Result<String> {
+        let quiet = echo.is_none() || self.stream_display == StreamDisplay::Quiet;
+        let mut request = request;
+        // Requests pinned to the fast model keep it whatever the hints say.
+        if request.model == self.model {
+            request.model = self.request_model().to_string();
+        }
+        if self.hints.deterministic {
+            request.temperature = Some(0.0);
         }
+        let color = echo.unwrap_or_default();
 
-        let mut full_text = String::new();
-        let mut stream = response.bytes_stream();
+        let input_tokens = serde_json::to_string(&request)?.len() / 4;
+        let started = Instant::now();
+        let waited = self.rate_limiter.acquire(input_tokens as u32).await;
+        if waited >= Duration::from_secs(1) {
+            eprintln!(
+                "{DIM}Queued {:.1}s for the API rate limit{RESET}",
+                waited.as_secs_f32()
+            );
+        }
 
-        print!("{}", GREEN);
-        io::stdout().flush().ok();
+        let mut timeouts = 0;
+        let mut retries = 0;
+        loop {
+            let progress = Mutex::new(Progress {
+                last_chunk: Instant::now(),
+                echoed: String::new(),
+            });
+            let spinner = quiet.then(|| Spinner::start(activity));
+            let result = tokio::select! {
+                result = self.fetch(&request, quiet, color, &progress) => Some(result),
+                _ = self.idle(&progress) => None,
+                _ = tokio::signal::ctrl_c() => {
+                    drop(spinner);
+                    if !quiet {
+                        println!("{}", RESET);
+                    }
+                    anyhow::bail!("Request to {} API cancelled", self.provider.name());
+                }
+            };
+            drop(spinner);
+            let echoed = progress.into_inner().map(|p| p.echoed).unwrap_or_default();
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk.context("Failed to read stream chunk")?;
-            let text = String::from_utf8_lossy(&chunk);
+            let Some(result) = result else {
+                timeouts += 1;
+                if timeouts >= TIMEOUT_ATTEMPTS {
+                    if !quiet {
+                        println!("{}", RESET);
+                    }
+                    metrics::llm_failed();
+                    anyhow::bail!(
+                        "{} API sent nothing for {}s after {} attempts (raise llm_timeout in config.toml if responses are legitimately slow)",
+                        self.provider.name(),
+                        self.timeout.as_secs(),
+                        TIMEOUT_ATTEMPTS
+                    );
+                }
+                if !quiet {
+                    clear_echoed(&echoed);
+                }
+                eprintln!(
+                    "{YELLOW}{} API sent nothing for {}s; retrying ({}/{})...{RESET}",
+                    self.provider.name(),
+                    self.timeout.as_secs(),
+                    timeouts,
+                    TIMEOUT_ATTEMPTS - 1
+                );
+                continue;
+            };
 
-            for line in text.lines() {
-                if let Some(data) = line.strip_prefix("data: ") {
-                    if data == "[DONE]" {
-                        continue;
+            let (full_text, output_tokens) = match result {
+                Ok(response) => response,
+                Err(e) => {
+                    let delay = e
+                        .downcast_ref::<ProviderError>()
+                        .filter(|_| retries < PROVIDER_RETRIES)
+                        .and_then(|error| Some((error.title(), error.retry_delay(retries)?)));
+                    let Some((title, delay)) = delay else {
+                        if !quiet {
+                            print!("{}", RESET);
+                        }
+                        metrics::llm_failed();
+                        return Err(e);
+                    };
+                    if !quiet {
+                        clear_echoed(&echoed);
                     }
-                    if let Ok(event) = serde_json::from_str::<StreamEvent>(data) {
-                        if event.event_type == "content_block_delta" {
-                            if let Some(delta) = event.delta {
-                                if let Some(text) = delta.text {
-                                    print!("{}", text);
-                                    io::stdout().flush().ok();
-                                    full_text.push_str(&text);
-                                }
-                            }
+                    retries += 1;
+                    eprintln!(
+                        "{YELLOW}{}; retrying in {}s ({}/{})...{RESET}",
+                        title,
+                        delay.as_secs(),
+                        retries,
+                        PROVIDER_RETRIES
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => continue,
+                        _ = tokio::signal::ctrl_c() => {
+                            anyhow::bail!("Request to {} API cancelled", self.provider.name())
                         }
                     }
                 }
+            };
+            let output_tokens = output_tokens.unwrap_or(full_text.len() as u32 / 4);
+            self.rate_limiter.record(output_tokens);
+            metrics::llm_request(started.elapsed(), input_tokens as u64, output_tokens.into());
+
+            if !quiet {
+                print!("{}", RESET);
+                println!();
             }
+            return Ok(full_text);
         }
+    }
+
+    async fn fetch(
+        &self,
+        request: &ApiRequest,
+        quiet: bool,
+        color: &str,
+        progress: &Mutex<Progress>,
+    ) -> Result<(String, Option<u32>)> {
+        if !quiet {
+            print!("{}", color);
+            io::stdout().flush().ok();
+        }
+        let mut echo = |text: &str| {
+            let mut progress = progress.lock().unwrap();
+            progress.last_chunk = Instant::now();
+            if !quiet {
+                progress.echoed.push_str(text);
+                print!("{}", text);
+                io::stdout().flush().ok();
+            }
+        };
 
-        print!("{}", RESET);
-        println!();
+        let completion = self.provider.stream(request, &mut echo).await?;
+        Ok((completion.text, completion.output_tokens))
+    }
 
-        let sql = full_text
+    // Resolves once the response has gone `timeout` without a new chunk.
+    async fn idle(&self, progress: &Mutex<Progress>) {
+        loop {
+            let deadline = progress.lock().unwrap().last_chunk + self.timeout;
+            if Instant::now() >= deadline {
+                return;
+            }
+            tokio::time::sleep_until(deadline.into()).await;
+        }
+    }
+
+    async fn stream_response(&self, request: ApiRequest) -> Result<String> {
+        let text = self
+            .stream_text(request, "Generating SQL...", Some(GREEN))
+            .await?;
+        metrics::sql_generated();
+
+        let sql = text
             .trim_start_matches("```sql")
             .trim_start_matches("```")
             .trim_end_matches("```")
             .trim()
             .to_string();
 
+        if self.stream_display == StreamDisplay::Quiet {
+            eprintln!("{}{}{}", GREEN, sql, RESET);
+        }
+
         Ok(sql)
     }
 
-    pub async fn text_to_sql(&self, schema: &Schema, question: &str) -> Result<String> {
+    pub async fn explain_sql(&self, schema: &Schema, sql: &str) -> Result<String> {
+        let mut system = format!(
+            r#"You are a PostgreSQL expert assistant. Your job is to explain SQL statements in plain language.
+
+Given the database schema below, explain what the user's SQL statement does: which tables it reads or modifies, how they are joined, what filters and aggregations apply, and what the result represents. Point out potential pitfalls such as NULL handling, row fan-out from joins, missing indexes, or locking and cascading effects of writes.
+
+IMPORTANT:
+- Be concise and use short paragraphs or bullet points
+- Do not rewrite the query unless a pitfall warrants a suggested fix
+
+Database Schema:
+{}
+"#,
+            schema.to_prompt_string()
+        );
+        system.push_str(&self.language_instructions());
+
+        let request = ApiRequest {
+            model: self.model.clone(),
+            max_tokens: 1024,
+            system,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: sql.to_string(),
+            }],
+            stream: Some(true),
+            temperature: None,
+        };
+
+        let text = self
+            .stream_text(request, "Explaining SQL...", Some(""))
+            .await?;
+        if self.stream_display == StreamDisplay::Quiet {
+            println!("{}", text.trim());
+        }
+
+        Ok(text)
+    }
+
+    pub async fn summarize_session(&self, transcript: &str) -> Result<String> {
+        let mut system = r#"You are a data analyst writing up an exploratory session against a PostgreSQL database.
+
+Given the questions asked, the SQL that answered them, and (when provided) their results, write a short narrative summary for a markdown report: what was investigated, the key findings, and any caveats about how the queries were written.
+
+IMPORTANT:
+- Write plain markdown paragraphs or bullet points, with no top-level heading
+- Only state findings supported by the results shown; if no results are shown, describe what each query measures instead
+- Be concise"#
+            .to_string();
+        system.push_str(&self.language_instructions());
+
+        let request = ApiRequest {
+            model: self.model.clone(),
+            max_tokens: 1024,
+            system,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: transcript.to_string(),
+            }],
+            stream: Some(true),
+            temperature: None,
+        };
+
+        self.stream_text(request, "Writing report...", None).await
+    }
+
+    pub async fn recommend_maintenance(&self, report: &str) -> Result<String> {
+        let mut system = r#"You are a PostgreSQL database administrator reviewing a health report built from catalog statistics.
+
+Given the report, list the findings worth acting on in priority order, most impactful first. For each one, explain the problem and the risk of fixing it in one or two sentences, then give the statement that fixes it on its own line starting with "SQL: ".
+
+IMPORTANT:
+- Be concise and number the recommendations
+- Each SQL line must hold exactly one complete statement ending with a semicolon
+- Do not use CONCURRENTLY with DROP INDEX; data-changing statements are previewed inside a transaction
+- Skip findings that are expected, such as an unused index on a table created moments ago, and say why
+- If nothing needs attention, say so"#
+            .to_string();
+        system.push_str(&self.language_instructions());
+
+        let request = ApiRequest {
+            model: self.model.clone(),
+            max_tokens: 1024,
+            system,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: report.to_string(),
+            }],
+            stream: Some(true),
+            temperature: None,
+        };
+
+        let text = self
+            .stream_text(request, "Prioritizing findings...", Some(""))
+            .await?;
+        if self.stream_display == StreamDisplay::Quiet {
+            println!("{}", text.trim());
+        }
+
+        Ok(text)
+    }
+
+    pub async fn suggest_follow_ups(
+        &self,
+        schema: &Schema,
+        question: &str,
+        sql: &str,
+        result: &ResultSet,
+    ) -> Result<Vec<String>> {
+        let tables: Vec<String> = schema
+            .tables
+            .iter()
+            .map(|table| {
+                let columns: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+                format!("{}({})", table.name, columns.join(", "))
+            })
+            .collect();
+        let mut system = format!(
+            r#"You are a data analyst helping a user explore a PostgreSQL database.
+
+Given the user's last question, the SQL that answered it, and what the result looked like, suggest {} short follow-up questions the user might ask next.
+
+IMPORTANT:
+- Return one question per line with no numbering, bullets, or commentary
+- Each question must be answerable from the tables below
+- Keep each question under 15 words
+
+Tables:
+{}
+"#,
+            FOLLOW_UP_COUNT,
+            tables.join("\n")
+        );
+        system.push_str(&self.language_instructions());
+
+        let shape = if self.shares_data() {
+            result.to_markdown(FOLLOW_UP_SAMPLE_ROWS)
+        } else {
+            format!(
+                "{} rows with columns: {}",
+                result.rows.len(),
+                result.columns.join(", ")
+            )
+        };
+        let request = ApiRequest {
+            model: self.fast_model.clone(),
+            max_tokens: 200,
+            system,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: format!(
+                    "Question: {}\n\nSQL:\n{}\n\nResult:\n{}",
+                    question, sql, shape
+                ),
+            }],
+            stream: Some(true),
+            temperature: None,
+        };
+
+        let text = self
+            .stream_text(request, "Suggesting follow-ups...", None)
+            .await?;
+        Ok(text
+            .lines()
+            .map(|line| LIST_MARKER.replace(line, "").trim().to_string())
+            .filter(|line| !line.is_empty())
+            .take(FOLLOW_UP_COUNT)
+            .collect())
+    }
+
+    pub async fn answer_schema_question(&self, schema: &Schema, question: &str) -> Result<String> {
+        let mut system = format!(
+            r#"You are a PostgreSQL expert assistant. Your job is to answer questions about the structure of a database.
+
+Using ONLY the schema below, answer the user's question about its tables, columns, keys, indexes, and relationships. Column and table descriptions come from database comments.
+
+IMPORTANT:
+- Be concise and answer in plain language
+- Do not write SQL against information_schema or the system catalogs
+- If the schema below does not contain the answer, say so
+
+Database Schema:
+{}
+"#,
+            schema.to_prompt_string()
+        );
+        system.push_str(&self.language_instructions());
+
+        let request = ApiRequest {
+            model: self.model.clone(),
+            max_tokens: 1024,
+            system,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: question.to_string(),
+            }],
+            stream: Some(true),
+            temperature: None,
+        };
+
+        let text = self
+            .stream_text(request, "Reading schema...", Some(""))
+            .await?;
+        if self.stream_display == StreamDisplay::Quiet {
+            println!("{}", text.trim());
+        }
+
+        Ok(text)
+    }
+
+    pub async fn choose_template(&self, templates: &str, question: &str) -> Result<String> {
+        let mut system = format!(
+            r#"You are a PostgreSQL expert assistant. Your job is to answer questions using a library of vetted SQL templates.
+
+Given the templates below, pick the single template that answers the user's question and fill in its parameters.
+
+IMPORTANT:
+- Respond with ONLY a JSON object: {{"template": "<name>", "params": {{"<parameter>": "<value>"}}}}
+- Provide a value for every parameter the template uses; use null only when SQL NULL is intended
+- If no template answers the question, respond with {{"template": null}}
+
+Templates:
+{}
+"#,
+            templates
+        );
+        system.push_str(&self.language_instructions());
+
+        let request = ApiRequest {
+            model: self.model.clone(),
+            max_tokens: 1024,
+            system,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: question.to_string(),
+            }],
+            stream: Some(true),
+            temperature: None,
+        };
+
+        self.stream_text(request, "Choosing template...", None)
+            .await
+    }
+
+    fn conversation(&self, question: &str) -> Vec<Message> {
         let mut messages = Vec::new();
+        let mut schema_change: Option<&str> = None;
 
         for turn in &self.history {
             messages.push(Message {
                 role: "user".to_string(),
-                content: turn.question.clone(),
+                content: with_schema_change(schema_change, &turn.question),
             });
+            schema_change = turn.schema_change.as_deref();
 
             let assistant_content = if let Some(result) = &turn.result {
                 format!("{}\n\n-- Result:\n{}", turn.sql, result)
@@ -168,17 +780,106 @@ Database Schema:
             });
         }
 
+        let referenced = named_results::referenced(&self.named_results, question);
+        let question = with_schema_change(schema_change, question);
+        let content = if referenced.is_empty() {
+            question
+        } else {
+            format!(
+                "{}\n\n{}",
+                question,
+                named_results::to_prompt_string(&referenced, self.shares_data())
+            )
+        };
         messages.push(Message {
             role: "user".to_string(),
-            content: question.to_string(),
+            content,
         });
+        messages
+    }
 
+    pub fn prompt_tokens(&self, schema: &Schema, question: &str) -> usize {
+        let system = self.system_prompt(schema);
+        let messages = serde_json::to_string(&self.conversation(question)).unwrap_or_default();
+        (system.len() + messages.len()) / 4
+    }
+
+    pub async fn text_to_sql(&self, schema: &Schema, question: &str) -> Result<String> {
         let request = ApiRequest {
-            model: MODEL,
+            model: self.model.clone(),
             max_tokens: 1024,
-            system: Self::system_prompt(schema),
-            messages,
+            system: self.system_prompt(schema),
+            messages: self.conversation(question),
             stream: Some(true),
+            temperature: None,
+        };
+
+        self.stream_response(request).await
+    }
+
+    pub async fn text_to_count_sql(&self, schema: &Schema, question: &str) -> Result<String> {
+        self.text_to_sql_with_rules(
+            schema,
+            question,
+            r#"
+COUNT MODE:
+- Answer with a single SELECT returning exactly one row with one column, a COUNT aggregate named count
+- Never return individual rows and never modify data
+"#,
+        )
+        .await
+    }
+
+    pub async fn text_to_write_sql(&self, schema: &Schema, question: &str) -> Result<String> {
+        self.text_to_sql_with_rules(
+            schema,
+            question,
+            r#"
+WRITE MODE:
+- The user wants to modify data; generate the narrowest statement that does exactly what was asked
+- Always include a WHERE clause that targets only the intended rows, unless the user explicitly asks to affect every row
+- Never DROP or TRUNCATE anything unless the user explicitly asks for it
+- Do not add RETURNING clauses; the statement is previewed in a rolled-back transaction before it is committed
+"#,
+        )
+        .await
+    }
+
+    pub async fn text_to_seed_sql(&self, schema: &Schema, description: &str) -> Result<String> {
+        self.text_to_sql_with_rules(
+            schema,
+            description,
+            r#"
+SEED MODE:
+- The user describes test data to create; generate INSERT statements that create exactly that data
+- Generate rows set-based with INSERT ... SELECT ... FROM generate_series(1, n), not one VALUES row per record
+- Create the number of rows asked for; if no number is given, create 20 per table
+- Make values realistic: pick names, cities, products and words from ARRAY literals with random(), spread dates over the past year, and honor proportions such as "10% with null phone numbers" with CASE WHEN random() < 0.1
+- Satisfy every NOT NULL, UNIQUE and foreign key constraint: insert parent rows first and reference them by selecting existing keys, never by guessing ids
+- Leave out identity, serial and generated columns so PostgreSQL fills them in
+- Never DELETE, UPDATE, DROP or TRUNCATE anything
+- Separate statements with semicolons and do not add BEGIN or COMMIT; the statements run in one transaction that is previewed first
+"#,
+        )
+        .await
+    }
+
+    async fn text_to_sql_with_rules(
+        &self,
+        schema: &Schema,
+        question: &str,
+        rules: &str,
+    ) -> Result<String> {
+        let mut system = self.system_prompt(schema);
+        system.push_str(rules);
+
+        let request = ApiRequest {
+            model: self.model.clone(),
+            max_tokens: 1024,
+            system,
+            messages: self.conversation(question),
+            stream: Some(true),
+            temperature: None,
         };
 
         self.stream_response(request).await
@@ -191,10 +892,11 @@ Database Schema:
         original_sql: &str,
         error: &str,
     ) -> Result<String> {
+        metrics::fix_attempted();
         let request = ApiRequest {
-            model: MODEL,
+            model: self.model.clone(),
             max_tokens: 1024,
-            system: Self::system_prompt(schema),
+            system: self.system_prompt(schema),
             messages: vec![
                 Message {
                     role: "user".to_string(),
@@ -208,13 +910,38 @@ Database Schema:
                     role: "user".to_string(),
                     content: format!(
                         "The query failed with this error:\n{}\n\nPlease fix the SQL query. Return ONLY the corrected SQL, nothing else.",
-                        error
+                        self.redact_error(error)
                     ),
                 },
             ],
             stream: Some(true),
+            temperature: None,
         };
 
         self.stream_response(request).await
     }
 }
+
+struct Progress {
+    last_chunk: Instant,
+    echoed: String,
+}
+
+// Erases a partially streamed response so a retry does not print it twice.
+fn clear_echoed(echoed: &str) {
+    let width = terminal::size()
+        .ok()
+        .map(|(w, _)| w as usize)
+        .filter(|w| *w > 0)
+        .unwrap_or(80);
+    let rows: usize = echoed
+        .split('\n')
+        .map(|line| line.chars().count().div_ceil(width).max(1))
+        .sum();
+    print!("{}\r", RESET);
+    if rows > 1 {
+        print!("\x1b[{}A", rows - 1);
+    }
+    print!("\x1b[J");
+    io::stdout().flush().ok();
+}
@@ -0,0 +1,36 @@
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io::{self, IsTerminal, Write};
+
+// OSC 52 is a request the terminal may silently ignore, so only the cases known not to work
+// can be reported.
+fn unsupported() -> Option<&'static str> {
+    if !io::stdout().is_terminal() {
+        return Some("output is not a terminal");
+    }
+    match std::env::var("TERM").as_deref() {
+        Err(_) | Ok("" | "dumb" | "linux") => return Some("this terminal does not support OSC 52"),
+        _ => {}
+    }
+    if std::env::var("TERM_PROGRAM").as_deref() == Ok("Apple_Terminal") {
+        return Some("Terminal.app does not support OSC 52");
+    }
+    None
+}
+
+pub fn copy(text: &str) -> Result<()> {
+    if let Some(reason) = unsupported() {
+        anyhow::bail!("{}", reason);
+    }
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    let sequence = if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    };
+    let mut stdout = io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
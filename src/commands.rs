@@ -0,0 +1,497 @@
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandKind {
+    Quit,
+    Help,
+    History,
+    Schema,
+    Mode,
+    Stream,
+    Preview,
+    Isolation,
+    Sandbox,
+    Replica,
+    Persona,
+    Alias,
+    Why,
+    Filter,
+    Sort,
+    Cols,
+    Render,
+    Wrap,
+    Format,
+    Json,
+    Scroll,
+    Explore,
+    On,
+    RefreshMv,
+    Activity,
+    Hot,
+    Health,
+    Locks,
+    Kill,
+    Similar,
+    Insights,
+    Report,
+    Good,
+    Bad,
+    Models,
+    Store,
+    Use,
+    Drill,
+    Export,
+    Output,
+    ExportSession,
+    SaveBlob,
+    Fixture,
+    Seed,
+    Graph,
+}
+
+pub struct Command {
+    pub kind: CommandKind,
+    pub names: &'static [&'static str],
+    pub usage: &'static str,
+    pub summary: &'static str,
+    pub details: &'static str,
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        kind: CommandKind::Quit,
+        names: &["\\q", "\\quit"],
+        usage: "\\q",
+        summary: "quit",
+        details: "Exits psqlm. Ctrl+D does the same.",
+    },
+    Command {
+        kind: CommandKind::Help,
+        names: &["\\help", "\\?"],
+        usage: "\\help [command]",
+        summary: "list commands, or show usage for one",
+        details: "Without an argument, lists every command. With a command name (with or without the backslash), shows its usage and details.",
+    },
+    Command {
+        kind: CommandKind::History,
+        names: &["\\history"],
+        usage: "\\history [search <text>|import [path]]",
+        summary: "list, search or import query history",
+        details: "Without arguments, lists the 20 most recent entries.\n\
+                  search <text>  finds past questions and SQL containing the text and puts the chosen one on the prompt\n\
+                  import [path]  adds the queries from psql's history file ($PSQL_HISTORY or ~/.psql_history)\n\
+                  psql's history is imported automatically the first time psqlm starts. Words from history and table and column names complete with Tab.",
+    },
+    Command {
+        kind: CommandKind::Schema,
+        names: &["\\schema"],
+        usage: "\\schema [pattern|snapshot|snapshots|diff]",
+        summary: "show/refresh schema, or save and compare schema versions",
+        details: "Without arguments, re-introspects the database and prints the schema sent to the model, paged through $PAGER when it is taller than the terminal.\n\
+                  <pattern>        shows only tables whose name matches a glob such as order* or billing.*, or a /regex/\n\
+                  snapshot [name]  saves the current schema\n\
+                  snapshots        lists saved snapshots\n\
+                  diff <a> [b]     shows DDL-style changes between two snapshots, or from a snapshot to the live schema\n\
+                  Snapshots are referenced by #number, name, or a timestamp prefix such as 2024-05-01.",
+    },
+    Command {
+        kind: CommandKind::Mode,
+        names: &["\\mode"],
+        usage: "\\mode [auto|confirm|show]",
+        summary: "show/set execution mode",
+        details: "auto     runs generated SQL immediately\n\
+                  confirm  asks before running (the default)\n\
+                  show     only displays the SQL",
+    },
+    Command {
+        kind: CommandKind::Stream,
+        names: &["\\stream"],
+        usage: "\\stream [live|quiet]",
+        summary: "show/set SQL generation display",
+        details: "live   echoes tokens as they arrive\n\
+                  quiet  shows a spinner, then the final SQL",
+    },
+    Command {
+        kind: CommandKind::Preview,
+        names: &["\\preview"],
+        usage: "\\preview [compact|full]",
+        summary: "show/set how write previews list affected rows",
+        details: "compact  shows the primary key and the columns an UPDATE sets or an INSERT lists (the default)\n\
+                  full     shows every column of the affected rows",
+    },
+    Command {
+        kind: CommandKind::Isolation,
+        names: &["\\isolation"],
+        usage: "\\isolation [level]",
+        summary: "set the isolation level for write previews and commits",
+        details: "read-committed, repeatable-read or serializable runs the preview and commit transactions at that level, shown in the preview banner; default uses the server's default_transaction_isolation. Without an argument, shows the current level. Set `isolation` in config.toml to change the default.",
+    },
+    Command {
+        kind: CommandKind::Sandbox,
+        names: &["\\sandbox"],
+        usage: "\\sandbox [on|off]",
+        summary: "try writes against scratch copies of their tables first",
+        details: "With the sandbox on, a generated write first runs against copies of the tables it touches (up to sandbox_rows rows each, 1000 by default) in a temporary psqlm_sandbox_<pid> schema. Its output and the row counts before and after are shown, the schema is dropped, and you choose whether to replay the statement against the real tables. Without arguments, toggles. Set `sandbox = true` in config.toml to start with it on.",
+    },
+    Command {
+        kind: CommandKind::Replica,
+        names: &["\\replica"],
+        usage: "\\replica [on|off]",
+        summary: "route reads to the profile's read replica or to the primary",
+        details: "When the profile has a [profiles.<name>.replica], queries that only read run there and writes always run on the primary. Statements that lock rows (FOR UPDATE, FOR SHARE) or call nextval/setval, and serializable transactions, stay on the primary. off sends reads to the primary too, for example when replication lag matters; on restores routing. Without arguments, toggles.",
+    },
+    Command {
+        kind: CommandKind::Persona,
+        names: &["\\persona"],
+        usage: "\\persona [name|default]",
+        summary: "switch the system-prompt persona",
+        details: "Changes the guidance given to the model: analyst prefers aggregates and always adds a LIMIT, dba may suggest DDL and maintenance, developer writes parameterized examples. Personas defined under [personas] in config.toml are listed too. Without an argument, lists the personas.",
+    },
+    Command {
+        kind: CommandKind::Why,
+        names: &["\\why"],
+        usage: "\\why [sql]",
+        summary: "explain a SQL statement (default: the last one)",
+        details: "Asks the model to explain the given statement, or the last generated one, in plain language, including pitfalls such as NULL handling or join fan-out.",
+    },
+    Command {
+        kind: CommandKind::Filter,
+        names: &["\\filter"],
+        usage: "\\filter [col] <text>",
+        summary: "filter the last result",
+        details: "Keeps rows containing <text> (case-insensitive) in the given column, or in any column when the first word is not a column name. Without arguments, clears the filter.",
+    },
+    Command {
+        kind: CommandKind::Sort,
+        names: &["\\sort"],
+        usage: "\\sort <col> [asc|desc]",
+        summary: "sort the last result",
+        details: "Sorts the last result by a column, numerically for number columns. Without arguments, restores the original order.",
+    },
+    Command {
+        kind: CommandKind::Cols,
+        names: &["\\cols"],
+        usage: "\\cols <a,b|-c>",
+        summary: "choose the columns of the last result",
+        details: "Shows only the listed columns, in that order, or hides columns prefixed with '-'. Without arguments, shows all columns again.",
+    },
+    Command {
+        kind: CommandKind::Render,
+        names: &["\\render"],
+        usage: "\\render <template>",
+        summary: "print each row of the last result through a template",
+        details: "Writes one line per row with {column} replaced by that row's value, as in \\render {name} <{email}>: {order_count} orders. NULL values become empty. Write {{ and }} for literal braces. Uses the last result with any \\filter, \\sort and \\cols applied.",
+    },
+    Command {
+        kind: CommandKind::Wrap,
+        names: &["\\wrap"],
+        usage: "\\wrap [on|off]",
+        summary: "wrap or truncate long values",
+        details: "Values longer than max_column_width are truncated with '…' by default; with wrapping on they continue on the following lines. Without arguments, toggles.",
+    },
+    Command {
+        kind: CommandKind::Scroll,
+        names: &["\\scroll"],
+        usage: "\\scroll <left|right|reset|col>",
+        summary: "page through columns wider than the terminal",
+        details: "Moves the visible window of a wide result one page right (the default) or left, back to the first column, or to start at the named column.",
+    },
+    Command {
+        kind: CommandKind::Explore,
+        names: &["\\explore"],
+        usage: "\\explore",
+        summary: "sort, filter and rearrange the last result interactively",
+        details: "Opens the last result in a full-screen table without re-running the query.\n\
+                  s        sorts by the current column; press again for descending, a third time to unsort\n\
+                  /        filters rows containing the typed text in any column; f filters on the current column\n\
+                  x        hides the current column; < and > move it left or right\n\
+                  p        pins columns up to the current one so they stay visible while scrolling right; again to unpin\n\
+                  r        resets the view\n\
+                  q, Esc or Enter closes the table and keeps the view for \\export, \\filter, \\sort and \\cols.\n\
+                  Results taller than the terminal open here automatically unless result_viewer = \"off\".",
+    },
+    Command {
+        kind: CommandKind::On,
+        names: &["\\on"],
+        usage: "\\on <profile|all> <question>",
+        summary: "run a question against other profiles",
+        details: "Runs a read-only question against one or all configured profiles concurrently, each with its own schema, and labels the results by source. Without arguments, lists the profiles.",
+    },
+    Command {
+        kind: CommandKind::Format,
+        names: &["\\format"],
+        usage: "\\format [table|csv|json|markdown]",
+        summary: "choose how results are printed",
+        details: "table is the aligned text table. csv prints comma-separated values with a header, json an array of objects with numbers, booleans and JSON columns typed, and markdown a table to paste into documents or tickets. Large results are only streamed as tables. Without arguments, shows the current format; --format sets it on the command line.",
+    },
+    Command {
+        kind: CommandKind::Json,
+        names: &["\\json"],
+        usage: "\\json [expand|collapse]",
+        summary: "pretty-print or flatten JSON values",
+        details: "JSON and JSONB values are pretty-printed and highlighted by default, keeping objects and arrays shorter than json_collapse_threshold (40 characters) on one line. collapse shows each value on a single line. Without arguments, toggles.",
+    },
+    Command {
+        kind: CommandKind::RefreshMv,
+        names: &["\\refresh-mv"],
+        usage: "\\refresh-mv <name> [concurrently]",
+        summary: "refresh a materialized view",
+        details: "Runs REFRESH MATERIALIZED VIEW through the usual confirmation. CONCURRENTLY requires a unique index on a populated view.",
+    },
+    Command {
+        kind: CommandKind::Activity,
+        names: &["\\activity"],
+        usage: "\\activity",
+        summary: "show running queries and cancel one",
+        details: "Lists active queries from pg_stat_activity with their duration and state, and offers to cancel one with pg_cancel_backend after a confirmation.",
+    },
+    Command {
+        kind: CommandKind::Hot,
+        names: &["\\hot"],
+        usage: "\\hot",
+        summary: "show the most-read and most-written tables",
+        details: "Combines pg_stat_user_tables and pg_statio_user_tables into the 15 busiest tables since statistics were last reset, with sequential and index scans and heap and index cache hit ratios, followed by bars comparing rows read and rows written.",
+    },
+    Command {
+        kind: CommandKind::Health,
+        names: &["\\health"],
+        usage: "\\health",
+        summary: "report unused indexes, unvacuumed and bloated tables",
+        details: "Combines catalog statistics into a report of unused and invalid indexes, tables never vacuumed, tables with over 20% dead rows, and columns that are always NULL. Afterwards it can ask Claude to prioritize the findings; any statement it suggests runs only after the usual confirmation.",
+    },
+    Command {
+        kind: CommandKind::Locks,
+        names: &["\\locks"],
+        usage: "\\locks",
+        summary: "show blocked sessions and what holds their locks",
+        details: "Pairs each session waiting on a lock with the sessions blocking it, using pg_locks and pg_stat_activity, and offers to terminate a blocking backend after a confirmation.",
+    },
+    Command {
+        kind: CommandKind::Kill,
+        names: &["\\kill"],
+        usage: "\\kill <pid>",
+        summary: "terminate a backend",
+        details: "Shows the session running on the given pid and, after a confirmation, ends it with pg_terminate_backend. Its open transaction is rolled back.",
+    },
+    Command {
+        kind: CommandKind::Similar,
+        names: &["\\similar"],
+        usage: "\\similar [sql]",
+        summary: "find similar past queries in pg_stat_statements",
+        details: "Compares the given SQL, or the last generated statement, with the normalized statements in pg_stat_statements and shows the closest matches with their call counts and timings.",
+    },
+    Command {
+        kind: CommandKind::Insights,
+        names: &["\\insights"],
+        usage: "\\insights",
+        summary: "summarize past questions on this database",
+        details: "Reads every question asked on this database and the SQL that ran for it, then lists the most-queried tables, questions asked three or more times (candidates for an alias or template), and how many questions failed on the first SQL and needed another attempt, with the tables those involved.",
+    },
+    Command {
+        kind: CommandKind::Report,
+        names: &["\\report"],
+        usage: "\\report [file]",
+        summary: "write the session's questions, SQL and results to markdown",
+        details: "Writes a markdown report with a model-written summary followed by every question, its final SQL and result table. In strict mode the summary sees only questions and SQL.",
+    },
+    Command {
+        kind: CommandKind::Export,
+        names: &["\\export"],
+        usage: "\\export csv|json [file] [options]",
+        summary: "write the current result to a CSV or JSON file",
+        details: "Writes the current result, with any \\filter, \\sort and \\cols applied, to a file. JSON exports are an array of objects with numbers, booleans and JSON columns kept as typed values. CSV defaults come from [csv] in config.toml; options override them for one export:\n\
+                  --delimiter=C     field separator, e.g. ';' or tab\n\
+                  --quote=STYLE     necessary, always, nonnumeric or never\n\
+                  --[no-]header     include the column names\n\
+                  --null=TEXT       text written for NULL values\n\
+                  --encoding=ENC    utf-8, utf-8-bom, utf-16le or latin1",
+    },
+    Command {
+        kind: CommandKind::Output,
+        names: &["\\o", "\\out"],
+        usage: "\\o [file]",
+        summary: "write the results of later queries to a file",
+        details: "Like psql's \\o: the file is emptied, then every result with rows is appended to it instead of printed, for both questions and SQL. Results use the \\format setting, except that a table format writes .csv, .json and .md files as CSV, JSON and markdown. Statements without rows still print here. Without an argument, results print to the terminal again.",
+    },
+    Command {
+        kind: CommandKind::ExportSession,
+        names: &["\\export-session"],
+        usage: "\\export-session [file]",
+        summary: "write the session's executed SQL to a replayable script",
+        details: "Writes every successfully executed statement of the session, in order, to a .sql file with the question that produced it as a comment above each one. The script can be replayed with psql -f.",
+    },
+    Command {
+        kind: CommandKind::SaveBlob,
+        names: &["\\save-blob"],
+        usage: "\\save-blob <column> <path>",
+        summary: "write bytea or large object values from the current result to files",
+        details: "Writes the column's values from the current result, with any \\filter and \\sort applied, to files instead of the terminal. bytea values are decoded to their raw bytes and numeric values are treated as large object OIDs and exported with \\lo_export. A single value is written to the path; several values go into the path as a directory, one row-N file each, with the extension guessed from the content (png, jpg, gif, pdf, zip, gz, otherwise bin).",
+    },
+    Command {
+        kind: CommandKind::Fixture,
+        names: &["\\fixture"],
+        usage: "\\fixture <table> [rows] [file] [--copy]",
+        summary: "sample rows and everything they reference into a fixture file",
+        details: "Samples random rows (20 by default) from the table and follows foreign keys to include every row they reference, then writes them as INSERT statements (or COPY data with --copy), parents first, in a .sql file that loads into a local test database with psql -f. Questions that ask for a fixture, like \"50 orders and their users as a fixture\", offer the same.",
+    },
+    Command {
+        kind: CommandKind::Seed,
+        names: &["\\seed"],
+        usage: "\\seed <description of the data>",
+        summary: "generate realistic test data from a description",
+        details: "Asks the model for INSERT statements, usually built on generate_series, that create the described data, such as \"100 users with realistic names, 10% with null phone numbers\". Rows for referenced tables are created first or taken from existing rows. The statements are previewed in a rolled-back transaction and only committed once you confirm.",
+    },
+    Command {
+        kind: CommandKind::Graph,
+        names: &["\\graph"],
+        usage: "\\graph <table> [hops] [file.mmd|file.dot] [--mermaid|--dot]",
+        summary: "draw the foreign keys around a table",
+        details: "Follows foreign keys in both directions from the table, 1 hop by default, and prints the tables reached as a tree. With a .mmd or .dot file, or --mermaid or --dot, writes a Mermaid ER diagram or a Graphviz graph with every column and foreign key instead (psqlm-graph-<table>-<timestamp> when no file is named).",
+    },
+    Command {
+        kind: CommandKind::Good,
+        names: &["\\good"],
+        usage: "\\good [reason]",
+        summary: "rate the last query as correct",
+        details: "Saves the last question and SQL as a positive example that guides future queries on this database.",
+    },
+    Command {
+        kind: CommandKind::Bad,
+        names: &["\\bad"],
+        usage: "\\bad [reason]",
+        summary: "rate the last query as wrong",
+        details: "Saves the last question and SQL as a negative example, with an optional reason, to steer future queries on this database.",
+    },
+    Command {
+        kind: CommandKind::Store,
+        names: &["\\store"],
+        usage: "\\store <name>",
+        summary: "save the last result under a name",
+        details: "Keeps the last question, its SQL and result under the given name for this session. Mentioning the name in a later question (\"compare q1 with last year\") adds the stored SQL and result to the prompt; in strict mode only the SQL is sent.",
+    },
+    Command {
+        kind: CommandKind::Use,
+        names: &["\\use"],
+        usage: "\\use [name]",
+        summary: "list stored results, or show one again",
+        details: "Without arguments, lists the stored results. With a name, shows the stored result again and makes it the current result for \\filter, \\sort and \\cols.",
+    },
+    Command {
+        kind: CommandKind::Drill,
+        names: &["\\drill"],
+        usage: "\\drill [question]",
+        summary: "pick a cell of the last result and ask about that row",
+        details: "Opens the last result in a full-screen table. Move with the arrow keys and press Enter on a cell, then ask a follow-up such as \"show me everything about this order\"; the selected value and the row's primary key are added to the prompt so the query targets exactly that row. Not available in strict mode, since it sends row values to the model.",
+    },
+    Command {
+        kind: CommandKind::Alias,
+        names: &["\\alias"],
+        usage: "\\alias [name [expansion] | -d name]",
+        summary: "define shortcuts for commands and prompts",
+        details: "Without arguments, lists aliases. \\alias rc \\report daily.md makes \\rc run that command, with any extra arguments appended; an expansion that is not a command is asked as a question. With only a name, shows that alias; -d removes it. Aliases defined here last for the session; put them under [aliases] in config.toml to keep them. Built-in commands cannot be redefined.",
+    },
+    Command {
+        kind: CommandKind::Models,
+        names: &["\\models"],
+        usage: "\\models [name]",
+        summary: "list available models and switch",
+        details: "Lists the models offered by the API with their context size and price per million tokens, and lets you pick one for the rest of the session. With a model id, switches directly. Set `model` in config.toml to change the default.",
+    },
+];
+
+const SQL_COMMANDS: &[CommandKind] = &[
+    CommandKind::On,
+    CommandKind::RefreshMv,
+    CommandKind::Kill,
+    CommandKind::Seed,
+    CommandKind::Fixture,
+];
+
+pub fn runs_sql(kind: CommandKind) -> bool {
+    SQL_COMMANDS.contains(&kind)
+}
+
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.names.contains(&name))
+}
+
+pub fn is_valid_alias(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        && find(&format!("\\{}", name)).is_none()
+}
+
+pub fn expand_alias(aliases: &BTreeMap<String, String>, line: &str) -> Option<String> {
+    let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let name = name.strip_prefix('\\')?;
+    if find(&format!("\\{}", name)).is_some() {
+        return None;
+    }
+    let expansion = aliases.get(name)?;
+    Some(format!("{} {}", expansion, args.trim()).trim().to_string())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+pub fn suggest(name: &str) -> Option<&'static str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    COMMANDS
+        .iter()
+        .flat_map(|c| c.names.iter().copied())
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+pub fn summary() -> String {
+    let width = COMMANDS.iter().map(|c| c.usage.len()).max().unwrap_or(0);
+    COMMANDS
+        .iter()
+        .map(|c| format!("  {:<width$}  {}\n", c.usage, c.summary))
+        .collect()
+}
+
+pub fn help(name: &str) -> Option<String> {
+    let name = if name.starts_with('\\') {
+        name.to_string()
+    } else {
+        format!("\\{}", name)
+    };
+    let command = find(&name)?;
+
+    let mut output = format!("Usage: {}\n\n{}\n", command.usage, command.details);
+    if command.names.len() > 1 {
+        output.push_str(&format!("\nAliases: {}\n", command.names.join(", ")));
+    }
+    Some(output)
+}
@@ -0,0 +1,216 @@
+use crate::quoting::quote;
+use crate::schema::{Column, Schema};
+use regex::Regex;
+use std::sync::LazyLock;
+
+static ALIAS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\b(?:from|join)\s+("?[\w.]+"?)(?:\s+as)?\s+("?\w+"?)"#).unwrap()
+});
+
+pub struct Completion {
+    pub replacement: String,
+    pub candidates: Vec<String>,
+}
+
+fn needs_quotes(name: &str) -> bool {
+    name.chars().next().is_none_or(|c| c.is_ascii_digit())
+        || name
+            .chars()
+            .any(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'))
+}
+
+pub fn identifier(name: &str) -> String {
+    if needs_quotes(name) {
+        quote(name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn unquote(word: &str) -> String {
+    match word.strip_prefix('"') {
+        Some(quoted) => quoted.trim_end_matches('"').replace("\"\"", "\""),
+        None => word.to_lowercase(),
+    }
+}
+
+pub fn word_before(line: &str, column: usize) -> String {
+    let before: Vec<char> = line.chars().take(column).collect();
+    let start = before
+        .iter()
+        .rposition(|c| !(c.is_alphanumeric() || *c == '_' || *c == '.' || *c == '"'))
+        .map_or(0, |i| i + 1);
+    before[start..].iter().collect()
+}
+
+fn short_name(table: &str) -> &str {
+    table.rsplit('.').next().unwrap_or(table)
+}
+
+fn display_table(table: &str) -> String {
+    match table.split_once('.') {
+        Some(("public", name)) => identifier(name),
+        Some((schema, name)) => format!("{}.{}", identifier(schema), identifier(name)),
+        None => identifier(table),
+    }
+}
+
+fn relation_columns<'a>(schema: &'a Schema, name: &str) -> Option<&'a [Column]> {
+    let name = name.to_lowercase();
+    let matches = |relation: &str| {
+        relation.to_lowercase() == name || short_name(relation).to_lowercase() == name
+    };
+    schema
+        .tables
+        .iter()
+        .find(|t| matches(&t.name))
+        .map(|t| t.columns.as_slice())
+        .or_else(|| {
+            schema
+                .materialized_views
+                .iter()
+                .find(|v| matches(&v.name))
+                .map(|v| v.columns.as_slice())
+        })
+        .or_else(|| {
+            schema
+                .views
+                .iter()
+                .find(|v| matches(&v.name))
+                .map(|v| v.columns.as_slice())
+        })
+}
+
+fn resolve_alias(sql: &str, alias: &str) -> Option<String> {
+    ALIAS
+        .captures_iter(sql)
+        .find_map(|captures| (unquote(&captures[2]) == alias).then(|| unquote(&captures[1])))
+}
+
+fn mentioned_columns<'a>(schema: &'a Schema, sql: &str) -> Vec<&'a Column> {
+    let lower = sql.to_lowercase();
+    let mentioned: Vec<&[Column]> = schema
+        .tables
+        .iter()
+        .filter(|t| lower.contains(&short_name(&t.name).to_lowercase()))
+        .map(|t| t.columns.as_slice())
+        .collect();
+    if mentioned.is_empty() {
+        schema.tables.iter().flat_map(|t| &t.columns).collect()
+    } else {
+        mentioned.into_iter().flatten().collect()
+    }
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut prefix: Vec<char> = first.chars().collect();
+    for candidate in &candidates[1..] {
+        let shared = prefix
+            .iter()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        prefix.truncate(shared);
+    }
+    prefix.into_iter().collect()
+}
+
+pub fn complete(schema: &Schema, sql: &str, word: &str) -> Option<Completion> {
+    let (qualifier, partial) = match word.rsplit_once('.') {
+        Some((qualifier, partial)) => (Some(qualifier), partial),
+        None => (None, word),
+    };
+    let needle = partial.trim_start_matches('"').to_lowercase();
+
+    let names: Vec<String> = match qualifier {
+        Some(qualifier) => {
+            let qualifier = unquote(qualifier);
+            let tables_in_schema: Vec<String> = schema
+                .tables
+                .iter()
+                .filter_map(|t| {
+                    let (owner, name) = t.name.split_once('.')?;
+                    (owner == qualifier).then(|| name.to_string())
+                })
+                .collect();
+            if tables_in_schema.is_empty() {
+                let relation = resolve_alias(sql, &qualifier).unwrap_or(qualifier);
+                relation_columns(schema, &relation)?
+                    .iter()
+                    .map(|c| c.name.clone())
+                    .collect()
+            } else {
+                tables_in_schema
+            }
+        }
+        None => schema
+            .tables
+            .iter()
+            .map(|t| t.name.clone())
+            .chain(
+                mentioned_columns(schema, sql)
+                    .into_iter()
+                    .map(|c| c.name.clone()),
+            )
+            .collect(),
+    };
+
+    let mut candidates: Vec<String> = names
+        .iter()
+        .filter(|name| short_name(name).to_lowercase().starts_with(&needle))
+        .map(|name| match qualifier {
+            Some(_) => identifier(name),
+            None => display_table(name),
+        })
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let completed = if candidates.len() == 1 {
+        candidates[0].clone()
+    } else {
+        let prefix = common_prefix(&candidates);
+        if prefix.chars().count() > partial.chars().count() {
+            prefix
+        } else {
+            partial.to_string()
+        }
+    };
+    let replacement = match qualifier {
+        Some(qualifier) => format!("{}.{}", qualifier, completed),
+        None => completed,
+    };
+    Some(Completion {
+        replacement,
+        candidates,
+    })
+}
+
+pub fn quote_word(schema: &Schema, word: &str) -> Option<String> {
+    if word.is_empty() || word.contains(['"', '.']) {
+        return None;
+    }
+    let lower = word.to_lowercase();
+    let mut matches: Vec<&str> = schema
+        .tables
+        .iter()
+        .flat_map(|t| {
+            t.name
+                .split('.')
+                .chain(t.columns.iter().map(|c| c.name.as_str()))
+        })
+        .filter(|name| name.to_lowercase() == lower)
+        .collect();
+    matches.sort();
+    matches.dedup();
+    match matches.as_slice() {
+        [name] if needs_quotes(name) => Some(quote(name)),
+        _ => None,
+    }
+}
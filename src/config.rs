@@ -1,7 +1,11 @@
 use anyhow::{Context, Result};
+use libloading::Library;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
@@ -12,21 +16,594 @@ pub enum ExecutionMode {
     Show,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Provider {
+    #[default]
+    Anthropic,
+    Openai,
+    AzureOpenai,
+    Ollama,
+}
+
+impl Provider {
+    pub fn api_key_variable(&self) -> Option<&'static str> {
+        match self {
+            Self::Anthropic => Some("ANTHROPIC_API_KEY"),
+            Self::Openai | Self::AzureOpenai => Some("OPENAI_API_KEY"),
+            Self::Ollama => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Anthropic => "Anthropic",
+            Self::Openai => "OpenAI",
+            Self::AzureOpenai => "Azure OpenAI",
+            Self::Ollama => "Ollama",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamDisplay {
+    #[default]
+    Live,
+    Quiet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaAnswers {
+    #[default]
+    Model,
+    Local,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitFormat {
+    Bytes,
+    Cents,
+    Epoch,
+    EpochMillis,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisplayRule {
+    pub column: String,
+    pub format: UnitFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TopicChange {
+    #[default]
+    Ask,
+    Reset,
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DataConsent {
+    #[default]
+    Ask,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewRows {
+    #[default]
+    Compact,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonDisplay {
+    #[default]
+    Expanded,
+    Collapsed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultViewer {
+    #[default]
+    Auto,
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl OutputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Markdown => "markdown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SafeMode {
+    #[default]
+    Auto,
+    Always,
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IsolationLevel {
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl IsolationLevel {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace([' ', '_'], "-").as_str() {
+            "read-committed" => Some(Self::ReadCommitted),
+            "repeatable-read" => Some(Self::RepeatableRead),
+            "serializable" => Some(Self::Serializable),
+            _ => None,
+        }
+    }
+
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::ReadCommitted => "READ COMMITTED",
+            Self::RepeatableRead => "REPEATABLE READ",
+            Self::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfirmKeys {
+    pub run: char,
+    pub edit_sql: char,
+    pub edit_prompt: char,
+    pub auto_run: char,
+    pub more: char,
+    pub cancel: char,
+}
+
+impl Default for ConfirmKeys {
+    fn default() -> Self {
+        Self {
+            run: 'y',
+            edit_sql: 'e',
+            edit_prompt: 'p',
+            auto_run: 'a',
+            more: 'm',
+            cancel: 'n',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Quoting {
+    #[default]
+    Necessary,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16le,
+    Latin1,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub quoting: Quoting,
+    pub header: bool,
+    pub null: String,
+    pub encoding: Encoding,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quoting: Quoting::default(),
+            header: true,
+            null: String::new(),
+            encoding: Encoding::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RateLimit {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_minute: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_execute: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_execute: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library: Option<String>,
+
+    #[serde(skip)]
+    pub loaded: Option<Arc<Library>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CostPreview {
+    #[serde(default)]
+    pub show: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm_above_tokens: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm_above_dollars: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Audit {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub explain: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WriteGuard {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Kiosk {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthMode {
+    #[default]
+    Password,
+    RdsIam,
+    Command,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Auth {
+    #[serde(default)]
+    pub mode: AuthMode,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_minutes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptColor {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl PromptColor {
+    pub fn ansi(self) -> &'static str {
+        match self {
+            PromptColor::Red => "\x1b[1;31m",
+            PromptColor::Yellow => "\x1b[1;33m",
+            PromptColor::Green => "\x1b[1;32m",
+            PromptColor::Blue => "\x1b[1;34m",
+            PromptColor::Magenta => "\x1b[1;35m",
+            PromptColor::Cyan => "\x1b[1;36m",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Environment {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<PromptColor>,
+
+    #[serde(default)]
+    pub production: bool,
+}
+
+impl Environment {
+    pub fn color(&self) -> Option<PromptColor> {
+        self.color.or(self.production.then_some(PromptColor::Red))
+    }
+
+    pub fn prompt(&self) -> String {
+        match &self.label {
+            Some(label) => format!("psqlm({})> ", label),
+            None => "psqlm> ".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default = "default_host")]
+    pub host: String,
+
+    #[serde(default = "default_port")]
+    pub port: String,
+
+    pub user: String,
+    pub database: String,
+    pub password: Option<String>,
+
+    #[serde(default)]
+    pub auth: Auth,
+
+    #[serde(default)]
+    pub write_guard: WriteGuard,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub csv: Option<CsvOptions>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<Hooks>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replica: Option<Replica>,
+
+    #[serde(flatten)]
+    pub environment: Environment,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replica {
+    pub host: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    pub description: String,
+    pub sql: String,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+}
+
+fn default_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_port() -> String {
+    "5432".to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
     #[serde(skip)]
     pub api_key: String,
 
+    #[serde(default)]
+    pub provider: Provider,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm_timeout: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fast_model: Option<String>,
+
     #[serde(default)]
     pub execution_mode: ExecutionMode,
+
+    #[serde(default)]
+    pub stream_display: StreamDisplay,
+
+    #[serde(default)]
+    pub strict_mode: bool,
+
+    #[serde(default)]
+    pub accessible: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_column_width: Option<usize>,
+
+    #[serde(default)]
+    pub wrap: bool,
+
+    #[serde(default)]
+    pub schema_answers: SchemaAnswers,
+
+    #[serde(default)]
+    pub topic_change: TopicChange,
+
+    #[serde(default)]
+    pub data_consent: DataConsent,
+
+    #[serde(default)]
+    pub format: OutputFormat,
+
+    #[serde(default)]
+    pub result_viewer: ResultViewer,
+
+    #[serde(default)]
+    pub json: JsonDisplay,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_collapse_threshold: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_rules: Option<Vec<DisplayRule>>,
+
+    #[serde(default)]
+    pub preview_rows: PreviewRows,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_threshold: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub isolation: Option<IsolationLevel>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serialization_retries: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_tag: Option<String>,
+
+    #[serde(default)]
+    pub confirm_keys: ConfirmKeys,
+
+    #[serde(default)]
+    pub safe_mode: SafeMode,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safe_mode_row_limit: Option<u64>,
+
+    #[serde(skip)]
+    pub safe_mode_active: bool,
+
+    #[serde(skip)]
+    pub demo: bool,
+
+    #[serde(skip)]
+    pub single_transaction: bool,
+
+    #[serde(skip)]
+    pub primary_reads: bool,
+
+    #[serde(skip)]
+    pub environment: Environment,
+
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+
+    #[serde(default)]
+    pub cost_preview: CostPreview,
+
+    #[serde(default)]
+    pub follow_ups: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_address: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recent_queries: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+
+    #[serde(default)]
+    pub view_definitions: bool,
+
+    #[serde(default)]
+    pub auth: Auth,
+
+    #[serde(default)]
+    pub write_guard: WriteGuard,
+
+    #[serde(default)]
+    pub kiosk: Kiosk,
+
+    #[serde(default)]
+    pub sandbox: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox_rows: Option<u64>,
+
+    #[serde(default)]
+    pub csv: CsvOptions,
+
+    #[serde(default)]
+    pub hooks: Hooks,
+
+    #[serde(default)]
+    pub audit: Audit,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, Template>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub personas: BTreeMap<String, String>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 struct ConfigFile {
     api_key: Option<String>,
 
-    #[serde(default)]
-    execution_mode: ExecutionMode,
+    #[serde(flatten)]
+    settings: Config,
 }
 
 fn config_dir() -> Result<PathBuf> {
@@ -40,26 +617,96 @@ fn config_path() -> Result<PathBuf> {
     Ok(config_dir()?.join("config.toml"))
 }
 
-pub async fn load_or_create() -> Result<Config> {
-    if let Ok(api_key) = std::env::var("ANTHROPIC_API_KEY") {
+// Serializes read-modify-write cycles on config.toml across psqlm processes; the lock is
+// released when the file handle drops.
+fn with_config_lock<T>(write: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+    let dir = config_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    let lock_path = dir.join("config.toml.lock");
+    let lock = File::create(&lock_path)
+        .with_context(|| format!("Failed to open lock file {:?}", lock_path))?;
+    lock.lock()
+        .with_context(|| format!("Failed to lock {:?}", lock_path))?;
+    write(&dir.join("config.toml"))
+}
+
+pub fn is_first_run() -> bool {
+    std::env::var("ANTHROPIC_API_KEY").is_err() && config_path().is_ok_and(|path| !path.exists())
+}
+
+pub fn save_initial(contents: &str) -> Result<PathBuf> {
+    toml::from_str::<ConfigFile>(contents).context("The generated config.toml is invalid")?;
+    with_config_lock(|path| {
+        write_atomic(path, contents)?;
+        Ok(path.to_path_buf())
+    })
+}
+
+pub fn save_template(name: &str, template: &Template) -> Result<PathBuf> {
+    let mut templates = toml::Table::new();
+    templates.insert(name.to_string(), toml::Value::try_from(template)?);
+    let mut block = toml::Table::new();
+    block.insert("templates".to_string(), toml::Value::Table(templates));
+    let block = toml::to_string(&block)?;
+
+    with_config_lock(|path| {
+        let existing = std::fs::read_to_string(path).unwrap_or_default();
+        let contents = if existing.trim().is_empty() {
+            block
+        } else {
+            format!("{}\n\n{}", existing.trim_end(), block)
+        };
+        toml::from_str::<ConfigFile>(&contents)
+            .with_context(|| format!("Could not add template {} to {:?}", name, path))?;
+        write_atomic(path, &contents)?;
+        Ok(path.to_path_buf())
+    })
+}
+
+pub fn load() -> Result<Config> {
+    Ok(load_config_file()?.unwrap_or_default().settings)
+}
+
+pub async fn load_or_create(session_key: Option<String>) -> Result<Config> {
+    if let Some(api_key) = session_key {
+        let config = load_config_file()?.unwrap_or_default();
+        return Ok(Config {
+            api_key,
+            ..config.settings
+        });
+    }
+
+    let config_file = load_config_file()?;
+    let provider = config_file
+        .as_ref()
+        .map(|config| config.settings.provider)
+        .unwrap_or_default();
+    let Some(variable) = provider.api_key_variable() else {
+        return Ok(config_file.unwrap_or_default().settings);
+    };
+    if let Ok(api_key) = std::env::var(variable) {
         let api_key: String = api_key.chars().filter(|c| !c.is_whitespace()).collect();
-        let config = load_config_file().unwrap_or_default();
         return Ok(Config {
             api_key,
-            execution_mode: config.execution_mode,
+            ..config_file.unwrap_or_default().settings
         });
     }
 
-    if let Ok(config_file) = load_config_file() {
-        if let Some(api_key) = config_file.api_key {
+    let settings = match config_file {
+        Some(ConfigFile {
+            api_key: Some(api_key),
+            settings,
+        }) => {
             return Ok(Config {
                 api_key,
-                execution_mode: config_file.execution_mode,
-            });
+                ..settings
+            })
         }
-    }
+        Some(config_file) => config_file.settings,
+        None => Config::default(),
+    };
 
-    let api_key = prompt_for_api_key()?;
+    let api_key = prompt_for_api_key(provider)?;
 
     print!("Save API key to config file? [y/n]: ");
     io::stdout().flush()?;
@@ -68,26 +715,30 @@ pub async fn load_or_create() -> Result<Config> {
     io::stdin().read_line(&mut input)?;
 
     if input.trim().to_lowercase() == "y" {
-        save_api_key(&api_key)?;
+        save_api_key(&api_key, &settings)?;
         println!("Saved to {:?}\n", config_path()?);
     }
 
     Ok(Config {
         api_key,
-        execution_mode: ExecutionMode::default(),
+        ..settings
     })
 }
 
-fn load_config_file() -> Result<ConfigFile> {
+fn load_config_file() -> Result<Option<ConfigFile>> {
     let path = config_path()?;
-    let contents = std::fs::read_to_string(&path)
-        .with_context(|| format!("Failed to read config file: {:?}", path))?;
-    let config: ConfigFile = toml::from_str(&contents)?;
-    Ok(config)
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read config file: {:?}", path)),
+    };
+    let config: ConfigFile = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse config file: {:?}", path))?;
+    Ok(Some(config))
 }
 
-fn prompt_for_api_key() -> Result<String> {
-    print!("Enter your Anthropic API key: ");
+fn prompt_for_api_key(provider: Provider) -> Result<String> {
+    print!("Enter your {} API key: ", provider.label());
     io::stdout().flush()?;
 
     let mut input = String::new();
@@ -101,17 +752,27 @@ fn prompt_for_api_key() -> Result<String> {
     Ok(key)
 }
 
-fn save_api_key(api_key: &str) -> Result<()> {
-    let dir = config_dir()?;
-    std::fs::create_dir_all(&dir)?;
-
-    let config = ConfigFile {
-        api_key: Some(api_key.to_string()),
-        execution_mode: ExecutionMode::default(),
-    };
-
-    let contents = toml::to_string_pretty(&config)?;
-    std::fs::write(config_path()?, contents)?;
+fn save_api_key(api_key: &str, settings: &Config) -> Result<()> {
+    with_config_lock(|path| {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(existing) => format!("api_key = {}\n{}", toml::Value::from(api_key), existing),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => toml::to_string_pretty(&ConfigFile {
+                api_key: Some(api_key.to_string()),
+                settings: settings.clone(),
+            })?,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read config file: {:?}", path))
+            }
+        };
+        toml::from_str::<ConfigFile>(&contents)
+            .with_context(|| format!("Could not add the API key to {:?}", path))?;
+        write_atomic(path, &contents)
+    })
+}
 
+pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temp, contents).with_context(|| format!("Failed to write {:?}", temp))?;
+    std::fs::rename(&temp, path).with_context(|| format!("Failed to replace {:?}", path))?;
     Ok(())
 }
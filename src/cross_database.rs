@@ -0,0 +1,122 @@
+use sqlparser::ast::{visit_expressions, visit_relations, Expr, Ident};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use std::ops::ControlFlow;
+
+const DBLINK_FUNCTIONS: &[&str] = &[
+    "dblink",
+    "dblink_exec",
+    "dblink_open",
+    "dblink_fetch",
+    "dblink_send_query",
+    "dblink_connect",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    Relation { database: String, name: String },
+    Dblink(String),
+}
+
+impl Reference {
+    pub fn database(&self) -> Option<&str> {
+        match self {
+            Reference::Relation { database, .. } => Some(database),
+            Reference::Dblink(_) => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Reference::Relation { database, name } => {
+                format!("{} lives in database {}", name, database)
+            }
+            Reference::Dblink(function) => {
+                format!(
+                    "{}() reaches another database through the dblink extension",
+                    function
+                )
+            }
+        }
+    }
+}
+
+fn normalize(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+fn is_dblink(name: &[Ident]) -> bool {
+    name.last()
+        .is_some_and(|ident| DBLINK_FUNCTIONS.contains(&normalize(ident).as_str()))
+}
+
+fn find_dblink_calls(sql: &str) -> Vec<Reference> {
+    let dialect = PostgreSqlDialect {};
+    let Ok(tokens) = Tokenizer::new(&dialect, sql).tokenize() else {
+        return Vec::new();
+    };
+
+    let mut references = Vec::new();
+    let mut tokens = tokens
+        .into_iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .peekable();
+    while let Some(token) = tokens.next() {
+        if let Token::Word(word) = token {
+            let name = word.value.to_lowercase();
+            if DBLINK_FUNCTIONS.contains(&name.as_str())
+                && tokens.peek() == Some(&Token::LParen)
+                && !references.contains(&Reference::Dblink(name.clone()))
+            {
+                references.push(Reference::Dblink(name));
+            }
+        }
+    }
+    references
+}
+
+pub fn find(sql: &str, current_database: &str) -> Vec<Reference> {
+    let dialect = PostgreSqlDialect {};
+    let Ok(statements) = Parser::parse_sql(&dialect, sql) else {
+        return find_dblink_calls(sql);
+    };
+
+    let mut references = Vec::new();
+    let mut add = |reference: Reference| {
+        if !references.contains(&reference) {
+            references.push(reference);
+        }
+    };
+
+    let _ = visit_relations(&statements, |relation| {
+        if is_dblink(&relation.0) {
+            add(Reference::Dblink(normalize(relation.0.last().unwrap())));
+        } else if relation.0.len() == 3 {
+            let database = normalize(&relation.0[0]);
+            if database != current_database {
+                add(Reference::Relation {
+                    database,
+                    name: relation.to_string(),
+                });
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    let _ = visit_expressions(&statements, |expr| {
+        if let Expr::Function(function) = expr {
+            if is_dblink(&function.name.0) {
+                add(Reference::Dblink(normalize(
+                    function.name.0.last().unwrap(),
+                )));
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    references
+}
@@ -0,0 +1,213 @@
+use crate::psql::PsqlConnection;
+use crate::result::ResultSet;
+use anyhow::Result;
+use sqlparser::ast::{
+    AlterColumnOperation, AlterTableOperation, Ident, ObjectName, ObjectType, Statement,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+
+const DEPENDENTS: &str = r#"
+WITH target AS (
+    SELECT c.oid, c.relname, a.attnum
+    FROM pg_class c
+    LEFT JOIN pg_attribute a ON a.attrelid = c.oid AND a.attname = {column} AND NOT a.attisdropped
+    WHERE c.oid = to_regclass({relation})
+      AND ({column} IS NULL OR a.attnum IS NOT NULL)
+)
+SELECT DISTINCT 'view' AS kind, v.oid::regclass::text AS name
+FROM target t
+JOIN pg_depend d ON d.refclassid = 'pg_class'::regclass AND d.refobjid = t.oid
+    AND (t.attnum IS NULL OR d.refobjsubid = t.attnum)
+JOIN pg_rewrite r ON d.classid = 'pg_rewrite'::regclass AND r.oid = d.objid
+JOIN pg_class v ON v.oid = r.ev_class AND v.oid <> t.oid
+UNION
+SELECT 'foreign key', format('%s on %s', con.conname, con.conrelid::regclass)
+FROM target t
+JOIN pg_constraint con ON con.contype = 'f' AND con.confrelid = t.oid AND con.conrelid <> t.oid
+    AND (t.attnum IS NULL OR t.attnum = ANY (con.confkey))
+UNION
+SELECT 'function', d.objid::regprocedure::text
+FROM target t
+JOIN pg_depend d ON d.classid = 'pg_proc'::regclass AND d.refclassid = 'pg_class'::regclass
+    AND d.refobjid = t.oid AND (t.attnum IS NULL OR d.refobjsubid = t.attnum)
+UNION
+SELECT 'function body', p.oid::regprocedure::text
+FROM target t
+JOIN pg_proc p ON p.prosrc ~* ('\m' || t.relname || '\M')
+JOIN pg_namespace n ON n.oid = p.pronamespace
+WHERE n.nspname NOT IN ('pg_catalog', 'information_schema')
+  AND ({column} IS NULL OR p.prosrc ~* ('\m' || {column} || '\M'))
+UNION
+SELECT 'index', i.indexrelid::regclass::text
+FROM target t
+JOIN pg_index i ON i.indrelid = t.oid AND t.attnum = ANY (i.indkey)
+WHERE NOT EXISTS (SELECT 1 FROM pg_constraint con WHERE con.conindid = i.indexrelid)
+UNION
+SELECT 'constraint', con.conname
+FROM target t
+JOIN pg_constraint con ON con.conrelid = t.oid AND t.attnum = ANY (con.conkey)
+ORDER BY 1, 2
+"#;
+
+enum Change {
+    Drop { cascade: bool },
+    DropColumn { cascade: bool },
+    AlterType,
+    Rename,
+}
+
+struct Target {
+    relation: String,
+    column: Option<String>,
+    change: Change,
+}
+
+#[derive(Debug, Default)]
+pub struct Impact {
+    pub fails: Vec<String>,
+    pub dropped: Vec<String>,
+    pub at_risk: Vec<String>,
+}
+
+impl Impact {
+    pub fn is_empty(&self) -> bool {
+        self.fails.is_empty() && self.dropped.is_empty() && self.at_risk.is_empty()
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        let sections = [
+            (&self.fails, "⛔ This will fail because these depend on it:"),
+            (&self.dropped, "⚠️  These will be dropped along with it:"),
+            (
+                &self.at_risk,
+                "⚠️  These will be rebuilt or may stop working:",
+            ),
+        ];
+        for (items, title) in sections {
+            if items.is_empty() {
+                continue;
+            }
+            output.push_str(title);
+            output.push('\n');
+            for item in items {
+                output.push_str(&format!("  - {}\n", item));
+            }
+        }
+        output
+    }
+}
+
+fn normalize(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+fn column_targets(name: &ObjectName, operations: &[AlterTableOperation]) -> Vec<Target> {
+    let target = |column: Option<&Ident>, change| Target {
+        relation: name.to_string(),
+        column: column.map(normalize),
+        change,
+    };
+    operations
+        .iter()
+        .filter_map(|operation| match operation {
+            AlterTableOperation::DropColumn {
+                column_name,
+                cascade,
+                ..
+            } => Some(target(
+                Some(column_name),
+                Change::DropColumn { cascade: *cascade },
+            )),
+            AlterTableOperation::AlterColumn {
+                column_name,
+                op: AlterColumnOperation::SetDataType { .. },
+            } => Some(target(Some(column_name), Change::AlterType)),
+            AlterTableOperation::RenameColumn {
+                old_column_name, ..
+            } => Some(target(Some(old_column_name), Change::Rename)),
+            AlterTableOperation::RenameTable { .. } => Some(target(None, Change::Rename)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn targets(sql: &str) -> Vec<Target> {
+    let dialect = PostgreSqlDialect {};
+    let Ok(statements) = Parser::parse_sql(&dialect, sql) else {
+        return Vec::new();
+    };
+
+    statements
+        .iter()
+        .flat_map(|statement| match statement {
+            Statement::Drop {
+                object_type: ObjectType::Table | ObjectType::View,
+                names,
+                cascade,
+                ..
+            } => names
+                .iter()
+                .map(|name| Target {
+                    relation: name.to_string(),
+                    column: None,
+                    change: Change::Drop { cascade: *cascade },
+                })
+                .collect(),
+            Statement::AlterTable {
+                name, operations, ..
+            } => column_targets(name, operations),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+fn literal(value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("'{}'", value.replace('\'', "''")),
+        None => "NULL::name".to_string(),
+    }
+}
+
+pub fn analyze(psql: &PsqlConnection, sql: &str) -> Result<Impact> {
+    let mut impact = Impact::default();
+    for target in targets(sql) {
+        let query = DEPENDENTS
+            .replace("{relation}", &literal(Some(&target.relation)))
+            .replace("{column}", &literal(target.column.as_deref()));
+        let (success, stdout, stderr) = psql.execute_csv(&query)?;
+        if !success {
+            anyhow::bail!("{}", stderr.trim());
+        }
+
+        let subject = match &target.column {
+            Some(column) => format!("{}.{}", target.relation, column),
+            None => target.relation.clone(),
+        };
+        for row in ResultSet::from_csv(&stdout)?.rows {
+            let (Some(kind), Some(name)) = (&row[0], &row[1]) else {
+                continue;
+            };
+            let item = format!("{} {} (uses {})", kind, name, subject);
+            let list = match (&target.change, kind.as_str()) {
+                (_, "function body") => &mut impact.at_risk,
+                (Change::Drop { cascade: false }, _)
+                | (Change::DropColumn { cascade: false }, "view" | "foreign key" | "function") => {
+                    &mut impact.fails
+                }
+                (Change::Drop { .. } | Change::DropColumn { .. }, _) => &mut impact.dropped,
+                (Change::AlterType, "view") => &mut impact.fails,
+                (Change::AlterType, _) => &mut impact.at_risk,
+                (Change::Rename, _) => continue,
+            };
+            if !list.contains(&item) {
+                list.push(item);
+            }
+        }
+    }
+    Ok(impact)
+}
@@ -0,0 +1,129 @@
+use crate::result::{ColumnType, ResultSet};
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+const FIRST_NAMES: &[&str] = &[
+    "Alex", "Blair", "Casey", "Dana", "Eden", "Finley", "Gray", "Harper", "Indy", "Jordan", "Kai",
+    "Logan", "Morgan", "Noel", "Oakley", "Parker", "Quinn", "Riley", "Sage", "Taylor",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Abbott", "Brooks", "Carver", "Dalton", "Ellis", "Foster", "Garner", "Hayes", "Irving",
+    "Jensen", "Keller", "Lowe", "Mercer", "Nolan", "Ortiz", "Porter", "Reyes", "Sutton", "Turner",
+    "Walsh",
+];
+
+const FIRST_NAME_COLUMNS: &[&str] = &["first_name", "firstname", "given_name", "forename"];
+const LAST_NAME_COLUMNS: &[&str] = &["last_name", "lastname", "surname", "family_name"];
+const FULL_NAME_COLUMNS: &[&str] = &[
+    "name",
+    "full_name",
+    "fullname",
+    "display_name",
+    "customer_name",
+    "contact_name",
+    "user_name",
+    "username",
+];
+const PHONE_COLUMNS: &[&str] = &["phone", "mobile", "cell", "telephone", "fax"];
+
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    FirstName,
+    LastName,
+    FullName,
+    Phone,
+    Other,
+}
+
+fn kind(column: &str) -> Kind {
+    let column = column.to_lowercase();
+    let words: Vec<&str> = column.split(|c: char| !c.is_alphanumeric()).collect();
+    if FIRST_NAME_COLUMNS.contains(&column.as_str()) {
+        Kind::FirstName
+    } else if LAST_NAME_COLUMNS.contains(&column.as_str()) {
+        Kind::LastName
+    } else if FULL_NAME_COLUMNS.contains(&column.as_str()) {
+        Kind::FullName
+    } else if words.iter().any(|word| PHONE_COLUMNS.contains(word)) {
+        Kind::Phone
+    } else {
+        Kind::Other
+    }
+}
+
+fn hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn pick(names: &[&'static str], seed: u64) -> &'static str {
+    names[(seed % names.len() as u64) as usize]
+}
+
+fn fake_name(value: &str, kind: Kind) -> String {
+    let seed = hash(value);
+    let first = pick(FIRST_NAMES, seed);
+    let last = pick(LAST_NAMES, seed / FIRST_NAMES.len() as u64);
+    match kind {
+        Kind::FirstName => first.to_string(),
+        Kind::LastName => last.to_string(),
+        _ => format!("{} {}", first, last),
+    }
+}
+
+fn fake_email(email: &str) -> String {
+    let seed = hash(&email.to_lowercase());
+    format!(
+        "{}.{}{}@example.com",
+        pick(FIRST_NAMES, seed).to_lowercase(),
+        pick(LAST_NAMES, seed / FIRST_NAMES.len() as u64).to_lowercase(),
+        seed % 100
+    )
+}
+
+fn fake_phone(value: &str) -> String {
+    format!("555-01{:02}", hash(value) % 100)
+}
+
+fn anonymize_value(value: &str, kind: Kind) -> String {
+    match kind {
+        Kind::FirstName | Kind::LastName | Kind::FullName if !EMAIL.is_match(value) => {
+            fake_name(value, kind)
+        }
+        Kind::Phone => fake_phone(value),
+        _ => scrub_emails(value),
+    }
+}
+
+pub fn scrub_emails(text: &str) -> String {
+    EMAIL
+        .replace_all(text, |caps: &Captures| fake_email(&caps[0]))
+        .to_string()
+}
+
+pub fn anonymize(result: &ResultSet) -> ResultSet {
+    let kinds: Vec<Kind> = result
+        .columns
+        .iter()
+        .zip(&result.types)
+        .map(|(column, &column_type)| match column_type {
+            ColumnType::Boolean => Kind::Other,
+            _ => kind(column),
+        })
+        .collect();
+
+    let mut anonymized = result.clone();
+    for row in &mut anonymized.rows {
+        for (value, &kind) in row.iter_mut().zip(&kinds) {
+            if let Some(value) = value {
+                *value = anonymize_value(value, kind);
+            }
+        }
+    }
+    anonymized
+}
@@ -0,0 +1,211 @@
+const RED: &str = "\x1b[31m";
+const HIGHLIGHT: &str = "\x1b[4;31m";
+const YELLOW: &str = "\x1b[33m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+const CONTEXT_LINES: usize = 2;
+
+const TRANSIENT_FAILURES: &[(&str, &str)] = &[
+    ("40001", "Serialization failure"),
+    ("40P01", "Deadlock detected"),
+];
+
+#[derive(Debug)]
+struct ErrorLocation {
+    line: usize,
+    snippet: String,
+    caret: usize,
+}
+
+#[derive(Debug, Default)]
+struct PgError {
+    message: Option<String>,
+    detail: Option<String>,
+    hint: Option<String>,
+    location: Option<ErrorLocation>,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+fn parse_location(line: &str, caret_line: &str) -> Option<ErrorLocation> {
+    let (number, snippet) = line.strip_prefix("LINE ")?.split_once(": ")?;
+    let prefix_len = "LINE ".len() + number.len() + ": ".len();
+    let caret = caret_line.find('^')?.checked_sub(prefix_len)?;
+
+    Some(ErrorLocation {
+        line: number.parse().ok()?,
+        snippet: snippet.to_string(),
+        caret,
+    })
+}
+
+// Splits the SQLSTATE off a message printed with VERBOSITY=verbose (`40001: could not ...`).
+fn split_sqlstate(message: &str) -> (Option<&str>, &str) {
+    match message.split_once(": ") {
+        Some((code, rest))
+            if code.len() == 5
+                && code
+                    .bytes()
+                    .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()) =>
+        {
+            (Some(code), rest)
+        }
+        _ => (None, message),
+    }
+}
+
+fn parse_error(stderr: &str) -> PgError {
+    let mut error = PgError::default();
+    let mut lines = stderr.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let field = |name: &str| line.strip_prefix(name).map(|v| v.trim().to_string());
+
+        if error.message.is_none() {
+            if let Some(severity) = ["ERROR:", "FATAL:", "PANIC:"]
+                .iter()
+                .find(|s| line.starts_with(*s))
+            {
+                error.message =
+                    field(severity).map(|m| format!("{} {}", severity, split_sqlstate(&m).1));
+                continue;
+            }
+        }
+
+        if let Some(detail) = field("DETAIL:") {
+            error.detail = Some(detail);
+        } else if let Some(hint) = field("HINT:") {
+            error.hint = Some(hint);
+        } else if line.starts_with("LOCATION:") {
+            continue;
+        } else if line.starts_with("LINE ") && error.location.is_none() {
+            let caret_line = lines.peek().copied().unwrap_or_default();
+            error.location = parse_location(line, caret_line);
+            if error.location.is_some() {
+                lines.next();
+            }
+        } else if !line.trim().is_empty() {
+            if error.message.is_none() {
+                error.before.push(line.to_string());
+            } else {
+                error.after.push(line.to_string());
+            }
+        }
+    }
+
+    error
+}
+
+fn locate(sql: &str, line_offset: usize, location: &ErrorLocation) -> Option<(usize, usize)> {
+    let row = location.line.checked_sub(1 + line_offset)?;
+    let line = sql.lines().nth(row)?;
+
+    let (snippet, caret) = match location.snippet.strip_prefix("...") {
+        Some(snippet) => (snippet, location.caret.checked_sub(3)?),
+        None => (location.snippet.as_str(), location.caret),
+    };
+    let snippet = snippet.strip_suffix("...").unwrap_or(snippet);
+    let start = line.find(snippet)?;
+
+    Some((row, line[..start].chars().count() + caret))
+}
+
+fn token_len(line: &str, column: usize) -> usize {
+    let chars: Vec<char> = line.chars().skip(column).collect();
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_' || *c == '$';
+
+    match chars.first() {
+        None => 0,
+        Some(&quote @ ('"' | '\'')) => chars[1..]
+            .iter()
+            .position(|&c| c == quote)
+            .map_or(chars.len(), |end| end + 2),
+        Some(c) if is_word(c) => chars.iter().take_while(|c| is_word(c)).count(),
+        Some(_) => 1,
+    }
+}
+
+fn render_sql(sql: &str, row: usize, column: usize) -> String {
+    let lines: Vec<&str> = sql.lines().collect();
+    let width = lines.len().to_string().len();
+    let first = row.saturating_sub(CONTEXT_LINES);
+    let last = (row + CONTEXT_LINES).min(lines.len() - 1);
+    let mut output = String::new();
+
+    for (i, line) in lines.iter().enumerate().take(last + 1).skip(first) {
+        if i != row {
+            output.push_str(&format!("  {:>width$} | {}\n", i + 1, line));
+            continue;
+        }
+
+        let len = token_len(line, column);
+        let before: String = line.chars().take(column).collect();
+        let token: String = line.chars().skip(column).take(len).collect();
+        let after: String = line.chars().skip(column + len).collect();
+        output.push_str(&format!(
+            "  {:>width$} | {}{HIGHLIGHT}{}{RESET}{}\n",
+            i + 1,
+            before,
+            token,
+            after
+        ));
+        output.push_str(&format!(
+            "  {:>width$} | {}{RED}{}{RESET}\n",
+            "",
+            " ".repeat(column),
+            "^".repeat(len.max(1))
+        ));
+    }
+
+    output
+}
+
+pub fn render_error(stderr: &str, sql: &str, line_offset: usize) -> String {
+    let error = parse_error(stderr);
+    let Some(message) = &error.message else {
+        return format!("{}\n", stderr.trim_end());
+    };
+
+    let mut output = String::new();
+    for line in &error.before {
+        output.push_str(&format!("{DIM}{}{RESET}\n", line));
+    }
+    output.push_str(&format!("{RED}{}{RESET}\n", message));
+
+    if let Some(location) = &error.location {
+        output.push('\n');
+        match locate(sql, line_offset, location) {
+            Some((row, column)) => output.push_str(&render_sql(sql, row, column)),
+            None => output.push_str(&format!(
+                "  {}\n  {}{RED}^{RESET}\n",
+                location.snippet,
+                " ".repeat(location.caret)
+            )),
+        }
+        output.push('\n');
+    }
+
+    if let Some(detail) = &error.detail {
+        output.push_str(&format!("Detail: {}\n", detail));
+    }
+    if let Some(hint) = &error.hint {
+        output.push_str(&format!("{YELLOW}Hint: {}{RESET}\n", hint));
+    }
+    for line in &error.after {
+        output.push_str(&format!("{DIM}{}{RESET}\n", line));
+    }
+
+    output
+}
+
+// Needs stderr from a connection with verbose errors, which prints the SQLSTATE on the ERROR line.
+pub fn transient_failure(stderr: &str) -> Option<&'static str> {
+    let code = stderr
+        .lines()
+        .find_map(|line| split_sqlstate(line.strip_prefix("ERROR:")?.trim_start()).0)?;
+    TRANSIENT_FAILURES
+        .iter()
+        .find(|(transient, _)| *transient == code)
+        .map(|(_, title)| *title)
+}
@@ -0,0 +1,102 @@
+use crate::psql::PsqlConnection;
+use crate::quoting::quote;
+use anyhow::Result;
+use serde_json::Value;
+
+pub const LARGE_TABLE_ROWS: f64 = 100_000.0;
+
+pub struct Estimate {
+    pub rows: f64,
+    pub affects_rows: bool,
+    pub total_cost: f64,
+    pub large_seq_scans: Vec<(String, f64)>,
+}
+
+fn seq_scans(plan: &Value, relations: &mut Vec<String>) {
+    if plan["Node Type"] == "Seq Scan" {
+        if let (Some(schema), Some(name)) =
+            (plan["Schema"].as_str(), plan["Relation Name"].as_str())
+        {
+            let relation = format!("{}.{}", schema, name);
+            if !relations.contains(&relation) {
+                relations.push(relation);
+            }
+        }
+    }
+    for child in plan["Plans"].as_array().into_iter().flatten() {
+        seq_scans(child, relations);
+    }
+}
+
+pub fn format_count(n: f64) -> String {
+    if n >= 1_000_000_000.0 {
+        format!("{:.1}B", n / 1_000_000_000.0)
+    } else if n >= 1_000_000.0 {
+        format!("{:.1}M", n / 1_000_000.0)
+    } else if n >= 10_000.0 {
+        format!("{:.0}k", n / 1_000.0)
+    } else {
+        format!("{}", n.max(0.0) as u64)
+    }
+}
+
+pub fn estimate(psql: &PsqlConnection, sql: &str) -> Result<Estimate> {
+    let plan = psql.explain_json(sql, true)?;
+
+    let affects_rows = plan["Node Type"] == "ModifyTable";
+    let rows = if affects_rows {
+        plan["Plans"][0]["Plan Rows"].as_f64()
+    } else {
+        plan["Plan Rows"].as_f64()
+    }
+    .unwrap_or_default();
+
+    let mut relations = Vec::new();
+    seq_scans(&plan, &mut relations);
+    let qualified: Vec<String> = relations
+        .iter()
+        .map(|r| r.split('.').map(quote).collect::<Vec<_>>().join("."))
+        .collect();
+    let table_rows = psql.table_rows(&qualified).unwrap_or_default();
+    let large_seq_scans = relations
+        .into_iter()
+        .filter_map(|relation| {
+            let rows = *table_rows.get(&relation)?;
+            (rows >= LARGE_TABLE_ROWS).then_some((relation, rows))
+        })
+        .collect();
+
+    Ok(Estimate {
+        rows,
+        affects_rows,
+        total_cost: plan["Total Cost"].as_f64().unwrap_or_default(),
+        large_seq_scans,
+    })
+}
+
+impl Estimate {
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Estimate: ~{} {}, cost {:.2}",
+            format_count(self.rows),
+            match (self.affects_rows, self.rows as u64 == 1) {
+                (true, true) => "row affected",
+                (true, false) => "rows affected",
+                (false, true) => "row",
+                (false, false) => "rows",
+            },
+            self.total_cost
+        );
+        if self.large_seq_scans.is_empty() {
+            summary.push_str(", no sequential scans on large tables");
+        }
+        for (relation, rows) in &self.large_seq_scans {
+            summary.push_str(&format!(
+                ", \x1b[33mseq scan on {} (~{} rows)\x1b[0m\x1b[2m",
+                relation,
+                format_count(*rows)
+            ));
+        }
+        summary
+    }
+}
@@ -0,0 +1,264 @@
+use crate::config::{CsvOptions, Encoding, OutputFormat, Quoting};
+use crate::json;
+use crate::report;
+use crate::result::{ResultSet, TableLayout};
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const REDIRECT_EXTENSIONS: &[&str] = &["csv", "tsv", "json", "md", "txt"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    format: Option<OutputFormat>,
+}
+
+impl OutputFile {
+    pub fn create(path: &str) -> Result<Self> {
+        let path = PathBuf::from(path);
+        std::fs::File::create(&path).with_context(|| format!("Failed to open {:?}", path))?;
+        let format = match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Some("csv") => Some(OutputFormat::Csv),
+            Some("json") => Some(OutputFormat::Json),
+            Some("md") => Some(OutputFormat::Markdown),
+            _ => None,
+        };
+        Ok(Self { path, format })
+    }
+
+    pub fn format(&self, default: OutputFormat) -> OutputFormat {
+        match default {
+            OutputFormat::Table => self.format.unwrap_or(default),
+            _ => default,
+        }
+    }
+
+    pub fn append(&self, contents: &str) -> Result<()> {
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .with_context(|| format!("Failed to write {:?}", self.path))
+    }
+}
+
+fn split_args(args: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut in_word = false;
+
+    for c in args.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    words
+}
+
+fn parse_delimiter(value: &str) -> Result<char> {
+    let delimiter = match value {
+        "\\t" | "tab" => '\t',
+        _ => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => anyhow::bail!("Delimiter must be a single character, got '{}'", value),
+            }
+        }
+    };
+    if !delimiter.is_ascii() || delimiter == '"' || delimiter == '\n' || delimiter == '\r' {
+        anyhow::bail!("Unsupported delimiter '{}'", value);
+    }
+    Ok(delimiter)
+}
+
+pub fn parse_options(defaults: &CsvOptions, args: &str) -> Result<(Option<String>, CsvOptions)> {
+    let mut options = defaults.clone();
+    let mut path = None;
+
+    for arg in split_args(args) {
+        let Some(flag) = arg.strip_prefix("--") else {
+            if path.is_some() {
+                anyhow::bail!("Unexpected argument: {}", arg);
+            }
+            path = Some(arg);
+            continue;
+        };
+
+        let (name, value) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (flag, None),
+        };
+        match (name, value) {
+            ("delimiter", Some(value)) => options.delimiter = parse_delimiter(value)?,
+            ("quote", Some(value)) => {
+                options.quoting = match value {
+                    "necessary" => Quoting::Necessary,
+                    "always" => Quoting::Always,
+                    "nonnumeric" => Quoting::NonNumeric,
+                    "never" => Quoting::Never,
+                    _ => anyhow::bail!(
+                        "Unknown quote style '{}' (use necessary, always, nonnumeric or never)",
+                        value
+                    ),
+                }
+            }
+            ("header", None) => options.header = true,
+            ("no-header", None) => options.header = false,
+            ("null", Some(value)) => options.null = value.to_string(),
+            ("encoding", Some(value)) => {
+                options.encoding = match value.to_lowercase().replace('_', "-").as_str() {
+                    "utf-8" | "utf8" => Encoding::Utf8,
+                    "utf-8-bom" | "utf8-bom" => Encoding::Utf8Bom,
+                    "utf-16le" | "utf16le" => Encoding::Utf16le,
+                    "latin1" | "latin-1" | "iso-8859-1" => Encoding::Latin1,
+                    _ => anyhow::bail!(
+                        "Unknown encoding '{}' (use utf-8, utf-8-bom, utf-16le or latin1)",
+                        value
+                    ),
+                }
+            }
+            _ => anyhow::bail!("Unknown option: {}", arg),
+        }
+    }
+
+    Ok((path, options))
+}
+
+fn encode(text: &str, encoding: Encoding) -> Result<Vec<u8>> {
+    Ok(match encoding {
+        Encoding::Utf8 => text.as_bytes().to_vec(),
+        Encoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat(),
+        Encoding::Utf16le => [0xFF, 0xFE]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect(),
+        Encoding::Latin1 => text
+            .chars()
+            .map(|c| {
+                u8::try_from(c as u32)
+                    .ok()
+                    .with_context(|| format!("'{}' cannot be encoded as latin1", c))
+            })
+            .collect::<Result<_>>()?,
+    })
+}
+
+pub fn to_csv(result: &ResultSet, options: &CsvOptions) -> Result<Vec<u8>> {
+    let quote_style = match options.quoting {
+        Quoting::Necessary => csv::QuoteStyle::Necessary,
+        Quoting::Always => csv::QuoteStyle::Always,
+        Quoting::NonNumeric => csv::QuoteStyle::NonNumeric,
+        Quoting::Never => csv::QuoteStyle::Never,
+    };
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(options.delimiter as u8)
+        .quote_style(quote_style)
+        .from_writer(Vec::new());
+
+    if options.header {
+        writer.write_record(&result.columns)?;
+    }
+    for row in &result.rows {
+        writer.write_record(row.iter().map(|v| v.as_deref().unwrap_or(&options.null)))?;
+    }
+
+    let text = String::from_utf8(writer.into_inner()?)?;
+    encode(&text, options.encoding)
+}
+
+pub fn split_redirect(input: &str) -> (&str, Option<Redirect>) {
+    let Some((before, after)) = input.rsplit_once('>') else {
+        return (input, None);
+    };
+    let path = after.trim();
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+    let is_redirect = !path.is_empty()
+        && !path.contains(char::is_whitespace)
+        && !path.starts_with(['=', '>', '\'', '"'])
+        && !before.ends_with(['-', '#', '>', '<'])
+        && !before.trim().is_empty()
+        && extension.is_some_and(|e| REDIRECT_EXTENSIONS.contains(&e.as_str()));
+    if !is_redirect {
+        return (input, None);
+    }
+    (
+        before.trim_end(),
+        Some(Redirect {
+            path: path.to_string(),
+        }),
+    )
+}
+
+pub fn write_redirect(
+    result: &ResultSet,
+    redirect: &Redirect,
+    layout: &TableLayout,
+    defaults: &CsvOptions,
+) -> Result<PathBuf> {
+    let path = Some(redirect.path.as_str());
+    let extension = Path::new(&redirect.path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let format = match extension.as_str() {
+        "csv" | "tsv" => OutputFormat::Csv,
+        "json" => OutputFormat::Json,
+        "md" => OutputFormat::Markdown,
+        _ => layout.format,
+    };
+    match format {
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(&json::to_json(result))?;
+            report::write_json(path, &json)
+        }
+        OutputFormat::Csv if extension == "tsv" => {
+            let options = CsvOptions {
+                delimiter: '\t',
+                ..defaults.clone()
+            };
+            report::write_csv(path, &to_csv(result, &options)?)
+        }
+        OutputFormat::Csv => report::write_csv(path, &to_csv(result, defaults)?),
+        format => report::write(
+            path,
+            &result.render_with(&TableLayout { format, ..*layout }),
+        ),
+    }
+}
@@ -0,0 +1,107 @@
+use crate::psql::PsqlConnection;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_EXEMPLARS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Feedback {
+    pub question: String,
+    pub sql: String,
+    pub good: bool,
+    pub reason: Option<String>,
+    pub timestamp: u64,
+}
+
+impl Feedback {
+    pub fn new(question: String, sql: String, good: bool, reason: Option<String>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            question,
+            sql,
+            good,
+            reason,
+            timestamp,
+        }
+    }
+}
+
+pub struct FeedbackStore {
+    path: PathBuf,
+}
+
+impl FeedbackStore {
+    pub fn for_database(psql: &PsqlConnection) -> Result<Self> {
+        let path = dirs::data_dir()
+            .context("Could not determine data directory")?
+            .join("psqlm")
+            .join("feedback")
+            .join(format!("{}.jsonl", psql.storage_key()));
+        Ok(Self { path })
+    }
+
+    pub fn load(&self) -> Vec<Feedback> {
+        std::fs::read_to_string(&self.path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    pub fn append(&self, feedback: &Feedback) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open feedback file: {:?}", self.path))?;
+        file.write_all(format!("{}\n", serde_json::to_string(feedback)?).as_bytes())?;
+
+        Ok(())
+    }
+}
+
+pub fn to_prompt_string(entries: &[Feedback]) -> String {
+    let mut good: Vec<&Feedback> = Vec::new();
+    let mut bad: Vec<&Feedback> = Vec::new();
+
+    for entry in entries.iter().rev() {
+        let bucket = if entry.good { &mut good } else { &mut bad };
+        if bucket.len() < MAX_EXEMPLARS && !bucket.iter().any(|e| e.question == entry.question) {
+            bucket.push(entry);
+        }
+    }
+
+    let mut output = String::new();
+
+    if !good.is_empty() {
+        output.push_str("Examples of queries the user approved for this database:\n");
+        for entry in good {
+            output.push_str(&format!("Q: {}\nSQL: {}\n\n", entry.question, entry.sql));
+        }
+    }
+
+    if !bad.is_empty() {
+        output.push_str("Queries the user rejected, with their reasons (avoid these mistakes):\n");
+        for entry in bad {
+            let reason = entry.reason.as_deref().unwrap_or("no reason given");
+            output.push_str(&format!(
+                "Q: {}\nSQL: {}\nReason: {}\n\n",
+                entry.question, entry.sql, reason
+            ));
+        }
+    }
+
+    output
+}
@@ -0,0 +1,330 @@
+use crate::psql::PsqlConnection;
+use crate::quoting::quote;
+use crate::result::ResultSet;
+use crate::schema::{Schema, Table};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_ROWS: usize = 20;
+const KEYS_PER_QUERY: usize = 500;
+
+type Row = Vec<Option<String>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Insert,
+    Copy,
+}
+
+pub struct Request {
+    pub table: String,
+    pub rows: usize,
+    pub path: Option<String>,
+    pub format: Format,
+}
+
+pub fn parse_request(args: &str, schema: &Schema) -> Option<Request> {
+    let mut table = None;
+    let mut rows = None;
+    let mut path = None;
+    let mut format = Format::Insert;
+
+    for word in args.split_whitespace() {
+        if word == "--copy" {
+            format = Format::Copy;
+        } else if word.ends_with(".sql") {
+            path = Some(word.to_string());
+        } else if let Ok(n) = word.parse::<usize>() {
+            rows = rows.or(Some(n));
+        } else if table.is_none() {
+            let word = word.trim_matches(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'));
+            table = schema.find_table(word).map(|t| t.name.clone());
+        }
+    }
+
+    Some(Request {
+        table: table?,
+        rows: rows.unwrap_or(DEFAULT_ROWS),
+        path,
+        format,
+    })
+}
+
+struct Sampled {
+    table: String,
+    columns: Vec<String>,
+    batches: Vec<Vec<Row>>,
+    seen: HashSet<Row>,
+}
+
+pub struct Fixture {
+    pub root: String,
+    pub root_rows: usize,
+    sampled: Vec<Sampled>,
+    parents: HashMap<String, Vec<String>>,
+}
+
+fn qualified(name: &str) -> String {
+    name.split('.').map(quote).collect::<Vec<_>>().join(".")
+}
+
+fn literal(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("'{}'", value.replace('\'', "''")),
+        None => "NULL".to_string(),
+    }
+}
+
+fn copy_value(value: &Option<String>) -> String {
+    match value {
+        Some(value) => value
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r"),
+        None => "\\N".to_string(),
+    }
+}
+
+fn fetch(psql: &PsqlConnection, sql: &str) -> Result<ResultSet> {
+    let (success, stdout, stderr) = psql.execute_csv(sql)?;
+    if !success {
+        anyhow::bail!("{}", stderr.trim());
+    }
+    ResultSet::from_csv(&stdout)
+}
+
+fn key_filter(columns: &[String], keys: &[Vec<Option<String>>]) -> String {
+    let columns: Vec<String> = columns.iter().map(|c| quote(c)).collect();
+    let tuples: Vec<String> = keys
+        .iter()
+        .map(|key| {
+            let values: Vec<String> = key.iter().map(literal).collect();
+            if values.len() == 1 {
+                values[0].clone()
+            } else {
+                format!("({})", values.join(", "))
+            }
+        })
+        .collect();
+
+    if columns.len() == 1 {
+        format!("{} IN ({})", columns[0], tuples.join(", "))
+    } else {
+        format!("({}) IN ({})", columns.join(", "), tuples.join(", "))
+    }
+}
+
+impl Fixture {
+    fn add(&mut self, table: &str, result: ResultSet) -> Vec<Row> {
+        let position = self.sampled.iter().position(|s| s.table == table);
+        let mut sampled = match position {
+            Some(i) => self.sampled.remove(i),
+            None => Sampled {
+                table: table.to_string(),
+                columns: result.columns.clone(),
+                batches: Vec::new(),
+                seen: HashSet::new(),
+            },
+        };
+
+        let new: Vec<Row> = result
+            .rows
+            .into_iter()
+            .filter(|row| sampled.seen.insert(row.clone()))
+            .collect();
+        if !new.is_empty() {
+            sampled.batches.push(new.clone());
+        }
+        self.sampled.push(sampled);
+        new
+    }
+
+    fn columns(&self, table: &str) -> &[String] {
+        self.sampled
+            .iter()
+            .find(|s| s.table == table)
+            .map(|s| s.columns.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn referenced(&self) -> (usize, usize) {
+        let total: usize = self.sampled.iter().map(|s| s.seen.len()).sum();
+        let tables = self.sampled.iter().filter(|s| s.table != self.root).count();
+        (total - self.root_rows, tables)
+    }
+
+    // Parents before children, so every row's references exist when it is inserted;
+    // tables in a foreign-key cycle keep the order they were sampled in.
+    fn insertion_order(&self) -> Vec<&Sampled> {
+        let mut remaining: Vec<&Sampled> = self.sampled.iter().rev().collect();
+        let mut ordered = Vec::new();
+        while !remaining.is_empty() {
+            let ready = remaining
+                .iter()
+                .position(|sampled| {
+                    self.parents
+                        .get(&sampled.table)
+                        .into_iter()
+                        .flatten()
+                        .all(|parent| {
+                            *parent == sampled.table
+                                || !remaining.iter().any(|other| other.table == *parent)
+                        })
+                })
+                .unwrap_or_default();
+            ordered.push(remaining.remove(ready));
+        }
+        ordered
+    }
+
+    pub fn to_sql(&self, format: Format) -> String {
+        let mut output = format!(
+            "-- psqlm fixture: {} rows sampled from {}, plus the rows they reference\nBEGIN;\n",
+            self.root_rows, self.root
+        );
+
+        for sampled in self.insertion_order() {
+            let rows: Vec<&Row> = sampled.batches.iter().rev().flatten().collect();
+            if rows.is_empty() {
+                continue;
+            }
+            let columns: Vec<String> = sampled.columns.iter().map(|c| quote(c)).collect();
+            output.push_str(&format!(
+                "\n-- {} ({} {})\n",
+                sampled.table,
+                rows.len(),
+                if rows.len() == 1 { "row" } else { "rows" }
+            ));
+
+            match format {
+                Format::Insert => {
+                    output.push_str(&format!(
+                        "INSERT INTO {} ({}) VALUES\n",
+                        qualified(&sampled.table),
+                        columns.join(", ")
+                    ));
+                    let values: Vec<String> = rows
+                        .iter()
+                        .map(|row| {
+                            let values: Vec<String> = row.iter().map(literal).collect();
+                            format!("  ({})", values.join(", "))
+                        })
+                        .collect();
+                    output.push_str(&values.join(",\n"));
+                    output.push_str(";\n");
+                }
+                Format::Copy => {
+                    output.push_str(&format!(
+                        "COPY {} ({}) FROM stdin;\n",
+                        qualified(&sampled.table),
+                        columns.join(", ")
+                    ));
+                    for row in rows {
+                        let values: Vec<String> = row.iter().map(copy_value).collect();
+                        output.push_str(&values.join("\t"));
+                        output.push('\n');
+                    }
+                    output.push_str("\\.\n");
+                }
+            }
+        }
+
+        output.push_str("\nCOMMIT;\n");
+        output
+    }
+}
+
+pub fn build(
+    psql: &PsqlConnection,
+    schema: &Schema,
+    table: &Table,
+    rows: usize,
+) -> Result<Fixture> {
+    let mut fixture = Fixture {
+        root: table.name.clone(),
+        root_rows: 0,
+        sampled: Vec::new(),
+        parents: HashMap::new(),
+    };
+
+    let sample = fetch(
+        psql,
+        &format!(
+            "SELECT * FROM {} ORDER BY random() LIMIT {}",
+            qualified(&table.name),
+            rows
+        ),
+    )?;
+    let new = fixture.add(&table.name, sample);
+    fixture.root_rows = new.len();
+
+    let mut requested: HashSet<(String, Vec<String>, Row)> = HashSet::new();
+    let mut pending = vec![(table.name.clone(), new)];
+
+    while let Some((name, rows)) = pending.pop() {
+        let Some(table) = schema.find_table(&name) else {
+            continue;
+        };
+        let columns = fixture.columns(&name).to_vec();
+
+        for fk in &table.foreign_keys {
+            let Some(parent) = schema.find_table(&fk.references_table) else {
+                continue;
+            };
+            let Some(indexes) = fk
+                .columns
+                .iter()
+                .map(|c| columns.iter().position(|name| name == c))
+                .collect::<Option<Vec<usize>>>()
+            else {
+                continue;
+            };
+
+            let keys: Vec<Row> = rows
+                .iter()
+                .map(|row| indexes.iter().map(|&i| row[i].clone()).collect::<Row>())
+                .filter(|key| key.iter().all(Option::is_some))
+                .filter(|key| {
+                    requested.insert((
+                        parent.name.clone(),
+                        fk.references_columns.clone(),
+                        key.clone(),
+                    ))
+                })
+                .collect();
+
+            for chunk in keys.chunks(KEYS_PER_QUERY) {
+                let referenced = fetch(
+                    psql,
+                    &format!(
+                        "SELECT * FROM {} WHERE {}",
+                        qualified(&parent.name),
+                        key_filter(&fk.references_columns, chunk)
+                    ),
+                )?;
+                let new = fixture.add(&parent.name, referenced);
+                if !new.is_empty() {
+                    pending.push((parent.name.clone(), new));
+                }
+            }
+        }
+    }
+
+    fixture.parents = fixture
+        .sampled
+        .iter()
+        .filter_map(|sampled| schema.find_table(&sampled.table))
+        .map(|table| {
+            let parents = table
+                .foreign_keys
+                .iter()
+                .filter_map(|fk| schema.find_table(&fk.references_table))
+                .map(|parent| parent.name.clone())
+                .collect();
+            (table.name.clone(), parents)
+        })
+        .collect();
+
+    Ok(fixture)
+}
@@ -0,0 +1,143 @@
+use crate::schema::{Identity, Schema, Table};
+use sqlparser::ast::{AssignmentTarget, Expr, Ident, ObjectName, SetExpr, Statement, TableFactor};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub table: String,
+    pub column: String,
+    kind: &'static str,
+}
+
+impl Violation {
+    pub fn describe(&self) -> String {
+        format!("{}.{} is {}", self.table, self.column, self.kind)
+    }
+}
+
+fn normalize(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+fn is_default(expr: &Expr) -> bool {
+    matches!(expr, Expr::Identifier(ident) if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("default"))
+}
+
+fn violation(table: &Table, column: &str) -> Option<Violation> {
+    let column = table.columns.iter().find(|c| c.name == column)?;
+    let kind = match (&column.generated, column.identity) {
+        (Some(_), _) => "a generated column",
+        (None, Some(Identity::Always)) => "an identity column (GENERATED ALWAYS)",
+        _ => return None,
+    };
+    Some(Violation {
+        table: table.name.clone(),
+        column: column.name.clone(),
+        kind,
+    })
+}
+
+fn find_table<'a>(schema: &'a Schema, name: &ObjectName) -> Option<&'a Table> {
+    let name: Vec<String> = name.0.iter().map(normalize).collect();
+    schema.find_table(&name.join("."))
+}
+
+fn inserted(
+    table: &Table,
+    columns: &[Ident],
+    source: Option<&SetExpr>,
+    violations: &mut Vec<Violation>,
+) {
+    let rows = match source {
+        Some(SetExpr::Values(values)) => Some(&values.rows),
+        _ => None,
+    };
+    let names: Vec<String> = if columns.is_empty() {
+        let width = rows
+            .and_then(|rows| rows.iter().map(Vec::len).max())
+            .unwrap_or(table.columns.len());
+        table
+            .columns
+            .iter()
+            .take(width)
+            .map(|c| c.name.clone())
+            .collect()
+    } else {
+        columns.iter().map(normalize).collect()
+    };
+
+    for (i, name) in names.iter().enumerate() {
+        let all_default =
+            rows.is_some_and(|rows| rows.iter().all(|row| row.get(i).is_none_or(is_default)));
+        if all_default {
+            continue;
+        }
+        if let Some(found) = violation(table, name) {
+            violations.push(found);
+        }
+    }
+}
+
+fn updated(table: &Table, targets: &[(&ObjectName, &Expr)], violations: &mut Vec<Violation>) {
+    for (target, value) in targets {
+        if is_default(value) {
+            continue;
+        }
+        let Some(column) = target.0.last() else {
+            continue;
+        };
+        if let Some(found) = violation(table, &normalize(column)) {
+            violations.push(found);
+        }
+    }
+}
+
+pub fn find(sql: &str, schema: &Schema) -> Vec<Violation> {
+    let dialect = PostgreSqlDialect {};
+    let Ok(statements) = Parser::parse_sql(&dialect, sql) else {
+        return Vec::new();
+    };
+
+    let mut violations = Vec::new();
+    for statement in &statements {
+        match statement {
+            Statement::Insert(insert) => {
+                if let Some(table) = find_table(schema, &insert.table_name) {
+                    inserted(
+                        table,
+                        &insert.columns,
+                        insert.source.as_ref().map(|query| query.body.as_ref()),
+                        &mut violations,
+                    );
+                }
+            }
+            Statement::Update {
+                table, assignments, ..
+            } => {
+                let TableFactor::Table { name, .. } = &table.relation else {
+                    continue;
+                };
+                let Some(table) = find_table(schema, name) else {
+                    continue;
+                };
+                let targets: Vec<(&ObjectName, &Expr)> = assignments
+                    .iter()
+                    .flat_map(|assignment| match &assignment.target {
+                        AssignmentTarget::ColumnName(column) => vec![(column, &assignment.value)],
+                        AssignmentTarget::Tuple(columns) => {
+                            columns.iter().map(|c| (c, &assignment.value)).collect()
+                        }
+                    })
+                    .collect();
+                updated(table, &targets, &mut violations);
+            }
+            _ => {}
+        }
+    }
+    violations.dedup();
+    violations
+}
@@ -0,0 +1,349 @@
+use crate::schema::{Schema, Table};
+use std::collections::{HashMap, VecDeque};
+
+pub const DEFAULT_HOPS: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Mermaid,
+    Dot,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Mermaid => "mmd",
+            Format::Dot => "dot",
+        }
+    }
+}
+
+pub struct Request {
+    pub table: String,
+    pub hops: usize,
+    pub path: Option<String>,
+    pub format: Option<Format>,
+}
+
+pub fn parse_request(args: &str, schema: &Schema) -> Option<Request> {
+    let mut table = None;
+    let mut hops = None;
+    let mut path = None;
+    let mut format = None;
+
+    for word in args.split_whitespace() {
+        if word == "--mermaid" {
+            format = Some(Format::Mermaid);
+        } else if word == "--dot" {
+            format = Some(Format::Dot);
+        } else if word.ends_with(".dot") || word.ends_with(".gv") {
+            path = Some(word.to_string());
+            format = format.or(Some(Format::Dot));
+        } else if word.ends_with(".mmd") || word.ends_with(".md") {
+            path = Some(word.to_string());
+            format = format.or(Some(Format::Mermaid));
+        } else if let Ok(n) = word.parse::<usize>() {
+            hops = hops.or(Some(n));
+        } else if table.is_none() {
+            let word = word.trim_matches(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'));
+            table = schema.find_table(word).map(|t| t.name.clone());
+        }
+    }
+
+    Some(Request {
+        table: table?,
+        hops: hops.unwrap_or(DEFAULT_HOPS),
+        path,
+        format,
+    })
+}
+
+struct Link<'a> {
+    from: &'a Table,
+    columns: &'a [String],
+    to: &'a str,
+    to_columns: &'a [String],
+}
+
+pub struct Graph<'a> {
+    center: &'a str,
+    tables: Vec<&'a Table>,
+    links: Vec<Link<'a>>,
+    parents: HashMap<&'a str, (&'a str, usize)>,
+}
+
+fn display(name: &str) -> &str {
+    name.strip_prefix("public.").unwrap_or(name)
+}
+
+fn links<'a>(schema: &'a Schema) -> Vec<Link<'a>> {
+    schema
+        .tables
+        .iter()
+        .flat_map(|table| {
+            table.foreign_keys.iter().map(move |fk| Link {
+                from: table,
+                columns: &fk.columns,
+                to: &fk.references_table,
+                to_columns: &fk.references_columns,
+            })
+        })
+        .collect()
+}
+
+pub fn build<'a>(schema: &'a Schema, center: &'a Table, hops: usize) -> Graph<'a> {
+    let all = links(schema);
+    let mut depth: HashMap<&str, usize> = HashMap::from([(center.name.as_str(), 0)]);
+    let mut parents = HashMap::new();
+    let mut tables = vec![center];
+    let mut queue = VecDeque::from([center.name.as_str()]);
+
+    while let Some(current) = queue.pop_front() {
+        let level = depth[current];
+        if level >= hops {
+            continue;
+        }
+        for (i, link) in all.iter().enumerate() {
+            let neighbor = if link.from.name == current {
+                link.to
+            } else if link.to == current {
+                link.from.name.as_str()
+            } else {
+                continue;
+            };
+            if depth.contains_key(neighbor) {
+                continue;
+            }
+            let Some(table) = schema.tables.iter().find(|t| t.name == neighbor) else {
+                continue;
+            };
+            depth.insert(neighbor, level + 1);
+            parents.insert(neighbor, (current, i));
+            tables.push(table);
+            queue.push_back(neighbor);
+        }
+    }
+
+    Graph {
+        center: &center.name,
+        tables,
+        links: all,
+        parents,
+    }
+}
+
+fn node_id(name: &str) -> String {
+    display(name)
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn mermaid_type(data_type: &str) -> String {
+    data_type
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "()[]".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn is_nullable(table: &Table, columns: &[String]) -> bool {
+    columns.iter().any(|name| {
+        table
+            .columns
+            .iter()
+            .any(|column| &column.name == name && column.is_nullable)
+    })
+}
+
+fn link_label(link: &Link) -> String {
+    format!(
+        "{} → {}",
+        link.columns.join(", "),
+        link.to_columns.join(", ")
+    )
+}
+
+impl Graph<'_> {
+    pub fn table_count(&self) -> usize {
+        self.tables.len()
+    }
+
+    fn includes(&self, name: &str) -> bool {
+        self.tables.iter().any(|table| table.name == name)
+    }
+
+    fn visible_links(&self) -> impl Iterator<Item = (usize, &Link<'_>)> {
+        self.links
+            .iter()
+            .enumerate()
+            .filter(|(_, link)| self.includes(&link.from.name) && self.includes(link.to))
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let mut output = String::from("erDiagram\n");
+        for (_, link) in self.visible_links() {
+            let cardinality = if is_nullable(link.from, link.columns) {
+                "}o--o|"
+            } else {
+                "}o--||"
+            };
+            output.push_str(&format!(
+                "    {} {} {} : \"{}\"\n",
+                node_id(&link.from.name),
+                cardinality,
+                node_id(link.to),
+                link_label(link).replace('"', "'")
+            ));
+        }
+        for table in &self.tables {
+            output.push_str(&format!("    {} {{\n", node_id(&table.name)));
+            for column in &table.columns {
+                let mut keys = Vec::new();
+                if table
+                    .primary_key
+                    .as_ref()
+                    .is_some_and(|pk| pk.contains(&column.name))
+                {
+                    keys.push("PK");
+                }
+                if table
+                    .foreign_keys
+                    .iter()
+                    .any(|fk| fk.columns.contains(&column.name))
+                {
+                    keys.push("FK");
+                }
+                output.push_str(&format!(
+                    "        {} {}{}\n",
+                    mermaid_type(&column.data_type),
+                    node_id(&column.name),
+                    if keys.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {}", keys.join(", "))
+                    }
+                ));
+            }
+            output.push_str("    }\n");
+        }
+        output
+    }
+
+    pub fn to_dot(&self) -> String {
+        let escape = |text: &str| {
+            text.chars()
+                .flat_map(|c| match c {
+                    '{' | '}' | '|' | '<' | '>' | '"' | '\\' => vec!['\\', c],
+                    c => vec![c],
+                })
+                .collect::<String>()
+        };
+        let mut output = format!("digraph \"{}\" {{\n", escape(display(self.center)));
+        output.push_str("    rankdir=LR;\n    node [shape=record, fontname=\"Helvetica\"];\n");
+        for table in &self.tables {
+            let columns: Vec<String> = table
+                .columns
+                .iter()
+                .map(|column| {
+                    format!(
+                        "{} : {}\\l",
+                        escape(&column.name),
+                        escape(&column.data_type)
+                    )
+                })
+                .collect();
+            let style = if table.name == self.center {
+                ", style=bold"
+            } else {
+                ""
+            };
+            output.push_str(&format!(
+                "    \"{}\" [label=\"{{{}|{}}}\"{}];\n",
+                display(&table.name),
+                escape(display(&table.name)),
+                columns.join(""),
+                style
+            ));
+        }
+        for (_, link) in self.visible_links() {
+            output.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                display(&link.from.name),
+                display(link.to),
+                link_label(link).replace('"', "\\\"")
+            ));
+        }
+        output.push_str("}\n");
+        output
+    }
+
+    fn tree_line(&self, table: &str) -> String {
+        let Some((parent, i)) = self.parents.get(table) else {
+            return display(table).to_string();
+        };
+        let link = &self.links[*i];
+        let arrow = if link.from.name == *parent {
+            "→"
+        } else {
+            "←"
+        };
+        format!(
+            "{} {} via {}",
+            arrow,
+            display(table),
+            link.columns.join(", ")
+        )
+    }
+
+    fn render_children(&self, table: &str, prefix: &str, output: &mut String) {
+        let children: Vec<&str> = self
+            .tables
+            .iter()
+            .map(|t| t.name.as_str())
+            .filter(|name| self.parents.get(name).map(|(p, _)| *p) == Some(table))
+            .collect();
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            output.push_str(&format!(
+                "{}{}{}\n",
+                prefix,
+                if last { "└── " } else { "├── " },
+                self.tree_line(child)
+            ));
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            self.render_children(child, &prefix, output);
+        }
+    }
+
+    pub fn to_ascii(&self) -> String {
+        let mut output = format!("{}\n", display(self.center));
+        self.render_children(self.center, "", &mut output);
+
+        let extra: Vec<String> = self
+            .visible_links()
+            .filter(|(i, _)| !self.parents.values().any(|(_, tree)| tree == i))
+            .map(|(_, link)| {
+                format!(
+                    "  {}.{} → {}",
+                    display(&link.from.name),
+                    link.columns.join(", "),
+                    display(link.to)
+                )
+            })
+            .collect();
+        if !extra.is_empty() {
+            output.push_str("\nAlso linked:\n");
+            for line in extra {
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+        output
+    }
+}
@@ -0,0 +1,125 @@
+use crate::psql::PsqlConnection;
+use crate::result::ResultSet;
+use anyhow::Result;
+
+const CHECKS: &[(&str, &str)] = &[
+    (
+        "Unused indexes",
+        r#"
+        SELECT
+            s.schemaname || '.' || s.relname AS table,
+            s.indexrelname AS index,
+            pg_size_pretty(pg_relation_size(s.indexrelid)) AS size
+        FROM pg_stat_user_indexes s
+        JOIN pg_index i USING (indexrelid)
+        WHERE s.idx_scan = 0
+          AND NOT i.indisunique
+          AND NOT i.indisprimary
+          AND i.indisvalid
+        ORDER BY pg_relation_size(s.indexrelid) DESC, 1, 2
+        LIMIT 20
+    "#,
+    ),
+    (
+        "Invalid indexes",
+        r#"
+        SELECT
+            n.nspname || '.' || t.relname AS table,
+            c.relname AS index,
+            pg_size_pretty(pg_relation_size(c.oid)) AS size
+        FROM pg_index i
+        JOIN pg_class c ON c.oid = i.indexrelid
+        JOIN pg_class t ON t.oid = i.indrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE NOT i.indisvalid
+          AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+        ORDER BY 1, 2
+    "#,
+    ),
+    (
+        "Tables never vacuumed",
+        r#"
+        SELECT
+            schemaname || '.' || relname AS table,
+            n_live_tup AS live_rows,
+            n_dead_tup AS dead_rows,
+            date_trunc('second', greatest(last_analyze, last_autoanalyze))::text AS last_analyzed
+        FROM pg_stat_user_tables
+        WHERE last_vacuum IS NULL AND last_autovacuum IS NULL
+        ORDER BY n_dead_tup DESC, 1
+        LIMIT 20
+    "#,
+    ),
+    (
+        "Bloated tables (over 20% dead rows)",
+        r#"
+        SELECT
+            schemaname || '.' || relname AS table,
+            n_live_tup AS live_rows,
+            n_dead_tup AS dead_rows,
+            round(100.0 * n_dead_tup / (n_live_tup + n_dead_tup), 1)::text || '%' AS dead,
+            date_trunc('second', greatest(last_vacuum, last_autovacuum))::text AS last_vacuumed
+        FROM pg_stat_user_tables
+        WHERE n_dead_tup >= 1000
+          AND n_dead_tup > 0.2 * (n_live_tup + n_dead_tup)
+        ORDER BY n_dead_tup DESC, 1
+        LIMIT 20
+    "#,
+    ),
+    (
+        "Dead columns (always NULL in the sampled statistics)",
+        r#"
+        SELECT
+            schemaname || '.' || tablename AS table,
+            attname AS column
+        FROM pg_stats
+        WHERE null_frac = 1
+          AND NOT inherited
+          AND schemaname NOT IN ('pg_catalog', 'information_schema')
+        ORDER BY 1, 2
+        LIMIT 50
+    "#,
+    ),
+];
+
+pub struct Section {
+    pub title: &'static str,
+    pub result: ResultSet,
+}
+
+pub fn report(psql: &PsqlConnection) -> Result<Vec<Section>> {
+    CHECKS
+        .iter()
+        .map(|(title, sql)| {
+            let (success, stdout, stderr) = psql.execute_csv(sql)?;
+            if !success {
+                anyhow::bail!("{} check failed: {}", title, stderr.trim());
+            }
+            Ok(Section {
+                title,
+                result: ResultSet::from_csv(&stdout)?,
+            })
+        })
+        .collect()
+}
+
+pub fn is_healthy(sections: &[Section]) -> bool {
+    sections
+        .iter()
+        .all(|section| section.result.rows.is_empty())
+}
+
+pub fn to_prompt_string(sections: &[Section]) -> String {
+    sections
+        .iter()
+        .map(|section| {
+            let body = if section.result.rows.is_empty() {
+                "None found.\n".to_string()
+            } else {
+                section.result.to_markdown(section.result.rows.len())
+            };
+            format!("## {}\n{}", section.title, body)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
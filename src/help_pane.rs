@@ -0,0 +1,243 @@
+use crate::commands::COMMANDS;
+use crate::config::ConfirmKeys;
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use ratatui::Terminal;
+use std::io;
+
+struct Topic {
+    section: &'static str,
+    title: String,
+    summary: String,
+    details: String,
+}
+
+impl Topic {
+    fn new(section: &'static str, title: &str, summary: &str, details: &str) -> Self {
+        Self {
+            section,
+            title: title.to_string(),
+            summary: summary.to_string(),
+            details: details.to_string(),
+        }
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        [&self.title, &self.summary, &self.details]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+}
+
+fn topics(keys: &ConfirmKeys) -> Vec<Topic> {
+    let mut topics: Vec<Topic> = COMMANDS
+        .iter()
+        .map(|c| Topic::new("Commands", c.usage, c.summary, c.details))
+        .collect();
+
+    topics.push(Topic::new(
+        "Prefixes",
+        "count: <question>",
+        "generate and auto-run a COUNT query",
+        "Produces a single-row COUNT query that runs immediately when it is read-only, whatever the execution mode.",
+    ));
+    topics.push(Topic::new(
+        "Prefixes",
+        "exact: <question>",
+        "generate with temperature 0",
+        "Asks the model for its most deterministic answer for this question only.",
+    ));
+    topics.push(Topic::new(
+        "Prefixes",
+        "verbose: <question>",
+        "explain the SQL before running it",
+        "Prints a plain-language explanation of the generated SQL, as \\why would, before it runs.",
+    ));
+    topics.push(Topic::new(
+        "Prefixes",
+        "fast: <question>",
+        "use the cheaper fast_model",
+        "Sends this question to fast_model (the provider's cheaper default unless set in config.toml). Prefixes combine, as in fast: verbose: count: orders today.",
+    ));
+    topics.push(Topic::new(
+        "Prefixes",
+        "<sql>",
+        "run SQL directly",
+        "Input that parses as SQL runs as-is; :name placeholders are prompted for and bound as literals.",
+    ));
+
+    topics.push(Topic::new(
+        "Modes",
+        "auto / confirm / show",
+        "execution mode, set with \\mode",
+        "auto runs generated SQL immediately, confirm asks first, show only displays it. Writes are always previewed in a rolled-back transaction before committing.",
+    ));
+    topics.push(Topic::new(
+        "Modes",
+        "live / quiet",
+        "stream display, set with \\stream",
+        "live echoes tokens as they arrive, quiet shows a spinner and then the final SQL.",
+    ));
+
+    topics.push(Topic::new(
+        "Keybindings",
+        "Confirm menu",
+        "choose what to do with generated SQL",
+        &format!(
+            "{} run, {} edit SQL, {} edit prompt, {} switch to auto mode, {} more actions (copy, explain, plan, save as template, export), {} cancel. Rebind under [confirm_keys] in config.toml.",
+            keys.run, keys.edit_sql, keys.edit_prompt, keys.auto_run, keys.more, keys.cancel
+        ),
+    ));
+    topics.push(Topic::new(
+        "Keybindings",
+        "Menus",
+        "pick an option",
+        "Up/Down (or k/j) move, Enter selects, Esc cancels.",
+    ));
+    topics.push(Topic::new(
+        "Keybindings",
+        "SQL editor",
+        "edit SQL before running it",
+        "Ctrl+S saves, Esc cancels, arrow keys move, Enter inserts a new line.",
+    ));
+    topics.push(Topic::new(
+        "Keybindings",
+        "Prompt",
+        "the psqlm> line",
+        "Up/Down recall history, Ctrl+C clears the line, Ctrl+D quits.",
+    ));
+
+    topics
+}
+
+fn render_lines(topics: &[Topic], query: &str) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut section = "";
+
+    for topic in topics.iter().filter(|t| t.matches(query)) {
+        if topic.section != section {
+            if !section.is_empty() {
+                lines.push(Line::default());
+            }
+            section = topic.section;
+            lines.push(Line::styled(
+                section,
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("  {}", topic.title),
+                Style::default().fg(Color::Green),
+            ),
+            Span::raw(format!("  {}", topic.summary)),
+        ]));
+        for detail in topic.details.lines() {
+            lines.push(Line::styled(
+                format!("      {}", detail.trim()),
+                Style::default().add_modifier(Modifier::DIM),
+            ));
+        }
+    }
+
+    if lines.is_empty() {
+        lines.push(Line::raw(format!("No help topics match '{}'.", query)));
+    }
+    lines
+}
+
+pub fn show(keys: &ConfirmKeys) -> Result<()> {
+    let topics = topics(keys);
+
+    terminal::enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut query = String::new();
+    let mut scroll: u16 = 0;
+
+    loop {
+        let lines = render_lines(&topics, &query);
+
+        let mut page = 0;
+        let mut line_count = 0;
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .constraints([
+                    Constraint::Length(3),
+                    Constraint::Min(3),
+                    Constraint::Length(1),
+                ])
+                .split(f.area());
+            page = chunks[1].height.saturating_sub(2);
+            let width = chunks[1].width.saturating_sub(2).max(1) as usize;
+            line_count = lines
+                .iter()
+                .map(|line| line.width().max(1).div_ceil(width) as u16)
+                .sum();
+
+            f.render_widget(
+                Paragraph::new(query.as_str())
+                    .block(Block::default().borders(Borders::ALL).title(" Search ")),
+                chunks[0],
+            );
+            f.render_widget(
+                Paragraph::new(lines)
+                    .wrap(Wrap { trim: false })
+                    .scroll((scroll, 0))
+                    .block(Block::default().borders(Borders::ALL).title(" psqlm help ")),
+                chunks[1],
+            );
+            f.render_widget(
+                Paragraph::new(
+                    "Type to search | Up/Down/PgUp/PgDn: Scroll | Esc: Clear search or close",
+                ),
+                chunks[2],
+            );
+        })?;
+
+        let max_scroll = line_count.saturating_sub(page);
+        if let Event::Key(key) = event::read()? {
+            match (key.code, key.modifiers) {
+                (KeyCode::Esc, _) if !query.is_empty() => {
+                    query.clear();
+                    scroll = 0;
+                }
+                (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => break,
+                (KeyCode::Up, _) => scroll = scroll.saturating_sub(1),
+                (KeyCode::Down, _) => scroll = (scroll + 1).min(max_scroll),
+                (KeyCode::PageUp, _) => scroll = scroll.saturating_sub(page),
+                (KeyCode::PageDown, _) => scroll = (scroll + page).min(max_scroll),
+                (KeyCode::Home, _) => scroll = 0,
+                (KeyCode::End, _) => scroll = max_scroll,
+                (KeyCode::Backspace, _) => {
+                    query.pop();
+                    scroll = 0;
+                }
+                (KeyCode::Char(c), _) => {
+                    query.push(c);
+                    scroll = 0;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    terminal::disable_raw_mode()?;
+    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+
+    Ok(())
+}
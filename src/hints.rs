@@ -0,0 +1,36 @@
+const DETERMINISTIC: &str = "exact:";
+const VERBOSE: &str = "verbose:";
+const FAST: &str = "fast:";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Hints {
+    pub deterministic: bool,
+    pub verbose: bool,
+    pub fast: bool,
+}
+
+fn strip_prefix<'a>(question: &'a str, prefix: &str) -> Option<&'a str> {
+    question
+        .get(..prefix.len())
+        .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+        .then(|| question[prefix.len()..].trim_start())
+}
+
+pub fn parse(question: &str) -> (Hints, &str) {
+    let mut hints = Hints::default();
+    let mut rest = question.trim_start();
+    loop {
+        if let Some(stripped) = strip_prefix(rest, DETERMINISTIC) {
+            hints.deterministic = true;
+            rest = stripped;
+        } else if let Some(stripped) = strip_prefix(rest, VERBOSE) {
+            hints.verbose = true;
+            rest = stripped;
+        } else if let Some(stripped) = strip_prefix(rest, FAST) {
+            hints.fast = true;
+            rest = stripped;
+        } else {
+            return (hints, rest);
+        }
+    }
+}
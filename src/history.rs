@@ -0,0 +1,150 @@
+use crate::schema::Schema;
+use anyhow::{Context, Result};
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::Helper;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+const MIN_WORD_LENGTH: usize = 3;
+
+pub fn psql_history_path() -> Option<PathBuf> {
+    std::env::var_os("PSQL_HISTORY")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".psql_history")))
+}
+
+fn decode_octal(line: &str) -> String {
+    let mut bytes = Vec::with_capacity(line.len());
+    let raw = line.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'\\'
+            && i + 3 < raw.len()
+            && raw[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b))
+        {
+            let code = raw[i + 1..i + 4]
+                .iter()
+                .fold(0u32, |n, b| n * 8 + u32::from(b - b'0'));
+            bytes.push(code as u8);
+            i += 4;
+        } else {
+            bytes.push(raw[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+pub fn read_psql_history(path: &Path) -> Result<Vec<String>> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let contents = String::from_utf8_lossy(&contents);
+
+    let mut lines = contents.lines().peekable();
+    let libedit = lines.peek() == Some(&"_HiStOrY_V2_");
+    if libedit {
+        lines.next();
+    }
+
+    let mut entries: Vec<String> = Vec::new();
+    for line in lines {
+        let line = if libedit {
+            decode_octal(line)
+        } else {
+            line.to_string()
+        };
+        let entry = line
+            .split('\x01')
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with("--"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if entry.is_empty() || entry.starts_with('\\') {
+            continue;
+        }
+        entries.retain(|e| *e != entry);
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+pub struct Vocabulary {
+    words: BTreeSet<String>,
+}
+
+impl Vocabulary {
+    pub fn new(schema: &Schema) -> Self {
+        let mut vocabulary = Self {
+            words: BTreeSet::new(),
+        };
+        vocabulary.learn_schema(schema);
+        vocabulary
+    }
+
+    fn learn_schema(&mut self, schema: &Schema) {
+        for table in &schema.tables {
+            self.words.insert(table.name.clone());
+            if let Some((_, name)) = table.name.split_once('.') {
+                self.words.insert(name.to_string());
+            }
+            for column in &table.columns {
+                self.words.insert(column.name.clone());
+            }
+        }
+    }
+
+    pub fn learn(&mut self, text: &str) {
+        for word in text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')) {
+            let word = word.trim_matches('.');
+            if word.chars().count() >= MIN_WORD_LENGTH
+                && !word.starts_with(|c: char| c.is_ascii_digit())
+            {
+                self.words.insert(word.to_string());
+            }
+        }
+    }
+}
+
+impl Completer for Vocabulary {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || *c == '.'))
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+        let prefix = line[start..pos].to_lowercase();
+        if prefix.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+
+        let candidates = self
+            .words
+            .iter()
+            .filter(|word| word.len() > prefix.len() && word.to_lowercase().starts_with(&prefix))
+            .cloned()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Vocabulary {
+    type Hint = String;
+}
+
+impl Highlighter for Vocabulary {}
+
+impl Validator for Vocabulary {}
+
+impl Helper for Vocabulary {}
@@ -0,0 +1,177 @@
+use crate::config::Hooks;
+use crate::psql::PsqlConnection;
+use anyhow::{Context, Result};
+use libloading::Library;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+type PreExecute = unsafe extern "C" fn(*const c_char, *mut *mut c_char) -> c_int;
+type PostExecute = unsafe extern "C" fn(*const c_char, *const c_char);
+type Free = unsafe extern "C" fn(*mut c_char);
+
+pub enum Verdict {
+    Run(String),
+    Veto(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Status {
+    Success,
+    Error,
+}
+
+impl Status {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Success => "success",
+            Status::Error => "error",
+        }
+    }
+}
+
+pub fn has_pre(hooks: &Hooks) -> bool {
+    hooks.pre_execute.is_some() || hooks.library.is_some()
+}
+
+pub fn has_post(hooks: &Hooks) -> bool {
+    hooks.post_execute.is_some() || hooks.library.is_some()
+}
+
+pub fn load_library(path: &str) -> Result<Library> {
+    unsafe { Library::new(path) }.with_context(|| format!("Failed to load hook library {}", path))
+}
+
+fn shell(
+    command: &str,
+    psql: &PsqlConnection,
+    question: &str,
+    sql: &str,
+    env: &[(&str, &str)],
+) -> Result<(bool, String, String)> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .env("PSQLM_HOST", &psql.host)
+        .env("PSQLM_PORT", &psql.port)
+        .env("PSQLM_USER", &psql.user)
+        .env("PSQLM_DATABASE", &psql.database)
+        .env("PSQLM_QUESTION", question)
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run hook: {}", command))?;
+
+    // written from a thread so a hook that fills its stdout before reading stdin cannot deadlock
+    let writer = child.stdin.take().map(|mut stdin| {
+        let sql = sql.to_string();
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(sql.as_bytes());
+        })
+    });
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run hook: {}", command))?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    Ok((output.status.success(), stdout, stderr))
+}
+
+fn library_pre_execute(library: &Library, sql: &str) -> Result<Verdict> {
+    let Ok(pre_execute) = (unsafe { library.get::<PreExecute>(b"psqlm_pre_execute") }) else {
+        return Ok(Verdict::Run(sql.to_string()));
+    };
+
+    let input = CString::new(sql).context("SQL contains a NUL byte")?;
+    let mut out: *mut c_char = std::ptr::null_mut();
+    let code = unsafe { pre_execute(input.as_ptr(), &mut out) };
+
+    let message = if out.is_null() {
+        None
+    } else {
+        let text = unsafe { CStr::from_ptr(out) }.to_string_lossy().to_string();
+        if let Ok(free) = unsafe { library.get::<Free>(b"psqlm_free") } {
+            unsafe { free(out) };
+        }
+        Some(text)
+    };
+
+    Ok(match code {
+        0 => Verdict::Run(message.unwrap_or_else(|| sql.to_string())),
+        _ => Verdict::Veto(message.unwrap_or_else(|| "rejected by the hook library".to_string())),
+    })
+}
+
+fn library_post_execute(library: &Library, sql: &str, status: Status) -> Result<()> {
+    let Ok(post_execute) = (unsafe { library.get::<PostExecute>(b"psqlm_post_execute") }) else {
+        return Ok(());
+    };
+
+    let sql = CString::new(sql).context("SQL contains a NUL byte")?;
+    let status = CString::new(status.as_str())?;
+    unsafe { post_execute(sql.as_ptr(), status.as_ptr()) };
+    Ok(())
+}
+
+pub fn pre_execute(
+    hooks: &Hooks,
+    psql: &PsqlConnection,
+    question: &str,
+    sql: &str,
+) -> Result<Verdict> {
+    let mut sql = sql.to_string();
+
+    if let Some(library) = &hooks.loaded {
+        match library_pre_execute(library, &sql)? {
+            Verdict::Run(rewritten) => sql = rewritten,
+            veto => return Ok(veto),
+        }
+    }
+
+    if let Some(command) = &hooks.pre_execute {
+        let (success, stdout, stderr) = shell(command, psql, question, &sql, &[])?;
+        if !success {
+            let reason = [stderr.trim(), stdout.trim()]
+                .into_iter()
+                .find(|r| !r.is_empty())
+                .unwrap_or("the pre-execution hook exited with an error");
+            return Ok(Verdict::Veto(reason.to_string()));
+        }
+        if !stdout.trim().is_empty() {
+            sql = stdout.trim().to_string();
+        }
+    }
+
+    Ok(Verdict::Run(sql))
+}
+
+pub fn post_execute(
+    hooks: &Hooks,
+    psql: &PsqlConnection,
+    question: &str,
+    sql: &str,
+    status: Status,
+    error: &str,
+) -> Result<()> {
+    if let Some(library) = &hooks.loaded {
+        library_post_execute(library, sql, status)?;
+    }
+
+    if let Some(command) = &hooks.post_execute {
+        let env = [
+            ("PSQLM_STATUS", status.as_str()),
+            ("PSQLM_ERROR", error.trim()),
+        ];
+        let (success, _, stderr) = shell(command, psql, question, sql, &env)?;
+        if !success {
+            anyhow::bail!("post-execution hook failed: {}", stderr.trim());
+        }
+    }
+
+    Ok(())
+}
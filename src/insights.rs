@@ -0,0 +1,240 @@
+use crate::psql::PsqlConnection;
+use crate::schema::Schema;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::visit_relations;
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TOP_TABLES: usize = 10;
+const TOP_QUESTIONS: usize = 5;
+const REPEAT_THRESHOLD: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attempt {
+    pub question: String,
+    pub sql: String,
+    pub success: bool,
+    pub timestamp: u64,
+}
+
+impl Attempt {
+    pub fn new(question: &str, sql: &str, success: bool) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            question: question.to_string(),
+            sql: sql.to_string(),
+            success,
+            timestamp,
+        }
+    }
+}
+
+pub struct QuestionLog {
+    path: PathBuf,
+}
+
+impl QuestionLog {
+    pub fn for_database(psql: &PsqlConnection) -> Result<Self> {
+        let path = dirs::data_dir()
+            .context("Could not determine data directory")?
+            .join("psqlm")
+            .join("questions")
+            .join(format!("{}.jsonl", psql.storage_key()));
+        Ok(Self { path })
+    }
+
+    pub fn load(&self) -> Vec<Attempt> {
+        std::fs::read_to_string(&self.path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    pub fn append(&self, attempt: &Attempt) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open question log: {:?}", self.path))?;
+        file.write_all(format!("{}\n", serde_json::to_string(attempt)?).as_bytes())?;
+
+        Ok(())
+    }
+}
+
+struct Asked<'a> {
+    question: &'a str,
+    attempts: Vec<&'a Attempt>,
+}
+
+impl Asked<'_> {
+    fn needed_fix(&self) -> bool {
+        self.attempts.len() > 1
+    }
+
+    fn succeeded(&self) -> bool {
+        self.attempts.iter().any(|a| a.success)
+    }
+
+    fn final_sql(&self) -> &str {
+        let last = self
+            .attempts
+            .iter()
+            .rev()
+            .find(|a| a.success)
+            .unwrap_or(&self.attempts[self.attempts.len() - 1]);
+        &last.sql
+    }
+}
+
+fn normalize(question: &str) -> String {
+    question
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['?', '.', '!', ';'])
+        .to_lowercase()
+}
+
+fn group(attempts: &[Attempt]) -> Vec<Asked<'_>> {
+    let mut asked: Vec<Asked> = Vec::new();
+    for attempt in attempts {
+        match asked.last_mut() {
+            Some(last) if last.question == attempt.question && !last.succeeded() => {
+                last.attempts.push(attempt)
+            }
+            _ => asked.push(Asked {
+                question: &attempt.question,
+                attempts: vec![attempt],
+            }),
+        }
+    }
+    asked
+}
+
+fn tables(sql: &str, schema: &Schema) -> Vec<String> {
+    let dialect = PostgreSqlDialect {};
+    let Ok(statements) = Parser::parse_sql(&dialect, sql) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = Vec::new();
+    let _ = visit_relations(&statements, |relation| {
+        let name = relation.to_string().replace('"', "");
+        let known = schema
+            .tables
+            .iter()
+            .map(|t| &t.name)
+            .chain(schema.views.iter().map(|v| &v.name))
+            .chain(schema.materialized_views.iter().map(|v| &v.name))
+            .find(|known| {
+                known.eq_ignore_ascii_case(&name)
+                    || known.rsplit('.').next() == Some(name.to_lowercase().as_str())
+            });
+        if let Some(known) = known.filter(|known| !names.contains(known)) {
+            names.push(known.clone());
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    names
+}
+
+fn ranked(counts: HashMap<String, usize>, limit: usize) -> Vec<(String, usize)> {
+    let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(limit);
+    ranked
+}
+
+fn percent(part: usize, whole: usize) -> usize {
+    (part * 100 + whole / 2) / whole.max(1)
+}
+
+pub fn render(attempts: &[Attempt], schema: &Schema) -> String {
+    let asked = group(attempts);
+    if asked.is_empty() {
+        return "No questions recorded for this database yet.\n".to_string();
+    }
+
+    let mut table_counts: HashMap<String, usize> = HashMap::new();
+    let mut fixed_tables: HashMap<String, usize> = HashMap::new();
+    let mut repeats: HashMap<String, (usize, &str)> = HashMap::new();
+    for question in &asked {
+        for table in tables(question.final_sql(), schema) {
+            if question.needed_fix() {
+                *fixed_tables.entry(table.clone()).or_default() += 1;
+            }
+            *table_counts.entry(table).or_default() += 1;
+        }
+        let entry = repeats
+            .entry(normalize(question.question))
+            .or_insert((0, question.question));
+        entry.0 += 1;
+    }
+
+    let mut output = format!(
+        "{} questions, {} statements run\n",
+        asked.len(),
+        attempts.len()
+    );
+
+    let top = ranked(table_counts, TOP_TABLES);
+    if !top.is_empty() {
+        output.push_str("\nMost-queried tables:\n");
+        for (table, count) in top {
+            output.push_str(&format!("  {:>5}  {}\n", count, table));
+        }
+    }
+
+    let mut repeated: Vec<(usize, &str)> = repeats
+        .into_values()
+        .filter(|(count, _)| *count >= REPEAT_THRESHOLD)
+        .collect();
+    repeated.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    repeated.truncate(TOP_QUESTIONS);
+    if !repeated.is_empty() {
+        output.push_str("\nAsked again and again (worth an \\alias or a [templates] entry):\n");
+        for (count, question) in repeated {
+            output.push_str(&format!("  {:>5}x {}\n", count, question));
+        }
+    }
+
+    let fixed = asked.iter().filter(|q| q.needed_fix()).count();
+    let failed = asked.iter().filter(|q| !q.succeeded()).count();
+    output.push_str(&format!(
+        "\nFix loops: {} of {} questions ({}%) failed on the first SQL and were run again",
+        fixed,
+        asked.len(),
+        percent(fixed, asked.len())
+    ));
+    if failed > 0 {
+        output.push_str(&format!("; {} never succeeded", failed));
+    }
+    output.push('\n');
+
+    let troubled = ranked(fixed_tables, TOP_QUESTIONS);
+    if !troubled.is_empty() {
+        output.push_str("Tables most often involved (comments on them may help the model):\n");
+        for (table, count) in troubled {
+            output.push_str(&format!("  {:>5}  {}\n", count, table));
+        }
+    }
+
+    output
+}
@@ -0,0 +1,363 @@
+use crate::claude::Client as ClaudeClient;
+use crate::config::StreamDisplay;
+use crate::llm::Anthropic;
+use crate::preview::update_diff;
+use crate::psql::PsqlConnection;
+use crate::result::ResultSet;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use testcontainers_modules::postgres::Postgres;
+use testcontainers_modules::testcontainers::runners::AsyncRunner;
+use testcontainers_modules::testcontainers::ContainerAsync;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const FIXTURE: &str = include_str!("../tests/fixtures/shop.sql");
+
+struct TestDatabase {
+    _container: ContainerAsync<Postgres>,
+    psql: PsqlConnection,
+}
+
+async fn start_database() -> TestDatabase {
+    let container = Postgres::default()
+        .with_init_sql(FIXTURE.to_string().into_bytes())
+        .start()
+        .await
+        .expect("failed to start postgres container");
+
+    let host = container.get_host().await.unwrap().to_string();
+    let port = container
+        .get_host_port_ipv4(5432)
+        .await
+        .unwrap()
+        .to_string();
+    let psql = PsqlConnection::new(
+        host,
+        port,
+        "postgres".to_string(),
+        "postgres".to_string(),
+        Some("postgres".to_string()),
+    );
+
+    TestDatabase {
+        _container: container,
+        psql,
+    }
+}
+
+fn query_value(psql: &PsqlConnection, sql: &str) -> String {
+    psql.query(sql).unwrap().trim().to_string()
+}
+
+struct MockLlm {
+    url: String,
+    requests: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockLlm {
+    async fn start(responses: &[&str]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/messages", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let mut responses: VecDeque<String> = responses.iter().map(|r| r.to_string()).collect();
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let body = read_request_body(&mut stream).await;
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(serde_json::from_slice(&body).unwrap_or_default());
+
+                let text = responses.pop_front().unwrap_or_default();
+                let _ = stream.write_all(sse_response(&text).as_bytes()).await;
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    fn client(&self, strict: bool) -> ClaudeClient {
+        let provider = Arc::new(Anthropic::new("test-key", Some(&self.url)));
+        ClaudeClient::new(provider, StreamDisplay::Live, strict)
+    }
+
+    fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn read_request_body(stream: &mut TcpStream) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let n = stream.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            return Vec::new();
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    };
+
+    let headers = String::from_utf8_lossy(&buffer[..header_end]).to_lowercase();
+    let content_length: usize = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|len| len.trim().parse().ok())
+        .unwrap_or(0);
+
+    while buffer.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+
+    buffer[header_end..].to_vec()
+}
+
+fn sse_response(text: &str) -> String {
+    let mut body = String::new();
+    let (first, second) = text.split_at(text.len() / 2);
+    for delta in [first, second] {
+        let event = json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": { "type": "text_delta", "text": delta },
+        });
+        body.push_str(&format!("event: content_block_delta\ndata: {}\n\n", event));
+    }
+    body.push_str("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n");
+
+    format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+fn last_user_message(request: &Value) -> String {
+    request["messages"]
+        .as_array()
+        .and_then(|messages| messages.last())
+        .and_then(|message| message["content"].as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[tokio::test]
+async fn introspects_fixture_schema() {
+    let db = start_database().await;
+    let schema = db.psql.introspect_schema().unwrap();
+
+    let customers = schema.find_table("customers").unwrap();
+    assert_eq!(customers.primary_key, Some(vec!["id".to_string()]));
+    let columns: Vec<&str> = customers.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(columns, ["id", "name", "tier", "balance", "note"]);
+    assert!(customers.columns[4].is_nullable);
+
+    let orders = schema.find_table("orders").unwrap();
+    assert_eq!(orders.foreign_keys.len(), 1);
+    assert_eq!(orders.foreign_keys[0].columns, ["customer_id"]);
+    assert!(orders
+        .indexes
+        .iter()
+        .any(|i| i.name == "orders_customer_id_idx"));
+
+    let totals = schema.find_materialized_view("customer_totals").unwrap();
+    assert!(totals.is_populated);
+    assert!(totals.has_unique_index);
+    assert!(schema
+        .to_prompt_string()
+        .contains("Materialized View: public.customer_totals"));
+
+    assert!(schema.find_table("gold_customers").is_none());
+    let gold = schema
+        .views
+        .iter()
+        .find(|v| v.name == "public.gold_customers")
+        .unwrap();
+    let columns: Vec<&str> = gold.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(columns, ["id", "name", "balance"]);
+    assert!(gold.definition.as_deref().unwrap().contains("'gold'"));
+
+    let prompt = schema.to_prompt_string();
+    assert!(prompt.contains("shipping_speed: 'standard', 'express', 'same day'"));
+    assert!(prompt.contains("address (street text, city text)"));
+}
+
+#[tokio::test]
+async fn update_preview_is_rolled_back() {
+    let db = start_database().await;
+    let schema = db.psql.introspect_schema().unwrap();
+
+    let diff = update_diff(
+        &db.psql,
+        &schema,
+        "UPDATE customers SET tier = 'gold' WHERE id = 1",
+    )
+    .unwrap();
+    assert!(diff.contains("Row id=1:"));
+    assert!(diff.contains("tier"));
+    assert!(diff.contains("gold"));
+
+    let diff = update_diff(
+        &db.psql,
+        &schema,
+        "UPDATE customers SET tier = 'gold' WHERE id = 42",
+    )
+    .unwrap();
+    assert!(diff.contains("(no rows matched)"));
+
+    assert_eq!(
+        query_value(&db.psql, "SELECT tier FROM customers WHERE id = 1"),
+        "silver"
+    );
+}
+
+#[tokio::test]
+async fn writes_commit_or_roll_back() {
+    let db = start_database().await;
+    let sql = "DELETE FROM orders WHERE customer_id = 1";
+
+    let (success, stdout, _) = db.psql.preview_write_with_returning(sql, "*").unwrap();
+    assert!(success);
+    assert!(stdout.contains("(2 rows)"));
+    assert_eq!(query_value(&db.psql, "SELECT count(*) FROM orders"), "3");
+
+    let (success, _, _) = db.psql.execute_write_with_confirmation(sql, false).unwrap();
+    assert!(success);
+    assert_eq!(query_value(&db.psql, "SELECT count(*) FROM orders"), "3");
+
+    let (success, _, _) = db.psql.execute_write_with_confirmation(sql, true).unwrap();
+    assert!(success);
+    assert_eq!(query_value(&db.psql, "SELECT count(*) FROM orders"), "1");
+}
+
+#[tokio::test]
+async fn failed_writes_are_reported() {
+    let db = start_database().await;
+    let sql = "INSERT INTO orders (id, customer_id, total) VALUES (1, 1, 5)";
+
+    let (success, _, stderr) = db.psql.preview_write_with_returning(sql, "*").unwrap();
+    assert!(!success);
+    assert!(stderr.contains("orders_pkey"));
+
+    let (success, _, _) = db.psql.execute_write_with_confirmation(sql, true).unwrap();
+    assert!(!success);
+    assert_eq!(query_value(&db.psql, "SELECT count(*) FROM orders"), "3");
+}
+
+#[tokio::test]
+async fn recovers_from_errors_with_model_fix() {
+    let db = start_database().await;
+    let schema = db.psql.introspect_schema().unwrap();
+    let llm = MockLlm::start(&["SELECT name FROM customers ORDER BY id"]).await;
+    let claude = llm.client(false);
+
+    let question = "customer names";
+    let broken = "SELECT nme FROM customers ORDER BY id";
+    let (success, _, stderr) = db.psql.execute_csv(broken).unwrap();
+    assert!(!success);
+    assert!(stderr.contains("column \"nme\" does not exist"));
+
+    let fixed = claude
+        .fix_sql(&schema, question, broken, &stderr)
+        .await
+        .unwrap();
+    assert_eq!(fixed, "SELECT name FROM customers ORDER BY id");
+
+    let (success, stdout, _) = db.psql.execute_csv(&fixed).unwrap();
+    assert!(success);
+    let result = ResultSet::from_csv(&stdout).unwrap();
+    assert_eq!(result.rows.len(), 2);
+
+    let requests = llm.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(last_user_message(&requests[0]).contains("column \"nme\" does not exist"));
+    assert!(requests[0]["system"]
+        .as_str()
+        .unwrap()
+        .contains("Table: public.customers"));
+}
+
+#[tokio::test]
+async fn strict_mode_keeps_rows_out_of_requests() {
+    let db = start_database().await;
+    let schema = db.psql.introspect_schema().unwrap();
+    let llm = MockLlm::start(&["```sql\nSELECT name FROM customers\n```", "SELECT 1"]).await;
+    let mut claude = llm.client(true);
+
+    let sql = claude.text_to_sql(&schema, "customer names").await.unwrap();
+    assert_eq!(sql, "SELECT name FROM customers");
+
+    let (_, stdout, _) = db.psql.execute_csv(&sql).unwrap();
+    let rendered = ResultSet::from_csv(&stdout).unwrap().render_table();
+    assert!(rendered.contains("Grace"));
+    claude.add_to_history("customer names".to_string(), sql, Some(rendered));
+
+    claude
+        .text_to_sql(&schema, "and their tiers")
+        .await
+        .unwrap();
+
+    let requests = llm.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(!requests[1].to_string().contains("Grace"));
+}
+
+#[tokio::test]
+async fn renders_result_tables() {
+    let db = start_database().await;
+
+    let (success, stdout, _) = db
+        .psql
+        .execute_csv("SELECT id, name, balance, note FROM customers ORDER BY id")
+        .unwrap();
+    assert!(success);
+
+    let result = ResultSet::from_csv(&stdout).unwrap();
+    assert_eq!(result.rows[0][3], None);
+    assert_eq!(
+        result.render_table(),
+        concat!(
+            " id | name  | balance | note\n",
+            "----+-------+---------+------\n",
+            "  1 | Ada   |  120.50 |\n",
+            "  2 | Grace |    8.00 | vip\n",
+            "(2 rows)\n",
+        )
+    );
+
+    let sorted = result.sort_by(2, false);
+    assert_eq!(sorted.rows[0][1].as_deref(), Some("Grace"));
+}
+
+#[tokio::test]
+async fn tags_executed_statements() {
+    let db = start_database().await;
+    let tagged = db
+        .psql
+        .tagged("psqlm test prompt_hash={prompt_hash}", "who is here");
+
+    let (success, stdout, _) = tagged
+        .execute_csv(
+            "SELECT application_name, query FROM pg_stat_activity WHERE pid = pg_backend_pid()",
+        )
+        .unwrap();
+    assert!(success);
+
+    let result = ResultSet::from_csv(&stdout).unwrap();
+    assert_eq!(result.rows[0][0].as_deref(), Some("psqlm"));
+    assert!(result.rows[0][1]
+        .as_deref()
+        .unwrap()
+        .starts_with("/* psqlm test prompt_hash="));
+}
@@ -0,0 +1,140 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intent {
+    Read,
+    Write,
+    Activity,
+    Locks,
+    Fixture,
+}
+
+const WRITE_VERBS: &[&str] = &[
+    "delete",
+    "remove",
+    "update",
+    "change",
+    "set",
+    "insert",
+    "add",
+    "create",
+    "drop",
+    "rename",
+    "truncate",
+    "mark",
+    "archive",
+    "deactivate",
+    "activate",
+    "reset",
+    "increase",
+    "decrease",
+    "assign",
+    "upsert",
+    "backfill",
+    "populate",
+    "purge",
+    "clear",
+];
+
+const LEADING_FILLER: &[&str] = &[
+    "please", "can", "could", "would", "you", "go", "ahead", "and",
+];
+
+const CANCEL_VERBS: &[&str] = &["kill", "cancel", "terminate", "stop", "abort"];
+
+const SESSION_TERMS: &[&str] = &[
+    "query",
+    "queries",
+    "backend",
+    "backends",
+    "session",
+    "sessions",
+    "pid",
+    "connection",
+    "connections",
+];
+
+const ACTIVITY_PHRASES: &[&str] = &[
+    "running queries",
+    "active queries",
+    "long running",
+    "long-running",
+    "currently running",
+    "what is running",
+    "what's running",
+    "slow queries right now",
+];
+
+const FIXTURE_TERMS: &[&str] = &["fixture", "fixtures"];
+
+const PAST_QUERY_TERMS: &[&str] = &[
+    "query",
+    "queries",
+    "statement",
+    "statements",
+    "report",
+    "reports",
+];
+
+const RELATIVE_DAY_TERMS: &[&str] = &["today", "yesterday", "tomorrow", "tonight"];
+
+const RELATIVE_PERIOD_PHRASES: &[&str] = &[
+    "this week",
+    "last week",
+    "this month",
+    "last month",
+    "this year",
+    "last year",
+    "this morning",
+    "this quarter",
+    "last quarter",
+];
+
+const LOCK_TERMS: &[&str] = &["lock", "locks", "locked", "blocking", "blocked", "deadlock"];
+
+fn words(question: &str) -> Vec<String> {
+    question
+        .to_lowercase()
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_string())
+        .collect()
+}
+
+pub fn classify(question: &str) -> Intent {
+    let lower = question.to_lowercase();
+    let words = words(question);
+    let has = |terms: &[&str]| words.iter().any(|w| terms.contains(&w.as_str()));
+
+    if has(FIXTURE_TERMS) {
+        return Intent::Fixture;
+    }
+    if has(LOCK_TERMS) && !has(WRITE_VERBS) {
+        return Intent::Locks;
+    }
+    if has(CANCEL_VERBS) && has(SESSION_TERMS) {
+        return Intent::Activity;
+    }
+    if ACTIVITY_PHRASES.iter().any(|p| lower.contains(p)) {
+        return Intent::Activity;
+    }
+
+    let verb = words.iter().find(|w| !LEADING_FILLER.contains(&w.as_str()));
+    if verb.is_some_and(|v| WRITE_VERBS.contains(&v.as_str())) {
+        return Intent::Write;
+    }
+
+    Intent::Read
+}
+
+pub fn mentions_past_query(question: &str) -> bool {
+    words(question)
+        .iter()
+        .any(|w| PAST_QUERY_TERMS.contains(&w.as_str()))
+}
+
+pub fn mentions_relative_date(question: &str) -> bool {
+    let lower = question.to_lowercase();
+    words(question)
+        .iter()
+        .any(|w| RELATIVE_DAY_TERMS.contains(&w.as_str()))
+        || RELATIVE_PERIOD_PHRASES.iter().any(|p| lower.contains(p))
+}
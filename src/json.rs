@@ -0,0 +1,152 @@
+use crate::result::{ColumnType, ResultSet};
+use serde_json::{Map, Value};
+
+pub const DEFAULT_INLINE_WIDTH: usize = 40;
+
+const KEY: &str = "\x1b[36m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const LITERAL: &str = "\x1b[35m";
+const RESET: &str = "\x1b[0m";
+
+pub fn parse_container(value: &str) -> Option<Value> {
+    if !value.starts_with(['{', '[']) {
+        return None;
+    }
+    serde_json::from_str(value)
+        .ok()
+        .filter(|v: &Value| v.is_object() || v.is_array())
+}
+
+pub fn compact(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let fields: Vec<String> = map
+                .iter()
+                .map(|(key, value)| format!("{}: {}", Value::from(key.as_str()), compact(value)))
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(compact).collect();
+            format!("[{}]", items.join(", "))
+        }
+        scalar => scalar.to_string(),
+    }
+}
+
+fn pretty_into(value: &Value, indent: usize, inline_width: usize, output: &mut String) {
+    let flat = compact(value);
+    let pad = "  ".repeat(indent + 1);
+    match value {
+        Value::Object(map) if flat.chars().count() > inline_width && !map.is_empty() => {
+            output.push_str("{\n");
+            for (i, (key, value)) in map.iter().enumerate() {
+                output.push_str(&format!("{}{}: ", pad, Value::from(key.as_str())));
+                pretty_into(value, indent + 1, inline_width, output);
+                output.push_str(if i + 1 < map.len() { ",\n" } else { "\n" });
+            }
+            output.push_str(&format!("{}}}", "  ".repeat(indent)));
+        }
+        Value::Array(items) if flat.chars().count() > inline_width && !items.is_empty() => {
+            output.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                output.push_str(&pad);
+                pretty_into(item, indent + 1, inline_width, output);
+                output.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            output.push_str(&format!("{}]", "  ".repeat(indent)));
+        }
+        _ => output.push_str(&flat),
+    }
+}
+
+pub fn pretty(value: &Value, inline_width: usize) -> String {
+    let mut output = String::new();
+    pretty_into(value, 0, inline_width, &mut output);
+    output
+}
+
+pub fn highlight(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut output = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '"' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i = (i + 1).min(chars.len());
+            let text: String = chars[start..i].iter().collect();
+            let is_key = chars[i..].iter().find(|c| !c.is_whitespace()) == Some(&':');
+            let color = if is_key { KEY } else { STRING };
+            output.push_str(&format!("{}{}{}", color, text, RESET));
+        } else if c == '-' || c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || "-+.eE".contains(chars[i])) {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            output.push_str(&format!("{}{}{}", NUMBER, text, RESET));
+        } else if c.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            output.push_str(&format!("{}{}{}", LITERAL, text, RESET));
+        } else {
+            output.push(c);
+            i += 1;
+        }
+    }
+
+    output
+}
+
+fn typed_value(value: &Option<String>, column_type: ColumnType) -> Value {
+    let Some(value) = value else {
+        return Value::Null;
+    };
+    match column_type {
+        ColumnType::Json => parse_container(value).unwrap_or_else(|| Value::from(value.as_str())),
+        ColumnType::Number => serde_json::from_str::<serde_json::Number>(value)
+            .map(Value::Number)
+            .unwrap_or_else(|_| Value::from(value.as_str())),
+        ColumnType::Boolean => Value::Bool(value == "t"),
+        ColumnType::Text => Value::from(value.as_str()),
+    }
+}
+
+pub fn to_json(result: &ResultSet) -> Value {
+    Value::Array(
+        result
+            .rows
+            .iter()
+            .map(|row| {
+                let object: Map<String, Value> = result
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .zip(&result.types)
+                    .map(|((column, value), &column_type)| {
+                        (column.clone(), typed_value(value, column_type))
+                    })
+                    .collect();
+                Value::Object(object)
+            })
+            .collect(),
+    )
+}
+
+// One object per line, so results appended to the same file stay valid (JSON Lines).
+pub fn to_ndjson(result: &ResultSet) -> String {
+    let Value::Array(rows) = to_json(result) else {
+        return String::new();
+    };
+    rows.iter().map(|row| format!("{}\n", row)).collect()
+}
@@ -0,0 +1,342 @@
+use crate::commands::CommandKind;
+use crate::config::Kiosk;
+use crate::schema::{name_pattern, Schema};
+use anyhow::Result;
+use regex::Regex;
+use sqlparser::ast::{Expr, Ident, Query, SetExpr, Statement, TableFactor, Visit, Visitor};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::ops::ControlFlow;
+
+const ALLOWED_COMMANDS: &[CommandKind] = &[
+    CommandKind::Quit,
+    CommandKind::Help,
+    CommandKind::History,
+    CommandKind::Schema,
+    CommandKind::Mode,
+    CommandKind::Stream,
+    CommandKind::Alias,
+    CommandKind::Why,
+    CommandKind::Filter,
+    CommandKind::Sort,
+    CommandKind::Cols,
+    CommandKind::Render,
+    CommandKind::Wrap,
+    CommandKind::Format,
+    CommandKind::Json,
+    CommandKind::Scroll,
+    CommandKind::Explore,
+    CommandKind::Insights,
+    CommandKind::Report,
+    CommandKind::Good,
+    CommandKind::Bad,
+    CommandKind::Store,
+    CommandKind::Drill,
+    CommandKind::Export,
+    CommandKind::Output,
+    CommandKind::Graph,
+];
+
+const BLOCKED_FUNCTION_PREFIXES: &[&str] = &["pg_", "lo_", "dblink"];
+
+const BLOCKED_FUNCTIONS: &[&str] = &[
+    "set_config",
+    "nextval",
+    "setval",
+    "currval",
+    "query_to_xml",
+    "query_to_xml_and_xmlschema",
+    "cursor_to_xml",
+    "table_to_xml",
+    "table_to_xml_and_xmlschema",
+    "schema_to_xml",
+    "database_to_xml",
+];
+
+pub fn allows(kind: CommandKind) -> bool {
+    ALLOWED_COMMANDS.contains(&kind)
+}
+
+fn patterns(kiosk: &Kiosk) -> Result<Vec<Regex>> {
+    kiosk
+        .tables
+        .iter()
+        .map(|table| name_pattern(table))
+        .collect()
+}
+
+fn matches_any(patterns: &[Regex], name: &str) -> bool {
+    let short = name.rsplit('.').next().unwrap_or(name);
+    patterns
+        .iter()
+        .any(|p| p.is_match(name) || p.is_match(short))
+}
+
+pub fn restrict(kiosk: &Kiosk, schema: &Schema) -> Result<Schema> {
+    let patterns = patterns(kiosk)?;
+    Ok(Schema {
+        tables: schema
+            .tables
+            .iter()
+            .filter(|t| matches_any(&patterns, &t.name))
+            .cloned()
+            .collect(),
+        materialized_views: schema
+            .materialized_views
+            .iter()
+            .filter(|v| matches_any(&patterns, &v.name))
+            .cloned()
+            .collect(),
+        views: schema
+            .views
+            .iter()
+            .filter(|v| matches_any(&patterns, &v.name))
+            .cloned()
+            .collect(),
+        enums: schema.enums.clone(),
+        composite_types: schema.composite_types.clone(),
+        extensions: schema.extensions.clone(),
+    })
+}
+
+fn normalize(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+fn is_blocked_function(name: &str) -> bool {
+    BLOCKED_FUNCTIONS.contains(&name)
+        || BLOCKED_FUNCTION_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+fn set_expr_violation(expr: &SetExpr) -> Option<&'static str> {
+    match expr {
+        SetExpr::Select(select) if select.into.is_some() => Some("SELECT INTO creates a table"),
+        SetExpr::Insert(_) | SetExpr::Update(_) => Some("the query modifies data"),
+        SetExpr::Table(_) => Some("write TABLE queries as SELECT * FROM"),
+        SetExpr::SetOperation { left, right, .. } => {
+            set_expr_violation(left).or_else(|| set_expr_violation(right))
+        }
+        _ => None,
+    }
+}
+
+struct Allowlist<'a> {
+    patterns: &'a [Regex],
+    ctes: Vec<String>,
+    scopes: Vec<usize>,
+}
+
+impl<'a> Allowlist<'a> {
+    fn new(patterns: &'a [Regex], ctes: Vec<String>) -> Self {
+        Allowlist {
+            patterns,
+            ctes,
+            scopes: Vec::new(),
+        }
+    }
+}
+
+impl Visitor for Allowlist<'_> {
+    type Break = String;
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<String> {
+        if !query.locks.is_empty() {
+            return ControlFlow::Break("the query locks rows".to_string());
+        }
+        if let Some(reason) = set_expr_violation(&query.body) {
+            return ControlFlow::Break(reason.to_string());
+        }
+        // a CTE is visible to the CTEs after it and to the query body, and to itself only
+        // under WITH RECURSIVE, so each body is checked against the names in scope there
+        let mut defined = 0;
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                let name = normalize(&cte.alias.name);
+                if with.recursive {
+                    self.ctes.push(name.clone());
+                }
+                cte.query
+                    .visit(&mut Allowlist::new(self.patterns, self.ctes.clone()))?;
+                if !with.recursive {
+                    self.ctes.push(name);
+                }
+                defined += 1;
+            }
+        }
+        self.scopes.push(defined);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<String> {
+        let defined = self.scopes.pop().unwrap_or(0);
+        self.ctes.truncate(self.ctes.len() - defined);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<String> {
+        let TableFactor::Table { name, args, .. } = factor else {
+            return ControlFlow::Continue(());
+        };
+        let parts: Vec<String> = name.0.iter().map(normalize).collect();
+        let relation = parts.join(".");
+        let is_cte = parts.len() == 1 && self.ctes.contains(&relation);
+        if args.is_some() {
+            if parts.last().is_some_and(|f| is_blocked_function(f)) {
+                return ControlFlow::Break(format!("{}() is not allowed", relation));
+            }
+        } else if !is_cte && !matches_any(self.patterns, &relation) {
+            return ControlFlow::Break(format!("{} is not on the allowlist", relation));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<String> {
+        if let Expr::Function(function) = expr {
+            if let Some(name) = function.name.0.last().map(normalize) {
+                if is_blocked_function(&name) {
+                    return ControlFlow::Break(format!("{}() is not allowed", name));
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+pub fn check(kiosk: &Kiosk, sql: &str) -> Result<()> {
+    if !kiosk.enabled {
+        return Ok(());
+    }
+
+    let dialect = PostgreSqlDialect {};
+    let Ok(statements) = Parser::parse_sql(&dialect, sql) else {
+        anyhow::bail!("Kiosk mode: the statement could not be parsed");
+    };
+    if statements.is_empty() {
+        anyhow::bail!("Kiosk mode: there is no statement to run");
+    }
+
+    let patterns = patterns(kiosk)?;
+    for statement in &statements {
+        if !matches!(statement, Statement::Query(_)) {
+            anyhow::bail!("Kiosk mode: only SELECT statements run");
+        }
+        if let ControlFlow::Break(reason) =
+            statement.visit(&mut Allowlist::new(&patterns, Vec::new()))
+        {
+            anyhow::bail!("Kiosk mode: {}", reason);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kiosk(tables: &[&str]) -> Kiosk {
+        Kiosk {
+            enabled: true,
+            tables: tables.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn refusal(sql: &str) -> String {
+        check(&kiosk(&["orders", "analytics.*"]), sql)
+            .unwrap_err()
+            .to_string()
+    }
+
+    fn allowed(sql: &str) -> bool {
+        check(&kiosk(&["orders", "analytics.*"]), sql).is_ok()
+    }
+
+    #[test]
+    fn allows_selects_on_allowlisted_tables() {
+        assert!(allowed("SELECT id, total FROM orders WHERE total > 10"));
+        assert!(allowed(
+            "SELECT * FROM public.orders o JOIN analytics.daily d ON d.day = o.created_at::date"
+        ));
+        assert!(allowed(
+            "SELECT * FROM orders WHERE id IN (SELECT order_id FROM analytics.refunds)"
+        ));
+    }
+
+    #[test]
+    fn refuses_tables_off_the_allowlist() {
+        assert!(refusal("SELECT * FROM customers").contains("customers is not on the allowlist"));
+        assert!(
+            refusal("SELECT * FROM orders WHERE customer_id IN (SELECT id FROM customers)")
+                .contains("customers")
+        );
+        assert!(refusal("SELECT * FROM reporting.orders_copy").contains("not on the allowlist"));
+    }
+
+    #[test]
+    fn refuses_select_into() {
+        assert!(refusal("SELECT * INTO orders_backup FROM orders").contains("SELECT INTO"));
+    }
+
+    #[test]
+    fn refuses_row_locks() {
+        assert!(refusal("SELECT * FROM orders FOR UPDATE").contains("locks rows"));
+        assert!(refusal("SELECT * FROM orders FOR SHARE SKIP LOCKED").contains("locks rows"));
+    }
+
+    #[test]
+    fn refuses_blocked_functions() {
+        assert!(refusal("SELECT pg_read_file('/etc/passwd')").contains("pg_read_file()"));
+        assert!(refusal("SELECT pg_catalog.pg_terminate_backend(42)")
+            .contains("pg_terminate_backend()"));
+        assert!(refusal("SELECT * FROM dblink('host=evil', 'SELECT 1')").contains("dblink()"));
+        assert!(refusal(
+            "SELECT id FROM orders WHERE set_config('role', 'admin', false) IS NOT NULL"
+        )
+        .contains("set_config()"));
+    }
+
+    #[test]
+    fn ctes_do_not_shadow_tables_off_the_allowlist() {
+        assert!(allowed(
+            "WITH big AS (SELECT * FROM orders WHERE total > 100) SELECT count(*) FROM big"
+        ));
+        assert!(
+            refusal("WITH customers AS (SELECT * FROM customers) SELECT * FROM customers")
+                .contains("customers is not on the allowlist")
+        );
+        assert!(refusal(
+            "SELECT * FROM (WITH customers AS (SELECT 1) SELECT * FROM customers) c, customers"
+        )
+        .contains("customers is not on the allowlist"));
+        assert!(allowed(
+            "WITH RECURSIVE walk AS (SELECT id FROM orders UNION ALL SELECT id + 1 FROM walk WHERE id < 10) SELECT * FROM walk"
+        ));
+    }
+
+    #[test]
+    fn checks_both_sides_of_set_operations() {
+        assert!(allowed(
+            "SELECT id FROM orders UNION SELECT id FROM analytics.orders"
+        ));
+        assert!(
+            refusal("SELECT id FROM orders UNION ALL SELECT id FROM customers")
+                .contains("customers")
+        );
+        assert!(
+            refusal("SELECT id FROM orders EXCEPT SELECT id FROM orders FOR UPDATE")
+                .contains("locks rows")
+        );
+    }
+
+    #[test]
+    fn refuses_statements_that_are_not_queries() {
+        assert!(refusal("DELETE FROM orders").contains("only SELECT"));
+        assert!(refusal("SELECT 1; UPDATE orders SET total = 0").contains("only SELECT"));
+        assert!(refusal("SELEC * FROM orders").contains("could not be parsed"));
+    }
+}
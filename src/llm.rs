@@ -0,0 +1,655 @@
+use crate::claude;
+use crate::config::Provider;
+use crate::provider_error::{ErrorDetail, ProviderError};
+use anyhow::{Context, Result};
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+const ANTHROPIC_URL: &str = "https://api.anthropic.com/v1/messages";
+const OPENAI_URL: &str = "https://api.openai.com/v1/chat/completions";
+const OPENAI_MODEL: &str = "gpt-4o";
+const OPENAI_FAST_MODEL: &str = "gpt-4o-mini";
+const OLLAMA_URL: &str = "http://localhost:11434";
+const OLLAMA_MODEL: &str = "llama3.1";
+
+#[derive(Debug, Serialize)]
+pub struct ApiRequest {
+    pub model: String,
+    pub max_tokens: u32,
+    pub system: String,
+    pub messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    #[serde(default)]
+    pub display_name: String,
+}
+
+pub struct Completion {
+    pub text: String,
+    pub output_tokens: Option<u32>,
+}
+
+pub type TextSink<'a> = &'a mut (dyn FnMut(&str) + Send);
+
+pub trait LlmProvider: fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn default_model(&self) -> &'static str;
+
+    fn default_fast_model(&self) -> &'static str;
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a ApiRequest,
+        on_text: TextSink<'a>,
+    ) -> BoxFuture<'a, Result<Completion>>;
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<ModelInfo>>>;
+}
+
+pub fn from_config(
+    provider: Provider,
+    api_key: &str,
+    api_url: Option<&str>,
+) -> Arc<dyn LlmProvider> {
+    match provider {
+        Provider::Anthropic => Arc::new(Anthropic::new(api_key, api_url)),
+        Provider::Openai => Arc::new(OpenAi::new(api_key, api_url, false)),
+        Provider::AzureOpenai => Arc::new(OpenAi::new(api_key, api_url, true)),
+        Provider::Ollama => Arc::new(Ollama::new(api_url)),
+    }
+}
+
+async fn provider_error(response: reqwest::Response) -> ProviderError {
+    let status = response.status().as_u16();
+    let retry_after = response
+        .headers()
+        .get("retry-after")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await.unwrap_or_default();
+    ProviderError::from_response(status, retry_after.as_deref(), &body)
+}
+
+async fn for_each_line(
+    response: reqwest::Response,
+    mut on_line: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    let mut stream = response.bytes_stream();
+    let mut pending = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.context("Failed to read stream chunk")?;
+        pending.push_str(&String::from_utf8_lossy(&chunk));
+
+        while let Some(end) = pending.find('\n') {
+            let line: String = pending.drain(..=end).collect();
+            on_line(line.trim_end())?;
+        }
+    }
+    if !pending.trim().is_empty() {
+        on_line(pending.trim_end())?;
+    }
+    Ok(())
+}
+
+async fn for_each_event(
+    response: reqwest::Response,
+    mut on_event: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    for_each_line(response, |line| match line.strip_prefix("data: ") {
+        Some(data) if data != "[DONE]" => on_event(data),
+        _ => Ok(()),
+    })
+    .await
+}
+
+#[derive(Debug)]
+pub struct Anthropic {
+    api_key: String,
+    api_url: String,
+    http: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelPage {
+    data: Vec<ModelInfo>,
+    #[serde(default)]
+    has_more: bool,
+    last_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    delta: Option<Delta>,
+    usage: Option<Usage>,
+    error: Option<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Delta {
+    text: Option<String>,
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    output_tokens: Option<u32>,
+}
+
+impl Anthropic {
+    pub fn new(api_key: &str, api_url: Option<&str>) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            api_url: api_url.unwrap_or(ANTHROPIC_URL).to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn models_url(&self) -> String {
+        match self.api_url.strip_suffix("/messages") {
+            Some(base) => format!("{}/models", base),
+            None => format!("{}/models", self.api_url.trim_end_matches('/')),
+        }
+    }
+
+    async fn fetch(&self, request: &ApiRequest, on_text: TextSink<'_>) -> Result<Completion> {
+        let response = self
+            .http
+            .post(&self.api_url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(request)
+            .send()
+            .await
+            .context("Failed to send request to Claude API")?;
+
+        if !response.status().is_success() {
+            return Err(provider_error(response).await.into());
+        }
+
+        let mut text = String::new();
+        let mut output_tokens = None;
+        for_each_event(response, |data| {
+            let Ok(event) = serde_json::from_str::<StreamEvent>(data) else {
+                return Ok(());
+            };
+            if let Some(tokens) = event.usage.and_then(|u| u.output_tokens) {
+                output_tokens = Some(tokens);
+            }
+            if let Some(error) = event.error {
+                return Err(ProviderError::from_event(error).into());
+            }
+            let refused =
+                event.delta.as_ref().and_then(|d| d.stop_reason.as_deref()) == Some("refusal");
+            if refused {
+                return Err(ProviderError::refusal().into());
+            }
+            if event.event_type == "content_block_delta" {
+                if let Some(delta) = event.delta.and_then(|d| d.text) {
+                    on_text(&delta);
+                    text.push_str(&delta);
+                }
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(Completion {
+            text,
+            output_tokens,
+        })
+    }
+
+    async fn models(&self) -> Result<Vec<ModelInfo>> {
+        let mut models = Vec::new();
+        let mut after: Option<String> = None;
+
+        loop {
+            let mut request = self
+                .http
+                .get(self.models_url())
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01")
+                .query(&[("limit", "100")]);
+            if let Some(after) = &after {
+                request = request.query(&[("after_id", after)]);
+            }
+
+            let response = request
+                .send()
+                .await
+                .context("Failed to list models from Claude API")?;
+            if !response.status().is_success() {
+                return Err(provider_error(response).await.into());
+            }
+
+            let page: ModelPage = response
+                .json()
+                .await
+                .context("Failed to parse model list")?;
+            models.extend(page.data);
+            match page.last_id {
+                Some(last_id) if page.has_more => after = Some(last_id),
+                _ => break,
+            }
+        }
+
+        Ok(models)
+    }
+}
+
+impl LlmProvider for Anthropic {
+    fn name(&self) -> &'static str {
+        "Claude"
+    }
+
+    fn default_model(&self) -> &'static str {
+        claude::MODEL
+    }
+
+    fn default_fast_model(&self) -> &'static str {
+        claude::FAST_MODEL
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a ApiRequest,
+        on_text: TextSink<'a>,
+    ) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(self.fetch(request, on_text))
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<ModelInfo>>> {
+        Box::pin(self.models())
+    }
+}
+
+#[derive(Debug)]
+pub struct OpenAi {
+    api_key: String,
+    api_url: String,
+    azure: bool,
+    http: reqwest::Client,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    #[serde(skip_serializing_if = "str::is_empty")]
+    model: &'a str,
+    max_tokens: u32,
+    messages: Vec<Message>,
+    stream: bool,
+    stream_options: StreamOptions,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChatChoice>,
+    usage: Option<ChatUsage>,
+    error: Option<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    delta: Option<ChatDelta>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatDelta {
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatUsage {
+    completion_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatModelPage {
+    data: Vec<ModelInfo>,
+}
+
+impl OpenAi {
+    pub fn new(api_key: &str, api_url: Option<&str>, azure: bool) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            api_url: api_url.unwrap_or(OPENAI_URL).to_string(),
+            azure,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.azure {
+            request.header("api-key", &self.api_key)
+        } else {
+            request.bearer_auth(&self.api_key)
+        }
+    }
+
+    async fn fetch(&self, request: &ApiRequest, on_text: TextSink<'_>) -> Result<Completion> {
+        let mut messages = vec![Message {
+            role: "system".to_string(),
+            content: request.system.clone(),
+        }];
+        messages.extend(request.messages.iter().cloned());
+        let body = ChatRequest {
+            model: if self.azure { "" } else { &request.model },
+            max_tokens: request.max_tokens,
+            messages,
+            stream: true,
+            stream_options: StreamOptions {
+                include_usage: true,
+            },
+            temperature: request.temperature,
+        };
+
+        let response = self
+            .authorized(self.http.post(&self.api_url))
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send request to {} API", self.name()))?;
+
+        if !response.status().is_success() {
+            return Err(provider_error(response).await.into());
+        }
+
+        let mut text = String::new();
+        let mut output_tokens = None;
+        for_each_event(response, |data| {
+            let Ok(chunk) = serde_json::from_str::<ChatChunk>(data) else {
+                return Ok(());
+            };
+            if let Some(error) = chunk.error {
+                return Err(ProviderError::from_event(error).into());
+            }
+            if let Some(tokens) = chunk.usage.and_then(|u| u.completion_tokens) {
+                output_tokens = Some(tokens);
+            }
+            for choice in chunk.choices {
+                if choice.finish_reason.as_deref() == Some("content_filter") {
+                    return Err(ProviderError::refusal().into());
+                }
+                if let Some(delta) = choice.delta.and_then(|d| d.content) {
+                    on_text(&delta);
+                    text.push_str(&delta);
+                }
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(Completion {
+            text,
+            output_tokens,
+        })
+    }
+
+    async fn models(&self) -> Result<Vec<ModelInfo>> {
+        if self.azure {
+            return Ok(Vec::new());
+        }
+
+        let url = match self.api_url.strip_suffix("/chat/completions") {
+            Some(base) => format!("{}/models", base),
+            None => format!("{}/models", self.api_url.trim_end_matches('/')),
+        };
+        let response = self
+            .authorized(self.http.get(url))
+            .send()
+            .await
+            .with_context(|| format!("Failed to list models from {} API", self.name()))?;
+        if !response.status().is_success() {
+            return Err(provider_error(response).await.into());
+        }
+
+        let page: ChatModelPage = response
+            .json()
+            .await
+            .context("Failed to parse model list")?;
+        Ok(page.data)
+    }
+}
+
+impl LlmProvider for OpenAi {
+    fn name(&self) -> &'static str {
+        if self.azure {
+            "Azure OpenAI"
+        } else {
+            "OpenAI"
+        }
+    }
+
+    fn default_model(&self) -> &'static str {
+        OPENAI_MODEL
+    }
+
+    fn default_fast_model(&self) -> &'static str {
+        OPENAI_FAST_MODEL
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a ApiRequest,
+        on_text: TextSink<'a>,
+    ) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(self.fetch(request, on_text))
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<ModelInfo>>> {
+        Box::pin(self.models())
+    }
+}
+
+#[derive(Debug)]
+pub struct Ollama {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaRequest<'a> {
+    model: &'a str,
+    messages: Vec<Message>,
+    stream: bool,
+    options: OllamaOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaOptions {
+    num_predict: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChunk {
+    message: Option<Message>,
+    eval_count: Option<u32>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaError {
+    error: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModel {
+    name: String,
+}
+
+impl Ollama {
+    pub fn new(base_url: Option<&str>) -> Self {
+        Self {
+            base_url: base_url
+                .unwrap_or(OLLAMA_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn unreachable(&self) -> String {
+        format!(
+            "Failed to reach Ollama at {} (is `ollama serve` running?)",
+            self.base_url
+        )
+    }
+
+    async fn error(response: reqwest::Response) -> ProviderError {
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<OllamaError>(&body)
+            .map(|body| body.error)
+            .unwrap_or(body);
+        ProviderError::from_response(status, None, &message)
+    }
+
+    async fn fetch(&self, request: &ApiRequest, on_text: TextSink<'_>) -> Result<Completion> {
+        let mut messages = vec![Message {
+            role: "system".to_string(),
+            content: request.system.clone(),
+        }];
+        messages.extend(request.messages.iter().cloned());
+        let body = OllamaRequest {
+            model: &request.model,
+            messages,
+            stream: true,
+            options: OllamaOptions {
+                num_predict: request.max_tokens,
+                temperature: request.temperature,
+            },
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .with_context(|| self.unreachable())?;
+
+        if !response.status().is_success() {
+            return Err(Self::error(response).await.into());
+        }
+
+        let mut text = String::new();
+        let mut output_tokens = None;
+        for_each_line(response, |line| {
+            let Ok(chunk) = serde_json::from_str::<OllamaChunk>(line) else {
+                return Ok(());
+            };
+            if let Some(error) = chunk.error {
+                let detail = ErrorDetail {
+                    error_type: String::new(),
+                    message: error,
+                };
+                return Err(ProviderError::from_event(detail).into());
+            }
+            if let Some(tokens) = chunk.eval_count {
+                output_tokens = Some(tokens);
+            }
+            if let Some(message) = chunk.message.filter(|m| !m.content.is_empty()) {
+                on_text(&message.content);
+                text.push_str(&message.content);
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(Completion {
+            text,
+            output_tokens,
+        })
+    }
+
+    async fn models(&self) -> Result<Vec<ModelInfo>> {
+        let response = self
+            .http
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .await
+            .with_context(|| self.unreachable())?;
+        if !response.status().is_success() {
+            return Err(Self::error(response).await.into());
+        }
+
+        let tags: OllamaTags = response
+            .json()
+            .await
+            .context("Failed to parse model list")?;
+        Ok(tags
+            .models
+            .into_iter()
+            .map(|model| ModelInfo {
+                id: model
+                    .name
+                    .strip_suffix(":latest")
+                    .unwrap_or(&model.name)
+                    .to_string(),
+                display_name: String::new(),
+            })
+            .collect())
+    }
+}
+
+impl LlmProvider for Ollama {
+    fn name(&self) -> &'static str {
+        "Ollama"
+    }
+
+    fn default_model(&self) -> &'static str {
+        OLLAMA_MODEL
+    }
+
+    fn default_fast_model(&self) -> &'static str {
+        OLLAMA_MODEL
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a ApiRequest,
+        on_text: TextSink<'a>,
+    ) -> BoxFuture<'a, Result<Completion>> {
+        Box::pin(self.fetch(request, on_text))
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<ModelInfo>>> {
+        Box::pin(self.models())
+    }
+}
@@ -1,52 +1,380 @@
+mod accessibility;
+mod audit;
+mod auth;
+mod blobs;
 mod claude;
+mod clipboard;
+mod commands;
+mod completion;
 mod config;
+mod cross_database;
+mod ddl_impact;
+mod demo;
+mod errors;
+mod estimate;
+mod export;
+mod feedback;
+mod fixtures;
+mod generated_columns;
+mod graph;
+mod health;
+mod help_pane;
+mod hints;
+mod history;
+mod hooks;
+mod insights;
+#[cfg(all(test, feature = "integration"))]
+mod integration_tests;
+mod intent;
+mod json;
+mod kiosk;
+mod llm;
+mod metrics;
+mod models;
+mod named_results;
+mod naming;
+mod onboarding;
+mod output;
+mod pager;
+mod params;
+mod personas;
+mod preview;
+mod profiles;
+mod provider_error;
 mod psql;
+mod quoting;
+mod rate_limit;
 mod repl;
+mod report;
+mod result;
+mod result_view;
+mod safe_mode;
+mod sandbox;
 mod schema;
+mod schema_questions;
+mod session;
+mod similar;
+mod snapshots;
+mod spinner;
+mod sql_format;
+mod streaming;
+mod templates;
+mod time_zones;
+mod topic;
+mod units;
+mod write_guard;
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand};
+use std::io::{self, IsTerminal, Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(name = "psqlm", version, about = "A natural language interface to PostgreSQL", disable_help_flag = true)]
 pub struct Args {
-    #[arg(long, action = clap::ArgAction::Help)]
+    #[arg(long, action = clap::ArgAction::Help, global = true)]
     help: Option<bool>,
 
-    #[arg(short = 'h', long, default_value = "localhost")]
+    #[arg(short = 'h', long, default_value = "localhost", global = true)]
     pub host: String,
 
-    #[arg(short, long, default_value = "5432")]
+    #[arg(short, long, default_value = "5432", global = true)]
     pub port: String,
 
-    #[arg(short = 'U', long = "username")]
-    pub user: String,
+    #[arg(short = 'U', long = "username", global = true)]
+    pub user: Option<String>,
 
-    #[arg(short, long = "dbname")]
-    pub database: String,
+    #[arg(short, long = "dbname", global = true)]
+    pub database: Option<String>,
 
-    #[arg(short = 'W', long)]
+    #[arg(short = 'W', long, global = true)]
     pub password: Option<String>,
+
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    #[arg(long, global = true)]
+    pub accessible: bool,
+
+    #[arg(long, global = true)]
+    pub demo: bool,
+
+    #[arg(long, value_enum, global = true)]
+    pub format: Option<config::OutputFormat>,
+
+    #[arg(short = 'c', long = "command", value_name = "QUESTION")]
+    pub question: Option<String>,
+
+    #[arg(short = '1', long)]
+    pub single_transaction: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    #[command(about = "Attach to a background session that keeps the schema and conversation")]
+    Attach,
+
+    #[command(hide = true)]
+    Daemon,
+}
+
+fn connect(args: &Args, config: &mut config::Config) -> Result<psql::PsqlConnection> {
+    match &args.profile {
+        Some(name) => {
+            let profile = config
+                .profiles
+                .get(name)
+                .with_context(|| format!("Unknown profile: {}", name))?;
+            config.write_guard = profile.write_guard.clone();
+            config.environment = profile.environment.clone();
+            if let Some(csv) = &profile.csv {
+                config.csv = csv.clone();
+            }
+            if let Some(hooks) = &profile.hooks {
+                config.hooks = hooks.clone();
+            }
+            if config.environment.production {
+                config
+                    .environment
+                    .label
+                    .get_or_insert_with(|| name.to_uppercase());
+            }
+            profiles::connect(profile)
+        }
+        None => psql::PsqlConnection::new(
+            args.host.clone(),
+            args.port.clone(),
+            args.user.clone().unwrap_or_default(),
+            args.database.clone().unwrap_or_default(),
+            args.password.clone(),
+        )
+        .with_auth(&config.auth),
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
-    let config = config::load_or_create().await?;
+    if args.profile.is_none() && (args.user.is_none() || args.database.is_none()) {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--username and --dbname are required unless --profile is given",
+            )
+            .exit();
+    }
+
+    if matches!(args.command, Some(Commands::Daemon)) {
+        let mut args = args;
+        args.password = args
+            .password
+            .take()
+            .or_else(|| std::env::var("PGPASSWORD").ok());
+        let mut config = config::load()?;
+        let psql = connect(&args, &mut config)?;
+        if let Some(address) = &config.metrics_address {
+            metrics::serve(address).await?;
+        }
+        return session::serve(psql).await;
+    }
+
+    let one_shot = match &args.question {
+        Some(question) => Some(question.clone()),
+        None if args.command.is_none() && !io::stdin().is_terminal() => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Some(input)
+        }
+        None => None,
+    };
+    if args.single_transaction && one_shot.is_none() {
+        anyhow::bail!("--single-transaction applies to -c and piped input");
+    }
+    let mut status: Box<dyn Write> = match one_shot {
+        Some(_) => Box::new(io::stderr()),
+        None => Box::new(io::stdout()),
+    };
+
+    let session_key = if config::is_first_run() && args.profile.is_none() {
+        if one_shot.is_some() {
+            anyhow::bail!("Run psqlm interactively once to set it up before using -c");
+        }
+        let psql = psql::PsqlConnection::new(
+            args.host.clone(),
+            args.port.clone(),
+            args.user.clone().unwrap_or_default(),
+            args.database.clone().unwrap_or_default(),
+            args.password.clone(),
+        );
+        onboarding::run(&psql).await?
+    } else {
+        None
+    };
+    let mut config = config::load_or_create(session_key).await?;
+    config.strict_mode |= args.strict;
+    config.demo = args.demo;
+    config.single_transaction = args.single_transaction;
+    if let Some(format) = args.format {
+        config.format = format;
+    }
+    units::configure(config.display_rules.as_deref())?;
+    if config.accessible || args.accessible || std::env::var("TERM").is_ok_and(|t| t == "dumb") {
+        accessibility::enable();
+    }
+
+    let psql = connect(&args, &mut config)?;
+    let profile = args
+        .profile
+        .clone()
+        .unwrap_or_else(|| profiles::DEFAULT_PROFILE.to_string());
 
-    let psql = psql::PsqlConnection::new(
-        args.host,
-        args.port,
-        args.user,
-        args.database,
-        args.password,
-    );
+    let (session, schema, history) = if matches!(args.command, Some(Commands::Attach)) {
+        let (session, state) = session::Session::attach(&psql, args.profile.as_deref()).await?;
+        writeln!(
+            status,
+            "Attached to session for {} ({} tables, {} questions in history)",
+            psql.database,
+            state.schema.tables.len(),
+            state.history.len()
+        )?;
+        (Some(session), state.schema, state.history)
+    } else {
+        writeln!(
+            status,
+            "Connecting to {} on {}...",
+            psql.database,
+            psql.address()
+        )?;
+        if let Some(replica) = psql.replica() {
+            writeln!(
+                status,
+                "Reads go to the replica on {}; writes go to the primary.",
+                replica.address()
+            )?;
+        }
+        if let Some(source) = psql.token_source() {
+            writeln!(
+                status,
+                "Authenticating with {}, refreshed automatically.",
+                source.describe()
+            )?;
+        }
+        let schema = psql.introspect_schema()?;
+        writeln!(status, "Schema loaded ({} tables)", schema.tables.len())?;
+        (None, schema, Vec::new())
+    };
+    if let Some(library) = config.hooks.library.clone() {
+        config.hooks.loaded = Some(Arc::new(hooks::load_library(&library)?));
+    }
+    let schema = if config.kiosk.enabled {
+        let schema = kiosk::restrict(&config.kiosk, &schema)?;
+        writeln!(
+            status,
+            "Kiosk mode: only SELECT statements on {} allowlisted tables and views run.",
+            schema.relation_count()
+        )?;
+        schema
+    } else {
+        schema
+    };
+    if config.strict_mode {
+        writeln!(
+            status,
+            "Strict mode: only schema metadata is sent to the model."
+        )?;
+    }
+    if config.demo {
+        writeln!(
+            status,
+            "Demo mode: emails, names and phone numbers in results are replaced with fakes."
+        )?;
+    }
+    config.safe_mode_active = match config.safe_mode {
+        config::SafeMode::Always => true,
+        config::SafeMode::Off => false,
+        config::SafeMode::Auto => psql.is_superuser().unwrap_or(false),
+    };
+    if config.safe_mode_active {
+        config.execution_mode = config::ExecutionMode::Confirm;
+        let reason = match config.safe_mode {
+            config::SafeMode::Auto => format!("{} is a superuser, so safe mode is on", psql.user),
+            _ => "Safe mode is on".to_string(),
+        };
+        writeln!(
+            status,
+            "\x1b[33m⚠️  {}: every query needs confirmation, destructive DDL must be confirmed by typing the database name, and writes over {} rows are refused.\x1b[0m",
+            reason,
+            config
+                .safe_mode_row_limit
+                .unwrap_or(safe_mode::DEFAULT_ROW_LIMIT)
+        )?;
+    }
+    writeln!(status)?;
 
-    println!("Connecting to {}...", psql.database);
-    let schema = psql.introspect_schema()?;
-    println!("Schema loaded ({} tables)\n", schema.tables.len());
+    if one_shot.is_some() {
+        config.stream_display = config::StreamDisplay::Quiet;
+    }
+    let provider = llm::from_config(config.provider, &config.api_key, config.api_url.as_deref());
+    let mut claude = claude::Client::new(provider, config.stream_display, config.strict_mode)
+        .with_rate_limit(config.rate_limit.clone());
+    if let Some(model) = &config.model {
+        claude = claude.with_model(model);
+    }
+    if let Some(fast_model) = &config.fast_model {
+        claude = claude.with_fast_model(fast_model);
+    }
+    if let Some(language) = &config.language {
+        claude = claude.with_language(language);
+    }
+    if let Some(timeout) = config.llm_timeout {
+        claude = claude.with_timeout(Duration::from_secs(timeout));
+    }
+    if let Some(name) = &config.persona {
+        let guidance = personas::guidance(&config.personas, name).with_context(|| {
+            format!(
+                "Unknown persona '{}' (available: {})",
+                name,
+                personas::names(&config.personas).join(", ")
+            )
+        })?;
+        claude.set_persona(Some(guidance));
+    }
+    claude.set_time_zone(config.time_zone.clone());
+    claude.set_view_definitions(config.view_definitions);
+    match &config.metrics_address {
+        Some(address) if session.is_some() => {
+            writeln!(
+                status,
+                "The session daemon serves metrics on http://{}/metrics\n",
+                address
+            )?;
+        }
+        Some(address) => {
+            metrics::serve(address).await?;
+            writeln!(status, "Serving metrics on http://{}/metrics\n", address)?;
+        }
+        None => {}
+    }
+    claude.validate_model().await?;
+    if let Ok(store) = feedback::FeedbackStore::for_database(&psql) {
+        claude.feedback = store.load();
+    }
+    claude.history = history;
 
-    let claude = claude::Client::new(&config.api_key);
+    if let Some(input) = one_shot {
+        if !repl::run_once(psql, claude, schema, config, &input).await? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    repl::run(psql, claude, schema, config).await
+    repl::run(psql, profile, claude, schema, config, session).await
 }
@@ -0,0 +1,272 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+struct Histogram {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: BUCKETS.iter().map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            if seconds <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn values(&self) -> impl Iterator<Item = &AtomicU64> {
+        self.buckets.iter().chain([&self.count, &self.sum_micros])
+    }
+
+    fn render(&self, name: &str, help: &str, output: &mut String) {
+        output.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} histogram\n",
+            name, help, name
+        ));
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            output.push_str(&format!(
+                "{}_bucket{{le=\"{}\"}} {}\n",
+                name,
+                bound,
+                bucket.load(Ordering::Relaxed)
+            ));
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        output.push_str(&format!("{}_bucket{{le=\"+Inf\"}} {}\n", name, count));
+        output.push_str(&format!(
+            "{}_sum {}\n{}_count {}\n",
+            name,
+            self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+            name,
+            count
+        ));
+    }
+}
+
+struct Metrics {
+    sql_generated: AtomicU64,
+    queries_succeeded: AtomicU64,
+    queries_failed: AtomicU64,
+    fix_attempts: AtomicU64,
+    llm_requests: AtomicU64,
+    llm_errors: AtomicU64,
+    input_tokens: AtomicU64,
+    output_tokens: AtomicU64,
+    llm_duration: Histogram,
+    query_duration: Histogram,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(|| Metrics {
+    sql_generated: AtomicU64::new(0),
+    queries_succeeded: AtomicU64::new(0),
+    queries_failed: AtomicU64::new(0),
+    fix_attempts: AtomicU64::new(0),
+    llm_requests: AtomicU64::new(0),
+    llm_errors: AtomicU64::new(0),
+    input_tokens: AtomicU64::new(0),
+    output_tokens: AtomicU64::new(0),
+    llm_duration: Histogram::new(),
+    query_duration: Histogram::new(),
+});
+
+impl Metrics {
+    fn values(&self) -> impl Iterator<Item = &AtomicU64> {
+        [
+            &self.sql_generated,
+            &self.queries_succeeded,
+            &self.queries_failed,
+            &self.fix_attempts,
+            &self.llm_requests,
+            &self.llm_errors,
+            &self.input_tokens,
+            &self.output_tokens,
+        ]
+        .into_iter()
+        .chain(self.llm_duration.values())
+        .chain(self.query_duration.values())
+    }
+}
+
+// Counts recorded since the last `take`, sent by attached sessions to the daemon that
+// serves the metrics endpoint.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot(Vec<u64>);
+
+pub fn take() -> Snapshot {
+    Snapshot(
+        METRICS
+            .values()
+            .map(|value| value.swap(0, Ordering::Relaxed))
+            .collect(),
+    )
+}
+
+pub fn add(snapshot: &Snapshot) {
+    for (value, delta) in METRICS.values().zip(&snapshot.0) {
+        value.fetch_add(*delta, Ordering::Relaxed);
+    }
+}
+
+pub fn sql_generated() {
+    METRICS.sql_generated.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn fix_attempted() {
+    METRICS.fix_attempts.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn query_executed(success: bool, elapsed: Duration) {
+    let counter = if success {
+        &METRICS.queries_succeeded
+    } else {
+        &METRICS.queries_failed
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+    METRICS.query_duration.observe(elapsed);
+}
+
+pub fn llm_request(elapsed: Duration, input_tokens: u64, output_tokens: u64) {
+    METRICS.llm_requests.fetch_add(1, Ordering::Relaxed);
+    METRICS
+        .input_tokens
+        .fetch_add(input_tokens, Ordering::Relaxed);
+    METRICS
+        .output_tokens
+        .fetch_add(output_tokens, Ordering::Relaxed);
+    METRICS.llm_duration.observe(elapsed);
+}
+
+pub fn llm_failed() {
+    METRICS.llm_errors.fetch_add(1, Ordering::Relaxed);
+}
+
+fn counter(name: &str, help: &str, value: &AtomicU64, output: &mut String) {
+    output.push_str(&format!(
+        "# HELP {} {}\n# TYPE {} counter\n{} {}\n",
+        name,
+        help,
+        name,
+        name,
+        value.load(Ordering::Relaxed)
+    ));
+}
+
+pub fn render() -> String {
+    let mut output = String::new();
+    counter(
+        "psqlm_sql_generated_total",
+        "SQL statements generated by the model, including fixes.",
+        &METRICS.sql_generated,
+        &mut output,
+    );
+    output.push_str(&format!(
+        "# HELP psqlm_queries_total Statements executed against the database.\n# TYPE psqlm_queries_total counter\npsqlm_queries_total{{status=\"succeeded\"}} {}\npsqlm_queries_total{{status=\"failed\"}} {}\n",
+        METRICS.queries_succeeded.load(Ordering::Relaxed),
+        METRICS.queries_failed.load(Ordering::Relaxed)
+    ));
+    counter(
+        "psqlm_fix_attempts_total",
+        "Times the model was asked to fix a failed statement.",
+        &METRICS.fix_attempts,
+        &mut output,
+    );
+    counter(
+        "psqlm_llm_requests_total",
+        "Completed requests to the model.",
+        &METRICS.llm_requests,
+        &mut output,
+    );
+    counter(
+        "psqlm_llm_errors_total",
+        "Requests to the model that failed.",
+        &METRICS.llm_errors,
+        &mut output,
+    );
+    counter(
+        "psqlm_llm_input_tokens_total",
+        "Estimated input tokens sent to the model.",
+        &METRICS.input_tokens,
+        &mut output,
+    );
+    counter(
+        "psqlm_llm_output_tokens_total",
+        "Output tokens returned by the model.",
+        &METRICS.output_tokens,
+        &mut output,
+    );
+    METRICS.llm_duration.render(
+        "psqlm_llm_request_duration_seconds",
+        "Time to receive a complete response from the model.",
+        &mut output,
+    );
+    METRICS.query_duration.render(
+        "psqlm_query_duration_seconds",
+        "Time to execute a statement against the database.",
+        &mut output,
+    );
+    output
+}
+
+async fn respond(mut stream: TcpStream) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut words = request.split_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render()),
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+pub async fn serve(address: &str) -> Result<()> {
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to listen for metrics on {}", address))?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let _ = respond(stream).await;
+            });
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,91 @@
+struct Pricing {
+    prefix: &'static str,
+    context_tokens: u32,
+    input_per_mtok: f64,
+    output_per_mtok: f64,
+}
+
+const PRICING: &[Pricing] = &[
+    Pricing {
+        prefix: "claude-opus-4-5",
+        context_tokens: 200_000,
+        input_per_mtok: 5.0,
+        output_per_mtok: 25.0,
+    },
+    Pricing {
+        prefix: "claude-opus-4",
+        context_tokens: 200_000,
+        input_per_mtok: 15.0,
+        output_per_mtok: 75.0,
+    },
+    Pricing {
+        prefix: "claude-sonnet-4",
+        context_tokens: 200_000,
+        input_per_mtok: 3.0,
+        output_per_mtok: 15.0,
+    },
+    Pricing {
+        prefix: "claude-haiku-4",
+        context_tokens: 200_000,
+        input_per_mtok: 1.0,
+        output_per_mtok: 5.0,
+    },
+    Pricing {
+        prefix: "claude-3-7-sonnet",
+        context_tokens: 200_000,
+        input_per_mtok: 3.0,
+        output_per_mtok: 15.0,
+    },
+    Pricing {
+        prefix: "claude-3-5-sonnet",
+        context_tokens: 200_000,
+        input_per_mtok: 3.0,
+        output_per_mtok: 15.0,
+    },
+    Pricing {
+        prefix: "claude-3-5-haiku",
+        context_tokens: 200_000,
+        input_per_mtok: 0.8,
+        output_per_mtok: 4.0,
+    },
+    Pricing {
+        prefix: "claude-3-opus",
+        context_tokens: 200_000,
+        input_per_mtok: 15.0,
+        output_per_mtok: 75.0,
+    },
+    Pricing {
+        prefix: "claude-3-haiku",
+        context_tokens: 200_000,
+        input_per_mtok: 0.25,
+        output_per_mtok: 1.25,
+    },
+];
+
+fn pricing(model: &str) -> Option<&'static Pricing> {
+    PRICING.iter().find(|p| model.starts_with(p.prefix))
+}
+
+fn format_price(price: f64) -> String {
+    if price.fract() == 0.0 {
+        format!("${}", price)
+    } else {
+        format!("${:.2}", price)
+    }
+}
+
+pub fn details(model: &str) -> String {
+    match pricing(model) {
+        Some(p) => format!(
+            "{}k context, {} in / {} out per MTok",
+            p.context_tokens / 1_000,
+            format_price(p.input_per_mtok),
+            format_price(p.output_per_mtok)
+        ),
+        None => "context and pricing unknown".to_string(),
+    }
+}
+
+pub fn input_cost(model: &str, tokens: usize) -> Option<f64> {
+    pricing(model).map(|p| tokens as f64 * p.input_per_mtok / 1_000_000.0)
+}
@@ -0,0 +1,47 @@
+use crate::report::{render_outcome, Entry, MAX_PROMPT_ROWS};
+use std::collections::BTreeMap;
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub fn referenced<'a>(
+    results: &'a BTreeMap<String, Entry>,
+    question: &str,
+) -> Vec<(&'a str, &'a Entry)> {
+    let words: Vec<String> = question
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map(str::to_lowercase)
+        .collect();
+
+    results
+        .iter()
+        .filter(|(name, _)| words.contains(&name.to_lowercase()))
+        .map(|(name, entry)| (name.as_str(), entry))
+        .collect()
+}
+
+pub fn to_prompt_string(referenced: &[(&str, &Entry)], include_results: bool) -> String {
+    let mut output = String::from(
+        "Stored results referenced in this question. Reuse their SQL (for example as a CTE) when the question builds on or compares against them:\n\n",
+    );
+
+    for (name, entry) in referenced {
+        output.push_str(&format!(
+            "{} (from the question \"{}\")\n",
+            name, entry.question
+        ));
+        output.push_str(&format!("SQL:\n{}\n", entry.sql.trim()));
+        if include_results {
+            output.push_str("Result:\n");
+            output.push_str(&render_outcome(&entry.outcome, MAX_PROMPT_ROWS));
+        }
+        output.push('\n');
+    }
+
+    output
+}
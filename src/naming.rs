@@ -0,0 +1,84 @@
+use crate::schema::Schema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Snake,
+    Camel,
+}
+
+fn style(name: &str) -> Option<Style> {
+    let has_upper = name.chars().any(|c| c.is_uppercase());
+    let has_underscore = name.trim_matches('_').contains('_');
+    match (has_upper, has_underscore) {
+        (false, true) => Some(Style::Snake),
+        (true, false) => Some(Style::Camel),
+        _ => None,
+    }
+}
+
+pub fn folded(name: &str) -> String {
+    name.chars()
+        .filter(|&c| c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn table_style(columns: impl Iterator<Item = Option<Style>>) -> Option<Option<Style>> {
+    let styles: Vec<Style> = columns.flatten().collect();
+    let first = *styles.first()?;
+    Some(styles.iter().all(|&s| s == first).then_some(first))
+}
+
+fn example(schema: &Schema, wanted: Style) -> Option<&str> {
+    schema
+        .tables
+        .iter()
+        .flat_map(|t| &t.columns)
+        .map(|c| c.name.as_str())
+        .find(|name| style(name) == Some(wanted))
+}
+
+pub fn to_prompt_string(schema: &Schema) -> Option<String> {
+    let mut snake = Vec::new();
+    let mut camel = Vec::new();
+    let mut mixed = Vec::new();
+    for table in &schema.tables {
+        match table_style(table.columns.iter().map(|c| style(&c.name))) {
+            Some(Some(Style::Snake)) => snake.push(table.name.as_str()),
+            Some(Some(Style::Camel)) => camel.push(table.name.as_str()),
+            Some(None) => mixed.push(table.name.as_str()),
+            None => {}
+        }
+    }
+
+    let mut output = String::from("\nNAMING:\n");
+    if camel.is_empty() && mixed.is_empty() {
+        output.push_str(&format!(
+            "- Column names are snake_case (such as {}); never write them in camelCase\n",
+            example(schema, Style::Snake)?
+        ));
+    } else if snake.is_empty() && mixed.is_empty() {
+        output.push_str(&format!(
+            "- Column names are camelCase (such as \"{0}\"); always double-quote them exactly as written, since unquoted {0} folds to lowercase, and never convert them to snake_case\n",
+            example(schema, Style::Camel)?
+        ));
+    } else {
+        output.push_str("- Naming conventions differ between tables, so copy every column name exactly from the schema instead of guessing its style\n");
+        if !snake.is_empty() {
+            output.push_str(&format!("- snake_case columns: {}\n", snake.join(", ")));
+        }
+        if !camel.is_empty() {
+            output.push_str(&format!(
+                "- camelCase columns, which must be double-quoted: {}\n",
+                camel.join(", ")
+            ));
+        }
+        if !mixed.is_empty() {
+            output.push_str(&format!(
+                "- Both styles within the same table: {}\n",
+                mixed.join(", ")
+            ));
+        }
+    }
+    Some(output)
+}
@@ -0,0 +1,323 @@
+use crate::claude::Client as ClaudeClient;
+use crate::config::{self, Provider, StreamDisplay};
+use crate::llm;
+use crate::provider_error::{ErrorKind, ProviderError};
+use crate::psql::PsqlConnection;
+use anyhow::Result;
+use std::io::{self, Write};
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+const PROVIDERS: &[(Provider, &str, &str)] = &[
+    (Provider::Anthropic, "anthropic", "Claude models"),
+    (
+        Provider::Openai,
+        "openai",
+        "OpenAI or an OpenAI-compatible gateway",
+    ),
+    (
+        Provider::AzureOpenai,
+        "azure-openai",
+        "an Azure OpenAI deployment",
+    ),
+    (
+        Provider::Ollama,
+        "ollama",
+        "a local Ollama server, no API key",
+    ),
+];
+
+const EXECUTION_MODES: &[(&str, &str)] = &[
+    ("confirm", "ask before running each statement"),
+    ("auto", "run generated SQL immediately"),
+    ("show", "only display the SQL"),
+];
+
+const SAFE_MODES: &[(&str, &str)] = &[
+    ("auto", "extra confirmations when connected as a superuser"),
+    ("always", "extra confirmations for every role"),
+    ("off", "no extra confirmations"),
+];
+
+fn ask(prompt: &str) -> Result<String> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        anyhow::bail!("Setup cancelled");
+    }
+    Ok(input.trim().to_string())
+}
+
+fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    let answer = ask(&format!("{} {}: ", prompt, hint))?.to_lowercase();
+    Ok(match answer.as_str() {
+        "" => default,
+        answer => answer.starts_with('y'),
+    })
+}
+
+fn choose(title: &str, options: &[(String, String)], default: usize) -> Result<usize> {
+    println!("{}", title);
+    for (i, (name, description)) in options.iter().enumerate() {
+        let marker = if i == default { "*" } else { " " };
+        if description.is_empty() {
+            println!("  {}{}. {}", marker, i + 1, name);
+        } else {
+            println!(
+                "  {}{}. {}  {DIM}{}{RESET}",
+                marker,
+                i + 1,
+                name,
+                description
+            );
+        }
+    }
+    loop {
+        let answer = ask(&format!("Choice [{}]: ", default + 1))?;
+        if answer.is_empty() {
+            return Ok(default);
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=options.len()).contains(&n) => return Ok(n - 1),
+            _ => println!("Enter a number from 1 to {}.", options.len()),
+        }
+    }
+}
+
+fn described(options: &[(&str, &str)]) -> Vec<(String, String)> {
+    options
+        .iter()
+        .map(|(name, description)| (name.to_string(), description.to_string()))
+        .collect()
+}
+
+fn api_url(provider: Provider) -> Result<Option<String>> {
+    match provider {
+        Provider::AzureOpenai => loop {
+            let url = ask("Deployment URL (https://<resource>.openai.azure.com/openai/deployments/<deployment>/chat/completions?api-version=...): ")?;
+            if !url.is_empty() {
+                return Ok(Some(url));
+            }
+            println!("The deployment URL cannot be empty.");
+        },
+        Provider::Ollama => {
+            let url = ask("Ollama server URL [http://localhost:11434]: ")?;
+            Ok((!url.is_empty()).then_some(url))
+        }
+        _ => Ok(None),
+    }
+}
+
+async fn api_key_and_models(
+    provider: Provider,
+    api_url: Option<&str>,
+) -> Result<(Option<String>, Vec<String>)> {
+    loop {
+        let api_key = match provider.api_key_variable() {
+            Some(_) => {
+                let api_key = ask(&format!("{} API key: ", provider.label()))?;
+                if api_key.is_empty() {
+                    println!("The API key cannot be empty.");
+                    continue;
+                }
+                Some(api_key)
+            }
+            None => None,
+        };
+
+        let llm = llm::from_config(provider, api_key.as_deref().unwrap_or_default(), api_url);
+        let client = ClaudeClient::new(llm, StreamDisplay::default(), false);
+        match client.list_models().await {
+            Ok(models) if models.is_empty() && provider == Provider::AzureOpenai => {
+                println!("{DIM}Azure deployments have no model list, so the key is first used with your first question.{RESET}\n");
+                return Ok((api_key, Vec::new()));
+            }
+            Ok(models) => {
+                match api_key {
+                    Some(_) => println!("{GREEN}✓ API key works.{RESET}\n"),
+                    None => println!("{GREEN}✓ Connected to {}.{RESET}\n", provider.label()),
+                }
+                return Ok((api_key, models.into_iter().map(|m| m.id).collect()));
+            }
+            Err(e)
+                if api_key.is_some()
+                    && e.downcast_ref::<ProviderError>().map(|e| e.kind)
+                        == Some(ErrorKind::Authentication) =>
+            {
+                println!("{YELLOW}The API rejected this key; check it and try again.{RESET}");
+            }
+            Err(e) if api_key.is_some() => {
+                println!(
+                    "{YELLOW}Could not check the key ({}); keeping it anyway.{RESET}\n",
+                    e
+                );
+                return Ok((api_key, Vec::new()));
+            }
+            Err(e) => {
+                println!(
+                    "{YELLOW}Could not reach {} ({}); setup will continue.{RESET}\n",
+                    provider.label(),
+                    e
+                );
+                return Ok((None, Vec::new()));
+            }
+        }
+    }
+}
+
+fn test_database(psql: &PsqlConnection) {
+    if psql.user.is_empty() || psql.database.is_empty() {
+        return;
+    }
+    print!("Connecting to {} on {}... ", psql.database, psql.address());
+    let _ = io::stdout().flush();
+    match psql.query("SELECT current_setting('server_version')") {
+        Ok(version) => println!("{GREEN}✓ PostgreSQL {}{RESET}\n", version.trim()),
+        Err(e) => println!(
+            "{YELLOW}failed.{RESET}\n{}\nSetup will continue; check the connection options before the next run.\n",
+            e.to_string().trim()
+        ),
+    }
+}
+
+fn render_config(
+    provider: Provider,
+    api_url: Option<&str>,
+    api_key: Option<&str>,
+    model: &str,
+    execution_mode: &str,
+    safe_mode: &str,
+    strict_mode: bool,
+) -> String {
+    let provider_name = PROVIDERS
+        .iter()
+        .find(|(p, _, _)| *p == provider)
+        .map_or("anthropic", |(_, name, _)| name);
+    let api_url = match api_url {
+        Some(url) => format!("api_url = {}\n", toml::Value::from(url)),
+        None => String::new(),
+    };
+    let api_key = match (api_key, provider.api_key_variable()) {
+        (Some(key), _) => format!("api_key = {}\n", toml::Value::from(key)),
+        (None, Some(variable)) => format!("# api_key = \"...\"   # or set {}\n", variable),
+        (None, None) => String::new(),
+    };
+    format!(
+        r#"# psqlm configuration, written by the setup wizard.
+# Commented-out lines show other settings with their defaults; the README describes them all.
+
+# The API that generates SQL: "anthropic", "openai", "azure-openai" or "ollama".
+provider = "{provider}"
+{api_url}{api_key}
+# Model used to generate SQL; \models lists the ones available to your key.
+model = {model}
+
+# How generated SQL runs: "confirm" asks first, "auto" runs it immediately, "show" only prints it.
+execution_mode = "{execution_mode}"
+
+# Extra confirmations for destructive statements and large writes:
+# "auto" for superusers, "always" for every role, "off" to disable.
+safe_mode = "{safe_mode}"
+# safe_mode_row_limit = 1000
+
+# Send only schema metadata to the model, never query results.
+strict_mode = {strict_mode}
+
+# Seconds to wait for the model before retrying.
+# llm_timeout = 120
+
+# Fetch results estimated above this many rows through a cursor.
+# stream_threshold = 10000
+
+# Try writes against scratch copies of their tables first.
+# sandbox = false
+
+# Suggest follow-up questions after each result.
+# follow_ups = false
+
+# [rate_limit]
+# requests_per_minute = 50
+# tokens_per_minute = 40000
+"#,
+        provider = provider_name,
+        api_url = api_url,
+        api_key = api_key,
+        model = toml::Value::from(model),
+        execution_mode = execution_mode,
+        safe_mode = safe_mode,
+        strict_mode = strict_mode,
+    )
+}
+
+pub async fn run(psql: &PsqlConnection) -> Result<Option<String>> {
+    println!("Welcome to psqlm! A few questions to set things up; press Enter to keep the default marked with *.\n");
+
+    let providers: Vec<(String, String)> = PROVIDERS
+        .iter()
+        .map(|(_, name, description)| (name.to_string(), description.to_string()))
+        .collect();
+    let provider = PROVIDERS[choose("Provider:", &providers, 0)?].0;
+    println!();
+    let api_url = api_url(provider)?;
+    let (api_key, mut models) = api_key_and_models(provider, api_url.as_deref()).await?;
+
+    let default_model = llm::from_config(provider, "", api_url.as_deref()).default_model();
+    if !models.iter().any(|model| model == default_model) {
+        models.insert(0, default_model.to_string());
+    }
+    let default = models
+        .iter()
+        .position(|model| model == default_model)
+        .unwrap_or_default();
+    let options: Vec<(String, String)> = models
+        .iter()
+        .map(|model| (model.clone(), String::new()))
+        .collect();
+    let model = &models[choose("Model:", &options, default)?];
+    println!();
+
+    test_database(psql);
+
+    let execution_mode = EXECUTION_MODES[choose(
+        "When should generated SQL run?",
+        &described(EXECUTION_MODES),
+        0,
+    )?]
+    .0;
+    println!();
+    let safe_mode = SAFE_MODES[choose("Safe mode:", &described(SAFE_MODES), 0)?].0;
+    println!();
+    let strict_mode = confirm(
+        "Strict mode (never send query results to the model)?",
+        false,
+    )?;
+    let stored = match provider.api_key_variable() {
+        Some(variable) if api_key.is_some() => confirm(
+            &format!(
+                "Store the API key in config.toml (otherwise set {})?",
+                variable
+            ),
+            true,
+        )?,
+        _ => false,
+    };
+
+    let contents = render_config(
+        provider,
+        api_url.as_deref(),
+        api_key.as_deref().filter(|_| stored),
+        model,
+        execution_mode,
+        safe_mode,
+        strict_mode,
+    );
+    let path = config::save_initial(&contents)?;
+    println!("\nWrote {}\n", path.display());
+
+    Ok(api_key.filter(|_| !stored))
+}
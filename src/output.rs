@@ -0,0 +1,225 @@
+use crate::export::{OutputFile, Redirect};
+use crate::report::Entry;
+use crate::result::{ResultSet, TableLayout};
+use anyhow::Result;
+
+#[derive(Debug, Clone, Default)]
+pub struct ViewState {
+    pub filter: Option<(Option<usize>, String)>,
+    pub sort: Option<(usize, bool)>,
+    pub columns: Option<Vec<usize>>,
+}
+
+impl ViewState {
+    pub fn apply(&self, result: &ResultSet) -> ResultSet {
+        let mut view = result.clone();
+        if let Some((column, needle)) = &self.filter {
+            view = view.filter(*column, needle);
+        }
+        if let Some((column, descending)) = self.sort {
+            view = view.sort_by(column, descending);
+        }
+        if let Some(columns) = &self.columns {
+            view = view.select(columns);
+        }
+        view
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Output {
+    last: Option<ResultSet>,
+    sql: Option<String>,
+    state: ViewState,
+    first_column: usize,
+    transcript: Vec<Entry>,
+    suggestions: Vec<String>,
+    redirect: Option<Redirect>,
+    file: Option<OutputFile>,
+}
+
+impl Output {
+    pub fn set_last(&mut self, result: ResultSet, sql: &str) {
+        *self = Self {
+            last: Some(result),
+            sql: Some(sql.to_string()),
+            transcript: std::mem::take(&mut self.transcript),
+            redirect: self.redirect.take(),
+            file: self.file.take(),
+            ..Self::default()
+        };
+    }
+
+    pub fn record(&mut self, entry: Entry) {
+        self.transcript.push(entry);
+    }
+
+    pub fn transcript(&self) -> &[Entry] {
+        &self.transcript
+    }
+
+    pub fn set_suggestions(&mut self, suggestions: Vec<String>) {
+        self.suggestions = suggestions;
+    }
+
+    pub fn suggestion(&self, line: &str) -> Option<String> {
+        let index = line.parse::<usize>().ok()?.checked_sub(1)?;
+        self.suggestions.get(index).cloned()
+    }
+
+    pub fn set_redirect(&mut self, redirect: Option<Redirect>) {
+        self.redirect = redirect;
+    }
+
+    pub fn is_redirected(&self) -> bool {
+        self.redirect.is_some() || self.file.is_some()
+    }
+
+    pub fn set_file(&mut self, file: Option<OutputFile>) {
+        self.file = file;
+    }
+
+    pub fn file(&self) -> Option<&OutputFile> {
+        self.file.as_ref()
+    }
+
+    pub fn take_redirect(&mut self) -> Option<Redirect> {
+        self.redirect.take()
+    }
+
+    pub fn last_sql(&self) -> Option<&str> {
+        self.sql.as_deref()
+    }
+
+    pub fn view(&self) -> Option<ResultSet> {
+        Some(self.state.apply(self.last.as_ref()?))
+    }
+
+    pub fn view_state(&self) -> Option<(&ResultSet, ViewState)> {
+        Some((self.last.as_ref()?, self.state.clone()))
+    }
+
+    pub fn set_view_state(&mut self, state: ViewState) {
+        self.state = state;
+        self.first_column = 0;
+    }
+
+    pub fn render(&self, layout: TableLayout) -> Option<String> {
+        let view = self.view()?;
+        Some(view.render_with(&TableLayout {
+            first_column: self.first_column,
+            ..layout
+        }))
+    }
+
+    fn last(&self) -> Result<&ResultSet> {
+        match &self.last {
+            Some(result) => Ok(result),
+            None => anyhow::bail!("No result set yet. Run a query first."),
+        }
+    }
+
+    fn column(&self, name: &str) -> Result<usize> {
+        let last = self.last()?;
+        match last.column_index(name) {
+            Some(i) => Ok(i),
+            None => anyhow::bail!(
+                "Unknown column: {} (available: {})",
+                name,
+                last.columns.join(", ")
+            ),
+        }
+    }
+
+    pub fn set_filter(&mut self, args: &str) -> Result<()> {
+        let last = self.last()?;
+        if args.is_empty() {
+            self.state.filter = None;
+            return Ok(());
+        }
+
+        self.state.filter = Some(match args.split_once(char::is_whitespace) {
+            Some((column, needle)) if last.column_index(column).is_some() => {
+                (last.column_index(column), needle.trim().to_string())
+            }
+            _ => (None, args.to_string()),
+        });
+        Ok(())
+    }
+
+    pub fn set_sort(&mut self, args: &str) -> Result<()> {
+        self.last()?;
+        let mut parts = args.split_whitespace();
+        let Some(column) = parts.next() else {
+            self.state.sort = None;
+            return Ok(());
+        };
+
+        let descending = match parts.next().map(|d| d.to_lowercase()).as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(other) => anyhow::bail!("Unknown sort direction: {} (use asc or desc)", other),
+        };
+        self.state.sort = Some((self.column(column)?, descending));
+        Ok(())
+    }
+
+    pub fn set_columns(&mut self, args: &str) -> Result<()> {
+        let last = self.last()?;
+        let names: Vec<&str> = args
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|n| !n.is_empty())
+            .collect();
+
+        if names.is_empty() {
+            self.state.columns = None;
+            return Ok(());
+        }
+
+        if names.iter().all(|n| n.starts_with('-')) {
+            let hidden = names
+                .iter()
+                .map(|n| self.column(&n[1..]))
+                .collect::<Result<Vec<_>>>()?;
+            self.state.columns = Some(
+                (0..last.columns.len())
+                    .filter(|i| !hidden.contains(i))
+                    .collect(),
+            );
+        } else {
+            self.state.columns = Some(
+                names
+                    .iter()
+                    .map(|n| self.column(n.trim_start_matches('+')))
+                    .collect::<Result<Vec<_>>>()?,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn scroll(&mut self, args: &str, layout: TableLayout) -> Result<()> {
+        self.last()?;
+        let Some(view) = self.view() else {
+            return Ok(());
+        };
+        let visible = view.visible_columns(&TableLayout {
+            first_column: self.first_column,
+            ..layout
+        });
+
+        self.first_column = match args {
+            "" | "right" => visible.end.min(view.columns.len().saturating_sub(1)),
+            "left" => self.first_column.saturating_sub(visible.len().max(1)),
+            "reset" => 0,
+            column => match view.column_index(column) {
+                Some(i) => i,
+                None => anyhow::bail!(
+                    "Unknown column: {} (available: {})",
+                    column,
+                    view.columns.join(", ")
+                ),
+            },
+        };
+        Ok(())
+    }
+}
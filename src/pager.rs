@@ -0,0 +1,57 @@
+use crate::accessibility;
+use crossterm::terminal;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const DEFAULT_PAGER: &str = "less -FRX";
+
+fn pager_command() -> Option<String> {
+    let pager = std::env::var("PSQL_PAGER")
+        .or_else(|_| std::env::var("PAGER"))
+        .unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    Some(pager).filter(|p| !p.trim().is_empty())
+}
+
+fn is_installed(pager: &str) -> bool {
+    let Some(program) = pager.split_whitespace().next() else {
+        return false;
+    };
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+pub fn page(text: &str) {
+    let height = terminal::size().map(|(_, h)| h as usize).unwrap_or(24);
+    let pager = pager_command()
+        .filter(|_| io::stdout().is_terminal() && !accessibility::enabled())
+        .filter(|_| text.lines().count() >= height)
+        .filter(|pager| is_installed(pager));
+
+    let Some(pager) = pager else {
+        print!("{}", text);
+        return;
+    };
+
+    let child = Command::new("sh")
+        .args(["-c", &pager])
+        .stdin(Stdio::piped())
+        .spawn();
+    let Ok(mut child) = child else {
+        print!("{}", text);
+        return;
+    };
+
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(text.as_bytes()).is_ok(),
+        None => false,
+    };
+    match child.wait() {
+        Ok(status) if matches!(status.code(), Some(126 | 127)) => print!("{}", text),
+        Ok(status) if written || status.success() => {}
+        _ => print!("{}", text),
+    }
+}
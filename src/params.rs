@@ -0,0 +1,184 @@
+use crate::schema::Schema;
+use anyhow::{Context, Result};
+use sqlparser::ast::{
+    visit_expressions, visit_expressions_mut, visit_relations, AssignmentTarget, Expr, SetExpr,
+    Statement, Value,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+#[derive(Debug, Clone)]
+pub struct Placeholder {
+    pub name: String,
+    pub column: Option<String>,
+    pub type_hint: Option<String>,
+}
+
+fn placeholder_name(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Value(Value::Placeholder(name)) => Some(name),
+        _ => None,
+    }
+}
+
+fn column_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Identifier(ident) => Some(ident.value.clone()),
+        Expr::CompoundIdentifier(parts) => parts.last().map(|i| i.value.clone()),
+        _ => None,
+    }
+}
+
+fn column_hints(statements: &[Statement]) -> HashMap<String, String> {
+    let mut hints = HashMap::new();
+    let mut hint = |value: &Expr, column: &Expr| {
+        if let (Some(name), Some(col)) = (placeholder_name(value), column_name(column)) {
+            hints.entry(name.to_string()).or_insert(col);
+        }
+    };
+
+    for statement in statements {
+        let _ = visit_expressions(statement, |expr| {
+            match expr {
+                Expr::BinaryOp { left, right, .. } => {
+                    hint(left, right);
+                    hint(right, left);
+                }
+                Expr::InList { expr, list, .. } => list.iter().for_each(|item| hint(item, expr)),
+                Expr::Between {
+                    expr, low, high, ..
+                } => {
+                    hint(low, expr);
+                    hint(high, expr);
+                }
+                Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } => {
+                    hint(pattern, expr)
+                }
+                _ => {}
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+
+    for statement in statements {
+        match statement {
+            Statement::Insert(insert) => {
+                let Some(source) = &insert.source else {
+                    continue;
+                };
+                if let SetExpr::Values(values) = source.body.as_ref() {
+                    for row in &values.rows {
+                        for (column, value) in insert.columns.iter().zip(row) {
+                            if let Some(name) = placeholder_name(value) {
+                                hints
+                                    .entry(name.to_string())
+                                    .or_insert_with(|| column.value.clone());
+                            }
+                        }
+                    }
+                }
+            }
+            Statement::Update { assignments, .. } => {
+                for assignment in assignments {
+                    if let (AssignmentTarget::ColumnName(column), Some(name)) =
+                        (&assignment.target, placeholder_name(&assignment.value))
+                    {
+                        if let Some(col) = column.0.last() {
+                            hints
+                                .entry(name.to_string())
+                                .or_insert_with(|| col.value.clone());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    hints
+}
+
+pub fn find_placeholders(sql: &str, schema: &Schema) -> Vec<Placeholder> {
+    let dialect = PostgreSqlDialect {};
+    let Ok(statements) = Parser::parse_sql(&dialect, sql) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = Vec::new();
+    let mut casts: HashMap<String, String> = HashMap::new();
+    let _ = visit_expressions(&statements, |expr| {
+        if let Some(name) = placeholder_name(expr) {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        if let Expr::Cast {
+            expr, data_type, ..
+        } = expr
+        {
+            if let Some(name) = placeholder_name(expr) {
+                casts.insert(name.to_string(), data_type.to_string());
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    names.sort_by_key(|name| {
+        name.strip_prefix('$')
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or(usize::MAX)
+    });
+
+    let mut referenced = Vec::new();
+    let _ = visit_relations(&statements, |relation| {
+        if let Some(table) = schema.find_table(&relation.to_string()) {
+            referenced.push(table);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    let hints = column_hints(&statements);
+
+    names
+        .into_iter()
+        .map(|name| {
+            let column = hints.get(&name).cloned();
+            let column_type = column.as_ref().and_then(|col| {
+                referenced
+                    .iter()
+                    .flat_map(|t| &t.columns)
+                    .find(|c| &c.name == col)
+                    .map(|c| c.data_type.clone())
+            });
+            let type_hint = casts.get(&name).cloned().or(column_type);
+            Placeholder {
+                name,
+                column,
+                type_hint,
+            }
+        })
+        .collect()
+}
+
+pub fn bind(sql: &str, values: &HashMap<String, Option<String>>) -> Result<String> {
+    let dialect = PostgreSqlDialect {};
+    let mut statements =
+        Parser::parse_sql(&dialect, sql).context("Failed to parse parameterized SQL")?;
+
+    let _ = visit_expressions_mut(&mut statements, |expr| {
+        if let Some(name) = placeholder_name(expr) {
+            if let Some(value) = values.get(name) {
+                *expr = Expr::Value(match value {
+                    Some(v) => Value::SingleQuotedString(v.clone()),
+                    None => Value::Null,
+                });
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    let bound: Vec<String> = statements.iter().map(|s| s.to_string()).collect();
+    Ok(format!("{};", bound.join(";\n")))
+}
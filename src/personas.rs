@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+const BUILTIN: &[(&str, &str)] = &[
+    (
+        "analyst",
+        "- The user is a data analyst exploring the data
+- Prefer aggregates (COUNT, SUM, AVG, GROUP BY) and readable column aliases over raw row dumps
+- Always end SELECT queries with a LIMIT (100 unless the question asks for more)
+- Do not generate DDL or data changes unless explicitly asked",
+    ),
+    (
+        "dba",
+        "- The user is a database administrator
+- DDL and maintenance statements (CREATE INDEX CONCURRENTLY, VACUUM, ANALYZE, REINDEX, ALTER TABLE) are welcome when they answer the question
+- Prefer the system catalogs and statistics views (pg_stat_*, pg_locks, pg_class) for questions about the server itself
+- Favor operations that avoid long exclusive locks",
+    ),
+    (
+        "developer",
+        "- The user is an application developer
+- Use explicit column lists instead of SELECT *
+- Use RETURNING on writes so the application can read back affected rows",
+    ),
+];
+
+pub fn guidance(custom: &BTreeMap<String, String>, name: &str) -> Option<String> {
+    custom.get(name).cloned().or_else(|| {
+        BUILTIN
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, guidance)| guidance.to_string())
+    })
+}
+
+pub fn names(custom: &BTreeMap<String, String>) -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN.iter().map(|(name, _)| name.to_string()).collect();
+    for name in custom.keys() {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
@@ -0,0 +1,224 @@
+use crate::psql::PsqlConnection;
+use crate::quoting::quote;
+use crate::schema::Schema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sqlparser::ast::{AssignmentTarget, SelectItem, Statement, TableFactor};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Deserialize)]
+struct UpdateSnapshot {
+    #[serde(skip)]
+    table: String,
+    before: Vec<Map<String, Value>>,
+    after: Vec<Map<String, Value>>,
+}
+
+struct SnapshotQuery {
+    table: String,
+    sql: String,
+}
+
+fn update_snapshot_query(sql: &str) -> Option<SnapshotQuery> {
+    let dialect = PostgreSqlDialect {};
+    let mut statements = Parser::parse_sql(&dialect, sql).ok()?;
+    if statements.len() != 1 {
+        return None;
+    }
+
+    let Statement::Update {
+        table,
+        from: None,
+        selection,
+        returning,
+        ..
+    } = &mut statements[0]
+    else {
+        return None;
+    };
+
+    if !table.joins.is_empty() {
+        return None;
+    }
+    let TableFactor::Table { name, .. } = &table.relation else {
+        return None;
+    };
+
+    let table_name = name.to_string();
+    let target = table.to_string();
+    let where_clause = selection
+        .as_ref()
+        .map(|s| format!(" WHERE {}", s))
+        .unwrap_or_default();
+
+    *returning = Some(vec![SelectItem::Wildcard(Default::default())]);
+    let update = statements[0].to_string();
+
+    let snapshot_sql = format!(
+        "WITH psqlm_before AS (SELECT * FROM {target}{where_clause}), \
+         psqlm_after AS ({update}) \
+         SELECT json_build_object(\
+         'before', (SELECT coalesce(json_agg(b), '[]'::json) FROM psqlm_before b), \
+         'after', (SELECT coalesce(json_agg(a), '[]'::json) FROM psqlm_after a))"
+    );
+
+    Some(SnapshotQuery {
+        table: table_name,
+        sql: snapshot_sql,
+    })
+}
+
+fn parse_snapshot(table: &str, output: &str) -> Option<UpdateSnapshot> {
+    let mut snapshot: UpdateSnapshot = serde_json::from_str(output.trim()).ok()?;
+    snapshot.table = table.to_string();
+    Some(snapshot)
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn render_diff(snapshot: &UpdateSnapshot, schema: &Schema) -> Option<String> {
+    let table = schema.find_table(&snapshot.table)?;
+    let pk = table.primary_key.as_ref()?;
+
+    let key_of = |row: &Map<String, Value>| -> Option<Vec<String>> {
+        pk.iter()
+            .map(|col| row.get(col).map(display_value))
+            .collect()
+    };
+
+    let columns: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+    let mut output = String::new();
+    let mut unchanged = 0;
+
+    if snapshot.after.is_empty() {
+        output.push_str("  (no rows matched)\n");
+    }
+
+    for after in &snapshot.after {
+        let key = key_of(after)?;
+        let before = snapshot
+            .before
+            .iter()
+            .find(|row| key_of(row).as_ref() == Some(&key));
+
+        let label: Vec<String> = pk
+            .iter()
+            .zip(&key)
+            .map(|(col, val)| format!("{}={}", col, val))
+            .collect();
+
+        let Some(before) = before else {
+            output.push_str(&format!("  Row {} (new)\n", label.join(", ")));
+            continue;
+        };
+
+        let changes: Vec<String> = columns
+            .iter()
+            .filter_map(|col| {
+                let old = before.get(*col).unwrap_or(&Value::Null);
+                let new = after.get(*col).unwrap_or(&Value::Null);
+                (old != new).then(|| {
+                    format!(
+                        "    {}: {RED}{}{RESET} -> {GREEN}{}{RESET}\n",
+                        col,
+                        display_value(old),
+                        display_value(new)
+                    )
+                })
+            })
+            .collect();
+
+        if changes.is_empty() {
+            unchanged += 1;
+            continue;
+        }
+
+        output.push_str(&format!("  Row {}:\n", label.join(", ")));
+        for change in changes {
+            output.push_str(&change);
+        }
+    }
+
+    if unchanged > 0 {
+        output.push_str(&format!("  ({} matched rows left unchanged)\n", unchanged));
+    }
+
+    Some(output)
+}
+
+pub fn update_diff(psql: &PsqlConnection, schema: &Schema, sql: &str) -> Option<String> {
+    let query = update_snapshot_query(sql)?;
+    let (success, stdout, _) = psql.query_in_rollback(&query.sql).ok()?;
+    if !success {
+        return None;
+    }
+
+    let snapshot = parse_snapshot(&query.table, &stdout)?;
+    render_diff(&snapshot, schema)
+}
+
+pub fn returning_columns(sql: &str, schema: &Schema) -> Option<String> {
+    let dialect = PostgreSqlDialect {};
+    let statements = Parser::parse_sql(&dialect, sql).ok()?;
+    let [statement] = statements.as_slice() else {
+        return None;
+    };
+
+    let (table, qualifier, changed) = match statement {
+        Statement::Update {
+            table, assignments, ..
+        } => {
+            let TableFactor::Table { name, alias, .. } = &table.relation else {
+                return None;
+            };
+            let qualifier = match alias {
+                Some(alias) => alias.name.to_string(),
+                None => name.to_string(),
+            };
+            let changed: Vec<String> = assignments
+                .iter()
+                .flat_map(|a| match &a.target {
+                    AssignmentTarget::ColumnName(column) => vec![column.clone()],
+                    AssignmentTarget::Tuple(columns) => columns.clone(),
+                })
+                .filter_map(|column| column.0.last().map(|ident| ident.to_string()))
+                .collect();
+            (name.to_string(), Some(qualifier), changed)
+        }
+        Statement::Insert(insert) if !insert.columns.is_empty() => (
+            insert.table_name.to_string(),
+            None,
+            insert.columns.iter().map(|c| c.to_string()).collect(),
+        ),
+        _ => return None,
+    };
+
+    let pk = schema.find_table(&table)?.primary_key.as_ref()?;
+    let mut columns: Vec<String> = pk.iter().map(|c| quote(c)).collect();
+    for column in changed {
+        let bare = column.trim_matches('"');
+        if !pk.iter().any(|c| c == bare) {
+            columns.push(column);
+        }
+    }
+
+    let columns: Vec<String> = match qualifier {
+        Some(qualifier) => columns
+            .iter()
+            .map(|c| format!("{}.{}", qualifier, c))
+            .collect(),
+        None => columns,
+    };
+    Some(columns.join(", "))
+}
@@ -0,0 +1,84 @@
+use crate::config::Profile;
+use crate::psql::PsqlConnection;
+use crate::schema::Schema;
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+pub struct ProfileConnections {
+    current: String,
+    profiles: BTreeMap<String, Profile>,
+    loaded: HashMap<String, (PsqlConnection, Schema)>,
+}
+
+impl ProfileConnections {
+    pub fn new(current: String, profiles: BTreeMap<String, Profile>) -> Self {
+        Self {
+            current,
+            profiles,
+            loaded: HashMap::new(),
+        }
+    }
+
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.profiles.keys().map(|name| name.as_str()).collect()
+    }
+
+    pub fn for_database(&self, database: &str) -> Vec<&str> {
+        self.profiles
+            .iter()
+            .filter(|(name, profile)| *name == database || profile.database == database)
+            .map(|(name, _)| name.as_str())
+            .filter(|name| *name != self.current)
+            .collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.profiles.contains_key(name)
+    }
+
+    pub fn get(&mut self, name: &str) -> Result<(&PsqlConnection, &Schema)> {
+        if !self.loaded.contains_key(name) {
+            let Some(profile) = self.profiles.get(name) else {
+                anyhow::bail!("Unknown profile: {}", name);
+            };
+
+            let psql = connect(profile)?;
+            println!("Connecting to {} ({})...", name, psql.database);
+            let schema = psql.introspect_schema()?;
+            self.loaded.insert(name.to_string(), (psql, schema));
+        }
+
+        let (psql, schema) = &self.loaded[name];
+        Ok((psql, schema))
+    }
+}
+
+pub fn connect(profile: &Profile) -> Result<PsqlConnection> {
+    let psql = PsqlConnection::new(
+        profile.host.clone(),
+        profile.port.clone(),
+        profile.user.clone(),
+        profile.database.clone(),
+        profile.password.clone(),
+    )
+    .with_auth(&profile.auth)?;
+    let Some(replica) = &profile.replica else {
+        return Ok(psql);
+    };
+
+    let replica = PsqlConnection::new(
+        replica.host.clone(),
+        replica.port.clone().unwrap_or_else(|| profile.port.clone()),
+        profile.user.clone(),
+        profile.database.clone(),
+        profile.password.clone(),
+    )
+    .with_auth(&profile.auth)?;
+    Ok(psql.with_replica(replica))
+}
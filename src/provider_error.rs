@@ -0,0 +1,162 @@
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+const OVERLOADED_BACKOFF: Duration = Duration::from_secs(2);
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(10);
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Overloaded,
+    RateLimited,
+    ContextTooLong,
+    ContentFiltered,
+    InvalidModel,
+    Authentication,
+    Other,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorDetail {
+    #[serde(rename = "type", default)]
+    pub error_type: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Debug)]
+pub struct ProviderError {
+    pub kind: ErrorKind,
+    status: Option<u16>,
+    message: String,
+    retry_after: Option<Duration>,
+}
+
+fn classify(status: Option<u16>, error_type: &str, message: &str) -> ErrorKind {
+    let message = message.to_lowercase();
+    if error_type == "overloaded_error" || matches!(status, Some(500 | 502 | 503 | 529)) {
+        ErrorKind::Overloaded
+    } else if error_type == "rate_limit_error" || status == Some(429) {
+        ErrorKind::RateLimited
+    } else if error_type == "request_too_large"
+        || status == Some(413)
+        || [
+            "prompt is too long",
+            "context window",
+            "context length",
+            "too many tokens",
+        ]
+        .iter()
+        .any(|phrase| message.contains(phrase))
+    {
+        ErrorKind::ContextTooLong
+    } else if message.contains("content filtering")
+        || message.contains("content policy")
+        || message.contains("content management policy")
+    {
+        ErrorKind::ContentFiltered
+    } else if message.starts_with("model:")
+        || ((error_type == "not_found_error" || status == Some(404)) && message.contains("model"))
+    {
+        ErrorKind::InvalidModel
+    } else if matches!(error_type, "authentication_error" | "permission_error")
+        || matches!(status, Some(401 | 403))
+    {
+        ErrorKind::Authentication
+    } else {
+        ErrorKind::Other
+    }
+}
+
+impl ProviderError {
+    pub fn from_response(status: u16, retry_after: Option<&str>, body: &str) -> Self {
+        let detail = serde_json::from_str::<ErrorBody>(body)
+            .map(|body| body.error)
+            .unwrap_or_else(|_| ErrorDetail {
+                error_type: String::new(),
+                message: body.trim().to_string(),
+            });
+        Self {
+            kind: classify(Some(status), &detail.error_type, &detail.message),
+            status: Some(status),
+            message: detail.message,
+            retry_after: retry_after
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs),
+        }
+    }
+
+    pub fn from_event(detail: ErrorDetail) -> Self {
+        Self {
+            kind: classify(None, &detail.error_type, &detail.message),
+            status: None,
+            message: detail.message,
+            retry_after: None,
+        }
+    }
+
+    pub fn refusal() -> Self {
+        Self {
+            kind: ErrorKind::ContentFiltered,
+            status: None,
+            message: "the model declined to answer".to_string(),
+            retry_after: None,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.kind {
+            ErrorKind::Overloaded => "The model API is temporarily overloaded",
+            ErrorKind::RateLimited => "Model API rate limit reached",
+            ErrorKind::ContextTooLong => "The request is too long for the model's context window",
+            ErrorKind::ContentFiltered => "The response was blocked by content filtering",
+            ErrorKind::InvalidModel => "The configured model is not available",
+            ErrorKind::Authentication => "The model API rejected the API key",
+            ErrorKind::Other => "Model API error",
+        }
+    }
+
+    fn guidance(&self) -> Option<&'static str> {
+        match self.kind {
+            ErrorKind::Overloaded => Some("This is usually brief; try again in a minute, or pick another model with \\models."),
+            ErrorKind::RateLimited => Some("Wait a moment before asking again, or set requests_per_minute and tokens_per_minute under [rate_limit] in config.toml to stay under your plan's limits."),
+            ErrorKind::ContextTooLong => Some("Ask about fewer tables or start a new topic so earlier questions are not sent, or choose a model with a larger context window."),
+            ErrorKind::ContentFiltered => Some("Rephrase the question; sending the same prompt again will be blocked again."),
+            ErrorKind::InvalidModel => Some("Pick an available model with \\models, or fix `model` in config.toml."),
+            ErrorKind::Authentication => Some("Check ANTHROPIC_API_KEY (OPENAI_API_KEY for OpenAI providers) or api_key in config.toml."),
+            ErrorKind::Other => None,
+        }
+    }
+
+    pub fn retry_delay(&self, retry: u32) -> Option<Duration> {
+        let delay = match self.kind {
+            ErrorKind::Overloaded => self
+                .retry_after
+                .unwrap_or(OVERLOADED_BACKOFF * 2u32.pow(retry)),
+            ErrorKind::RateLimited => self.retry_after.unwrap_or(DEFAULT_RATE_LIMIT_WAIT),
+            _ => return None,
+        };
+        (delay <= MAX_RETRY_WAIT).then_some(delay)
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{} ({}): {}", self.title(), status, self.message)?,
+            None => write!(f, "{}: {}", self.title(), self.message)?,
+        }
+        if let Some(guidance) = self.guidance() {
+            write!(f, "\n{}", guidance)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ProviderError {}
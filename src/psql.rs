@@ -1,18 +1,209 @@
-use crate::schema::{Column, ForeignKey, Index, Schema, Table};
+use crate::auth::TokenSource;
+use crate::config::{Auth, IsolationLevel};
+use crate::result::{csv_row, ResultSet, NULL_SENTINEL};
+use crate::schema::{
+    Column, CompositeType, EnumType, Extension, ForeignKey, Identity, Index, MaterializedView,
+    Schema, Table, View,
+};
 use anyhow::{Context, Result};
+use sqlparser::ast::{Expr, ObjectName, Query, SetExpr, Statement, TableFactor, Visit, Visitor};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
 use std::collections::HashMap;
-use std::process::Command;
+use std::io::{Read, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+pub const APPLICATION_NAME: &str = "psqlm";
+pub const DEFAULT_QUERY_TAG: &str = "psqlm user={user} prompt_hash={prompt_hash}";
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+fn prompt_hash(prompt: &str) -> String {
+    let hash = prompt.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let dialect = PostgreSqlDialect {};
+    let Ok(tokens) = Tokenizer::new(&dialect, sql)
+        .with_unescape(false)
+        .tokenize()
+    else {
+        return vec![sql.to_string()];
+    };
+
+    tokens
+        .split(|token| *token == Token::SemiColon)
+        .map(|statement| {
+            statement
+                .iter()
+                .skip_while(|token| matches!(token, Token::Whitespace(_)))
+                .map(Token::to_string)
+                .collect::<String>()
+        })
+        .filter(|statement| !statement.trim().is_empty())
+        .collect()
+}
+
+fn any_statement_starts_with(sql: &str, keywords: &[&str]) -> bool {
+    split_statements(sql).iter().any(|statement| {
+        let first_word = statement.split_whitespace().next().unwrap_or("");
+        keywords.contains(&first_word.to_uppercase().as_str())
+    })
+}
 
 pub fn is_write_operation(sql: &str) -> bool {
+    any_statement_starts_with(
+        sql,
+        &[
+            "INSERT", "UPDATE", "DELETE", "DROP", "ALTER", "TRUNCATE", "CREATE",
+        ],
+    )
+}
+
+pub fn is_schema_change(sql: &str) -> bool {
+    any_statement_starts_with(sql, &["CREATE", "ALTER", "DROP", "COMMENT"])
+}
+
+pub fn returns_rows(sql: &str) -> bool {
     let sql_upper = sql.trim().to_uppercase();
     let first_word = sql_upper.split_whitespace().next().unwrap_or("");
 
     matches!(
         first_word,
-        "INSERT" | "UPDATE" | "DELETE" | "DROP" | "ALTER" | "TRUNCATE" | "CREATE"
+        "SELECT" | "WITH" | "VALUES" | "TABLE" | "SHOW" | "EXPLAIN"
     )
 }
 
+const READ_ONLY_FUNCTIONS: &str = "\
+    abs age array_agg array_length array_position array_remove array_to_string avg bit_and \
+    bit_or bool_and bool_or btrim cardinality cast ceil ceiling char_length character_length \
+    clock_timestamp coalesce concat concat_ws corr count cume_dist current_date current_setting \
+    current_time current_timestamp current_user date_bin date_part date_trunc dense_rank div \
+    every exp extract first_value floor format generate_series generate_subscripts greatest \
+    initcap json_agg json_array_elements json_array_elements_text json_array_length \
+    json_build_array json_build_object json_each json_each_text json_extract_path \
+    json_extract_path_text json_object_agg json_object_keys json_typeof jsonb_agg \
+    jsonb_array_elements jsonb_array_elements_text jsonb_array_length jsonb_build_array \
+    jsonb_build_object jsonb_each jsonb_each_text jsonb_extract_path jsonb_extract_path_text \
+    jsonb_object_agg jsonb_object_keys jsonb_path_query jsonb_pretty jsonb_typeof justify_days \
+    justify_hours justify_interval lag last_value lead least left length ln localtime \
+    localtimestamp log lower lpad ltrim make_date make_interval make_time make_timestamp \
+    make_timestamptz max min mod mode now nth_value ntile nullif octet_length overlay \
+    percent_rank percentile_cont percentile_disc position power random rank regexp_count \
+    regexp_instr regexp_like regexp_match regexp_matches regexp_replace regexp_split_to_array \
+    regexp_split_to_table repeat replace reverse right round row_number row_to_json rpad rtrim \
+    sign split_part sqrt starts_with statement_timestamp stddev stddev_pop stddev_samp \
+    string_agg string_to_array strpos substr substring sum timezone to_char to_date to_json \
+    to_jsonb to_number to_timestamp transaction_timestamp translate trim trunc unnest upper \
+    var_pop var_samp variance width_bucket";
+
+struct ReadOnly;
+
+impl Visitor for ReadOnly {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        if !query.locks.is_empty() || modifies(&query.body) {
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<()> {
+        match factor {
+            TableFactor::Table {
+                name,
+                args: Some(_),
+                ..
+            } if !is_read_only_function(name) => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Function(function) if !is_read_only_function(&function.name) => {
+                ControlFlow::Break(())
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+pub fn is_read_only_function(name: &ObjectName) -> bool {
+    let qualified = match name.0.as_slice() {
+        [function] => Some(function),
+        [schema, function] if schema.value.eq_ignore_ascii_case("pg_catalog") => Some(function),
+        _ => None,
+    };
+    qualified.is_some_and(|function| {
+        function.quote_style.is_none()
+            && READ_ONLY_FUNCTIONS
+                .split_whitespace()
+                .any(|name| function.value.eq_ignore_ascii_case(name))
+    })
+}
+
+fn modifies(body: &SetExpr) -> bool {
+    match body {
+        SetExpr::Select(select) => select.into.is_some(),
+        SetExpr::Insert(_) | SetExpr::Update(_) => true,
+        SetExpr::SetOperation { left, right, .. } => modifies(left) || modifies(right),
+        _ => false,
+    }
+}
+
+fn is_read_only(statement: &Statement) -> bool {
+    match statement {
+        Statement::Query(_) => statement.visit(&mut ReadOnly).is_continue(),
+        Statement::Explain {
+            analyze: false,
+            statement,
+            options,
+            ..
+        } => {
+            let analyzes = options.iter().flatten().any(|option| {
+                option.name.value.eq_ignore_ascii_case("analyze")
+                    && !option.arg.as_ref().is_some_and(|arg| {
+                        matches!(
+                            arg.to_string().to_lowercase().as_str(),
+                            "false" | "off" | "0"
+                        )
+                    })
+            });
+            !analyzes && is_read_only(statement)
+        }
+        _ => false,
+    }
+}
+
+pub fn is_read_only_sql(sql: &str) -> bool {
+    let dialect = PostgreSqlDialect {};
+    Parser::parse_sql(&dialect, sql)
+        .is_ok_and(|statements| !statements.is_empty() && statements.iter().all(is_read_only))
+}
+
+fn split_host(host: &str, port: String) -> (String, String) {
+    let Some(bracketed) = host.strip_prefix('[') else {
+        return (host.to_string(), port);
+    };
+    match bracketed.split_once(']') {
+        Some((address, "")) => (address.to_string(), port),
+        Some((address, rest)) => match rest.strip_prefix(':') {
+            Some(port) if !port.is_empty() => (address.to_string(), port.to_string()),
+            _ => (host.to_string(), port),
+        },
+        None => (host.to_string(), port),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PsqlConnection {
     pub host: String,
@@ -20,6 +211,11 @@ pub struct PsqlConnection {
     pub user: String,
     pub database: String,
     pub password: Option<String>,
+    pub tag: Option<String>,
+    pub isolation: Option<IsolationLevel>,
+    verbose_errors: bool,
+    token_source: Option<Arc<TokenSource>>,
+    replica: Option<Box<PsqlConnection>>,
 }
 
 impl PsqlConnection {
@@ -30,23 +226,176 @@ impl PsqlConnection {
         database: String,
         password: Option<String>,
     ) -> Self {
+        let (host, port) = split_host(&host, port);
         Self {
             host,
             port,
             user,
             database,
             password,
+            tag: None,
+            isolation: None,
+            verbose_errors: false,
+            token_source: None,
+            replica: None,
+        }
+    }
+
+    pub fn with_auth(self, auth: &Auth) -> Result<Self> {
+        let token_source =
+            TokenSource::from_config(auth, &self.host, &self.port, &self.user, &self.database)?;
+        Ok(Self {
+            token_source: token_source.map(Arc::new),
+            ..self
+        })
+    }
+
+    pub fn with_replica(self, replica: PsqlConnection) -> Self {
+        Self {
+            replica: Some(Box::new(replica)),
+            ..self
+        }
+    }
+
+    pub fn replica(&self) -> Option<&PsqlConnection> {
+        self.replica.as_deref()
+    }
+
+    pub fn for_read(&self, sql: &str) -> &PsqlConnection {
+        match &self.replica {
+            Some(replica)
+                if is_read_only_sql(sql)
+                    && self.isolation != Some(IsolationLevel::Serializable) =>
+            {
+                replica
+            }
+            _ => self,
+        }
+    }
+
+    pub fn token_source(&self) -> Option<&TokenSource> {
+        self.token_source.as_deref()
+    }
+
+    pub fn current_password(&self) -> Option<String> {
+        match &self.token_source {
+            Some(source) => match source.token() {
+                Ok(token) => Some(token),
+                Err(e) => {
+                    eprintln!("{:#}", e);
+                    None
+                }
+            },
+            None => self.password.clone(),
         }
     }
 
+    pub fn tagged(&self, format: &str, prompt: &str) -> Self {
+        if format.trim().is_empty() {
+            return self.clone();
+        }
+
+        let os_user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| self.user.clone());
+        let tag = format
+            .replace("{user}", &os_user)
+            .replace("{db_user}", &self.user)
+            .replace("{database}", &self.database)
+            .replace("{prompt_hash}", &prompt_hash(prompt))
+            .replace("*/", "* /");
+
+        Self {
+            tag: Some(format!("/* {} */", tag.trim())),
+            replica: self
+                .replica
+                .as_ref()
+                .map(|replica| Box::new(replica.tagged(format, prompt))),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_isolation(&self, isolation: Option<IsolationLevel>) -> Self {
+        Self {
+            isolation,
+            replica: self
+                .replica
+                .as_ref()
+                .map(|replica| Box::new(replica.with_isolation(isolation))),
+            ..self.clone()
+        }
+    }
+
+    // Errors carry their SQLSTATE (`ERROR:  40001: ...`), so callers can tell failures apart
+    // without matching message text.
+    pub fn with_verbose_errors(&self) -> Self {
+        Self {
+            verbose_errors: true,
+            replica: self
+                .replica
+                .as_ref()
+                .map(|replica| Box::new(replica.with_verbose_errors())),
+            ..self.clone()
+        }
+    }
+
+    fn begin(&self) -> String {
+        match self.isolation {
+            Some(level) => format!("BEGIN ISOLATION LEVEL {}", level.as_sql()),
+            None => "BEGIN".to_string(),
+        }
+    }
+
+    pub fn is_socket(&self) -> bool {
+        self.host.starts_with('/') || self.host.starts_with('@')
+    }
+
+    pub fn address(&self) -> String {
+        if self.is_socket() {
+            format!("{}/.s.PGSQL.{}", self.host.trim_end_matches('/'), self.port)
+        } else if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+
+    pub fn tag_line_count(&self) -> usize {
+        self.tag.as_ref().map_or(0, |tag| tag.lines().count())
+    }
+
+    fn tag_sql(&self, sql: &str) -> String {
+        match &self.tag {
+            Some(tag) => format!("{}\n{}", tag, sql),
+            None => sql.to_string(),
+        }
+    }
+
+    pub fn storage_key(&self) -> String {
+        format!("{}_{}_{}", self.host, self.port, self.database)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect()
+    }
+
     fn base_command(&self) -> Command {
         let mut cmd = Command::new("psql");
-        cmd.args(["-h", &self.host])
+        cmd.env("PGAPPNAME", APPLICATION_NAME)
+            .args(["-h", &self.host])
             .args(["-p", &self.port])
             .args(["-U", &self.user])
             .args(["-d", &self.database]);
+        if self.verbose_errors {
+            cmd.args(["-v", "VERBOSITY=verbose"]);
+        }
 
-        if let Some(pw) = &self.password {
+        if let Some(pw) = self.current_password() {
             cmd.env("PGPASSWORD", pw);
         }
 
@@ -69,10 +418,107 @@ impl PsqlConnection {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 
+    // Column names and types of the last statement's result, from psql's \gdesc, which plans the
+    // query without running it.
+    pub fn describe(&self, sql: &str) -> Result<Vec<(String, String)>> {
+        let statement = split_statements(sql).pop().unwrap_or_default();
+        let mut child = self
+            .base_command()
+            .args(["--csv", "-q", "-v", "ON_ERROR_STOP=1", "-f", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to execute psql")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(format!("{}\n\\gdesc\n", statement.trim_end_matches(';')).as_bytes())?;
+        }
+        let output = child.wait_with_output().context("Failed to execute psql")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to describe the result: {}", stderr.trim());
+        }
+        let description = ResultSet::from_csv(&String::from_utf8_lossy(&output.stdout))?;
+        Ok(description
+            .rows
+            .into_iter()
+            .filter_map(|row| match row.as_slice() {
+                [Some(name), Some(data_type)] => Some((name.clone(), data_type.clone())),
+                _ => None,
+            })
+            .collect())
+    }
+
+    pub fn lo_export(&self, oid: u32, path: &Path) -> Result<()> {
+        let path = path.to_string_lossy().replace('\'', "''");
+        let output = self
+            .base_command()
+            .args(["-c", &format!("\\lo_export {} '{}'", oid, path)])
+            .output()
+            .context("Failed to execute psql")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to export large object {}: {}", oid, stderr.trim());
+        }
+        Ok(())
+    }
+
     pub fn execute_capture(&self, sql: &str) -> Result<(bool, String, String)> {
         let output = self
             .base_command()
-            .args(["-c", sql])
+            .args(["-c", &self.tag_sql(sql)])
+            .output()
+            .context("Failed to execute psql")?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        Ok((output.status.success(), stdout, stderr))
+    }
+
+    pub fn execute_csv(&self, sql: &str) -> Result<(bool, String, String)> {
+        let output = self
+            .base_command()
+            .args(["--csv", "-q"])
+            .args(["-P", &format!("null={}", NULL_SENTINEL)])
+            .args(["-c", &self.tag_sql(sql)])
+            .output()
+            .context("Failed to execute psql")?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        Ok((output.status.success(), stdout, stderr))
+    }
+
+    pub fn execute_single_transaction(
+        &self,
+        sql: &str,
+        csv: bool,
+    ) -> Result<(bool, String, String)> {
+        let mut command = self.base_command();
+        if csv {
+            command
+                .args(["--csv", "-q"])
+                .args(["-P", &format!("null={}", NULL_SENTINEL)]);
+        }
+        command
+            .args(["--single-transaction", "-v", "ON_ERROR_STOP=1"])
+            .args(["-c", "\\set QUIET on"]);
+        if let Some(level) = self.isolation {
+            command.args([
+                "-c",
+                &format!("SET TRANSACTION ISOLATION LEVEL {}", level.as_sql()),
+            ]);
+        }
+        if !csv {
+            command.args(["-c", "\\set QUIET off"]);
+        }
+        let output = command
+            .args(["-c", &self.tag_sql(sql)])
             .output()
             .context("Failed to execute psql")?;
 
@@ -91,8 +537,9 @@ impl PsqlConnection {
 
         let output = self
             .base_command()
-            .args(["-c", "BEGIN"])
-            .args(["-c", sql])
+            .args(["-v", "ON_ERROR_STOP=1"])
+            .args(["-c", &self.begin()])
+            .args(["-c", &self.tag_sql(sql)])
             .args(["-c", transaction_end])
             .output()
             .context("Failed to execute psql")?;
@@ -103,18 +550,209 @@ impl PsqlConnection {
         Ok((output.status.success(), stdout, stderr))
     }
 
-    pub fn preview_write_with_returning(&self, sql: &str) -> Result<(bool, String, String)> {
-        let sql_with_returning = if sql.to_uppercase().contains("RETURNING") {
+    pub fn execute_rolled_back(&self, steps: &[String]) -> Result<(bool, String, String)> {
+        let mut command = self.base_command();
+        command
+            .args(["-v", "ON_ERROR_STOP=1"])
+            .args(["-c", &self.begin()]);
+        for step in steps {
+            command.args(["-c", step]);
+        }
+        let output = command
+            .args(["-c", "ROLLBACK"])
+            .output()
+            .context("Failed to execute psql")?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        Ok((output.status.success(), stdout, stderr))
+    }
+
+    pub fn preview_write_with_returning(
+        &self,
+        sql: &str,
+        columns: &str,
+    ) -> Result<(bool, String, String)> {
+        let trimmed = sql.trim().trim_end_matches(';');
+        let sql_upper = trimmed.to_uppercase();
+        let first_word = sql_upper.split_whitespace().next().unwrap_or("");
+        // DDL is previewed here too, and RETURNING only exists on INSERT, UPDATE and DELETE.
+        let sql_with_returning = if split_statements(trimmed).len() > 1
+            || sql_upper.contains("RETURNING")
+            || !matches!(first_word, "INSERT" | "UPDATE" | "DELETE")
+        {
             sql.to_string()
         } else {
-            let trimmed = sql.trim().trim_end_matches(';');
-            format!("{} RETURNING *;", trimmed)
+            format!("{} RETURNING {};", trimmed, columns)
+        };
+
+        let output = self
+            .base_command()
+            .args(["-v", "ON_ERROR_STOP=1"])
+            .args(["-c", &self.begin()])
+            .args(["-c", &self.tag_sql(&sql_with_returning)])
+            .args(["-c", "ROLLBACK"])
+            .output()
+            .context("Failed to execute psql")?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        Ok((output.status.success(), stdout, stderr))
+    }
+
+    pub fn explain_json(&self, sql: &str, verbose: bool) -> Result<serde_json::Value> {
+        let sql = sql.trim().trim_end_matches(';');
+        let options = if verbose {
+            "FORMAT JSON, VERBOSE"
+        } else {
+            "FORMAT JSON"
         };
+        let plan = self.query(&format!("EXPLAIN ({}) {}", options, sql))?;
+        let plan: serde_json::Value =
+            serde_json::from_str(plan.trim()).context("Failed to parse query plan")?;
+        Ok(plan[0]["Plan"].clone())
+    }
+
+    pub fn estimated_rows(&self, sql: &str) -> Result<Option<u64>> {
+        let plan = self.explain_json(sql, false)?;
+        Ok(plan["Plan Rows"].as_f64().map(|rows| rows as u64))
+    }
+
+    pub fn table_rows(&self, relations: &[String]) -> Result<HashMap<String, f64>> {
+        if relations.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let oids: Vec<String> = relations
+            .iter()
+            .map(|r| format!("'{}'::regclass", r.replace('\'', "''")))
+            .collect();
+        let output = self.query(&format!(
+            "SELECT n.nspname || '.' || c.relname, c.reltuples FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE c.oid IN ({})",
+            oids.join(", ")
+        ))?;
+
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let (name, rows) = line.split_once('|')?;
+                Some((name.to_string(), rows.trim().parse().ok()?))
+            })
+            .collect())
+    }
+
+    pub fn stream_csv(
+        &self,
+        sql: &str,
+        fetch_count: usize,
+        stop: &AtomicBool,
+        mut on_batch: impl FnMut(ResultSet),
+    ) -> Result<(bool, usize, String)> {
+        let mut child = self
+            .base_command()
+            .args(["--csv", "-q"])
+            .args(["-P", &format!("null={}", NULL_SENTINEL)])
+            .args(["-v", &format!("FETCH_COUNT={}", fetch_count)])
+            .args(["-c", &self.tag_sql(sql)])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to execute psql")?;
+
+        let stdout = child.stdout.take().context("Failed to read psql output")?;
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(stdout);
+        let columns: Vec<String> = match reader.headers() {
+            Ok(headers) => headers.iter().map(|h| h.to_string()).collect(),
+            Err(_) => Vec::new(),
+        };
+
+        let mut batch = Vec::new();
+        let mut total = 0;
+        for record in reader.records() {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let Ok(record) = record else {
+                break;
+            };
+            batch.push(csv_row(&record, columns.len()));
+            if batch.len() == fetch_count {
+                total += batch.len();
+                on_batch(ResultSet::new(columns.clone(), std::mem::take(&mut batch)));
+            }
+        }
+        if !batch.is_empty() && !stop.load(Ordering::Relaxed) {
+            total += batch.len();
+            on_batch(ResultSet::new(columns, batch));
+        }
+
+        if stop.load(Ordering::Relaxed) {
+            let _ = child.kill();
+        }
+        let output = child.wait_with_output().context("Failed to execute psql")?;
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        Ok((output.status.success(), total, stderr))
+    }
+
+    pub fn copy_out(
+        &self,
+        sql: &str,
+        writer: &mut impl Write,
+        stop: &AtomicBool,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<(bool, u64, String)> {
+        let mut child = self
+            .base_command()
+            .args(["-q", "-v", "ON_ERROR_STOP=1"])
+            .args(["-c", &self.tag_sql(sql)])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to execute psql")?;
+
+        let mut stdout = child.stdout.take().context("Failed to read psql output")?;
+        // Drained on its own thread so a chatty stderr cannot fill its pipe and stall the copy.
+        let mut stderr = child.stderr.take().context("Failed to read psql errors")?;
+        let errors = std::thread::spawn(move || {
+            let mut errors = Vec::new();
+            let _ = stderr.read_to_end(&mut errors);
+            errors
+        });
+        let mut buffer = vec![0; COPY_CHUNK_SIZE];
+        let mut total = 0;
+        while !stop.load(Ordering::Relaxed) {
+            let read = stdout.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..read])?;
+            total += read as u64;
+            on_progress(total);
+        }
+        writer.flush()?;
 
+        if stop.load(Ordering::Relaxed) {
+            let _ = child.kill();
+        }
+        let status = child.wait().context("Failed to execute psql")?;
+        let errors = errors.join().unwrap_or_default();
+        let stderr = String::from_utf8_lossy(&errors).to_string();
+
+        Ok((status.success(), total, stderr))
+    }
+
+    pub fn query_in_rollback(&self, sql: &str) -> Result<(bool, String, String)> {
         let output = self
             .base_command()
-            .args(["-c", "BEGIN"])
-            .args(["-c", &sql_with_returning])
+            .args(["-q", "-t", "-A"])
+            .args(["-v", "ON_ERROR_STOP=1"])
+            .args(["-c", &self.begin()])
+            .args(["-c", &self.tag_sql(sql)])
             .args(["-c", "ROLLBACK"])
             .output()
             .context("Failed to execute psql")?;
@@ -125,6 +763,131 @@ impl PsqlConnection {
         Ok((output.status.success(), stdout, stderr))
     }
 
+    pub fn is_superuser(&self) -> Result<bool> {
+        let output = self.query("SELECT rolsuper FROM pg_roles WHERE rolname = current_user")?;
+        Ok(output.trim() == "t")
+    }
+
+    pub fn activity(&self) -> Result<ResultSet> {
+        let sql = r#"
+            SELECT
+                pid,
+                datname AS database,
+                usename AS user,
+                application_name AS application,
+                state,
+                date_trunc('second', now() - query_start)::text AS duration,
+                left(regexp_replace(query, '\s+', ' ', 'g'), 60) AS query
+            FROM pg_stat_activity
+            WHERE backend_type = 'client backend'
+              AND pid <> pg_backend_pid()
+              AND state IS DISTINCT FROM 'idle'
+            ORDER BY query_start
+        "#;
+
+        let (success, stdout, stderr) = self.execute_csv(sql)?;
+        if !success {
+            anyhow::bail!("psql query failed: {}", stderr);
+        }
+
+        ResultSet::from_csv(&stdout)
+    }
+
+    pub fn locks(&self) -> Result<ResultSet> {
+        let sql = r#"
+            SELECT
+                blocked.pid AS blocked_pid,
+                blocked.usename AS blocked_user,
+                date_trunc('second', now() - blocked.query_start)::text AS waiting,
+                left(regexp_replace(blocked.query, '\s+', ' ', 'g'), 50) AS blocked_query,
+                blocking.pid AS blocking_pid,
+                blocking.usename AS blocking_user,
+                blocking.state AS blocking_state,
+                left(regexp_replace(blocking.query, '\s+', ' ', 'g'), 50) AS blocking_query
+            FROM pg_stat_activity blocked
+            CROSS JOIN LATERAL unnest(pg_blocking_pids(blocked.pid)) AS holder(pid)
+            JOIN pg_stat_activity blocking ON blocking.pid = holder.pid
+            ORDER BY blocked.query_start
+        "#;
+
+        let (success, stdout, stderr) = self.execute_csv(sql)?;
+        if !success {
+            anyhow::bail!("psql query failed: {}", stderr);
+        }
+
+        ResultSet::from_csv(&stdout)
+    }
+
+    pub fn table_usage(&self, limit: usize) -> Result<ResultSet> {
+        let sql = format!(
+            r#"
+            SELECT
+                t.schemaname || '.' || t.relname AS table,
+                coalesce(t.seq_tup_read, 0) + coalesce(t.idx_tup_fetch, 0) AS rows_read,
+                t.n_tup_ins + t.n_tup_upd + t.n_tup_del AS rows_written,
+                t.seq_scan,
+                coalesce(t.idx_scan, 0) AS idx_scan,
+                CASE WHEN io.heap_blks_hit + io.heap_blks_read > 0
+                    THEN round(100.0 * io.heap_blks_hit / (io.heap_blks_hit + io.heap_blks_read), 1)::text || '%'
+                END AS cache_hit,
+                CASE WHEN coalesce(io.idx_blks_hit, 0) + coalesce(io.idx_blks_read, 0) > 0
+                    THEN round(100.0 * io.idx_blks_hit / (io.idx_blks_hit + io.idx_blks_read), 1)::text || '%'
+                END AS index_cache_hit
+            FROM pg_stat_user_tables t
+            JOIN pg_statio_user_tables io USING (relid)
+            ORDER BY coalesce(t.seq_tup_read, 0) + coalesce(t.idx_tup_fetch, 0)
+                + t.n_tup_ins + t.n_tup_upd + t.n_tup_del DESC, 1
+            LIMIT {}
+        "#,
+            limit
+        );
+
+        let (success, stdout, stderr) = self.execute_csv(&sql)?;
+        if !success {
+            anyhow::bail!("psql query failed: {}", stderr);
+        }
+
+        ResultSet::from_csv(&stdout)
+    }
+
+    pub fn cancel_backend(&self, pid: &str) -> Result<bool> {
+        let pid: i32 = pid.parse().context("Invalid backend pid")?;
+        let output = self.query(&format!("SELECT pg_cancel_backend({})", pid))?;
+        Ok(output.trim() == "t")
+    }
+
+    pub fn backend(&self, pid: &str) -> Result<ResultSet> {
+        let pid: i32 = pid.parse().context("Invalid backend pid")?;
+        let sql = format!(
+            r#"
+            SELECT
+                pid,
+                datname AS database,
+                usename AS user,
+                application_name AS application,
+                state,
+                date_trunc('second', now() - xact_start)::text AS transaction_age,
+                left(regexp_replace(query, '\s+', ' ', 'g'), 60) AS query
+            FROM pg_stat_activity
+            WHERE pid = {}
+        "#,
+            pid
+        );
+
+        let (success, stdout, stderr) = self.execute_csv(&sql)?;
+        if !success {
+            anyhow::bail!("psql query failed: {}", stderr);
+        }
+
+        ResultSet::from_csv(&stdout)
+    }
+
+    pub fn terminate_backend(&self, pid: &str) -> Result<bool> {
+        let pid: i32 = pid.parse().context("Invalid backend pid")?;
+        let output = self.query(&format!("SELECT pg_terminate_backend({})", pid))?;
+        Ok(output.trim() == "t")
+    }
+
     pub fn introspect_schema(&self) -> Result<Schema> {
         let mut tables: HashMap<String, Table> = HashMap::new();
 
@@ -132,11 +895,16 @@ impl PsqlConnection {
             SELECT
                 table_schema || '.' || table_name,
                 column_name,
-                data_type,
+                CASE WHEN data_type = 'USER-DEFINED' THEN udt_name ELSE data_type END,
                 is_nullable,
-                column_default
+                column_default,
+                identity_generation,
+                generation_expression
             FROM information_schema.columns
             WHERE table_schema NOT IN ('pg_catalog', 'information_schema', 'pg_toast')
+            AND (table_schema, table_name) NOT IN (
+                SELECT table_schema, table_name FROM information_schema.views
+            )
             ORDER BY table_schema, table_name, ordinal_position
         "#;
 
@@ -160,6 +928,16 @@ impl PsqlConnection {
                             Some(s.to_string())
                         }
                     }),
+                    comment: None,
+                    identity: match parts.get(5).map(|s| s.trim()) {
+                        Some("ALWAYS") => Some(Identity::Always),
+                        Some("BY DEFAULT") => Some(Identity::ByDefault),
+                        _ => None,
+                    },
+                    generated: parts
+                        .get(6..)
+                        .map(|rest| rest.join("|").trim().to_string())
+                        .filter(|s| !s.is_empty()),
                 };
 
                 tables
@@ -170,6 +948,7 @@ impl PsqlConnection {
                         primary_key: None,
                         foreign_keys: Vec::new(),
                         indexes: Vec::new(),
+                        comment: None,
                     })
                     .columns
                     .push(column);
@@ -292,8 +1071,261 @@ impl PsqlConnection {
             }
         }
 
+        let comments_sql = r#"
+            SELECT
+                n.nspname || '.' || c.relname,
+                coalesce(a.attname, ''),
+                regexp_replace(d.description, '\s+', ' ', 'g')
+            FROM pg_description d
+            JOIN pg_class c ON c.oid = d.objoid AND d.classoid = 'pg_class'::regclass
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            LEFT JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum = d.objsubid
+            WHERE n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast')
+        "#;
+
+        let output = self.query(comments_sql)?;
+        for line in output.lines() {
+            let parts: Vec<&str> = line.splitn(3, '|').collect();
+            if parts.len() < 3 {
+                continue;
+            }
+            let Some(table) = tables.get_mut(parts[0].trim()) else {
+                continue;
+            };
+            let comment = Some(parts[2].trim().to_string());
+            if parts[1].is_empty() {
+                table.comment = comment;
+            } else if let Some(column) = table.columns.iter_mut().find(|c| c.name == parts[1]) {
+                column.comment = comment;
+            }
+        }
+
         Ok(Schema {
             tables: tables.into_values().collect(),
+            materialized_views: self.introspect_materialized_views()?,
+            views: self.introspect_views()?,
+            enums: self.introspect_enums()?,
+            composite_types: self.introspect_composite_types()?,
+            extensions: self.introspect_extensions()?,
         })
     }
+
+    fn introspect_extensions(&self) -> Result<Vec<Extension>> {
+        let output = self.query(
+            "SELECT extname, extversion FROM pg_extension WHERE extname <> 'plpgsql' ORDER BY extname",
+        )?;
+
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once('|'))
+            .map(|(name, version)| Extension {
+                name: name.trim().to_string(),
+                version: version.trim().to_string(),
+            })
+            .collect())
+    }
+
+    fn introspect_enums(&self) -> Result<Vec<EnumType>> {
+        let output = self.query(
+            r#"
+            SELECT t.oid::regtype::text, e.enumlabel
+            FROM pg_type t
+            JOIN pg_enum e ON e.enumtypid = t.oid
+            JOIN pg_namespace n ON n.oid = t.typnamespace
+            WHERE n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast')
+            ORDER BY 1, e.enumsortorder
+        "#,
+        )?;
+
+        let mut enums: Vec<EnumType> = Vec::new();
+        for (name, value) in output.lines().filter_map(|line| line.split_once('|')) {
+            match enums.last_mut() {
+                Some(last) if last.name == name => last.values.push(value.to_string()),
+                _ => enums.push(EnumType {
+                    name: name.to_string(),
+                    values: vec![value.to_string()],
+                }),
+            }
+        }
+        Ok(enums)
+    }
+
+    fn introspect_composite_types(&self) -> Result<Vec<CompositeType>> {
+        let output = self.query(
+            r#"
+            SELECT t.oid::regtype::text, a.attname, format_type(a.atttypid, a.atttypmod)
+            FROM pg_type t
+            JOIN pg_class c ON c.oid = t.typrelid AND c.relkind = 'c'
+            JOIN pg_namespace n ON n.oid = t.typnamespace
+            JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped
+            WHERE n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast')
+            ORDER BY 1, a.attnum
+        "#,
+        )?;
+
+        let mut types: Vec<CompositeType> = Vec::new();
+        for line in output.lines() {
+            let parts: Vec<&str> = line.split('|').collect();
+            if parts.len() < 3 {
+                continue;
+            }
+            let field = Column {
+                name: parts[1].to_string(),
+                data_type: parts[2].to_string(),
+                is_nullable: true,
+                default: None,
+                comment: None,
+                identity: None,
+                generated: None,
+            };
+            match types.last_mut() {
+                Some(last) if last.name == parts[0] => last.fields.push(field),
+                _ => types.push(CompositeType {
+                    name: parts[0].to_string(),
+                    fields: vec![field],
+                }),
+            }
+        }
+        Ok(types)
+    }
+
+    fn introspect_views(&self) -> Result<Vec<View>> {
+        let views_sql = r#"
+            SELECT
+                n.nspname || '.' || c.relname,
+                regexp_replace(pg_get_viewdef(c.oid, true), '\s+', ' ', 'g')
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE c.relkind = 'v'
+            AND n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast')
+            ORDER BY 1
+        "#;
+
+        let mut views: Vec<View> = Vec::new();
+        let output = self.query(views_sql)?;
+        for line in output.lines() {
+            let Some((name, definition)) = line.split_once('|') else {
+                continue;
+            };
+            let definition = definition.trim();
+            views.push(View {
+                name: name.trim().to_string(),
+                columns: Vec::new(),
+                definition: (!definition.is_empty()).then(|| definition.to_string()),
+            });
+        }
+
+        let columns_sql = r#"
+            SELECT
+                n.nspname || '.' || c.relname,
+                a.attname,
+                format_type(a.atttypid, a.atttypmod)
+            FROM pg_attribute a
+            JOIN pg_class c ON c.oid = a.attrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE c.relkind = 'v' AND a.attnum > 0 AND NOT a.attisdropped
+            AND n.nspname NOT IN ('pg_catalog', 'information_schema', 'pg_toast')
+            ORDER BY n.nspname, c.relname, a.attnum
+        "#;
+
+        let output = self.query(columns_sql)?;
+        for line in output.lines() {
+            let parts: Vec<&str> = line.split('|').collect();
+            if parts.len() < 3 {
+                continue;
+            }
+            if let Some(view) = views.iter_mut().find(|v| v.name == parts[0].trim()) {
+                view.columns.push(Column {
+                    name: parts[1].trim().to_string(),
+                    data_type: parts[2].trim().to_string(),
+                    is_nullable: true,
+                    default: None,
+                    comment: None,
+                    identity: None,
+                    generated: None,
+                });
+            }
+        }
+
+        Ok(views)
+    }
+
+    fn introspect_materialized_views(&self) -> Result<Vec<MaterializedView>> {
+        let mut views: HashMap<String, MaterializedView> = HashMap::new();
+
+        let matview_sql = r#"
+            SELECT
+                n.nspname || '.' || c.relname,
+                m.ispopulated,
+                CASE WHEN has_function_privilege('pg_stat_file(text, boolean)', 'execute')
+                    THEN (pg_stat_file(pg_relation_filepath(c.oid), true)).modification::text
+                END,
+                EXISTS (
+                    SELECT 1 FROM pg_index i
+                    WHERE i.indrelid = c.oid AND i.indisunique AND i.indpred IS NULL
+                )
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            JOIN pg_matviews m ON m.schemaname = n.nspname AND m.matviewname = c.relname
+            WHERE c.relkind = 'm'
+        "#;
+
+        let output = self.query(matview_sql)?;
+        for line in output.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parts: Vec<&str> = line.split('|').collect();
+            if parts.len() >= 4 {
+                let name = parts[0].trim().to_string();
+                let last_refresh = parts[2].trim();
+                views.insert(
+                    name.clone(),
+                    MaterializedView {
+                        name,
+                        columns: Vec::new(),
+                        is_populated: parts[1].trim() == "t",
+                        last_refresh: (!last_refresh.is_empty()).then(|| last_refresh.to_string()),
+                        has_unique_index: parts[3].trim() == "t",
+                    },
+                );
+            }
+        }
+
+        let columns_sql = r#"
+            SELECT
+                n.nspname || '.' || c.relname,
+                a.attname,
+                format_type(a.atttypid, a.atttypmod),
+                NOT a.attnotnull
+            FROM pg_attribute a
+            JOIN pg_class c ON c.oid = a.attrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE c.relkind = 'm' AND a.attnum > 0 AND NOT a.attisdropped
+            ORDER BY n.nspname, c.relname, a.attnum
+        "#;
+
+        let output = self.query(columns_sql)?;
+        for line in output.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parts: Vec<&str> = line.split('|').collect();
+            if parts.len() >= 4 {
+                if let Some(view) = views.get_mut(parts[0].trim()) {
+                    view.columns.push(Column {
+                        name: parts[1].trim().to_string(),
+                        data_type: parts[2].trim().to_string(),
+                        is_nullable: parts[3].trim() == "t",
+                        default: None,
+                        comment: None,
+                        identity: None,
+                        generated: None,
+                    });
+                }
+            }
+        }
+
+        Ok(views.into_values().collect())
+    }
 }
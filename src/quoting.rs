@@ -0,0 +1,263 @@
+use crate::naming;
+use crate::schema::Schema;
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::keywords::{Keyword, RESERVED_FOR_COLUMN_ALIAS, RESERVED_FOR_TABLE_ALIAS};
+use sqlparser::tokenizer::{Token, TokenWithLocation, Tokenizer};
+use std::collections::{HashMap, HashSet};
+
+pub struct QuotingReport {
+    pub sql: String,
+    pub corrections: Vec<String>,
+    pub restyled: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+type Identifiers = HashMap<String, Vec<String>>;
+
+fn schema_identifiers(
+    schema: &Schema,
+    words: &HashSet<String>,
+) -> (Identifiers, Identifiers, HashSet<String>) {
+    let mut identifiers: Identifiers = HashMap::new();
+    let mut styled: Identifiers = HashMap::new();
+    let mut relations = HashSet::new();
+    let mut add = |name: &str| {
+        for entry in [
+            identifiers.entry(name.to_lowercase()).or_default(),
+            styled.entry(naming::folded(name)).or_default(),
+        ] {
+            if !entry.iter().any(|n| n == name) {
+                entry.push(name.to_string());
+            }
+        }
+    };
+
+    let all_relations: Vec<_> = schema
+        .tables
+        .iter()
+        .map(|t| (&t.name, &t.columns))
+        .chain(
+            schema
+                .materialized_views
+                .iter()
+                .map(|v| (&v.name, &v.columns)),
+        )
+        .chain(schema.views.iter().map(|v| (&v.name, &v.columns)))
+        .collect();
+
+    let is_referenced = |name: &str| {
+        name.rsplit('.')
+            .next()
+            .is_some_and(|short| words.contains(&short.to_lowercase()))
+    };
+    let any_referenced = all_relations.iter().any(|(name, _)| is_referenced(name));
+
+    for (name, columns) in all_relations {
+        for part in name.split('.') {
+            add(part);
+            relations.insert(part.to_lowercase());
+        }
+        if any_referenced && !is_referenced(name) {
+            continue;
+        }
+        for column in columns {
+            add(&column.name);
+        }
+    }
+
+    (identifiers, styled, relations)
+}
+
+fn byte_offset(sql: &str, line: u64, column: u64) -> Option<usize> {
+    let mut current_line = 1;
+    let mut line_start = 0;
+    for (i, c) in sql.char_indices() {
+        if current_line == line {
+            break;
+        }
+        if c == '\n' {
+            current_line += 1;
+            line_start = i + 1;
+        }
+    }
+
+    sql[line_start..]
+        .char_indices()
+        .nth(column.checked_sub(1)? as usize)
+        .map(|(i, _)| line_start + i)
+}
+
+// Words that continue a multi-word type name such as `timestamp with time zone`.
+const TYPE_CONTINUATIONS: &[Keyword] = &[
+    Keyword::PRECISION,
+    Keyword::VARYING,
+    Keyword::WITH,
+    Keyword::WITHOUT,
+    Keyword::TIME,
+    Keyword::ZONE,
+];
+
+// Indexes of the words that name a type: after `::`, after `AS` inside `CAST(...)`, and after
+// `TYPE`. Type names are not schema identifiers, so they are left as written.
+fn type_positions(tokens: &[TokenWithLocation]) -> HashSet<usize> {
+    let mut positions = HashSet::new();
+    let mut casts: Vec<bool> = Vec::new();
+    let mut expect_type = false;
+    let mut in_type = false;
+    let mut previous: Option<&Token> = None;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match &token.token {
+            Token::Whitespace(_) => continue,
+            Token::Word(word)
+                if expect_type
+                    || (in_type && matches!(previous, Some(Token::Period)))
+                    || (in_type && TYPE_CONTINUATIONS.contains(&word.keyword)) =>
+            {
+                positions.insert(i);
+                expect_type = false;
+                in_type = true;
+            }
+            Token::Period | Token::LBracket | Token::RBracket if in_type => {}
+            token => {
+                in_type = false;
+                match token {
+                    Token::DoubleColon => expect_type = true,
+                    Token::Word(word) if word.keyword == Keyword::TYPE => expect_type = true,
+                    Token::Word(word) if word.keyword == Keyword::AS => {
+                        expect_type = casts.last() == Some(&true)
+                    }
+                    Token::LParen => casts.push(matches!(
+                        previous,
+                        Some(Token::Word(word)) if word.keyword == Keyword::CAST
+                    )),
+                    Token::RParen => {
+                        casts.pop();
+                    }
+                    _ => {}
+                }
+            }
+        }
+        previous = Some(&token.token);
+    }
+
+    positions
+}
+
+pub fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+pub fn fix_identifier_quoting(sql: &str, schema: &Schema) -> QuotingReport {
+    let mut report = QuotingReport {
+        sql: sql.to_string(),
+        corrections: Vec::new(),
+        restyled: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let dialect = PostgreSqlDialect {};
+    let Ok(tokens) = Tokenizer::new(&dialect, sql).tokenize_with_location() else {
+        return report;
+    };
+
+    let words: HashSet<String> = tokens
+        .iter()
+        .filter_map(|t| match &t.token {
+            Token::Word(word) => Some(word.value.to_lowercase()),
+            _ => None,
+        })
+        .collect();
+    let (identifiers, styled, relations) = schema_identifiers(schema, &words);
+    let types = type_positions(&tokens);
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let Token::Word(word) = &token.token else {
+            continue;
+        };
+        if types.contains(&i) {
+            continue;
+        }
+
+        let is_function_call = tokens[i + 1..]
+            .iter()
+            .find(|t| !matches!(t.token, Token::Whitespace(_)))
+            .is_some_and(|t| t.token == Token::LParen);
+        if is_function_call && !relations.contains(&word.value.to_lowercase()) {
+            continue;
+        }
+
+        let (folded, written_len) = match word.quote_style {
+            None if !RESERVED_FOR_TABLE_ALIAS.contains(&word.keyword)
+                && !RESERVED_FOR_COLUMN_ALIAS.contains(&word.keyword) =>
+            {
+                (word.value.to_lowercase(), word.value.chars().count())
+            }
+            Some('"') => (
+                word.value.clone(),
+                word.value.chars().count() + 2 + word.value.matches('"').count(),
+            ),
+            _ => continue,
+        };
+
+        let has_style = word.value.contains('_') || word.value.chars().any(char::is_uppercase);
+        let (candidates, restyled) = match identifiers.get(&word.value.to_lowercase()) {
+            Some(candidates) => (candidates, false),
+            None if has_style => match styled.get(&naming::folded(&word.value)) {
+                Some(candidates) => (candidates, true),
+                None => continue,
+            },
+            None => continue,
+        };
+        if candidates.contains(&folded) {
+            continue;
+        }
+
+        let original: String = match word.quote_style {
+            Some(_) => quote(&word.value),
+            None => word.value.clone(),
+        };
+
+        if candidates.len() > 1 {
+            let message = format!(
+                "{} is ambiguous, did you mean one of: {}?",
+                original,
+                candidates
+                    .iter()
+                    .map(|c| quote(c))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            if !report.warnings.contains(&message) {
+                report.warnings.push(message);
+            }
+            continue;
+        }
+
+        let Some(start) = byte_offset(sql, token.location.line, token.location.column) else {
+            continue;
+        };
+        let end = sql[start..]
+            .char_indices()
+            .nth(written_len)
+            .map_or(sql.len(), |(i, _)| start + i);
+
+        let corrected = quote(&candidates[0]);
+        let correction = format!("{} -> {}", original, corrected);
+        let corrections = match restyled {
+            true => &mut report.restyled,
+            false => &mut report.corrections,
+        };
+        if !corrections.contains(&correction) {
+            corrections.push(correction);
+        }
+        replacements.push((start, end, corrected));
+    }
+
+    for (start, end, corrected) in replacements.into_iter().rev() {
+        report.sql.replace_range(start..end, &corrected);
+    }
+
+    report
+}
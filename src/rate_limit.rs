@@ -0,0 +1,112 @@
+use crate::config::RateLimit;
+use crate::spinner::Spinner;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+struct Window {
+    requests: VecDeque<Instant>,
+    tokens: VecDeque<(Instant, u32)>,
+}
+
+impl Window {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .requests
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= WINDOW)
+        {
+            self.requests.pop_front();
+        }
+        while self
+            .tokens
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) >= WINDOW)
+        {
+            self.tokens.pop_front();
+        }
+    }
+
+    fn token_count(&self) -> u32 {
+        self.tokens.iter().map(|(_, n)| n).sum()
+    }
+
+    fn wait_time(&self, limits: &RateLimit, tokens: u32, now: Instant) -> Option<Duration> {
+        let until_expired = |t: Instant| (t + WINDOW).saturating_duration_since(now);
+
+        if let Some(rpm) = limits.requests_per_minute {
+            if self.requests.len() >= rpm as usize {
+                return self.requests.front().map(|t| until_expired(*t));
+            }
+        }
+
+        if let Some(tpm) = limits.tokens_per_minute {
+            let mut used = self.token_count();
+            if used > 0 && used + tokens > tpm {
+                for (t, n) in &self.tokens {
+                    used -= n;
+                    if used + tokens <= tpm || used == 0 {
+                        return Some(until_expired(*t));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limits: RateLimit,
+    queue: Arc<tokio::sync::Mutex<()>>,
+    window: Arc<Mutex<Window>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimit) -> Self {
+        Self {
+            limits,
+            queue: Arc::new(tokio::sync::Mutex::new(())),
+            window: Arc::new(Mutex::new(Window::default())),
+        }
+    }
+
+    pub async fn acquire(&self, tokens: u32) -> Duration {
+        let started = Instant::now();
+        let _turn = self.queue.lock().await;
+        let mut spinner = None;
+
+        loop {
+            let wait = {
+                let mut window = self.window.lock().unwrap();
+                let now = Instant::now();
+                window.prune(now);
+                match window.wait_time(&self.limits, tokens, now) {
+                    Some(wait) => wait,
+                    None => {
+                        window.requests.push_back(now);
+                        window.tokens.push_back((now, tokens));
+                        break;
+                    }
+                }
+            };
+            spinner.get_or_insert_with(|| Spinner::start("Waiting for API rate limit..."));
+            tokio::time::sleep(wait.max(Duration::from_millis(10))).await;
+        }
+
+        drop(spinner);
+        started.elapsed()
+    }
+
+    pub fn record(&self, tokens: u32) {
+        self.window
+            .lock()
+            .unwrap()
+            .tokens
+            .push_back((Instant::now(), tokens));
+    }
+}
@@ -1,44 +1,149 @@
+use crate::accessibility;
+use crate::audit;
+use crate::blobs;
 use crate::claude::Client as ClaudeClient;
-use crate::config::{Config, ExecutionMode};
-use crate::psql::{is_write_operation, PsqlConnection};
+use crate::clipboard;
+use crate::commands::{self, CommandKind};
+use crate::completion;
+use crate::config::{
+    self, Config, DataConsent, ExecutionMode, IsolationLevel, JsonDisplay, Kiosk, OutputFormat,
+    PreviewRows, ResultViewer, SchemaAnswers, StreamDisplay, Template, TopicChange,
+};
+use crate::cross_database;
+use crate::ddl_impact;
+use crate::demo;
+use crate::errors::{self, render_error};
+use crate::estimate;
+use crate::export;
+use crate::feedback::{Feedback, FeedbackStore};
+use crate::fixtures;
+use crate::generated_columns;
+use crate::graph;
+use crate::health;
+use crate::help_pane;
+use crate::hints;
+use crate::history::{self, Vocabulary};
+use crate::hooks::{self, Status, Verdict};
+use crate::insights::{self, Attempt, QuestionLog};
+use crate::intent::{self, Intent};
+use crate::json;
+use crate::kiosk;
+use crate::metrics;
+use crate::models;
+use crate::named_results;
+use crate::output::Output;
+use crate::pager;
+use crate::params::{bind, find_placeholders};
+use crate::personas;
+use crate::preview::{returning_columns, update_diff};
+use crate::profiles::ProfileConnections;
+use crate::psql::{
+    is_read_only_sql, is_schema_change, is_write_operation, returns_rows, PsqlConnection,
+    DEFAULT_QUERY_TAG,
+};
+use crate::quoting::{fix_identifier_quoting, quote};
+use crate::report::{self, Entry, Outcome};
+use crate::result::{ResultSet, TableLayout, DEFAULT_MAX_COLUMN_WIDTH};
+use crate::result_view;
+use crate::safe_mode;
+use crate::sandbox;
 use crate::schema::Schema;
-use anyhow::Result;
+use crate::schema_questions::{is_schema_question, relevant_schema};
+use crate::session::{Lease, Session};
+use crate::similar::{self, find_similar};
+use crate::snapshots::{self, SnapshotStore};
+use crate::spinner::run_blocking;
+use crate::sql_format;
+use crate::streaming::{self, Streamed};
+use crate::templates;
+use crate::time_zones;
+use crate::topic;
+use crate::units;
+use crate::write_guard;
+use anyhow::{Context, Result};
 use crossterm::cursor;
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use futures_util::future::join_all;
 use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Constraint, Layout};
 use ratatui::style::{Color, Style};
 use ratatui::widgets::{Block, Borders, Paragraph};
 use ratatui::Terminal;
 use rustyline::error::ReadlineError;
-use rustyline::DefaultEditor;
+use rustyline::history::DefaultHistory;
+use rustyline::{CompletionType, Editor};
 use sqlparser::dialect::PostgreSqlDialect;
 use sqlparser::parser::Parser;
-use std::io::{self, Write};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tui_textarea::TextArea;
 
+const COUNT_PREFIX: &str = "count:";
+const HISTORY_SEARCH_LIMIT: usize = 20;
+const HOT_TABLES: usize = 15;
+const DEFAULT_SERIALIZATION_RETRIES: u32 = 3;
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
 pub async fn run(
     psql: PsqlConnection,
+    profile: String,
     mut claude: ClaudeClient,
     mut schema: Schema,
     mut config: Config,
+    session: Option<Session>,
 ) -> Result<()> {
-    let mut rl = DefaultEditor::new()?;
+    let mut rl: Editor<Vocabulary, DefaultHistory> = Editor::with_config(
+        rustyline::Config::builder()
+            .completion_type(CompletionType::List)
+            .build(),
+    )?;
+    rl.set_helper(Some(Vocabulary::new(&schema)));
 
     let history_path = dirs::data_dir()
         .map(|p| p.join("psqlm").join("history.txt"))
         .unwrap_or_default();
+    let first_run = !history_path.exists();
+    if let Some(parent) = history_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
     let _ = rl.load_history(&history_path);
+    let learned: Vec<String> = rl.history().iter().cloned().collect();
+    if let Some(vocabulary) = rl.helper_mut() {
+        learned.iter().for_each(|entry| vocabulary.learn(entry));
+    }
+    if first_run {
+        if let Some(path) = history::psql_history_path().filter(|p| p.exists()) {
+            if let Ok(imported) = import_psql_history(&mut rl, &path) {
+                if imported > 0 {
+                    println!(
+                        "Imported {} queries from {} (\\history search finds them)",
+                        imported,
+                        path.display()
+                    );
+                }
+            }
+        }
+    }
+    let mut initial: Option<String> = None;
 
-    println!("Type your question in natural language, or use commands:");
-    println!("  \\q          - quit");
-    println!("  \\schema     - show/refresh schema");
-    println!("  \\mode [m]   - show/set execution mode (auto/confirm/show)");
-    println!();
+    let mut connections = ProfileConnections::new(profile, config.profiles.clone());
+    let mut output = Output::default();
+
+    if let Some(banner) = environment_banner(&psql, &config) {
+        println!("{}", banner);
+    }
+    println!("Type your question in natural language. \\help lists commands, \\q quits.\n");
+    let prompt = colored_prompt(&config);
 
     loop {
-        let readline = rl.readline("psqlm> ");
+        let readline = match initial.take() {
+            _ if accessibility::enabled() => accessibility::readline(&prompt),
+            Some(text) => rl.readline_with_initial(&prompt, (&text, "")),
+            None => rl.readline(&prompt),
+        };
 
         match readline {
             Ok(line) => {
@@ -48,9 +153,63 @@ pub async fn run(
                 }
 
                 let _ = rl.add_history_entry(line);
+                let _ = rl.append_history(&history_path);
+                if let Some(vocabulary) = rl.helper_mut() {
+                    vocabulary.learn(line);
+                }
+                let expanded = commands::expand_alias(&config.aliases, line);
+                let line = expanded.as_deref().unwrap_or(line);
+
+                let command = line.split_whitespace().next().and_then(commands::find);
+                if command.map(|c| c.kind) == Some(CommandKind::History) {
+                    let args = line.split_once(char::is_whitespace).map(|(_, a)| a.trim());
+                    if let Err(e) = history_command(&mut rl, args.unwrap_or(""), &mut initial) {
+                        eprintln!("Error: {}", e);
+                    }
+                    continue;
+                }
+
+                let statement = if line.starts_with('\\') {
+                    command
+                        .filter(|c| commands::runs_sql(c.kind))
+                        .map(|_| line.to_string())
+                } else {
+                    let question = match output.suggestion(line) {
+                        Some(question) => {
+                            println!("\x1b[2m{}\x1b[0m", question);
+                            question
+                        }
+                        None => line.to_string(),
+                    };
+                    output.set_suggestions(Vec::new());
+                    Some(question)
+                };
+                let turn = match (&session, &statement) {
+                    (Some(session), Some(statement)) => {
+                        match take_turn(session, statement, &mut claude).await {
+                            Ok(Some(lease)) => Some(lease),
+                            Ok(None) => continue,
+                            Err(e) => {
+                                eprintln!("Session sync failed: {}", e);
+                                None
+                            }
+                        }
+                    }
+                    _ => None,
+                };
 
                 if line.starts_with('\\') {
-                    match handle_command(line, &psql, &mut schema, &mut config) {
+                    let result = handle_command(
+                        line,
+                        &psql,
+                        &mut connections,
+                        &mut claude,
+                        &mut schema,
+                        &mut config,
+                        &mut output,
+                    )
+                    .await;
+                    match result {
                         Ok(should_quit) => {
                             if should_quit {
                                 break;
@@ -58,12 +217,31 @@ pub async fn run(
                         }
                         Err(e) => eprintln!("Error: {}", e),
                     }
-                    continue;
+                } else if let Some(question) = statement {
+                    let (question, redirect) = export::split_redirect(&question);
+                    output.set_redirect(redirect);
+                    let result = handle_query(
+                        question,
+                        &psql,
+                        &mut connections,
+                        &mut claude,
+                        &mut schema,
+                        &mut config,
+                        &mut output,
+                    )
+                    .await;
+                    output.set_redirect(None);
+                    if let Err(e) = result {
+                        eprintln!("Error: {}", e);
+                    }
                 }
 
-                if let Err(e) = handle_query(line, &psql, &mut claude, &schema, &mut config).await {
-                    eprintln!("Error: {}", e);
+                if let Some(session) = &session {
+                    if let Err(e) = session.update(&schema, &claude.history).await {
+                        eprintln!("Session sync failed: {}", e);
+                    }
                 }
+                drop(turn);
             }
             Err(ReadlineError::Interrupted) => {
                 println!("^C");
@@ -79,36 +257,318 @@ pub async fn run(
         }
     }
 
-    if let Some(parent) = history_path.parent() {
-        let _ = std::fs::create_dir_all(parent);
+    let _ = rl.append_history(&history_path);
+
+    Ok(())
+}
+
+pub async fn run_once(
+    psql: PsqlConnection,
+    claude: ClaudeClient,
+    schema: Schema,
+    config: Config,
+    input: &str,
+) -> Result<bool> {
+    let input = input.trim();
+    let sql = if is_valid_sql(input) {
+        input.to_string()
+    } else {
+        match intent::classify(input) {
+            Intent::Write => claude.text_to_write_sql(&schema, input).await?,
+            _ => claude.text_to_sql(&schema, input).await?,
+        }
+    };
+
+    let report = fix_identifier_quoting(&sql, &schema);
+    for warning in &report.warnings {
+        eprintln!("⚠️  {}", warning);
+    }
+    let mut sql = report.sql;
+    if !run_pre_hook(&psql, &config, input, &mut sql).await? {
+        return Ok(false);
+    }
+    write_guard::check(&config.write_guard, &sql)?;
+    kiosk::check(&config.kiosk, &sql)?;
+
+    if config.execution_mode == ExecutionMode::Show {
+        println!("{}", sql);
+        return Ok(true);
+    }
+    if is_write_operation(&sql)
+        && (config.execution_mode != ExecutionMode::Auto || config.safe_mode_active)
+    {
+        anyhow::bail!(
+            "Not running a write without confirmation; set execution_mode = \"auto\" (with safe mode off) to allow writes from -c"
+        );
+    }
+    if !is_read_only_sql(&sql) && config.environment.production {
+        anyhow::bail!(
+            "Not running a statement that may write on a production profile without typed confirmation"
+        );
+    }
+
+    let psql = psql
+        .tagged(query_tag_format(&config), input)
+        .with_isolation(config.isolation);
+    let has_rows = returns_rows(&sql);
+    let conn = read_connection(&psql, &sql, &config).clone();
+    let statement = sql.clone();
+    let started = Instant::now();
+    let single_transaction = config.single_transaction;
+    let (success, stdout, stderr) = run_blocking("Running query...", move || {
+        if single_transaction {
+            conn.execute_single_transaction(&statement, has_rows)
+        } else if has_rows {
+            conn.execute_csv(&statement)
+        } else {
+            conn.execute_capture(&statement)
+        }
+    })
+    .await?;
+    record_execution(&psql, &config, input, &sql, success, &stderr, started).await;
+
+    if !success {
+        print_error(&psql, &sql, &stderr);
+        return Ok(false);
+    }
+    if has_rows {
+        let result = ResultSet::from_csv(&stdout)?;
+        print!(
+            "{}",
+            result.render_with(&TableLayout {
+                max_width: None,
+                ..table_layout(&config)
+            })
+        );
+    } else {
+        print!("{}", displayed(&config, &stdout));
+    }
+    Ok(true)
+}
+
+async fn take_turn(
+    session: &Session,
+    statement: &str,
+    claude: &mut ClaudeClient,
+) -> Result<Option<Lease>> {
+    let mut waiting = false;
+    loop {
+        let holder = match session.begin(statement).await? {
+            Ok((lease, history)) => {
+                claude.history = history;
+                return Ok(Some(lease));
+            }
+            Err(holder) => holder,
+        };
+        if !waiting {
+            println!("⏳ {}", holder.describe());
+            if pick_option(&["Wait for it to finish", "Cancel"])? != Some(0) {
+                println!("Cancelled.\n");
+                return Ok(None);
+            }
+            println!("\x1b[2mWaiting for the other statement. Ctrl+C stops waiting.\x1b[0m");
+            waiting = true;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(500)) => {}
+            _ = tokio::signal::ctrl_c() => {
+                println!("Cancelled.\n");
+                return Ok(None);
+            }
+        }
+    }
+}
+
+fn import_psql_history(rl: &mut Editor<Vocabulary, DefaultHistory>, path: &Path) -> Result<usize> {
+    let entries = history::read_psql_history(path)?;
+    for entry in &entries {
+        let _ = rl.add_history_entry(entry.as_str());
+        if let Some(vocabulary) = rl.helper_mut() {
+            vocabulary.learn(entry);
+        }
+    }
+    Ok(entries.len())
+}
+
+fn history_command(
+    rl: &mut Editor<Vocabulary, DefaultHistory>,
+    args: &str,
+    initial: &mut Option<String>,
+) -> Result<()> {
+    let (action, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let rest = rest.trim();
+
+    match action {
+        "import" => {
+            let path = match rest {
+                "" => history::psql_history_path().context("Could not locate ~/.psql_history")?,
+                path => PathBuf::from(path),
+            };
+            let imported = import_psql_history(rl, &path)?;
+            println!("Imported {} queries from {}\n", imported, path.display());
+        }
+        "search" if !rest.is_empty() => {
+            let term = rest.to_lowercase();
+            let mut matches: Vec<String> = Vec::new();
+            for entry in rl.history().iter().rev() {
+                if entry.to_lowercase().contains(&term)
+                    && !entry.starts_with("\\history")
+                    && !matches.contains(entry)
+                {
+                    matches.push(entry.clone());
+                }
+                if matches.len() == HISTORY_SEARCH_LIMIT {
+                    break;
+                }
+            }
+            if matches.is_empty() {
+                println!("No history entries match '{}'.\n", rest);
+                return Ok(());
+            }
+
+            let width = terminal::size()
+                .map(|(w, _)| w as usize)
+                .unwrap_or(80)
+                .saturating_sub(8);
+            let mut options: Vec<String> = matches
+                .iter()
+                .map(|entry| match entry.char_indices().nth(width) {
+                    Some((i, _)) => format!("{}…", &entry[..i]),
+                    None => entry.clone(),
+                })
+                .collect();
+            options.push("Cancel".to_string());
+            let labels: Vec<&str> = options.iter().map(String::as_str).collect();
+
+            if let Some(selected) = pick_option(&labels)?.filter(|&i| i < matches.len()) {
+                if accessibility::enabled() {
+                    println!("{}\n", matches[selected]);
+                    let _ = rl.add_history_entry(matches[selected].as_str());
+                } else {
+                    *initial = Some(matches[selected].clone());
+                }
+            }
+        }
+        "" => {
+            let entries: Vec<&String> = rl.history().iter().collect();
+            let start = entries.len().saturating_sub(HISTORY_SEARCH_LIMIT);
+            for (i, entry) in entries.iter().enumerate().skip(start) {
+                println!("{:>5}  {}", i + 1, entry);
+            }
+            println!();
+        }
+        _ => println!("Usage: \\history [search <text>|import [path]]"),
     }
-    let _ = rl.save_history(&history_path);
 
     Ok(())
 }
 
-fn handle_command(
+async fn handle_command(
     line: &str,
     psql: &PsqlConnection,
+    connections: &mut ProfileConnections,
+    claude: &mut ClaudeClient,
     schema: &mut Schema,
     config: &mut Config,
+    output: &mut Output,
 ) -> Result<bool> {
     let parts: Vec<&str> = line.split_whitespace().collect();
     let cmd = parts.first().unwrap_or(&"");
 
-    match *cmd {
-        "\\q" | "\\quit" => return Ok(true),
-
-        "\\schema" => {
-            println!("Refreshing schema...");
-            *schema = psql.introspect_schema()?;
-            println!("Schema loaded ({} tables):\n", schema.tables.len());
-            print!("{}", schema.to_prompt_string());
+    let Some(command) = commands::find(cmd) else {
+        match commands::suggest(cmd) {
+            Some(suggestion) => println!("Unknown command, did you mean {}?", suggestion),
+            None => println!("Unknown command: {} (\\help lists commands)", cmd),
         }
+        return Ok(false);
+    };
+    if config.kiosk.enabled && !kiosk::allows(command.kind) {
+        println!("{} is not available in kiosk mode.", command.names[0]);
+        return Ok(false);
+    }
+
+    match command.kind {
+        CommandKind::Quit => return Ok(true),
+
+        CommandKind::History => println!("\\history is only available at the psqlm prompt"),
+
+        CommandKind::Help => match parts.get(1) {
+            Some(name) => match commands::help(name) {
+                Some(help) => println!("{}", help),
+                None => println!("Unknown command: {}", name),
+            },
+            None if io::stdout().is_terminal() && !accessibility::enabled() => {
+                help_pane::show(&config.confirm_keys)?
+            }
+            None => {
+                print!("{}", commands::summary());
+                println!("\nUse \\help <command> for details.");
+            }
+        },
+
+        CommandKind::Schema => match parts.get(1).copied() {
+            Some("snapshot" | "snapshots" | "diff") if config.kiosk.enabled => {
+                println!("Schema snapshots are not available in kiosk mode.\n");
+            }
+            Some("snapshot") => {
+                let name = parts
+                    .get(2..)
+                    .map(|p| p.join(" "))
+                    .filter(|n| !n.is_empty());
+                refresh_schema(psql, claude, schema, &config.kiosk)?;
+                let snapshot = SnapshotStore::for_database(psql)?.save(name, schema)?;
+                println!(
+                    "Saved snapshot {} ({} tables)\n",
+                    snapshot.label(),
+                    snapshot.schema.tables.len()
+                );
+            }
+            Some("snapshots") => {
+                let snapshots = SnapshotStore::for_database(psql)?.list();
+                if snapshots.is_empty() {
+                    println!("No snapshots yet. Use \\schema snapshot [name] to take one.\n");
+                }
+                for (i, snapshot) in snapshots.iter().enumerate() {
+                    println!(
+                        "  #{:<3} {} ({} tables)",
+                        i + 1,
+                        snapshot.label(),
+                        snapshot.schema.tables.len()
+                    );
+                }
+                println!();
+            }
+            Some("diff") => show_schema_diff(psql, claude, schema, &parts[2..])?,
+            Some(_) => {
+                let pattern = parts[1..].join(" ");
+                refresh_schema(psql, claude, schema, &config.kiosk)?;
+                let matching = schema.matching(&pattern)?;
+                if matching.relation_count() == 0 {
+                    println!("No tables match {}.\n", pattern);
+                    return Ok(false);
+                }
+                println!(
+                    "{} of {} tables match {}:\n",
+                    matching.relation_count(),
+                    schema.relation_count(),
+                    pattern
+                );
+                pager::page(&matching.to_prompt_string());
+            }
+            None => {
+                println!("Refreshing schema...");
+                refresh_schema(psql, claude, schema, &config.kiosk)?;
+                println!("Schema loaded ({} tables):\n", schema.tables.len());
+                pager::page(&schema.to_prompt_string());
+            }
+        },
 
-        "\\mode" => {
+        CommandKind::Mode => {
             if let Some(mode) = parts.get(1) {
                 match *mode {
+                    "auto" if config.safe_mode_active => {
+                        println!("Auto mode is disabled while safe mode is on.");
+                    }
                     "auto" => {
                         config.execution_mode = ExecutionMode::Auto;
                         println!("Execution mode: auto (run immediately)");
@@ -133,10 +593,1377 @@ fn handle_command(
             }
         }
 
-        _ => println!("Unknown command: {}", cmd),
+        CommandKind::Isolation => {
+            let args = parts[1..].join(" ");
+            if args == "default" {
+                config.isolation = None;
+            } else if !args.is_empty() {
+                match IsolationLevel::parse(&args) {
+                    Some(level) => config.isolation = Some(level),
+                    None => {
+                        println!(
+                            "Unknown isolation level. Use: read-committed, repeatable-read, serializable, or default"
+                        );
+                        return Ok(false);
+                    }
+                }
+            }
+            match config.isolation {
+                Some(level) => println!("Isolation level: {}", level.as_sql()),
+                None => println!("Isolation level: server default"),
+            }
+        }
+
+        CommandKind::Persona => {
+            let names = personas::names(&config.personas);
+            match parts.get(1).copied() {
+                None => {
+                    for name in &names {
+                        let current = if config.persona.as_deref() == Some(name) {
+                            " (current)"
+                        } else {
+                            ""
+                        };
+                        println!("  {}{}", name, current);
+                    }
+                    if config.persona.is_none() {
+                        println!("No persona is active.");
+                    }
+                }
+                Some("default") => {
+                    claude.set_persona(None);
+                    config.persona = None;
+                    println!("Persona cleared.");
+                }
+                Some(name) => match personas::guidance(&config.personas, name) {
+                    Some(guidance) => {
+                        claude.set_persona(Some(guidance));
+                        config.persona = Some(name.to_string());
+                        println!("Persona: {}", name);
+                    }
+                    None => println!("Unknown persona. Use: {}, or default", names.join(", ")),
+                },
+            }
+        }
+
+        CommandKind::Alias => match parts.get(1..).unwrap_or_default() {
+            [] if config.aliases.is_empty() => println!("No aliases defined."),
+            [] => {
+                for (name, expansion) in &config.aliases {
+                    println!("  \\{} = {}", name, expansion);
+                }
+            }
+            ["-d", name] => match config.aliases.remove(name.trim_start_matches('\\')) {
+                Some(_) => println!("Alias removed."),
+                None => println!("No alias named {}", name),
+            },
+            [name] => match config.aliases.get(name.trim_start_matches('\\')) {
+                Some(expansion) => println!("  {} = {}", name, expansion),
+                None => println!("No alias named {}", name),
+            },
+            [name, ..] => {
+                let name = name.trim_start_matches('\\');
+                if !commands::is_valid_alias(name) {
+                    println!("Alias names use letters, digits, - and _, and cannot shadow a built-in command.");
+                    return Ok(false);
+                }
+                let expansion = line
+                    .split_once(char::is_whitespace)
+                    .and_then(|(_, rest)| rest.trim_start().split_once(char::is_whitespace))
+                    .map(|(_, expansion)| expansion.trim().to_string())
+                    .unwrap_or_default();
+                println!("\\{} = {}", name, expansion);
+                config.aliases.insert(name.to_string(), expansion);
+            }
+        },
+
+        CommandKind::Stream => {
+            if let Some(display) = parts.get(1) {
+                match *display {
+                    "live" => {
+                        config.stream_display = StreamDisplay::Live;
+                        println!("Stream display: live (echo tokens as they arrive)");
+                    }
+                    "quiet" => {
+                        config.stream_display = StreamDisplay::Quiet;
+                        println!("Stream display: quiet (spinner, then final SQL)");
+                    }
+                    _ => println!("Unknown display. Use: live or quiet"),
+                }
+                claude.stream_display = config.stream_display;
+            } else {
+                let display_str = match config.stream_display {
+                    StreamDisplay::Live => "live",
+                    StreamDisplay::Quiet => "quiet",
+                };
+                println!("Current stream display: {}", display_str);
+            }
+        }
+
+        CommandKind::Preview => match parts.get(1).copied() {
+            Some("compact") => {
+                config.preview_rows = PreviewRows::Compact;
+                println!("Write previews: compact (primary key and changed columns)");
+            }
+            Some("full") => {
+                config.preview_rows = PreviewRows::Full;
+                println!("Write previews: full rows");
+            }
+            Some(_) => println!("Unknown preview setting. Use: compact or full"),
+            None => println!(
+                "Current write previews: {}",
+                match config.preview_rows {
+                    PreviewRows::Compact => "compact",
+                    PreviewRows::Full => "full",
+                }
+            ),
+        },
+
+        CommandKind::Why => {
+            let args = line[cmd.len()..].trim();
+            let sql = if args.is_empty() {
+                claude.history.last().map(|turn| turn.sql.clone())
+            } else {
+                Some(args.to_string())
+            };
+
+            match sql {
+                Some(sql) => {
+                    println!();
+                    claude.explain_sql(schema, &sql).await?;
+                    println!();
+                }
+                None => println!("No previous statement to explain. Usage: \\why [sql]"),
+            }
+        }
+
+        CommandKind::Report => {
+            let args = line[cmd.len()..].trim();
+            write_report(psql, claude, output, args).await?;
+        }
+
+        CommandKind::Export => {
+            let args = line[cmd.len()..].trim();
+            let (format, args) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+            if format != "csv" && format != "json" {
+                println!("Usage: {}\n", command.usage);
+                return Ok(false);
+            }
+            let Some(result) = output.view() else {
+                println!("No result set yet. Run a query first.\n");
+                return Ok(false);
+            };
+            let path = if format == "json" {
+                let path = Some(args.trim()).filter(|p| !p.is_empty());
+                let json = serde_json::to_string_pretty(&json::to_json(&result))?;
+                report::write_json(path, &json)?
+            } else {
+                let (path, options) = export::parse_options(&config.csv, args)?;
+                let csv = export::to_csv(&result, &options)?;
+                report::write_csv(path.as_deref(), &csv)?
+            };
+            println!("Wrote {} rows to {}\n", result.rows.len(), path.display());
+        }
+
+        CommandKind::SaveBlob => {
+            let args = line[cmd.len()..].trim();
+            let Some((column, path)) = args.split_once(char::is_whitespace) else {
+                println!("Usage: {}\n", command.usage);
+                return Ok(false);
+            };
+            let Some(result) = output.view() else {
+                println!("No result set yet. Run a query first.\n");
+                return Ok(false);
+            };
+            let sql = output.last_sql().map(str::to_string);
+            let (conn, column, path) = (psql.clone(), column.to_string(), path.trim().to_string());
+            let paths = run_blocking("Writing...", move || {
+                blobs::save(&conn, &result, sql.as_deref(), &column, &path)
+            })
+            .await?;
+            match paths.as_slice() {
+                [path] => println!("Wrote {}\n", path.display()),
+                paths => println!(
+                    "Wrote {} files to {}\n",
+                    paths.len(),
+                    paths[0].parent().unwrap_or(Path::new(".")).display()
+                ),
+            }
+        }
+
+        CommandKind::Fixture => {
+            let args = line[cmd.len()..].trim();
+            write_fixture(psql, schema, args)?;
+        }
+
+        CommandKind::Seed => {
+            let description = line[cmd.len()..].trim();
+            if description.is_empty() {
+                println!("Usage: {}\n", command.usage);
+                return Ok(false);
+            }
+            consent_to_results(claude, config)?;
+            check_prompt_cost(claude, schema, config, description)?;
+            println!();
+            let sql = claude.text_to_seed_sql(schema, description).await?;
+            println!();
+            let sql = fix_quoting(&sql, schema);
+            if !is_write_operation(&sql) {
+                println!("The model did not return INSERT statements; try describing the data differently.\n");
+                return Ok(false);
+            }
+            execute_with_recovery(psql, claude, schema, description, &sql, config, output).await?;
+        }
+
+        CommandKind::Graph => {
+            let args = line[cmd.len()..].trim();
+            show_graph(schema, args)?;
+        }
+
+        CommandKind::ExportSession => {
+            let entries = output.transcript();
+            if entries.is_empty() {
+                println!("Nothing has been executed yet.\n");
+                return Ok(false);
+            }
+            let args = line[cmd.len()..].trim();
+            let script = report::to_sql_script(&psql.database, entries);
+            let path = report::write_script((!args.is_empty()).then_some(args), &script)?;
+            println!("Wrote {} statements to {}\n", entries.len(), path.display());
+        }
+
+        CommandKind::Insights => {
+            let attempts = QuestionLog::for_database(psql)?.load();
+            let insights = insights::render(&attempts, schema);
+            pager::page(&displayed(config, &insights));
+            println!();
+        }
+
+        CommandKind::Similar => {
+            let args = line[cmd.len()..].trim();
+            let sql = if args.is_empty() {
+                claude.history.last().map(|turn| turn.sql.clone())
+            } else {
+                Some(args.to_string())
+            };
+
+            match sql {
+                Some(sql) => {
+                    let similar = find_similar(psql, &sql)?;
+                    if similar.rows.is_empty() {
+                        println!("No similar queries found in pg_stat_statements.");
+                    } else {
+                        print!("{}", similar.render_table());
+                    }
+                    println!();
+                }
+                None => println!("No previous statement to compare. Usage: \\similar [sql]"),
+            }
+        }
+
+        CommandKind::On => {
+            let args = line[cmd.len()..].trim();
+            match args.split_once(char::is_whitespace) {
+                Some((target, question)) => {
+                    let current = connections.current().to_string();
+                    let main = (current.as_str(), psql, &*schema);
+                    query_on_profiles(target, question.trim(), main, connections, claude, config)
+                        .await?;
+                }
+                None => {
+                    let current = connections.current();
+                    println!("Profiles: {} (current)", current);
+                    for name in connections.names() {
+                        if name != current {
+                            println!("          {}", name);
+                        }
+                    }
+                    println!("Usage: \\on <profile|all> <question>");
+                }
+            }
+        }
+
+        CommandKind::Good | CommandKind::Bad => {
+            let Some(turn) = claude.history.last() else {
+                println!("No query to rate yet.");
+                return Ok(false);
+            };
+
+            let good = command.kind == CommandKind::Good;
+            let reason = line[cmd.len()..].trim();
+            let entry = Feedback::new(
+                turn.question.clone(),
+                turn.sql.clone(),
+                good,
+                (!reason.is_empty()).then(|| reason.to_string()),
+            );
+
+            FeedbackStore::for_database(psql)?.append(&entry)?;
+            claude.feedback.push(entry);
+            println!(
+                "Recorded {} rating; it will guide future queries on this database.",
+                if good { "positive" } else { "negative" }
+            );
+        }
+
+        CommandKind::Filter | CommandKind::Sort | CommandKind::Cols => {
+            let args = line[cmd.len()..].trim();
+            match command.kind {
+                CommandKind::Filter => output.set_filter(args)?,
+                CommandKind::Sort => output.set_sort(args)?,
+                _ => output.set_columns(args)?,
+            }
+            if let Some(table) = output.render(table_layout(config)) {
+                print!("{}", table);
+                println!();
+            }
+        }
+
+        CommandKind::Render => {
+            let template = line[cmd.len()..].trim();
+            let template = template
+                .strip_prefix('"')
+                .and_then(|t| t.strip_suffix('"'))
+                .unwrap_or(template);
+            if template.is_empty() {
+                println!("Usage: {}", command.usage);
+                return Ok(false);
+            }
+            let Some(view) = output.view() else {
+                println!("No result to render yet.");
+                return Ok(false);
+            };
+            let view = if config.demo {
+                demo::anonymize(&view)
+            } else {
+                view
+            };
+            print!("{}", view.render_template(template)?);
+            println!();
+        }
+
+        CommandKind::Scroll => {
+            output.scroll(parts.get(1).copied().unwrap_or(""), table_layout(config))?;
+            if let Some(table) = output.render(table_layout(config)) {
+                print!("{}", table);
+                println!();
+            }
+        }
+
+        CommandKind::Explore => {
+            let Some((last, mut state)) = output.view_state() else {
+                println!("No result to explore yet.");
+                return Ok(false);
+            };
+            let last = last.displayed(&table_layout(config));
+            if result_view::explore(&last, &mut state)? {
+                output.set_view_state(state);
+                if let Some(table) = output.render(table_layout(config)) {
+                    print!("{}", table);
+                    println!();
+                }
+            }
+        }
+
+        CommandKind::Sandbox => {
+            match parts.get(1).copied() {
+                Some("on") => config.sandbox = true,
+                Some("off") => config.sandbox = false,
+                None => config.sandbox = !config.sandbox,
+                Some(_) => {
+                    println!("Usage: \\sandbox [on|off]");
+                    return Ok(false);
+                }
+            }
+            println!(
+                "Writes {} against a sandbox first.\n",
+                if config.sandbox {
+                    "run"
+                } else {
+                    "no longer run"
+                }
+            );
+        }
+
+        CommandKind::Replica => {
+            let Some(replica) = psql.replica() else {
+                println!("This connection has no read replica; add [profiles.<name>.replica] to config.toml.\n");
+                return Ok(false);
+            };
+            match parts.get(1).copied() {
+                Some("on") => config.primary_reads = false,
+                Some("off") => config.primary_reads = true,
+                None => config.primary_reads = !config.primary_reads,
+                Some(_) => {
+                    println!("Usage: \\replica [on|off]");
+                    return Ok(false);
+                }
+            }
+            if config.primary_reads {
+                println!(
+                    "Reads and writes go to the primary on {}.\n",
+                    psql.address()
+                );
+            } else {
+                println!(
+                    "Reads go to the replica on {}; writes go to the primary.\n",
+                    replica.address()
+                );
+            }
+        }
+
+        CommandKind::Wrap => {
+            match parts.get(1).copied() {
+                Some("on") => config.wrap = true,
+                Some("off") => config.wrap = false,
+                None => config.wrap = !config.wrap,
+                Some(_) => {
+                    println!("Usage: \\wrap [on|off]");
+                    return Ok(false);
+                }
+            }
+            println!(
+                "Long values are {}.",
+                if config.wrap { "wrapped" } else { "truncated" }
+            );
+            if let Some(table) = output.render(table_layout(config)) {
+                print!("{}", table);
+                println!();
+            }
+        }
+
+        CommandKind::Output => {
+            let path = line[cmd.len()..].trim();
+            if path.is_empty() {
+                match output.file() {
+                    Some(file) => println!(
+                        "Results are shown here again instead of written to {}.\n",
+                        file.path.display()
+                    ),
+                    None => println!("Results are already shown here.\n"),
+                }
+                output.set_file(None);
+                return Ok(false);
+            }
+            let file = export::OutputFile::create(path)?;
+            println!(
+                "Writing results to {} as {} until \\o.\n",
+                file.path.display(),
+                file.format(config.format).name()
+            );
+            output.set_file(Some(file));
+        }
+
+        CommandKind::Format => {
+            match parts.get(1).copied() {
+                Some("table") => config.format = OutputFormat::Table,
+                Some("csv") => config.format = OutputFormat::Csv,
+                Some("json") => config.format = OutputFormat::Json,
+                Some("markdown" | "md") => config.format = OutputFormat::Markdown,
+                None => {}
+                Some(_) => {
+                    println!("Usage: {}", command.usage);
+                    return Ok(false);
+                }
+            }
+            println!("Results are shown as {}.", config.format.name());
+            if let Some(table) = output.render(table_layout(config)) {
+                print!("{}", table);
+                println!();
+            }
+        }
+
+        CommandKind::Json => {
+            match parts.get(1).copied() {
+                Some("expand") => config.json = JsonDisplay::Expanded,
+                Some("collapse") => config.json = JsonDisplay::Collapsed,
+                None => {
+                    config.json = match config.json {
+                        JsonDisplay::Expanded => JsonDisplay::Collapsed,
+                        JsonDisplay::Collapsed => JsonDisplay::Expanded,
+                    }
+                }
+                Some(_) => {
+                    println!("Usage: \\json [expand|collapse]");
+                    return Ok(false);
+                }
+            }
+            println!(
+                "JSON values are {}.",
+                match config.json {
+                    JsonDisplay::Expanded => "pretty-printed",
+                    JsonDisplay::Collapsed => "shown on one line",
+                }
+            );
+            if let Some(table) = output.render(table_layout(config)) {
+                print!("{}", table);
+                println!();
+            }
+        }
+
+        CommandKind::RefreshMv => match parts.get(1) {
+            Some(name) => {
+                let concurrently = parts.get(2) == Some(&"concurrently");
+                refresh_materialized_view(name, concurrently, psql, claude, schema, config, output)
+                    .await?;
+            }
+            None => println!("Usage: \\refresh-mv <name> [concurrently]"),
+        },
+
+        CommandKind::Activity => show_activity(psql)?,
+
+        CommandKind::Hot => show_hot_tables(psql)?,
+
+        CommandKind::Health => show_health(psql, claude, schema, config, output).await?,
+
+        CommandKind::Locks => show_locks(psql)?,
+
+        CommandKind::Kill => match parts.get(1) {
+            Some(pid) => terminate_backend(psql, pid)?,
+            None => println!("Usage: \\kill <pid>"),
+        },
+
+        CommandKind::Store => {
+            let Some(name) = parts.get(1).copied() else {
+                println!("Usage: \\store <name>");
+                return Ok(false);
+            };
+            if !named_results::is_valid_name(name) {
+                println!(
+                    "Result names must be letters, digits and underscores, starting with a letter."
+                );
+                return Ok(false);
+            }
+            let Some(entry) = output.transcript().last().cloned() else {
+                println!("No result to store yet.");
+                return Ok(false);
+            };
+
+            let replaced = claude
+                .named_results
+                .insert(name.to_string(), entry)
+                .is_some();
+            println!(
+                "{} {}; mention it in a question to build on it.",
+                if replaced { "Replaced" } else { "Stored" },
+                name
+            );
+        }
+
+        CommandKind::Use => match parts.get(1).copied() {
+            Some(name) => {
+                let Some(entry) = claude.named_results.get(name).cloned() else {
+                    println!("No stored result named {} (\\use lists them)", name);
+                    return Ok(false);
+                };
+                println!("{}: {}\n{}\n", name, entry.question, entry.sql.trim());
+                match entry.outcome {
+                    Outcome::Rows(result) => {
+                        output.set_last(result, &entry.sql);
+                        if let Some(table) = output.render(table_layout(config)) {
+                            print!("{}", table);
+                        }
+                    }
+                    Outcome::Text(text) => print!("{}", text),
+                }
+                println!();
+            }
+            None if claude.named_results.is_empty() => {
+                println!("No stored results. Use \\store <name> after a query.");
+            }
+            None => {
+                for (name, entry) in &claude.named_results {
+                    println!("  {:<12} {}", name, entry.question);
+                }
+                println!();
+            }
+        },
+
+        CommandKind::Drill => {
+            if !claude.shares_data() {
+                println!(
+                    "Drill-down sends row values to the model, which strict mode does not allow."
+                );
+                return Ok(false);
+            }
+            let Some(result) = output.view() else {
+                println!("No result to drill into yet.");
+                return Ok(false);
+            };
+            let Some(cell) = result_view::select_cell(&result)? else {
+                println!("Cancelled.\n");
+                return Ok(false);
+            };
+
+            let mut question = line[cmd.len()..].trim().to_string();
+            if question.is_empty() {
+                print!("Follow-up about this row: ");
+                io::stdout().flush()?;
+                io::stdin().read_line(&mut question)?;
+                question = question.trim().to_string();
+            }
+            if question.is_empty() {
+                println!("Cancelled.\n");
+                return Ok(false);
+            }
+
+            let sql = output.transcript().last().map(|entry| entry.sql.clone());
+            let context = result_view::drill_down_context(&result, cell, sql.as_deref(), schema);
+            let question = format!("{}\n\n{}", question, context);
+            handle_query(&question, psql, connections, claude, schema, config, output).await?;
+        }
+
+        CommandKind::Models => {
+            let args = line[cmd.len()..].trim();
+            choose_model(claude, config, args).await?;
+        }
+    }
+
+    Ok(false)
+}
+
+async fn refresh_materialized_view(
+    name: &str,
+    concurrently: bool,
+    psql: &PsqlConnection,
+    claude: &mut ClaudeClient,
+    schema: &mut Schema,
+    config: &mut Config,
+    output: &mut Output,
+) -> Result<()> {
+    let Some(view) = schema.find_materialized_view(name) else {
+        println!("Unknown materialized view: {}", name);
+        return Ok(());
+    };
+
+    if concurrently && !(view.has_unique_index && view.is_populated) {
+        println!(
+            "{} cannot be refreshed concurrently: it needs a unique index and must already be populated.",
+            view.name
+        );
+        return Ok(());
+    }
+
+    let question = format!("Refresh materialized view {}", view.name);
+    let sql = format!(
+        "REFRESH MATERIALIZED VIEW {}{};",
+        if concurrently { "CONCURRENTLY " } else { "" },
+        view.name
+            .split('.')
+            .map(quote)
+            .collect::<Vec<_>>()
+            .join(".")
+    );
+
+    println!("\x1b[32m{}\x1b[0m\n", sql);
+    let context = SqlContext {
+        psql,
+        claude,
+        schema,
+        question: &question,
+    };
+    let Some(sql) = confirm_sql(sql, &context, config).await? else {
+        println!("Cancelled.\n");
+        return Ok(());
+    };
+
+    execute_with_recovery(psql, claude, schema, &question, &sql, config, output).await?;
+    refresh_schema(psql, claude, schema, &config.kiosk)?;
+
+    Ok(())
+}
+
+async fn write_report(
+    psql: &PsqlConnection,
+    claude: &ClaudeClient,
+    output: &Output,
+    path: &str,
+) -> Result<()> {
+    let entries = output.transcript();
+    if entries.is_empty() {
+        println!("Nothing to report yet. Ask a question first.\n");
+        return Ok(());
+    }
+
+    let transcript = report::to_prompt_string(entries, claude.shares_data());
+    let narrative = match claude.summarize_session(&transcript).await {
+        Ok(narrative) => Some(narrative),
+        Err(e) => {
+            eprintln!("⚠️  Could not write a summary: {}", e);
+            None
+        }
+    };
+
+    let markdown = report::to_markdown(&psql.database, entries, narrative.as_deref());
+    let path = report::write((!path.is_empty()).then_some(path), &markdown)?;
+    println!(
+        "Wrote report with {} questions to {}\n",
+        entries.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn refresh_schema(
+    psql: &PsqlConnection,
+    claude: &mut ClaudeClient,
+    schema: &mut Schema,
+    kiosk: &Kiosk,
+) -> Result<()> {
+    let mut refreshed = psql.introspect_schema()?;
+    if kiosk.enabled {
+        refreshed = kiosk::restrict(kiosk, &refreshed)?;
+    }
+    let changes: Vec<String> = snapshots::diff(schema, &refreshed)
+        .iter()
+        .map(|change| snapshots::plain(change))
+        .collect();
+    if !changes.is_empty() && !claude.history.is_empty() {
+        claude.note_schema_change(&changes);
+        println!("\x1b[2mSchema changed; noted for follow-up questions.\x1b[0m");
+    }
+    *schema = refreshed;
+    Ok(())
+}
+
+fn show_schema_diff(
+    psql: &PsqlConnection,
+    claude: &mut ClaudeClient,
+    schema: &mut Schema,
+    refs: &[&str],
+) -> Result<()> {
+    let store = SnapshotStore::for_database(psql)?;
+    let Some(from) = refs.first() else {
+        println!("Usage: \\schema diff <snapshot> [snapshot] (by #number, name, or timestamp)\n");
+        return Ok(());
+    };
+
+    let old = store.find(from)?;
+    let (label, new) = match refs.get(1) {
+        Some(to) => {
+            let snapshot = store.find(to)?;
+            (snapshot.label(), snapshot.schema)
+        }
+        None => {
+            refresh_schema(psql, claude, schema, &Kiosk::default())?;
+            ("current schema".to_string(), schema.clone())
+        }
+    };
+
+    println!("Changes from {} to {}:\n", old.label(), label);
+    let changes = snapshots::diff(&old.schema, &new);
+    if changes.is_empty() {
+        println!("  (no changes)");
+    }
+    for change in changes {
+        println!("  {}", change);
+    }
+    println!();
+    Ok(())
+}
+
+async fn choose_model(claude: &mut ClaudeClient, config: &mut Config, name: &str) -> Result<()> {
+    let available = claude.list_models().await?;
+
+    if !name.is_empty() {
+        if !available.iter().any(|m| m.id == name) {
+            println!("Unknown model: {} (\\models lists them)\n", name);
+            return Ok(());
+        }
+        claude.set_model(name);
+        config.model = Some(name.to_string());
+        println!("Model: {} ({})\n", name, models::details(name));
+        return Ok(());
+    }
+
+    let width = available.iter().map(|m| m.id.len()).max().unwrap_or(0);
+    let name_width = available
+        .iter()
+        .map(|m| m.display_name.len())
+        .max()
+        .unwrap_or(0);
+    let mut options: Vec<String> = available
+        .iter()
+        .map(|m| {
+            let current = if m.id == claude.model() {
+                " (current)"
+            } else {
+                ""
+            };
+            format!(
+                "{:<width$}  {:<name_width$}  {}{}",
+                m.id,
+                m.display_name,
+                models::details(&m.id),
+                current
+            )
+        })
+        .collect();
+    options.push("Keep current".to_string());
+    let labels: Vec<&str> = options.iter().map(String::as_str).collect();
+
+    let Some(selected) = pick_option(&labels)?.filter(|&i| i < available.len()) else {
+        println!("Model: {}\n", claude.model());
+        return Ok(());
+    };
+
+    let model = &available[selected].id;
+    claude.set_model(model);
+    config.model = Some(model.clone());
+    println!("Switched to {} for this session.\n", model);
+    Ok(())
+}
+
+fn write_fixture(psql: &PsqlConnection, schema: &Schema, args: &str) -> Result<()> {
+    let Some(request) = fixtures::parse_request(args, schema) else {
+        println!("Name a table to sample, e.g. \\fixture orders 50\n");
+        return Ok(());
+    };
+    let Some(table) = schema.find_table(&request.table) else {
+        return Ok(());
+    };
+
+    let fixture = fixtures::build(psql, schema, table, request.rows)?;
+    let path = report::write_fixture(
+        request.path.as_deref(),
+        &table.name,
+        &fixture.to_sql(request.format),
+    )?;
+    let (referenced, tables) = fixture.referenced();
+    println!(
+        "Wrote {} rows from {} and {} referenced {} from {} other {} to {}\n",
+        fixture.root_rows,
+        fixture.root,
+        referenced,
+        if referenced == 1 { "row" } else { "rows" },
+        tables,
+        if tables == 1 { "table" } else { "tables" },
+        path.display()
+    );
+    Ok(())
+}
+
+fn show_graph(schema: &Schema, args: &str) -> Result<()> {
+    let Some(request) = graph::parse_request(args, schema) else {
+        println!("Name a table to draw, e.g. \\graph orders 2\n");
+        return Ok(());
+    };
+    let Some(table) = schema.find_table(&request.table) else {
+        return Ok(());
+    };
+
+    let graph = graph::build(schema, table, request.hops);
+    let Some(format) = request.format else {
+        print!("{}", graph.to_ascii());
+        println!();
+        return Ok(());
+    };
+    let contents = match format {
+        graph::Format::Mermaid => graph.to_mermaid(),
+        graph::Format::Dot => graph.to_dot(),
+    };
+    let path = report::write_graph(
+        request.path.as_deref(),
+        &table.name,
+        format.extension(),
+        &contents,
+    )?;
+    println!(
+        "Wrote {} {} within {} {} of {} to {}\n",
+        graph.table_count(),
+        if graph.table_count() == 1 {
+            "table"
+        } else {
+            "tables"
+        },
+        request.hops,
+        if request.hops == 1 { "hop" } else { "hops" },
+        table.name,
+        path.display()
+    );
+    Ok(())
+}
+
+fn show_locks(psql: &PsqlConnection) -> Result<()> {
+    let locks = psql.locks()?;
+    if locks.rows.is_empty() {
+        println!("No sessions are waiting on locks.\n");
+        return Ok(());
+    }
+
+    print!("{}", locks.render_table());
+    println!();
+
+    let cell = |row: &[Option<String>], column: &str| -> String {
+        locks
+            .column_index(column)
+            .and_then(|i| row[i].clone())
+            .unwrap_or_default()
+    };
+
+    let mut blocking: Vec<(String, String)> = Vec::new();
+    for row in &locks.rows {
+        let pid = cell(row, "blocking_pid");
+        if blocking.iter().any(|(p, _)| *p == pid) {
+            continue;
+        }
+        let label = format!(
+            "Terminate {} ({}, {}): {}",
+            pid,
+            cell(row, "blocking_user"),
+            cell(row, "blocking_state"),
+            cell(row, "blocking_query")
+        );
+        blocking.push((pid, label));
+    }
+
+    let mut options: Vec<&str> = blocking.iter().map(|(_, label)| label.as_str()).collect();
+    options.push("Done");
+
+    let Some(selected) = pick_option(&options)?.filter(|&i| i < blocking.len()) else {
+        println!();
+        return Ok(());
+    };
+    terminate_backend(psql, &blocking[selected].0)
+}
+
+fn show_hot_tables(psql: &PsqlConnection) -> Result<()> {
+    let usage = psql.table_usage(HOT_TABLES)?;
+    if usage.rows.is_empty() {
+        println!("No table statistics yet.\n");
+        return Ok(());
+    }
+
+    print!("{}", usage.render_table());
+    println!();
+
+    let count = |row: &[Option<String>], column: &str| -> f64 {
+        usage
+            .column_index(column)
+            .and_then(|i| row[i].as_deref())
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_default()
+    };
+    let tables: Vec<(String, f64, f64)> = usage
+        .rows
+        .iter()
+        .map(|row| {
+            (
+                row[0].clone().unwrap_or_default(),
+                count(row, "rows_read"),
+                count(row, "rows_written"),
+            )
+        })
+        .collect();
+
+    let name_width = tables
+        .iter()
+        .map(|(name, _, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let terminal_width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+    let bar_width = terminal_width.saturating_sub(name_width + 20).max(10) / 2;
+    let max_read = tables.iter().map(|(_, r, _)| *r).fold(0.0, f64::max);
+    let max_written = tables.iter().map(|(_, _, w)| *w).fold(0.0, f64::max);
+    let bar = |value: f64, max: f64| -> String {
+        let len = if max > 0.0 {
+            ((value / max) * bar_width as f64).ceil() as usize
+        } else {
+            0
+        };
+        format!("{:<bar_width$}", "█".repeat(len))
+    };
+
+    println!(
+        "{:<name_width$}  \x1b[32m{:<bar_width$}\x1b[0m {:>7}  \x1b[33m{:<bar_width$}\x1b[0m {:>7}",
+        "", "rows read", "", "rows written", ""
+    );
+    for (name, read, written) in &tables {
+        println!(
+            "{:<name_width$}  \x1b[32m{}\x1b[0m {:>7}  \x1b[33m{}\x1b[0m {:>7}",
+            name,
+            bar(*read, max_read),
+            estimate::format_count(*read),
+            bar(*written, max_written),
+            estimate::format_count(*written)
+        );
+    }
+    println!();
+    Ok(())
+}
+
+async fn show_health(
+    psql: &PsqlConnection,
+    claude: &mut ClaudeClient,
+    schema: &mut Schema,
+    config: &mut Config,
+    output: &mut Output,
+) -> Result<()> {
+    let conn = psql.clone();
+    let sections =
+        run_blocking("Checking database health...", move || health::report(&conn)).await?;
+
+    for section in &sections {
+        println!("\x1b[1m{}\x1b[0m", section.title);
+        if section.result.rows.is_empty() {
+            println!("  none\n");
+        } else {
+            print!("{}", section.result.render_table());
+            println!();
+        }
+    }
+    if health::is_healthy(&sections) {
+        return Ok(());
+    }
+
+    match pick_option(&["Ask Claude for prioritized recommendations", "Done"])? {
+        Some(0) => {}
+        _ => {
+            println!();
+            return Ok(());
+        }
+    }
+    println!();
+    let recommendations = claude
+        .recommend_maintenance(&health::to_prompt_string(&sections))
+        .await?;
+    println!();
+
+    let mut statements: Vec<String> = recommendations
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("SQL:"))
+        .map(|sql| sql.trim().trim_matches('`').trim().to_string())
+        .filter(|sql| !sql.is_empty())
+        .collect();
+    while !statements.is_empty() {
+        let mut options: Vec<String> = statements
+            .iter()
+            .map(|sql| format!("Run {}", sql))
+            .collect();
+        options.push("Done".to_string());
+        let labels: Vec<&str> = options.iter().map(String::as_str).collect();
+        let Some(choice) = pick_option(&labels)?.filter(|&i| i < statements.len()) else {
+            println!();
+            return Ok(());
+        };
+
+        let sql = statements.remove(choice);
+        println!("\x1b[32m{}\x1b[0m\n", sql);
+        let context = SqlContext {
+            psql,
+            claude,
+            schema,
+            question: "Apply health recommendation",
+        };
+        let Some(sql) = confirm_sql(sql, &context, config).await? else {
+            println!("Cancelled.\n");
+            continue;
+        };
+        execute_with_recovery(
+            psql,
+            claude,
+            schema,
+            "Apply health recommendation",
+            &sql,
+            config,
+            output,
+        )
+        .await?;
+        if is_schema_change(&sql) {
+            refresh_schema(psql, claude, schema, &config.kiosk)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn terminate_backend(psql: &PsqlConnection, pid: &str) -> Result<()> {
+    let backend = psql.backend(pid)?;
+    if backend.rows.is_empty() {
+        println!("No backend is running with pid {}.\n", pid);
+        return Ok(());
+    }
+
+    print!("{}", backend.render_table());
+    println!(
+        "\nTerminate pid {}? Its connection is closed and any open transaction is rolled back.",
+        pid
+    );
+    if pick_option(&["Leave it", "Terminate backend"])? != Some(1) {
+        println!("Left running.\n");
+        return Ok(());
+    }
+
+    if psql.terminate_backend(pid)? {
+        println!("Terminated pid {}.\n", pid);
+    } else {
+        println!(
+            "Could not terminate pid {} (already gone or not permitted).\n",
+            pid
+        );
+    }
+
+    Ok(())
+}
+
+fn show_activity(psql: &PsqlConnection) -> Result<()> {
+    let activity = psql.activity()?;
+    if activity.rows.is_empty() {
+        println!("No other active queries.\n");
+        return Ok(());
+    }
+
+    print!("{}", activity.render_table());
+    println!();
+
+    let cell = |row: &[Option<String>], column: &str| -> String {
+        activity
+            .column_index(column)
+            .and_then(|i| row[i].clone())
+            .unwrap_or_default()
+    };
+
+    let mut options: Vec<String> = activity
+        .rows
+        .iter()
+        .map(|row| {
+            format!(
+                "Cancel {} ({}): {}",
+                cell(row, "pid"),
+                cell(row, "duration"),
+                cell(row, "query")
+            )
+        })
+        .collect();
+    options.push("Done".to_string());
+    let labels: Vec<&str> = options.iter().map(String::as_str).collect();
+
+    let Some(selected) = pick_option(&labels)?.filter(|&i| i < activity.rows.len()) else {
+        println!();
+        return Ok(());
+    };
+
+    let pid = cell(&activity.rows[selected], "pid");
+    println!("\nCancel the query running on pid {}?", pid);
+    if pick_option(&["Keep running", "Cancel query"])? != Some(1) {
+        println!("Left running.\n");
+        return Ok(());
+    }
+
+    if psql.cancel_backend(&pid)? {
+        println!("Sent cancel request to pid {}.\n", pid);
+    } else {
+        println!(
+            "Could not cancel pid {} (already finished or not permitted).\n",
+            pid
+        );
+    }
+
+    Ok(())
+}
+
+async fn query_on_profiles(
+    target: &str,
+    question: &str,
+    main: (&str, &PsqlConnection, &Schema),
+    connections: &mut ProfileConnections,
+    claude: &mut ClaudeClient,
+    config: &mut Config,
+) -> Result<()> {
+    let (main_name, main_psql, main_schema) = main;
+
+    let names: Vec<String> = if target == "all" {
+        std::iter::once(main_name)
+            .chain(connections.names().into_iter().filter(|n| *n != main_name))
+            .map(|n| n.to_string())
+            .collect()
+    } else if target == main_name || connections.contains(target) {
+        vec![target.to_string()]
+    } else {
+        anyhow::bail!("Unknown profile: {}", target);
+    };
+
+    let mut jobs = Vec::new();
+    for name in names {
+        let (conn, conn_schema) = if name == main_name {
+            (main_psql.clone(), main_schema)
+        } else {
+            let (conn, conn_schema) = connections.get(&name)?;
+            (conn.clone(), conn_schema)
+        };
+
+        println!("\n[{}]", name);
+        let is_raw_sql = is_valid_sql(question);
+        let sql = if is_raw_sql {
+            question.to_string()
+        } else {
+            generate_sql(claude, conn_schema, config, question).await?
+        };
+        let sql = fix_quoting(&sql, conn_schema);
+
+        if !is_read_only_sql(&sql) {
+            println!(
+                "Skipped: \\on only runs read queries; connect to {} for writes.",
+                name
+            );
+            continue;
+        }
+
+        let sql = match config.execution_mode {
+            _ if is_raw_sql => sql,
+            ExecutionMode::Show => continue,
+            ExecutionMode::Confirm => {
+                let context = SqlContext {
+                    psql: &conn,
+                    claude,
+                    schema: conn_schema,
+                    question,
+                };
+                match confirm_sql(sql, &context, config).await? {
+                    Some(sql) => sql,
+                    None => {
+                        println!("Skipped.");
+                        continue;
+                    }
+                }
+            }
+            ExecutionMode::Auto => sql,
+        };
+        let mut sql = sql;
+        if !run_pre_hook(&conn, config, question, &mut sql).await? {
+            continue;
+        }
+        if !is_read_only_sql(&sql) {
+            println!(
+                "Skipped: the pre-execution hook turned this into a write; connect to {} for writes.",
+                name
+            );
+            continue;
+        }
+        if let Err(e) = kiosk::check(&config.kiosk, &sql) {
+            println!("⛔ [{}] {}. Not running it.", name, e);
+            continue;
+        }
+
+        let conn = conn.tagged(query_tag_format(config), question);
+        let reader = read_connection(&conn, &sql, config).clone();
+        let started = Instant::now();
+        jobs.push(tokio::task::spawn_blocking(move || {
+            let outcome = reader.execute_capture(&sql);
+            (name, conn, sql, started, outcome)
+        }));
+    }
+
+    for job in join_all(jobs).await {
+        let (name, conn, sql, started, outcome) = job?;
+        println!("\n── [{}] ──", name);
+        match outcome {
+            Ok((success, stdout, stderr)) => {
+                record_execution(&conn, config, question, &sql, success, &stderr, started).await;
+                if success {
+                    print!("{}", stdout);
+                } else {
+                    print_error(&conn, &sql, &stderr);
+                }
+            }
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+    println!();
+
+    Ok(())
+}
+
+fn table_layout(config: &Config) -> TableLayout {
+    TableLayout {
+        max_column_width: config.max_column_width.unwrap_or(DEFAULT_MAX_COLUMN_WIDTH),
+        wrap: config.wrap,
+        max_width: terminal::size().ok().map(|(width, _)| width as usize),
+        first_column: 0,
+        json_expanded: config.json == JsonDisplay::Expanded,
+        json_inline_width: config
+            .json_collapse_threshold
+            .unwrap_or(json::DEFAULT_INLINE_WIDTH),
+        highlight: io::stdout().is_terminal(),
+        demo: config.demo,
+        units: true,
+        format: config.format,
+    }
+}
+
+fn colored_prompt(config: &Config) -> String {
+    let prompt = config.environment.prompt();
+    match config.environment.color() {
+        Some(color) if !accessibility::enabled() => format!("{}{}\x1b[0m", color.ansi(), prompt),
+        _ => prompt,
+    }
+}
+
+fn environment_banner(psql: &PsqlConnection, config: &Config) -> Option<String> {
+    let environment = &config.environment;
+    let label = environment.label.as_deref()?;
+    let banner = format!(
+        " {} · {}@{}/{}{} ",
+        label,
+        psql.user,
+        psql.host,
+        psql.database,
+        if environment.production {
+            " · production: writes must be confirmed by name"
+        } else {
+            ""
+        }
+    );
+    Some(match environment.color() {
+        Some(color) if !accessibility::enabled() => {
+            format!("{}\x1b[7m{}\x1b[0m", color.ansi(), banner)
+        }
+        _ => banner,
+    })
+}
+
+fn production_allows(config: &Config, action: &str, refused: &str) -> Result<bool> {
+    let Some(label) = config
+        .environment
+        .label
+        .as_deref()
+        .filter(|_| config.environment.production)
+    else {
+        return Ok(true);
+    };
+    print!(
+        "This is a production database. Type {} to {}: ",
+        label, action
+    );
+    io::stdout().flush()?;
+    let mut typed = String::new();
+    io::stdin().read_line(&mut typed)?;
+    if typed.trim() != label {
+        println!("Confirmation did not match. {}\n", refused);
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn opens_viewer(config: &Config, rows: usize) -> bool {
+    let height = terminal::size().map_or(usize::MAX, |(_, height)| height as usize);
+    config.result_viewer == ResultViewer::Auto
+        && config.format == OutputFormat::Table
+        && io::stdout().is_terminal()
+        && !accessibility::enabled()
+        && rows + 4 > height
+}
+
+fn displayed(config: &Config, text: &str) -> String {
+    if config.demo {
+        demo::scrub_emails(text)
+    } else {
+        text.to_string()
     }
+}
 
-    Ok(false)
+fn print_error(psql: &PsqlConnection, sql: &str, stderr: &str) {
+    eprint!("{}", render_error(stderr, sql, psql.tag_line_count()));
 }
 
 fn is_valid_sql(input: &str) -> bool {
@@ -164,74 +1991,625 @@ fn is_valid_sql(input: &str) -> bool {
     Parser::parse_sql(&dialect, input).is_ok()
 }
 
+enum CrossDatabase {
+    Run,
+    Regenerate,
+    Profile(String),
+    Cancel,
+}
+
+fn cross_database_action(
+    references: &[cross_database::Reference],
+    psql: &PsqlConnection,
+    connections: &ProfileConnections,
+) -> Result<CrossDatabase> {
+    println!(
+        "\x1b[33m⚠️  This SQL reaches outside the connected database {}:\x1b[0m",
+        psql.database
+    );
+    for reference in references {
+        println!("   - {}", reference.describe());
+    }
+    println!();
+
+    let mut profiles: Vec<&str> = Vec::new();
+    for database in references.iter().filter_map(|r| r.database()) {
+        for name in connections.for_database(database) {
+            if !profiles.contains(&name) {
+                profiles.push(name);
+            }
+        }
+    }
+
+    let mut options: Vec<String> = profiles
+        .iter()
+        .map(|name| format!("Ask on profile {} instead", name))
+        .collect();
+    options.push(format!("Regenerate using only {}", psql.database));
+    options.push("Run anyway".to_string());
+    options.push("Cancel".to_string());
+    let labels: Vec<&str> = options.iter().map(String::as_str).collect();
+
+    Ok(match pick_option(&labels)? {
+        Some(i) if i < profiles.len() => CrossDatabase::Profile(profiles[i].to_string()),
+        Some(i) if i == profiles.len() => CrossDatabase::Regenerate,
+        Some(i) if i == profiles.len() + 1 => CrossDatabase::Run,
+        _ => CrossDatabase::Cancel,
+    })
+}
+
+enum TimeZoneChoice {
+    Use(String),
+    Run,
+    Cancel,
+}
+
+fn time_zone_action(psql: &PsqlConnection, columns: &[String]) -> Result<TimeZoneChoice> {
+    let session = time_zones::session(psql).ok();
+    println!(
+        "\x1b[33m⚠️  The question names calendar days, but {} {} points in time; days start at a different moment in each time zone.\x1b[0m",
+        columns.join(", "),
+        if columns.len() == 1 { "stores" } else { "store" }
+    );
+    println!("Which time zone should day boundaries use?\n");
+
+    let mut zones: Vec<(String, String)> = Vec::new();
+    if let Some(session) = &session {
+        zones.push((session.clone(), format!("Session time zone ({})", session)));
+    }
+    if let Some(local) = time_zones::local().filter(|local| Some(local) != session.as_ref()) {
+        zones.push((
+            local.clone(),
+            format!("This machine's time zone ({})", local),
+        ));
+    }
+    if !zones
+        .iter()
+        .any(|(zone, _)| zone.eq_ignore_ascii_case("UTC"))
+    {
+        zones.push(("UTC".to_string(), "UTC".to_string()));
+    }
+    let mut options: Vec<&str> = zones.iter().map(|(_, label)| label.as_str()).collect();
+    options.extend(["Another time zone...", "Run as is", "Cancel"]);
+
+    Ok(match pick_option(&options)? {
+        Some(i) if i < zones.len() => TimeZoneChoice::Use(zones[i].0.clone()),
+        Some(i) if i == zones.len() => {
+            print!("Time zone (e.g. America/New_York): ");
+            io::stdout().flush()?;
+            let mut zone = String::new();
+            io::stdin().read_line(&mut zone)?;
+            let zone = zone.trim();
+            if zone.is_empty() {
+                TimeZoneChoice::Cancel
+            } else if !time_zones::is_known(psql, zone)? {
+                println!("PostgreSQL does not know the time zone {}.", zone);
+                TimeZoneChoice::Cancel
+            } else {
+                TimeZoneChoice::Use(zone.to_string())
+            }
+        }
+        Some(i) if i == zones.len() + 1 => TimeZoneChoice::Run,
+        _ => TimeZoneChoice::Cancel,
+    })
+}
+
 async fn handle_query(
     question: &str,
     psql: &PsqlConnection,
+    connections: &mut ProfileConnections,
     claude: &mut ClaudeClient,
-    schema: &Schema,
+    schema: &mut Schema,
+    config: &mut Config,
+    output: &mut Output,
+) -> Result<()> {
+    let (hints, question) = hints::parse(question);
+    claude.set_hints(hints);
+    if hints.fast {
+        println!(
+            "\x1b[2mUsing {} for this question.\x1b[0m",
+            claude.request_model()
+        );
+    }
+    let recent = match config.recent_queries {
+        Some(limit) if limit > 0 && intent::mentions_past_query(question) => {
+            recent_statements(psql, limit).await
+        }
+        _ => None,
+    };
+    claude.set_recent_queries(recent);
+    let result = answer_question(question, psql, connections, claude, schema, config, output).await;
+    claude.set_hints(hints::Hints::default());
+    claude.set_recent_queries(None);
+    result
+}
+
+async fn recent_statements(psql: &PsqlConnection, limit: usize) -> Option<String> {
+    let conn = psql.clone();
+    let digest = run_blocking("Reading recent statements...", move || {
+        similar::recent_statements(&conn, limit)
+    })
+    .await;
+    match digest {
+        Ok(digest) if !digest.is_empty() => {
+            println!(
+                "\x1b[2mIncluding {} recent statements from pg_stat_statements.\x1b[0m",
+                digest.lines().count()
+            );
+            Some(digest)
+        }
+        Ok(_) => None,
+        Err(e) => {
+            println!("\x1b[2mRecent statements unavailable: {}\x1b[0m", e);
+            None
+        }
+    }
+}
+
+async fn answer_question(
+    question: &str,
+    psql: &PsqlConnection,
+    connections: &mut ProfileConnections,
+    claude: &mut ClaudeClient,
+    schema: &mut Schema,
     config: &mut Config,
+    output: &mut Output,
 ) -> Result<()> {
+    let count_only = question
+        .get(..COUNT_PREFIX.len())
+        .is_some_and(|p| p.eq_ignore_ascii_case(COUNT_PREFIX));
+    let question = if count_only {
+        question[COUNT_PREFIX.len()..].trim()
+    } else {
+        question
+    };
+
     let mut current_question = question.to_string();
     let mut current_sql: Option<String> = None;
     let mut is_raw_sql = false;
 
-    if is_valid_sql(question) {
-        current_sql = Some(question.to_string());
+    if count_only {
+        if question.is_empty() {
+            println!("Usage: count: <question>\n");
+            return Ok(());
+        }
+    } else if is_valid_sql(question) {
+        let Some(sql) = prompt_parameters(question, schema)? else {
+            println!("Cancelled.\n");
+            return Ok(());
+        };
+        current_sql = Some(sql);
         is_raw_sql = true;
+    } else if !question.contains(result_view::DRILL_DOWN_HEADER) && is_schema_question(question) {
+        let relevant = relevant_schema(question, schema);
+        println!();
+        match config.schema_answers {
+            SchemaAnswers::Model => {
+                claude.answer_schema_question(&relevant, question).await?;
+            }
+            SchemaAnswers::Local => print!("{}", relevant.to_prompt_string()),
+        }
+        println!();
+        return Ok(());
+    }
+
+    let intent = intent::classify(question);
+    if !count_only
+        && !is_raw_sql
+        && !config.kiosk.enabled
+        && matches!(intent, Intent::Activity | Intent::Locks | Intent::Fixture)
+    {
+        let helper = match intent {
+            Intent::Locks => "Show blocked and blocking sessions (pg_locks)",
+            Intent::Fixture => "Write a fixture file with sampled rows and the rows they reference",
+            _ => "Show running queries and cancel one (pg_stat_activity)",
+        };
+        println!();
+        match pick_option(&[helper, "Generate SQL for this question"])? {
+            Some(0) if intent == Intent::Locks => return show_locks(psql),
+            Some(0) if intent == Intent::Fixture => return write_fixture(psql, schema, question),
+            Some(0) => return show_activity(psql),
+            Some(_) => {}
+            None => {
+                println!("Cancelled.\n");
+                return Ok(());
+            }
+        }
+    }
+
+    if !is_raw_sql && topic::is_new_topic(question, &claude.history, schema) {
+        let reset = match config.topic_change {
+            TopicChange::Ask => {
+                println!("\nThis looks unrelated to the previous questions. Start a new topic?");
+                pick_option(&["Start a new topic", "Keep the conversation"])? == Some(0)
+            }
+            TopicChange::Reset => {
+                println!(
+                    "\x1b[2mNew topic; earlier questions are no longer sent as context.\x1b[0m"
+                );
+                true
+            }
+            TopicChange::Off => false,
+        };
+        if reset {
+            claude.history.clear();
+        }
     }
 
     loop {
         if current_sql.is_none() {
-            println!("");
-            let sql = claude.text_to_sql(schema, &current_question).await?;
             println!();
-            current_sql = Some(sql);
+            let sql = if count_only {
+                consent_to_results(claude, config)?;
+                check_prompt_cost(claude, schema, config, &current_question)?;
+                claude.text_to_count_sql(schema, &current_question).await?
+            } else {
+                generate_sql(claude, schema, config, &current_question).await?
+            };
+            println!();
+            current_sql = Some(fix_quoting(&sql, schema));
             is_raw_sql = false;
         }
 
         let sql = current_sql.as_ref().unwrap();
+        if claude.hints().verbose {
+            if let Err(e) = claude.explain_sql(schema, sql).await {
+                println!("Could not explain the SQL: {}", e);
+            }
+            println!();
+        }
 
-        if is_raw_sql {
-            execute_with_recovery(psql, claude, schema, &current_question, sql, config).await?;
+        if let Err(e) = kiosk::check(&config.kiosk, sql) {
+            println!("⛔ {}. Not running it.\n", e);
             return Ok(());
         }
 
-        match config.execution_mode {
-            ExecutionMode::Show => {
-                return Ok(());
+        if is_raw_sql {
+            execute_with_recovery(psql, claude, schema, &current_question, sql, config, output)
+                .await?;
+            if is_schema_change(sql) {
+                refresh_schema(psql, claude, schema, &config.kiosk)?;
             }
-            ExecutionMode::Confirm => match confirm_execution(config)? {
-                RunChoice::Run | RunChoice::AutoRun => {}
-                RunChoice::EditSql => {
-                    current_sql = Some(prompt_edit_sql(sql)?);
-                    is_raw_sql = false;
+            return Ok(());
+        }
+
+        let foreign = cross_database::find(sql, &psql.database);
+        if !foreign.is_empty() {
+            match cross_database_action(&foreign, psql, connections)? {
+                CrossDatabase::Run => {}
+                CrossDatabase::Regenerate => {
+                    println!();
+                    let question = format!(
+                        "{}\n[Use only tables in the connected database {}; PostgreSQL cannot query other databases]",
+                        current_question, psql.database
+                    );
+                    let sql = generate_sql(claude, schema, config, &question).await?;
+                    println!();
+                    current_sql = Some(fix_quoting(&sql, schema));
                     continue;
                 }
-                RunChoice::EditPrompt => {
-                    print!("Enter new prompt: ");
-                    io::stdout().flush()?;
-                    let mut new_prompt = String::new();
-                    io::stdin().read_line(&mut new_prompt)?;
-                    let new_prompt = new_prompt.trim();
-                    if new_prompt.is_empty() {
-                        println!("Cancelled.\n");
-                        return Ok(());
-                    }
-                    current_question = new_prompt.to_string();
-                    current_sql = None;
+                CrossDatabase::Profile(name) => {
+                    let current = connections.current().to_string();
+                    let main = (current.as_str(), psql, &*schema);
+                    return query_on_profiles(
+                        &name,
+                        &current_question,
+                        main,
+                        connections,
+                        claude,
+                        config,
+                    )
+                    .await;
+                }
+                CrossDatabase::Cancel => {
+                    println!("Cancelled.\n");
+                    return Ok(());
+                }
+            }
+        }
+
+        let generated = generated_columns::find(sql, schema);
+        if !generated.is_empty() {
+            println!("\x1b[33m⚠️  This SQL supplies values PostgreSQL generates itself:\x1b[0m");
+            for violation in &generated {
+                println!("   - {}", violation.describe());
+            }
+            println!();
+            match pick_option(&["Regenerate without them", "Run anyway", "Cancel"])? {
+                Some(0) => {
+                    println!();
+                    let columns: Vec<String> = generated
+                        .iter()
+                        .map(|v| format!("{}.{}", v.table, v.column))
+                        .collect();
+                    let question = format!(
+                        "{}\n[Do not supply values for {}; PostgreSQL generates them, so leave them out or use DEFAULT]",
+                        current_question,
+                        columns.join(", ")
+                    );
+                    let sql = generate_sql(claude, schema, config, &question).await?;
+                    println!();
+                    current_sql = Some(fix_quoting(&sql, schema));
                     continue;
                 }
-                RunChoice::Cancel => {
+                Some(1) => {}
+                _ => {
                     println!("Cancelled.\n");
                     return Ok(());
                 }
-            },
+            }
+        }
+
+        let columns = time_zones::timestamptz_columns(sql, schema);
+        if !columns.is_empty() && intent::mentions_relative_date(&current_question) {
+            match claude.time_zone() {
+                Some(zone) => current_sql = Some(time_zones::annotate(sql, zone)),
+                None if time_zones::names_zone(sql) => {}
+                None => match time_zone_action(psql, &columns)? {
+                    TimeZoneChoice::Use(zone) => {
+                        println!(
+                            "\x1b[2mDay boundaries use {} for the rest of the session.\x1b[0m\n",
+                            zone
+                        );
+                        claude.set_time_zone(Some(zone));
+                        let sql = generate_sql(claude, schema, config, &current_question).await?;
+                        println!();
+                        current_sql = Some(fix_quoting(&sql, schema));
+                        continue;
+                    }
+                    TimeZoneChoice::Run => {}
+                    TimeZoneChoice::Cancel => {
+                        println!("Cancelled.\n");
+                        return Ok(());
+                    }
+                },
+            }
+        }
+        let sql = current_sql.as_ref().unwrap();
+
+        if is_write_operation(sql) && intent::classify(&current_question) == Intent::Read {
+            println!("⚠️  The question reads like a lookup, but this SQL modifies data.\n");
+        }
+
+        let mode = if count_only && !is_write_operation(sql) && !config.safe_mode_active {
+            ExecutionMode::Auto
+        } else {
+            config.execution_mode
+        };
+
+        if mode == ExecutionMode::Confirm {
+            show_estimate(psql, sql).await;
+        }
+
+        match mode {
+            ExecutionMode::Show => {
+                return Ok(());
+            }
+            ExecutionMode::Confirm => {
+                let context = SqlContext {
+                    psql,
+                    claude,
+                    schema,
+                    question: &current_question,
+                };
+                match confirm_execution(sql, &context, config).await? {
+                    RunChoice::Run | RunChoice::AutoRun => {}
+                    RunChoice::EditSql => {
+                        current_sql = Some(prompt_edit_sql(sql, schema)?);
+                        is_raw_sql = false;
+                        continue;
+                    }
+                    RunChoice::EditPrompt => {
+                        print!("Enter new prompt: ");
+                        io::stdout().flush()?;
+                        let mut new_prompt = String::new();
+                        io::stdin().read_line(&mut new_prompt)?;
+                        let new_prompt = new_prompt.trim();
+                        if new_prompt.is_empty() {
+                            println!("Cancelled.\n");
+                            return Ok(());
+                        }
+                        current_question = new_prompt.to_string();
+                        current_sql = None;
+                        continue;
+                    }
+                    RunChoice::Cancel => {
+                        println!("Cancelled.\n");
+                        return Ok(());
+                    }
+                }
+            }
             ExecutionMode::Auto => {}
         }
 
-        execute_with_recovery(psql, claude, schema, &current_question, sql, config).await?;
+        execute_with_recovery(psql, claude, schema, &current_question, sql, config, output).await?;
+        if is_schema_change(sql) {
+            refresh_schema(psql, claude, schema, &config.kiosk)?;
+        }
+        return Ok(());
+    }
+}
+
+async fn show_estimate(psql: &PsqlConnection, sql: &str) {
+    let (conn, sql) = (psql.clone(), sql.to_string());
+    let estimate = run_blocking("Estimating cost...", move || {
+        estimate::estimate(&conn, &sql)
+    })
+    .await;
+    if let Ok(estimate) = estimate {
+        println!("\x1b[2m{}\x1b[0m\n", estimate.summary());
+    }
+}
+
+fn check_prompt_cost(
+    claude: &ClaudeClient,
+    schema: &Schema,
+    config: &Config,
+    question: &str,
+) -> Result<()> {
+    let preview = &config.cost_preview;
+    if !preview.show
+        && preview.confirm_above_tokens.is_none()
+        && preview.confirm_above_dollars.is_none()
+    {
+        return Ok(());
+    }
+
+    let tokens = claude.prompt_tokens(schema, question);
+    let cost = models::input_cost(claude.request_model(), tokens);
+    let summary = format!(
+        "~{} input tokens{}",
+        estimate::format_count(tokens as f64),
+        cost.map(|c| format!(" (~${:.4})", c)).unwrap_or_default()
+    );
+
+    let over = preview
+        .confirm_above_tokens
+        .is_some_and(|limit| tokens > limit)
+        || preview
+            .confirm_above_dollars
+            .zip(cost)
+            .is_some_and(|(limit, cost)| cost > limit);
+    if !over {
+        if preview.show {
+            println!("\x1b[2m{}\x1b[0m", summary);
+        }
+        return Ok(());
+    }
+
+    println!(
+        "\x1b[33m⚠️  This question sends {} to {}.\x1b[0m",
+        summary,
+        claude.request_model()
+    );
+    if pick_option(&["Send", "Cancel"])? != Some(0) {
+        anyhow::bail!("Cancelled before sending {}", summary);
+    }
+    Ok(())
+}
+
+fn consent_to_results(claude: &mut ClaudeClient, config: &mut Config) -> Result<()> {
+    let rows = claude.unshared_rows();
+    if rows == 0 {
+        claude.approve_results();
         return Ok(());
     }
+
+    match config.data_consent {
+        DataConsent::Always => claude.approve_results(),
+        DataConsent::Never => claude.withhold_results(),
+        DataConsent::Ask => {
+            println!(
+                "\x1b[33m⚠️  {} {} of data from earlier results will be sent to the model. Proceed?\x1b[0m",
+                rows,
+                if rows == 1 { "row" } else { "rows" }
+            );
+            match pick_option(&[
+                "Send them",
+                "Send them for the rest of the session",
+                "Send only the SQL",
+            ])? {
+                Some(0) => claude.approve_results(),
+                Some(1) => {
+                    config.data_consent = DataConsent::Always;
+                    claude.approve_results();
+                }
+                _ => claude.withhold_results(),
+            }
+            println!();
+        }
+    }
+    Ok(())
+}
+
+async fn generate_sql(
+    claude: &mut ClaudeClient,
+    schema: &Schema,
+    config: &mut Config,
+    question: &str,
+) -> Result<String> {
+    consent_to_results(claude, config)?;
+    check_prompt_cost(claude, schema, config, question)?;
+
+    if !config.templates.is_empty() {
+        let catalog = templates::to_prompt_string(&config.templates);
+        let response = claude.choose_template(&catalog, question).await?;
+        match templates::fill(&config.templates, &response, schema) {
+            Ok(Some((name, sql))) => {
+                println!("Using template {}:", name);
+                println!("\x1b[32m{}\x1b[0m", sql);
+                return Ok(sql);
+            }
+            Ok(None) => println!("No template matches; generating free-form SQL."),
+            Err(e) => println!("⚠️  {}; generating free-form SQL.", e),
+        }
+    }
+
+    match intent::classify(question) {
+        Intent::Write => claude.text_to_write_sql(schema, question).await,
+        _ => claude.text_to_sql(schema, question).await,
+    }
+}
+
+fn prompt_parameters(sql: &str, schema: &Schema) -> Result<Option<String>> {
+    let placeholders = find_placeholders(sql, schema);
+    if placeholders.is_empty() {
+        return Ok(Some(sql.to_string()));
+    }
+
+    println!("Enter parameter values (NULL for null, empty line to cancel):");
+    let mut values = HashMap::new();
+    for placeholder in &placeholders {
+        let hint = match (&placeholder.type_hint, &placeholder.column) {
+            (Some(ty), Some(col)) => format!(" [{}, {}]", ty, col),
+            (Some(ty), None) => format!(" [{}]", ty),
+            (None, Some(col)) => format!(" [{}]", col),
+            (None, None) => String::new(),
+        };
+        print!("  {}{}: ", placeholder.name, hint);
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let input = input.trim_end_matches(['\r', '\n']);
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let value = (!input.eq_ignore_ascii_case("null")).then(|| input.to_string());
+        values.insert(placeholder.name.clone(), value);
+    }
+
+    let bound = bind(sql, &values)?;
+    println!("\x1b[32m{}\x1b[0m", bound);
+    Ok(Some(bound))
+}
+
+fn fix_quoting(sql: &str, schema: &Schema) -> String {
+    let report = fix_identifier_quoting(sql, schema);
+    for warning in &report.warnings {
+        println!("⚠️  {}", warning);
+    }
+    if !report.restyled.is_empty() {
+        println!(
+            "Renamed identifiers to the schema's naming style: {}",
+            report.restyled.join(", ")
+        );
+    }
+    if !report.corrections.is_empty() {
+        println!(
+            "Quoted identifiers to match the schema: {}",
+            report.corrections.join(", ")
+        );
+    }
+    if !report.corrections.is_empty() || !report.restyled.is_empty() {
+        println!("\x1b[32m{}\x1b[0m\n", report.sql);
+    }
+    report.sql
+}
+
+fn query_tag_format(config: &Config) -> &str {
+    config.query_tag.as_deref().unwrap_or(DEFAULT_QUERY_TAG)
 }
 
 enum RunChoice {
@@ -243,6 +2621,26 @@ enum RunChoice {
 }
 
 fn pick_option(options: &[&str]) -> Result<Option<usize>> {
+    pick_option_with_shortcuts(options, &[])
+}
+
+fn pick_option_with_shortcuts(
+    options: &[&str],
+    shortcuts: &[(char, Option<usize>)],
+) -> Result<Option<usize>> {
+    let options: Vec<String> = options
+        .iter()
+        .enumerate()
+        .map(
+            |(i, option)| match shortcuts.iter().find(|(_, o)| *o == Some(i)) {
+                Some((key, _)) => format!("{option} [{key}]"),
+                None => option.to_string(),
+            },
+        )
+        .collect();
+    if accessibility::enabled() {
+        return accessibility::pick(&options, shortcuts);
+    }
     let mut selected: usize = 0;
     let mut stdout = io::stdout();
 
@@ -266,10 +2664,17 @@ fn pick_option(options: &[&str]) -> Result<Option<usize>> {
     let result = loop {
         if let Event::Key(key) = event::read()? {
             match key.code {
+                KeyCode::Char(c)
+                    if !key.modifiers.contains(KeyModifiers::CONTROL)
+                        && shortcuts.iter().any(|(key, _)| *key == c) =>
+                {
+                    break shortcuts
+                        .iter()
+                        .find(|(key, _)| *key == c)
+                        .and_then(|(_, option)| *option);
+                }
                 KeyCode::Up | KeyCode::Char('k') => {
-                    if selected > 0 {
-                        selected -= 1;
-                    }
+                    selected = selected.saturating_sub(1);
                 }
                 KeyCode::Down | KeyCode::Char('j') => {
                     if selected < options.len() - 1 {
@@ -295,22 +2700,172 @@ fn pick_option(options: &[&str]) -> Result<Option<usize>> {
     write!(stdout, "\r")?;
     stdout.flush()?;
 
-    Ok(result)
+    Ok(result)
+}
+
+async fn confirm_sql(
+    mut sql: String,
+    context: &SqlContext<'_>,
+    config: &mut Config,
+) -> Result<Option<String>> {
+    loop {
+        match confirm_execution(&sql, context, config).await? {
+            RunChoice::Run | RunChoice::AutoRun => return Ok(Some(sql)),
+            RunChoice::EditSql => sql = prompt_edit_sql(&sql, context.schema)?,
+            RunChoice::EditPrompt | RunChoice::Cancel => return Ok(None),
+        }
+    }
+}
+
+struct SqlContext<'a> {
+    psql: &'a PsqlConnection,
+    claude: &'a ClaudeClient,
+    schema: &'a Schema,
+    question: &'a str,
+}
+
+async fn confirm_execution(
+    sql: &str,
+    context: &SqlContext<'_>,
+    config: &mut Config,
+) -> Result<RunChoice> {
+    let options = &[
+        "Run",
+        "Edit SQL",
+        "Edit prompt",
+        "Always run (auto-mode)",
+        "More actions...",
+    ];
+    loop {
+        let keys = &config.confirm_keys;
+        let shortcuts = [
+            (keys.run, Some(0)),
+            (keys.edit_sql, Some(1)),
+            (keys.edit_prompt, Some(2)),
+            (keys.auto_run, Some(3)),
+            (keys.more, Some(4)),
+            (keys.cancel, None),
+        ];
+        let choice = pick_option_with_shortcuts(options, &shortcuts)?;
+        if choice == Some(4) {
+            more_actions(sql, context, config).await?;
+            continue;
+        }
+        return match choice {
+            Some(0) => Ok(RunChoice::Run),
+            Some(1) => Ok(RunChoice::EditSql),
+            Some(2) => Ok(RunChoice::EditPrompt),
+            Some(3) if config.safe_mode_active => {
+                println!("Auto mode is disabled while safe mode is on; running this one.\n");
+                Ok(RunChoice::Run)
+            }
+            Some(3) => {
+                config.execution_mode = ExecutionMode::Auto;
+                println!("Auto-run enabled. Use \\mode confirm to disable.\n");
+                Ok(RunChoice::AutoRun)
+            }
+            _ => Ok(RunChoice::Cancel),
+        };
+    }
+}
+
+async fn more_actions(sql: &str, context: &SqlContext<'_>, config: &mut Config) -> Result<()> {
+    let options = &[
+        "Copy to clipboard",
+        "Explain in English",
+        "Show EXPLAIN plan",
+        "Save as template",
+        "Export to a .sql file",
+        "Back",
+    ];
+    match pick_option(options)? {
+        Some(0) => match clipboard::copy(sql) {
+            Ok(()) => println!("Copied the SQL to the clipboard.\n"),
+            Err(e) => println!("Could not copy to the clipboard: {}\n", e),
+        },
+        Some(1) => {
+            println!();
+            match context.claude.explain_sql(context.schema, sql).await {
+                Ok(_) => println!("\n"),
+                Err(e) => println!("Could not explain the SQL: {}\n", e),
+            }
+        }
+        Some(2) => show_plan(context.psql, sql).await,
+        Some(3) => save_template(sql, context.question, config)?,
+        Some(4) => {
+            let entry = Entry {
+                question: context.question.to_string(),
+                sql: sql.to_string(),
+                outcome: Outcome::Text(String::new()),
+            };
+            let contents = report::to_sql_script(&context.psql.database, &[entry]);
+            let path = report::write_script(None, &contents)?;
+            println!("Wrote {}\n", path.display());
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn show_plan(psql: &PsqlConnection, sql: &str) {
+    let conn = psql.clone();
+    let explain = format!("EXPLAIN {}", sql.trim().trim_end_matches(';'));
+    let plan = run_blocking("Planning...", move || conn.execute_capture(&explain)).await;
+    match plan {
+        Ok((true, stdout, _)) => println!("{}\n", stdout.trim_end()),
+        Ok((false, _, stderr)) => println!("{}\n", stderr.trim()),
+        Err(e) => println!("Could not run EXPLAIN: {}\n", e),
+    }
 }
 
-fn confirm_execution(config: &mut Config) -> Result<RunChoice> {
-    let options = &["Run", "Edit SQL", "Edit prompt", "Always run (auto-mode)"];
-    match pick_option(options)? {
-        Some(0) => Ok(RunChoice::Run),
-        Some(1) => Ok(RunChoice::EditSql),
-        Some(2) => Ok(RunChoice::EditPrompt),
-        Some(3) => {
-            config.execution_mode = ExecutionMode::Auto;
-            println!("Auto-run enabled. Use \\mode confirm to disable.\n");
-            Ok(RunChoice::AutoRun)
-        }
-        _ => Ok(RunChoice::Cancel),
+fn save_template(sql: &str, question: &str, config: &mut Config) -> Result<()> {
+    print!("Template name: ");
+    io::stdout().flush()?;
+    let mut name = String::new();
+    io::stdin().read_line(&mut name)?;
+    let name = name.trim();
+    if name.is_empty() {
+        println!("Cancelled.\n");
+        return Ok(());
+    }
+    if !named_results::is_valid_name(name) {
+        println!(
+            "Template names must be letters, digits and underscores, starting with a letter.\n"
+        );
+        return Ok(());
+    }
+    if config.templates.contains_key(name) {
+        println!("A template named {} already exists.\n", name);
+        return Ok(());
+    }
+
+    let default = if question.trim() == sql.trim() {
+        ""
+    } else {
+        question.trim()
+    };
+    if default.is_empty() {
+        print!("Description: ");
+    } else {
+        print!("Description [{}]: ", default);
     }
+    io::stdout().flush()?;
+    let mut description = String::new();
+    io::stdin().read_line(&mut description)?;
+    let description = match description.trim() {
+        "" => default.to_string(),
+        description => description.to_string(),
+    };
+
+    let template = Template {
+        description,
+        sql: sql.trim().to_string(),
+        params: BTreeMap::new(),
+    };
+    let path = config::save_template(name, &template)?;
+    config.templates.insert(name.to_string(), template);
+    println!("Saved template {} to {}\n", name, path.display());
+    Ok(())
 }
 
 async fn execute_with_recovery(
@@ -320,10 +2875,23 @@ async fn execute_with_recovery(
     original_question: &str,
     sql: &str,
     config: &mut Config,
+    output: &mut Output,
 ) -> Result<()> {
+    let psql = &psql
+        .tagged(query_tag_format(config), original_question)
+        .with_isolation(config.isolation);
     let mut current_sql = sql.to_string();
 
     loop {
+        current_sql = fix_quoting(&current_sql, schema);
+        if let Err(e) = write_guard::check(&config.write_guard, &current_sql) {
+            println!("⛔ {}. Not running it.\n", e);
+            return Ok(());
+        }
+        if let Err(e) = kiosk::check(&config.kiosk, &current_sql) {
+            println!("⛔ {}. Not running it.\n", e);
+            return Ok(());
+        }
         let is_write = is_write_operation(&current_sql);
 
         if is_write {
@@ -334,34 +2902,215 @@ async fn execute_with_recovery(
                 original_question,
                 &mut current_sql,
                 config,
+                output,
             )
             .await?;
             return Ok(());
         }
 
-        println!();
-        let (success, stdout, stderr) = psql.execute_capture(&current_sql)?;
+        let unhooked = current_sql.clone();
+        if !run_pre_hook(psql, config, original_question, &mut current_sql).await? {
+            return Ok(());
+        }
+        if current_sql != unhooked {
+            let checked = write_guard::check(&config.write_guard, &current_sql)
+                .and_then(|()| kiosk::check(&config.kiosk, &current_sql));
+            if let Err(e) = checked {
+                println!("⛔ {}. Not running it.\n", e);
+                return Ok(());
+            }
+        }
+        if is_write_operation(&current_sql) {
+            continue;
+        }
 
-        if !stdout.is_empty() {
-            print!("{}", stdout);
+        println!();
+        let has_rows = returns_rows(&current_sql);
+        let copy = streaming::is_copy_to_stdout(&current_sql);
+        if !copy
+            && !is_read_only_sql(&current_sql)
+            && !production_allows(config, "run it", "Not running it.")?
+        {
+            return Ok(());
         }
+        let estimate = if copy || output.is_redirected() {
+            None
+        } else {
+            stream_estimate(psql, &current_sql, config).await
+        };
+        let (success, stdout, stderr) = match estimate {
+            _ if copy => {
+                match copy_to_file(psql, config, original_question, &current_sql, output).await? {
+                    Some(stderr) => (false, String::new(), stderr),
+                    None => return Ok(()),
+                }
+            }
+            Some(estimate) => {
+                println!(
+                    "\x1b[2mStreaming ~{} rows through a cursor, {} at a time. Ctrl+C stops the fetch.\x1b[0m",
+                    estimate,
+                    streaming::FETCH_COUNT
+                );
+                let started = Instant::now();
+                let reader = read_connection(psql, &current_sql, config);
+                let streamed =
+                    streaming::stream(reader, &current_sql, table_layout(config)).await?;
+                let success = streamed.success || streamed.stopped;
+                record_execution(
+                    psql,
+                    config,
+                    original_question,
+                    &current_sql,
+                    success,
+                    &streamed.stderr,
+                    started,
+                )
+                .await;
+                if success {
+                    finish_stream(claude, output, original_question, &current_sql, streamed);
+                    return Ok(());
+                }
+                (false, String::new(), streamed.stderr)
+            }
+            None => {
+                let conn = read_connection(psql, &current_sql, config).with_verbose_errors();
+                let sql = current_sql.clone();
+                let started = Instant::now();
+                let run = move || {
+                    if has_rows {
+                        conn.execute_csv(&sql)
+                    } else {
+                        conn.execute_capture(&sql)
+                    }
+                };
+                // DO, CALL and side-effecting functions are never re-run
+                let result = if is_read_only_sql(&current_sql) {
+                    run_retrying("Running query...", config, run).await?
+                } else {
+                    run_blocking("Running query...", run).await?
+                };
+                record_execution(
+                    psql,
+                    config,
+                    original_question,
+                    &current_sql,
+                    result.0,
+                    &result.2,
+                    started,
+                )
+                .await;
+                result
+            }
+        };
 
         if success {
+            let redirect = output.take_redirect();
+            let rendered = if has_rows {
+                let result = ResultSet::from_csv(&stdout)?;
+                let table = result.render_table();
+                output.record(Entry {
+                    question: original_question.to_string(),
+                    sql: current_sql.clone(),
+                    outcome: Outcome::Rows(result.clone()),
+                });
+                let rows = result.rows.len();
+                let layout = TableLayout {
+                    max_width: None,
+                    highlight: false,
+                    ..table_layout(config)
+                };
+                let written = match (&redirect, output.file()) {
+                    (Some(redirect), _) => Some(export::write_redirect(
+                        &result,
+                        redirect,
+                        &layout,
+                        &config.csv,
+                    )?),
+                    (None, Some(file)) => {
+                        let format = file.format(config.format);
+                        let mut text = match format {
+                            OutputFormat::Json => json::to_ndjson(&result),
+                            _ => result.render_with(&TableLayout { format, ..layout }),
+                        };
+                        if matches!(format, OutputFormat::Table | OutputFormat::Markdown) {
+                            text.push('\n');
+                        }
+                        file.append(&text)?;
+                        Some(file.path.clone())
+                    }
+                    (None, None) => None,
+                };
+                let blob_hint = blobs::hint(&result);
+                output.set_last(result, &current_sql);
+                match written {
+                    Some(path) => println!(
+                        "Wrote {} {} to {}",
+                        rows,
+                        if rows == 1 { "row" } else { "rows" },
+                        path.display()
+                    ),
+                    None if opens_viewer(config, rows) => {
+                        if let Some((last, mut state)) = output.view_state() {
+                            let last = last.displayed(&table_layout(config));
+                            if result_view::explore(&last, &mut state)? {
+                                output.set_view_state(state);
+                            }
+                        }
+                        println!("({} rows; \\explore opens them again)", rows);
+                        if let Some(hint) = blob_hint {
+                            println!("\x1b[2m{}\x1b[0m", hint);
+                        }
+                    }
+                    None => {
+                        print!(
+                            "{}",
+                            output.render(table_layout(config)).unwrap_or_default()
+                        );
+                        if let Some(hint) = blob_hint {
+                            println!("\x1b[2m{}\x1b[0m", hint);
+                        }
+                    }
+                }
+                table
+            } else {
+                if let Some(redirect) = &redirect {
+                    println!(
+                        "\x1b[2mThe statement returned no rows, so nothing was written to {}.\x1b[0m",
+                        redirect.path
+                    );
+                }
+                print!("{}", displayed(config, &stdout));
+                output.record(Entry {
+                    question: original_question.to_string(),
+                    sql: current_sql.clone(),
+                    outcome: Outcome::Text(stdout.clone()),
+                });
+                stdout
+            };
+
             claude.add_to_history(
                 original_question.to_string(),
                 current_sql.clone(),
-                Some(stdout.clone()),
+                Some(rendered),
             );
             println!();
+            if has_rows && config.follow_ups {
+                suggest_follow_ups(claude, schema, original_question, &current_sql, output).await;
+            }
             return Ok(());
         }
 
-        eprintln!("{}", stderr);
+        if !stdout.is_empty() {
+            print!("{}", displayed(config, &stdout));
+        }
+
+        print_error(psql, &current_sql, &stderr);
         println!();
 
         match prompt_error_action()? {
             ErrorAction::Fix => {
                 current_sql = ask_claude_to_fix(
+                    psql,
                     claude,
                     schema,
                     original_question,
@@ -375,10 +3124,10 @@ async fn execute_with_recovery(
                 }
             }
             ErrorAction::Edit => {
-                current_sql = prompt_edit_sql(&current_sql)?;
+                current_sql = prompt_edit_sql(&current_sql, schema)?;
                 println!();
             }
-            ErrorAction::Retry => match prompt_new_question(claude, schema, config).await? {
+            ErrorAction::Retry => match prompt_new_question(psql, claude, schema, config).await? {
                 Some(sql) => current_sql = sql,
                 None => return Ok(()),
             },
@@ -390,6 +3139,296 @@ async fn execute_with_recovery(
     }
 }
 
+async fn run_retrying<F>(message: &str, config: &Config, run: F) -> Result<(bool, String, String)>
+where
+    F: Fn() -> Result<(bool, String, String)> + Clone + Send + 'static,
+{
+    let retries = config
+        .serialization_retries
+        .unwrap_or(DEFAULT_SERIALIZATION_RETRIES);
+    let mut attempt = 0;
+    loop {
+        let result = run_blocking(message, run.clone()).await?;
+        let failure = errors::transient_failure(&result.2);
+        let Some(failure) = failure.filter(|_| !result.0 && attempt < retries) else {
+            return Ok(result);
+        };
+        attempt += 1;
+        let delay = RETRY_BACKOFF * 2u32.pow(attempt - 1);
+        println!(
+            "\x1b[33m⚠️  {}; retrying in {} ms ({} of {}).\x1b[0m",
+            failure,
+            delay.as_millis(),
+            attempt,
+            retries
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+async fn copy_to_file(
+    psql: &PsqlConnection,
+    config: &Config,
+    question: &str,
+    sql: &str,
+    output: &mut Output,
+) -> Result<Option<String>> {
+    print!("Write COPY output to file: ");
+    io::stdout().flush()?;
+    let mut path = String::new();
+    io::stdin().read_line(&mut path)?;
+    let path = PathBuf::from(path.trim());
+    if path.as_os_str().is_empty() {
+        println!("Cancelled.\n");
+        return Ok(None);
+    }
+    if path.exists() {
+        println!("{} already exists.", path.display());
+        if pick_option(&["Overwrite", "Cancel"])? != Some(0) {
+            println!("Cancelled.\n");
+            return Ok(None);
+        }
+    }
+
+    let started = Instant::now();
+    let copied = streaming::copy_to_file(read_connection(psql, sql, config), sql, &path).await?;
+    let success = copied.success || copied.stopped;
+    record_execution(
+        psql,
+        config,
+        question,
+        sql,
+        success,
+        &copied.stderr,
+        started,
+    )
+    .await;
+    if !success {
+        let _ = std::fs::remove_file(&path);
+        return Ok(Some(copied.stderr));
+    }
+
+    let summary = format!(
+        "{} {} to {}",
+        if copied.stopped {
+            "Copy stopped after writing"
+        } else {
+            "Wrote"
+        },
+        units::format_bytes(copied.bytes),
+        path.display()
+    );
+    println!("{}\n", summary);
+    output.record(Entry {
+        question: question.to_string(),
+        sql: sql.to_string(),
+        outcome: Outcome::Text(format!("{}\n", summary)),
+    });
+    Ok(None)
+}
+
+async fn suggest_follow_ups(
+    claude: &ClaudeClient,
+    schema: &Schema,
+    question: &str,
+    sql: &str,
+    output: &mut Output,
+) {
+    let Some(result) = output.view() else {
+        return;
+    };
+    let suggestions = match claude
+        .suggest_follow_ups(schema, question, sql, &result)
+        .await
+    {
+        Ok(suggestions) if !suggestions.is_empty() => suggestions,
+        _ => return,
+    };
+
+    println!("\x1b[2mFollow-ups (type a number to ask):\x1b[0m");
+    for (i, suggestion) in suggestions.iter().enumerate() {
+        println!("  \x1b[36m{}.\x1b[0m {}", i + 1, suggestion);
+    }
+    println!();
+    output.set_suggestions(suggestions);
+}
+
+async fn run_pre_hook(
+    psql: &PsqlConnection,
+    config: &Config,
+    question: &str,
+    sql: &mut String,
+) -> Result<bool> {
+    if !hooks::has_pre(&config.hooks) {
+        return Ok(true);
+    }
+
+    let (hooks, conn, question, input) = (
+        config.hooks.clone(),
+        psql.clone(),
+        question.to_string(),
+        sql.clone(),
+    );
+    let verdict = run_blocking("Running pre-execution hook...", move || {
+        hooks::pre_execute(&hooks, &conn, &question, &input)
+    })
+    .await?;
+
+    match verdict {
+        Verdict::Run(rewritten) => {
+            if rewritten.trim() != sql.trim() {
+                println!(
+                    "\x1b[2mThe pre-execution hook rewrote the SQL:\x1b[0m\n{}\n",
+                    rewritten
+                );
+                *sql = rewritten;
+            }
+            Ok(true)
+        }
+        Verdict::Veto(reason) => {
+            println!("⛔ Rejected by the pre-execution hook: {}\n", reason);
+            Ok(false)
+        }
+    }
+}
+
+async fn record_execution(
+    psql: &PsqlConnection,
+    config: &Config,
+    question: &str,
+    sql: &str,
+    success: bool,
+    stderr: &str,
+    started: Instant,
+) {
+    metrics::query_executed(success, started.elapsed());
+    if let Err(e) = QuestionLog::for_database(psql)
+        .and_then(|log| log.append(&Attempt::new(question, sql, success)))
+    {
+        eprintln!("\x1b[33mQuestion log: {}\x1b[0m", e);
+    }
+    if config.audit.enabled {
+        let (conn, explain, question, sql, stderr) = (
+            psql.clone(),
+            config.audit.explain,
+            question.to_string(),
+            sql.to_string(),
+            stderr.to_string(),
+        );
+        let message = if explain {
+            "Capturing plan for the audit log..."
+        } else {
+            "Writing audit log..."
+        };
+        if let Err(e) = run_blocking(message, move || {
+            audit::record(&conn, explain, &question, &sql, success, &stderr)
+        })
+        .await
+        {
+            eprintln!("\x1b[33mAudit log: {}\x1b[0m", e);
+        }
+    }
+
+    run_post_hook(psql, config, question, sql, success, stderr).await;
+}
+
+async fn run_post_hook(
+    psql: &PsqlConnection,
+    config: &Config,
+    question: &str,
+    sql: &str,
+    success: bool,
+    stderr: &str,
+) {
+    if !hooks::has_post(&config.hooks) {
+        return;
+    }
+
+    let status = if success {
+        Status::Success
+    } else {
+        Status::Error
+    };
+    let error = if success {
+        String::new()
+    } else {
+        stderr.to_string()
+    };
+    let (hooks, conn, question, sql) = (
+        config.hooks.clone(),
+        psql.clone(),
+        question.to_string(),
+        sql.to_string(),
+    );
+    let result = run_blocking("Running post-execution hook...", move || {
+        hooks::post_execute(&hooks, &conn, &question, &sql, status, &error)
+    })
+    .await;
+    if let Err(e) = result {
+        eprintln!("\x1b[33m⚠️  {}\x1b[0m", e);
+    }
+}
+
+async fn stream_estimate(psql: &PsqlConnection, sql: &str, config: &Config) -> Option<u64> {
+    let threshold = config
+        .stream_threshold
+        .unwrap_or(streaming::DEFAULT_THRESHOLD);
+    if threshold == 0 || config.format != OutputFormat::Table || !streaming::is_streamable(sql) {
+        return None;
+    }
+
+    let (conn, sql) = (read_connection(psql, sql, config).clone(), sql.to_string());
+    let estimate = run_blocking("Estimating result size...", move || {
+        conn.estimated_rows(&sql)
+    })
+    .await
+    .ok()
+    .flatten()?;
+    (estimate > threshold).then_some(estimate)
+}
+
+fn read_connection<'a>(psql: &'a PsqlConnection, sql: &str, config: &Config) -> &'a PsqlConnection {
+    if config.primary_reads {
+        psql
+    } else {
+        psql.for_read(sql)
+    }
+}
+
+fn finish_stream(
+    claude: &mut ClaudeClient,
+    output: &mut Output,
+    question: &str,
+    sql: &str,
+    streamed: Streamed,
+) {
+    let rows = if streamed.rows == 1 { "row" } else { "rows" };
+    if streamed.stopped {
+        println!("Fetch stopped after {} {}.", streamed.rows, rows);
+    } else {
+        println!("({} {})", streamed.rows, rows);
+    }
+
+    let Some(first_batch) = streamed.first_batch else {
+        println!();
+        return;
+    };
+    let rendered = format!(
+        "{}({} rows streamed; only the first {} are kept)\n",
+        first_batch.render_table(),
+        streamed.rows,
+        first_batch.rows.len()
+    );
+    output.record(Entry {
+        question: question.to_string(),
+        sql: sql.to_string(),
+        outcome: Outcome::Rows(first_batch.clone()),
+    });
+    output.set_last(first_batch, sql);
+    claude.add_to_history(question.to_string(), sql.to_string(), Some(rendered));
+    println!();
+}
+
 async fn execute_write_with_transaction(
     psql: &PsqlConnection,
     claude: &mut ClaudeClient,
@@ -397,74 +3436,163 @@ async fn execute_write_with_transaction(
     original_question: &str,
     current_sql: &mut String,
     config: &mut Config,
+    output: &mut Output,
 ) -> Result<()> {
     loop {
+        *current_sql = fix_quoting(current_sql, schema);
+        if !run_pre_hook(psql, config, original_question, current_sql).await? {
+            return Ok(());
+        }
+        if let Err(e) = write_guard::check(&config.write_guard, current_sql) {
+            println!("⛔ {}. Not running it.\n", e);
+            return Ok(());
+        }
+        if config.sandbox && !try_in_sandbox(psql, schema, current_sql, config).await? {
+            println!("Cancelled.\n");
+            return Ok(());
+        }
         println!();
-        println!("⚠️  This is a WRITE operation. Previewing in a transaction (will rollback)...\n");
+        if is_schema_change(current_sql) {
+            let (conn, sql) = (psql.clone(), current_sql.clone());
+            match run_blocking("Checking dependent objects...", move || {
+                ddl_impact::analyze(&conn, &sql)
+            })
+            .await
+            {
+                Ok(impact) if !impact.is_empty() => println!("{}", impact.render()),
+                Ok(_) => {}
+                Err(e) => println!("\x1b[2mCould not check dependent objects: {}\x1b[0m", e),
+            }
+        }
+        let level = match config.isolation {
+            Some(level) => format!("{} ", level.as_sql()),
+            None => String::new(),
+        };
+        let target = match &config.environment.label {
+            Some(label) => format!(" on {}", label),
+            None => String::new(),
+        };
+        println!(
+            "⚠️  This is a WRITE operation{}. Previewing in a {}transaction (will rollback)...\n",
+            target, level
+        );
+
+        let (conn, preview_schema, sql) = (psql.clone(), schema.clone(), current_sql.clone());
+        let diff = run_blocking("Previewing changes...", move || {
+            Ok(update_diff(&conn, &preview_schema, &sql))
+        })
+        .await?;
 
-        let (success, stdout, stderr) = psql.preview_write_with_returning(current_sql)?;
+        if let Some(diff) = diff {
+            println!("Changes that will be applied:");
+            print!("{}", diff);
+        } else {
+            let returning = match config.preview_rows {
+                PreviewRows::Compact => returning_columns(current_sql, schema),
+                PreviewRows::Full => None,
+            };
+            let (conn, sql) = (psql.with_verbose_errors(), current_sql.clone());
+            let (success, stdout, stderr) =
+                run_retrying("Previewing changes...", config, move || {
+                    conn.preview_write_with_returning(&sql, returning.as_deref().unwrap_or("*"))
+                })
+                .await?;
 
-        if !success {
-            eprintln!("{}", stderr);
-            println!();
+            if !success {
+                print_error(psql, current_sql, &stderr);
+                println!();
 
-            match prompt_error_action()? {
-                ErrorAction::Fix => {
-                    *current_sql = ask_claude_to_fix(
-                        claude,
-                        schema,
-                        original_question,
-                        current_sql,
-                        &stderr,
-                        config,
-                    )
-                    .await?;
-                    if current_sql.is_empty() {
-                        return Ok(());
+                match prompt_error_action()? {
+                    ErrorAction::Fix => {
+                        *current_sql = ask_claude_to_fix(
+                            psql,
+                            claude,
+                            schema,
+                            original_question,
+                            current_sql,
+                            &stderr,
+                            config,
+                        )
+                        .await?;
+                        if current_sql.is_empty() {
+                            return Ok(());
+                        }
+                        continue;
                     }
-                    continue;
-                }
-                ErrorAction::Edit => {
-                    *current_sql = prompt_edit_sql(current_sql)?;
-                    println!();
-                    continue;
-                }
-                ErrorAction::Retry => {
-                    match prompt_new_question(claude, schema, config).await? {
-                        Some(sql) => *current_sql = sql,
-                        None => return Ok(()),
+                    ErrorAction::Edit => {
+                        *current_sql = prompt_edit_sql(current_sql, schema)?;
+                        println!();
+                        continue;
+                    }
+                    ErrorAction::Retry => {
+                        match prompt_new_question(psql, claude, schema, config).await? {
+                            Some(sql) => *current_sql = sql,
+                            None => return Ok(()),
+                        }
+                        continue;
+                    }
+                    ErrorAction::Cancel => {
+                        println!("Cancelled.\n");
+                        return Ok(());
                     }
-                    continue;
-                }
-                ErrorAction::Cancel => {
-                    println!("Cancelled.\n");
-                    return Ok(());
                 }
             }
-        }
 
-        if !stdout.is_empty() {
-            println!("Rows that will be affected:");
-            print!("{}", stdout);
+            if !stdout.is_empty() {
+                println!("Rows that will be affected:");
+                print!("{}", displayed(config, &stdout));
+            }
         }
 
         println!("\n(Preview complete - changes were rolled back)");
         match prompt_commit_action()? {
             CommitAction::Commit => {
-                let (success, stdout, stderr) =
-                    psql.execute_write_with_confirmation(current_sql, true)?;
+                if config.safe_mode_active && !safe_mode_allows(psql, current_sql, config).await? {
+                    return Ok(());
+                }
+                if !production_allows(config, "commit", "Rolled back.")? {
+                    return Ok(());
+                }
+                let (conn, sql) = (psql.with_verbose_errors(), current_sql.clone());
+                let started = Instant::now();
+                let (success, stdout, stderr) = run_blocking("Committing...", move || {
+                    conn.execute_write_with_confirmation(&sql, true)
+                })
+                .await?;
+                record_execution(
+                    psql,
+                    config,
+                    original_question,
+                    current_sql,
+                    success,
+                    &stderr,
+                    started,
+                )
+                .await;
                 if success {
                     println!("✓ Transaction committed.\n");
                     if !stdout.is_empty() {
-                        print!("{}", stdout);
+                        print!("{}", displayed(config, &stdout));
                     }
+                    output.record(Entry {
+                        question: original_question.to_string(),
+                        sql: current_sql.clone(),
+                        outcome: Outcome::Text(stdout.clone()),
+                    });
                     claude.add_to_history(
                         original_question.to_string(),
                         current_sql.clone(),
                         Some(stdout),
                     );
                 } else {
-                    eprintln!("Commit failed: {}", stderr);
+                    eprintln!("Commit failed:");
+                    print_error(psql, current_sql, &stderr);
+                    if let Some(failure) = errors::transient_failure(&stderr) {
+                        println!(
+                            "{} is a transient concurrency error and the transaction was rolled back. It is not retried automatically because the statement changes data; run it again when ready.\n",
+                            failure
+                        );
+                    }
                 }
                 return Ok(());
             }
@@ -473,7 +3601,7 @@ async fn execute_write_with_transaction(
                 return Ok(());
             }
             CommitAction::Edit => {
-                *current_sql = prompt_edit_sql(current_sql)?;
+                *current_sql = prompt_edit_sql(current_sql, schema)?;
                 println!();
                 continue;
             }
@@ -481,7 +3609,64 @@ async fn execute_write_with_transaction(
     }
 }
 
+async fn try_in_sandbox(
+    psql: &PsqlConnection,
+    schema: &Schema,
+    sql: &str,
+    config: &Config,
+) -> Result<bool> {
+    let rows = config.sandbox_rows.unwrap_or(sandbox::DEFAULT_SANDBOX_ROWS);
+    let (conn, sandbox_schema, sandbox_sql) = (psql.clone(), schema.clone(), sql.to_string());
+    let outcome = run_blocking("Running in sandbox...", move || {
+        Ok(sandbox::run(&conn, &sandbox_schema, &sandbox_sql, rows))
+    })
+    .await?;
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            println!(
+                "\n🧪 Sandbox unavailable: {:#}. Falling back to the usual preview.",
+                e
+            );
+            return Ok(true);
+        }
+    };
+
+    println!();
+    if outcome.copied.is_empty() {
+        println!("🧪 Ran in sandbox schema {}.", outcome.schema);
+    } else {
+        println!(
+            "🧪 Ran in sandbox schema {} against copies of {} (up to {} rows each).",
+            outcome.schema,
+            outcome.copied.join(", "),
+            rows
+        );
+    }
+    if !outcome.output.trim().is_empty() {
+        print!("{}", displayed(config, &outcome.output));
+    }
+    if !outcome.success {
+        println!("\x1b[31mThe statement failed in the sandbox:\x1b[0m");
+        println!("{}", outcome.error.trim_end());
+    }
+    for change in &outcome.changes {
+        let summary = match (change.before, change.after) {
+            (Some(before), Some(after)) if before == after => format!("{} rows, unchanged", after),
+            (Some(before), Some(after)) => format!("{} → {} rows", before, after),
+            (None, Some(after)) => format!("created, {} rows", after),
+            (Some(before), None) => format!("dropped ({} rows)", before),
+            (None, None) => continue,
+        };
+        println!("  {}: {}", change.table, summary);
+    }
+    println!("\x1b[2m(The sandbox was rolled back along with any side effects.)\x1b[0m\n");
+
+    Ok(pick_option(&["Replay against the real database", "Cancel"])? == Some(0))
+}
+
 async fn ask_claude_to_fix(
+    psql: &PsqlConnection,
     claude: &ClaudeClient,
     schema: &Schema,
     original_question: &str,
@@ -494,11 +3679,17 @@ async fn ask_claude_to_fix(
         .fix_sql(schema, original_question, current_sql, error)
         .await?;
 
+    let context = SqlContext {
+        psql,
+        claude,
+        schema,
+        question: original_question,
+    };
     loop {
-        match confirm_execution(config)? {
+        match confirm_execution(&fixed_sql, &context, config).await? {
             RunChoice::Run | RunChoice::AutoRun => return Ok(fixed_sql),
             RunChoice::EditSql => {
-                fixed_sql = prompt_edit_sql(&fixed_sql)?;
+                fixed_sql = prompt_edit_sql(&fixed_sql, schema)?;
                 continue;
             }
             RunChoice::EditPrompt | RunChoice::Cancel => {
@@ -510,7 +3701,8 @@ async fn ask_claude_to_fix(
 }
 
 async fn prompt_new_question(
-    claude: &ClaudeClient,
+    psql: &PsqlConnection,
+    claude: &mut ClaudeClient,
     schema: &Schema,
     config: &mut Config,
 ) -> Result<Option<String>> {
@@ -527,13 +3719,19 @@ async fn prompt_new_question(
 
     println!("\n");
 
-    let mut new_sql = claude.text_to_sql(schema, new_question).await?;
+    let mut new_sql = generate_sql(claude, schema, config, new_question).await?;
 
+    let context = SqlContext {
+        psql,
+        claude,
+        schema,
+        question: new_question,
+    };
     loop {
-        match confirm_execution(config)? {
+        match confirm_execution(&new_sql, &context, config).await? {
             RunChoice::Run | RunChoice::AutoRun => return Ok(Some(new_sql)),
             RunChoice::EditSql => {
-                new_sql = prompt_edit_sql(&new_sql)?;
+                new_sql = prompt_edit_sql(&new_sql, schema)?;
                 continue;
             }
             RunChoice::EditPrompt | RunChoice::Cancel => {
@@ -550,6 +3748,49 @@ enum CommitAction {
     Edit,
 }
 
+async fn safe_mode_allows(psql: &PsqlConnection, sql: &str, config: &Config) -> Result<bool> {
+    let limit = config
+        .safe_mode_row_limit
+        .unwrap_or(safe_mode::DEFAULT_ROW_LIMIT);
+    let (conn, statement) = (psql.clone(), sql.to_string());
+    let (success, stdout, stderr) = run_blocking("Counting affected rows...", move || {
+        conn.execute_write_with_confirmation(&statement, false)
+    })
+    .await?;
+    if !success {
+        println!(
+            "⛔ Safe mode: could not count the affected rows ({}). Rolled back.\n",
+            stderr.trim()
+        );
+        return Ok(false);
+    }
+
+    let affected = safe_mode::affected_rows(&stdout);
+    if affected > limit {
+        println!(
+            "⛔ Safe mode: this would modify {} rows, over the limit of {}. Rolled back.\n",
+            affected, limit
+        );
+        return Ok(false);
+    }
+
+    if safe_mode::is_destructive(sql) {
+        print!(
+            "This statement drops or renames objects. Type the database name ({}) to commit: ",
+            psql.database
+        );
+        io::stdout().flush()?;
+        let mut typed = String::new();
+        io::stdin().read_line(&mut typed)?;
+        if typed.trim() != psql.database {
+            println!("Confirmation did not match. Rolled back.\n");
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
 fn prompt_commit_action() -> Result<CommitAction> {
     let options = &[
         "Commit transaction",
@@ -585,7 +3826,11 @@ fn prompt_error_action() -> Result<ErrorAction> {
     }
 }
 
-fn prompt_edit_sql(current_sql: &str) -> Result<String> {
+fn prompt_edit_sql(current_sql: &str, schema: &Schema) -> Result<String> {
+    if accessibility::enabled() {
+        return accessibility::edit(current_sql);
+    }
+
     terminal::enable_raw_mode()?;
     let mut stdout = io::stdout();
     crossterm::execute!(stdout, EnterAlternateScreen)?;
@@ -593,7 +3838,14 @@ fn prompt_edit_sql(current_sql: &str) -> Result<String> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let lines: Vec<String> = current_sql.lines().map(|s| s.to_string()).collect();
+    let width = terminal.size()?.width.saturating_sub(2) as usize;
+    let sql = if sql_format::needs_formatting(current_sql, width) {
+        sql_format::format(current_sql)
+    } else {
+        current_sql.to_string()
+    };
+
+    let lines: Vec<String> = sql.lines().map(|s| s.to_string()).collect();
     let mut textarea = TextArea::new(lines);
     textarea.set_cursor_line_style(Style::default());
     textarea.set_block(
@@ -603,27 +3855,66 @@ fn prompt_edit_sql(current_sql: &str) -> Result<String> {
     );
     textarea.set_style(Style::default().fg(Color::Green));
 
+    let mut candidates: Vec<String> = Vec::new();
     let result = loop {
         terminal.draw(|f| {
             let chunks = Layout::default()
                 .constraints([Constraint::Min(3), Constraint::Length(1)])
                 .split(f.area());
 
+            let (row, column) = textarea.cursor();
             f.render_widget(&textarea, chunks[0]);
-            f.render_widget(
-                Paragraph::new("Ctrl+S: Save | Esc: Cancel | Arrow keys: Move | Enter: New line"),
-                chunks[1],
-            );
+            let status = if candidates.is_empty() {
+                format!(
+                    "Ln {}, Col {} | Ctrl+S: Save | Esc: Cancel | Ctrl+F: Format | Ctrl+Space: Complete | Enter: New line",
+                    row + 1,
+                    column + 1
+                )
+            } else {
+                candidates.join("  ")
+            };
+            f.render_widget(Paragraph::new(status), chunks[1]);
         })?;
 
         if let Event::Key(key) = event::read()? {
+            candidates.clear();
             match (key.code, key.modifiers) {
+                (KeyCode::Char(' '), KeyModifiers::CONTROL) | (KeyCode::Null, _) => {
+                    let (row, column) = textarea.cursor();
+                    let word = completion::word_before(&textarea.lines()[row], column);
+                    let sql = textarea.lines().join("\n");
+                    if let Some(completed) = completion::complete(schema, &sql, &word) {
+                        replace_word(&mut textarea, &word, &completed.replacement);
+                        if completed.candidates.len() > 1 {
+                            candidates = completed.candidates;
+                        }
+                    }
+                }
                 (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
                     break Some(textarea.lines().join("\n"));
                 }
                 (KeyCode::Esc, _) => {
                     break None;
                 }
+                (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
+                    let formatted = sql_format::format(&textarea.lines().join("\n"));
+                    let mut formatted =
+                        TextArea::new(formatted.lines().map(str::to_string).collect());
+                    formatted.set_cursor_line_style(Style::default());
+                    formatted.set_block(textarea.block().cloned().unwrap_or_default());
+                    formatted.set_style(textarea.style());
+                    textarea = formatted;
+                }
+                (KeyCode::Char(c), modifiers)
+                    if !modifiers.contains(KeyModifiers::CONTROL) && " ,.;()=".contains(c) =>
+                {
+                    quote_word_before_cursor(&mut textarea, schema);
+                    textarea.input(key);
+                }
+                (KeyCode::Enter | KeyCode::Tab, _) => {
+                    quote_word_before_cursor(&mut textarea, schema);
+                    textarea.input(key);
+                }
                 _ => {
                     textarea.input(key);
                 }
@@ -636,3 +3927,24 @@ fn prompt_edit_sql(current_sql: &str) -> Result<String> {
 
     Ok(result.unwrap_or_else(|| current_sql.to_string()))
 }
+
+fn replace_word(textarea: &mut TextArea, word: &str, replacement: &str) {
+    for _ in word.chars() {
+        textarea.delete_char();
+    }
+    textarea.insert_str(replacement);
+}
+
+fn quote_word_before_cursor(textarea: &mut TextArea, schema: &Schema) {
+    let (row, column) = textarea.cursor();
+    let line = &textarea.lines()[row];
+    let before: String = line.chars().take(column).collect();
+    if before.matches('\'').count() % 2 == 1 {
+        return;
+    }
+    let word = completion::word_before(line, column);
+    let word = word.rsplit('.').next().unwrap_or_default().to_string();
+    if let Some(quoted) = completion::quote_word(schema, &word) {
+        replace_word(textarea, &word, &quoted);
+    }
+}
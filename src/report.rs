@@ -0,0 +1,166 @@
+use crate::result::ResultSet;
+use crate::snapshots::format_timestamp;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_REPORT_ROWS: usize = 50;
+pub const MAX_PROMPT_ROWS: usize = 20;
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Rows(ResultSet),
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub question: String,
+    pub sql: String,
+    pub outcome: Outcome,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn render_outcome(outcome: &Outcome, max_rows: usize) -> String {
+    match outcome {
+        Outcome::Rows(result) => result.to_markdown(max_rows),
+        Outcome::Text(text) if text.trim().is_empty() => String::new(),
+        Outcome::Text(text) => format!("```\n{}\n```\n", text.trim_end()),
+    }
+}
+
+pub fn to_prompt_string(entries: &[Entry], include_results: bool) -> String {
+    let mut output = String::new();
+
+    for (i, entry) in entries.iter().enumerate() {
+        output.push_str(&format!("Question {}: {}\n", i + 1, entry.question));
+        output.push_str(&format!("SQL:\n{}\n", entry.sql));
+        if include_results {
+            output.push_str("Result:\n");
+            output.push_str(&render_outcome(&entry.outcome, MAX_PROMPT_ROWS));
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+pub fn to_markdown(database: &str, entries: &[Entry], narrative: Option<&str>) -> String {
+    let mut output = format!(
+        "# Report: {}\n\n_Generated by psqlm on {} UTC_\n\n",
+        database,
+        format_timestamp(now())
+    );
+
+    if let Some(narrative) = narrative {
+        output.push_str("## Summary\n\n");
+        output.push_str(narrative.trim());
+        output.push_str("\n\n");
+    }
+
+    for (i, entry) in entries.iter().enumerate() {
+        output.push_str(&format!("## {}. {}\n\n", i + 1, entry.question));
+        output.push_str(&format!("```sql\n{}\n```\n\n", entry.sql.trim()));
+
+        let outcome = render_outcome(&entry.outcome, MAX_REPORT_ROWS);
+        if !outcome.is_empty() {
+            output.push_str(&outcome);
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+pub fn to_sql_script(database: &str, entries: &[Entry]) -> String {
+    let mut output = format!(
+        "-- psqlm session against {}\n-- Exported {} UTC\n",
+        database,
+        format_timestamp(now())
+    );
+
+    for (i, entry) in entries.iter().enumerate() {
+        output.push('\n');
+        let question = if entry.question.trim() == entry.sql.trim() {
+            ""
+        } else {
+            entry.question.as_str()
+        };
+        if question.is_empty() {
+            output.push_str(&format!("-- {}.\n", i + 1));
+        }
+        for (j, line) in question.lines().enumerate() {
+            let prefix = if j == 0 {
+                format!("-- {}. ", i + 1)
+            } else {
+                "--    ".to_string()
+            };
+            output.push_str(format!("{}{}", prefix, line).trim_end());
+            output.push('\n');
+        }
+
+        let sql = entry.sql.trim();
+        output.push_str(sql);
+        if !sql.ends_with(';') {
+            output.push(';');
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+fn write_or_default(
+    path: Option<&str>,
+    default: String,
+    contents: impl AsRef<[u8]>,
+) -> Result<PathBuf> {
+    let path = PathBuf::from(path.map(str::to_string).unwrap_or(default));
+    std::fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(path)
+}
+
+pub fn write(path: Option<&str>, contents: &str) -> Result<PathBuf> {
+    write_or_default(path, format!("psqlm-report-{}.md", now()), contents)
+}
+
+pub fn write_script(path: Option<&str>, contents: &str) -> Result<PathBuf> {
+    write_or_default(path, format!("psqlm-session-{}.sql", now()), contents)
+}
+
+pub fn write_csv(path: Option<&str>, contents: &[u8]) -> Result<PathBuf> {
+    write_or_default(path, format!("psqlm-export-{}.csv", now()), contents)
+}
+
+pub fn write_json(path: Option<&str>, contents: &str) -> Result<PathBuf> {
+    write_or_default(path, format!("psqlm-export-{}.json", now()), contents)
+}
+
+pub fn write_graph(
+    path: Option<&str>,
+    table: &str,
+    extension: &str,
+    contents: &str,
+) -> Result<PathBuf> {
+    let table = table.rsplit('.').next().unwrap_or(table);
+    write_or_default(
+        path,
+        format!("psqlm-graph-{}-{}.{}", table, now(), extension),
+        contents,
+    )
+}
+
+pub fn write_fixture(path: Option<&str>, table: &str, contents: &str) -> Result<PathBuf> {
+    let table = table.rsplit('.').next().unwrap_or(table);
+    write_or_default(
+        path,
+        format!("psqlm-fixture-{}-{}.sql", table, now()),
+        contents,
+    )
+}
@@ -0,0 +1,516 @@
+use crate::blobs;
+use crate::config::{CsvOptions, OutputFormat};
+use crate::demo;
+use crate::export;
+use crate::json;
+use crate::units;
+use anyhow::{Context, Result};
+use std::cmp::Ordering;
+use std::ops::Range;
+
+pub const NULL_SENTINEL: &str = "\u{1}";
+pub const DEFAULT_MAX_COLUMN_WIDTH: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Number,
+    Boolean,
+    Json,
+    Text,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub types: Vec<ColumnType>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TableLayout {
+    pub max_column_width: usize,
+    pub wrap: bool,
+    pub max_width: Option<usize>,
+    pub first_column: usize,
+    pub json_expanded: bool,
+    pub json_inline_width: usize,
+    pub highlight: bool,
+    pub demo: bool,
+    pub units: bool,
+    pub format: OutputFormat,
+}
+
+fn fit_line(line: &str, layout: &TableLayout) -> Vec<String> {
+    let max = layout.max_column_width;
+    let len = line.chars().count();
+    if max == 0 || len <= max {
+        return vec![line.to_string()];
+    }
+
+    let chars: Vec<char> = line.chars().collect();
+    if layout.wrap {
+        chars
+            .chunks(max)
+            .map(|chunk| chunk.iter().collect())
+            .collect()
+    } else {
+        let truncated: String = chars[..max.saturating_sub(1)].iter().collect();
+        vec![format!("{}…", truncated)]
+    }
+}
+
+fn infer_type<'a>(values: impl Iterator<Item = &'a Option<String>>) -> ColumnType {
+    let mut values = values.flatten().peekable();
+    if values.peek().is_none() {
+        return ColumnType::Text;
+    }
+
+    let values: Vec<&String> = values.collect();
+    if values.iter().all(|v| v.parse::<f64>().is_ok()) {
+        ColumnType::Number
+    } else if values.iter().all(|v| *v == "t" || *v == "f") {
+        ColumnType::Boolean
+    } else if values.iter().all(|v| json::parse_container(v).is_some()) {
+        ColumnType::Json
+    } else {
+        ColumnType::Text
+    }
+}
+
+pub fn csv_row(record: &csv::StringRecord, width: usize) -> Vec<Option<String>> {
+    let mut row: Vec<Option<String>> = record
+        .iter()
+        .take(width)
+        .map(|v| (v != NULL_SENTINEL).then(|| v.to_string()))
+        .collect();
+    row.resize(width, None);
+    row
+}
+
+impl ResultSet {
+    pub fn from_csv(output: &str) -> Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(output.as_bytes());
+
+        let columns: Vec<String> = reader
+            .headers()
+            .context("Failed to parse result header")?
+            .iter()
+            .map(|h| h.to_string())
+            .collect();
+
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.context("Failed to parse result row")?;
+            rows.push(csv_row(&record, columns.len()));
+        }
+
+        Ok(Self::new(columns, rows))
+    }
+
+    pub fn new(columns: Vec<String>, rows: Vec<Vec<Option<String>>>) -> Self {
+        let types = (0..columns.len())
+            .map(|i| infer_type(rows.iter().map(|row: &Vec<Option<String>>| &row[i])))
+            .collect();
+
+        Self {
+            columns,
+            types,
+            rows,
+        }
+    }
+
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c == name).or_else(|| {
+            self.columns
+                .iter()
+                .position(|c| c.eq_ignore_ascii_case(name))
+        })
+    }
+
+    pub fn filter(&self, column: Option<usize>, needle: &str) -> ResultSet {
+        let needle = needle.to_lowercase();
+        let matches = |value: &Option<String>| {
+            value
+                .as_ref()
+                .is_some_and(|v| v.to_lowercase().contains(&needle))
+        };
+
+        let rows = self
+            .rows
+            .iter()
+            .filter(|row| match column {
+                Some(i) => matches(&row[i]),
+                None => row.iter().any(matches),
+            })
+            .cloned()
+            .collect();
+
+        ResultSet {
+            rows,
+            ..self.clone()
+        }
+    }
+
+    pub fn sort_by(&self, column: usize, descending: bool) -> ResultSet {
+        let mut rows = self.rows.clone();
+        let number = |v: &Option<String>| v.as_ref().and_then(|v| v.parse::<f64>().ok());
+
+        rows.sort_by(|a, b| {
+            let ordering = match (&a[column], &b[column]) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(x), Some(y)) if self.types[column] == ColumnType::Number => {
+                    number(&a[column])
+                        .partial_cmp(&number(&b[column]))
+                        .unwrap_or_else(|| x.cmp(y))
+                }
+                (Some(x), Some(y)) => x.cmp(y),
+            };
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        ResultSet {
+            rows,
+            ..self.clone()
+        }
+    }
+
+    pub fn select(&self, columns: &[usize]) -> ResultSet {
+        ResultSet {
+            columns: columns.iter().map(|&i| self.columns[i].clone()).collect(),
+            types: columns.iter().map(|&i| self.types[i]).collect(),
+            rows: self
+                .rows
+                .iter()
+                .map(|row| columns.iter().map(|&i| row[i].clone()).collect())
+                .collect(),
+        }
+    }
+
+    fn cell_lines(
+        value: &Option<String>,
+        column_type: ColumnType,
+        layout: &TableLayout,
+    ) -> Vec<String> {
+        let value = value.as_deref().unwrap_or("");
+        if let Some(placeholder) = blobs::placeholder(value) {
+            return vec![placeholder];
+        }
+        let json = (column_type == ColumnType::Json)
+            .then(|| json::parse_container(value))
+            .flatten()
+            .map(|parsed| match layout.json_expanded {
+                true => json::pretty(&parsed, layout.json_inline_width),
+                false => json::compact(&parsed),
+            });
+        json.as_deref()
+            .unwrap_or(value)
+            .lines()
+            .flat_map(|line| fit_line(line, layout))
+            .collect()
+    }
+
+    fn headers(&self, layout: &TableLayout) -> Vec<String> {
+        let truncate = TableLayout {
+            wrap: false,
+            ..*layout
+        };
+        self.columns
+            .iter()
+            .map(|name| fit_line(name, &truncate).remove(0))
+            .collect()
+    }
+
+    fn column_widths(&self, layout: &TableLayout) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .headers(layout)
+            .iter()
+            .map(|c| c.chars().count())
+            .collect();
+        for row in &self.rows {
+            for (i, value) in row.iter().enumerate() {
+                for line in Self::cell_lines(value, self.types[i], layout) {
+                    widths[i] = widths[i].max(line.chars().count());
+                }
+            }
+        }
+        widths
+    }
+
+    pub fn visible_columns(&self, layout: &TableLayout) -> Range<usize> {
+        if self.columns.is_empty() {
+            return 0..0;
+        }
+
+        let widths = self.column_widths(layout);
+        let start = layout.first_column.min(widths.len() - 1);
+        let Some(max_width) = layout.max_width else {
+            return start..widths.len();
+        };
+
+        let mut end = start + 1;
+        let mut total = widths[start] + 2;
+        while end < widths.len() && total + widths[end] + 3 <= max_width {
+            total += widths[end] + 3;
+            end += 1;
+        }
+        start..end
+    }
+
+    pub fn render_table(&self) -> String {
+        self.render_with(&TableLayout::default())
+    }
+
+    pub fn displayed(&self, layout: &TableLayout) -> ResultSet {
+        let raw = matches!(layout.format, OutputFormat::Csv | OutputFormat::Json);
+        let mut result = self.clone();
+        if layout.units && !raw {
+            result = units::apply(&result);
+        }
+        if layout.demo {
+            result = demo::anonymize(&result);
+        }
+        result
+    }
+
+    pub fn render_with(&self, layout: &TableLayout) -> String {
+        if layout.units || layout.demo {
+            return self.displayed(layout).render_with(&TableLayout {
+                units: false,
+                demo: false,
+                ..*layout
+            });
+        }
+        match layout.format {
+            OutputFormat::Table => {}
+            OutputFormat::Markdown => return self.to_markdown(self.rows.len()),
+            OutputFormat::Csv => {
+                return export::to_csv(self, &CsvOptions::default())
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .unwrap_or_default()
+            }
+            OutputFormat::Json => {
+                return format!(
+                    "{}\n",
+                    serde_json::to_string_pretty(&json::to_json(self)).unwrap_or_default()
+                )
+            }
+        }
+        let visible = self.visible_columns(layout);
+        let mut output = self.render_rows(layout, true);
+
+        let count = self.rows.len();
+        output.push_str(&format!(
+            "({} {})\n",
+            count,
+            if count == 1 { "row" } else { "rows" }
+        ));
+
+        if visible.len() < self.columns.len() {
+            output.push_str(&format!(
+                "(columns {}-{} of {}; \\scroll left/right for more)\n",
+                visible.start + 1,
+                visible.end,
+                self.columns.len()
+            ));
+        }
+
+        output
+    }
+
+    pub fn render_rows(&self, layout: &TableLayout, with_header: bool) -> String {
+        if layout.units {
+            return units::apply(self).render_rows(
+                &TableLayout {
+                    units: false,
+                    ..*layout
+                },
+                with_header,
+            );
+        }
+        if layout.demo {
+            return demo::anonymize(self).render_rows(
+                &TableLayout {
+                    demo: false,
+                    ..*layout
+                },
+                with_header,
+            );
+        }
+        let widths = self.column_widths(layout);
+        let visible = self.visible_columns(layout);
+        let mut output = String::new();
+
+        if with_header {
+            let header: Vec<String> = self
+                .headers(layout)
+                .iter()
+                .zip(&widths)
+                .skip(visible.start)
+                .take(visible.len())
+                .map(|(name, &width)| {
+                    let pad = width - name.chars().count();
+                    let left = pad / 2;
+                    format!(" {}{}{} ", " ".repeat(left), name, " ".repeat(pad - left))
+                })
+                .collect();
+            output.push_str(header.join("|").trim_end());
+            output.push('\n');
+
+            let separator: Vec<String> = widths[visible.clone()]
+                .iter()
+                .map(|w| "-".repeat(w + 2))
+                .collect();
+            output.push_str(&separator.join("+"));
+            output.push('\n');
+        }
+
+        for row in &self.rows {
+            let lines: Vec<Vec<String>> = row[visible.clone()]
+                .iter()
+                .zip(visible.clone())
+                .map(|(value, i)| Self::cell_lines(value, self.types[i], layout))
+                .collect();
+            let height = lines.iter().map(|l| l.len()).max().unwrap_or(1).max(1);
+
+            for line_no in 0..height {
+                let cells: Vec<String> = lines
+                    .iter()
+                    .zip(visible.clone())
+                    .map(|(cell, i)| {
+                        let text = cell.get(line_no).map(String::as_str).unwrap_or("");
+                        if self.types[i] == ColumnType::Number {
+                            format!(" {:>width$} ", text, width = widths[i])
+                        } else if self.types[i] == ColumnType::Json && layout.highlight {
+                            let pad = widths[i] - text.chars().count();
+                            format!(" {}{} ", json::highlight(text), " ".repeat(pad))
+                        } else {
+                            format!(" {:<width$} ", text, width = widths[i])
+                        }
+                    })
+                    .collect();
+                output.push_str(cells.join("|").trim_end());
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+
+    pub fn render_template(&self, template: &str) -> Result<String> {
+        enum Part {
+            Text(String),
+            Column(usize),
+        }
+
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed {
+                        anyhow::bail!(
+                            "Unclosed '{{' in template; write '{{{{' for a literal brace"
+                        );
+                    }
+                    let Some(column) = self.column_index(name.trim()) else {
+                        anyhow::bail!(
+                            "Unknown column in template: {} (available: {})",
+                            name,
+                            self.columns.join(", ")
+                        );
+                    };
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                    parts.push(Part::Column(column));
+                }
+                '}' => {
+                    anyhow::bail!("Unmatched '}}' in template; write '}}}}' for a literal brace")
+                }
+                c => text.push(c),
+            }
+        }
+        parts.push(Part::Text(text));
+
+        let mut output = String::new();
+        for row in &self.rows {
+            for part in &parts {
+                match part {
+                    Part::Text(text) => output.push_str(text),
+                    Part::Column(i) => output.push_str(row[*i].as_deref().unwrap_or("")),
+                }
+            }
+            output.push('\n');
+        }
+        Ok(output)
+    }
+
+    pub fn to_markdown(&self, max_rows: usize) -> String {
+        let escape = |value: &str| match blobs::placeholder(value) {
+            Some(placeholder) => placeholder,
+            None => value.replace('|', "\\|").replace('\n', " "),
+        };
+        let mut output = format!(
+            "| {} |\n",
+            self.columns
+                .iter()
+                .map(|c| escape(c))
+                .collect::<Vec<_>>()
+                .join(" | ")
+        );
+
+        let alignments: Vec<&str> = self
+            .types
+            .iter()
+            .map(|t| match t {
+                ColumnType::Number => "---:",
+                _ => "---",
+            })
+            .collect();
+        output.push_str(&format!("| {} |\n", alignments.join(" | ")));
+
+        for row in self.rows.iter().take(max_rows) {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|v| v.as_deref().map(escape).unwrap_or_default())
+                .collect();
+            output.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+
+        if self.rows.len() > max_rows {
+            output.push_str(&format!(
+                "\n_{} of {} rows shown._\n",
+                max_rows,
+                self.rows.len()
+            ));
+        }
+
+        output
+    }
+}
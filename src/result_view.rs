@@ -0,0 +1,438 @@
+use crate::accessibility;
+use crate::output::ViewState;
+use crate::result::ResultSet;
+use crate::schema::Schema;
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
+use ratatui::Terminal;
+use std::io;
+
+const MAX_CELL_WIDTH: usize = 30;
+pub const DRILL_DOWN_HEADER: &str = "Drill-down: the user selected a cell in the previous result";
+
+fn display(value: &Option<String>) -> String {
+    match value {
+        Some(value) => value.replace('\n', " "),
+        None => "NULL".to_string(),
+    }
+}
+
+fn column_widths(result: &ResultSet) -> Vec<usize> {
+    (0..result.columns.len())
+        .map(|i| {
+            result
+                .rows
+                .iter()
+                .map(|row| display(&row[i]).chars().count())
+                .chain(std::iter::once(result.columns[i].chars().count()))
+                .max()
+                .unwrap_or(0)
+                .min(MAX_CELL_WIDTH)
+        })
+        .collect()
+}
+
+fn visible_columns(widths: &[usize], first: usize, available: usize) -> usize {
+    let mut used = 0;
+    let mut count = 0;
+    for width in &widths[first..] {
+        used += width + 1;
+        if count > 0 && used > available {
+            break;
+        }
+        count += 1;
+    }
+    count
+}
+
+fn select_cell_by_name(result: &ResultSet) -> Result<Option<(usize, usize)>> {
+    let rows = result.rows.len();
+    let Some(row) = accessibility::prompt_line(&format!("Row number (1-{}): ", rows))? else {
+        return Ok(None);
+    };
+    let Some(row) = row
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|r| (1..=rows).contains(r))
+    else {
+        println!("No such row.");
+        return Ok(None);
+    };
+
+    let Some(column) =
+        accessibility::prompt_line(&format!("Column ({}): ", result.columns.join(", ")))?
+    else {
+        return Ok(None);
+    };
+    let Some(column) = result.column_index(column.trim()) else {
+        println!("No such column.");
+        return Ok(None);
+    };
+
+    Ok(Some((row - 1, column)))
+}
+
+pub fn select_cell(result: &ResultSet) -> Result<Option<(usize, usize)>> {
+    if result.rows.is_empty() || result.columns.is_empty() {
+        return Ok(None);
+    }
+    if accessibility::enabled() {
+        return select_cell_by_name(result);
+    }
+
+    terminal::enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let widths = column_widths(result);
+    let (mut row, mut column, mut first_column) = (0, 0, 0);
+    let mut page = 1;
+
+    let selected = loop {
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .constraints([Constraint::Min(3), Constraint::Length(1)])
+                .split(f.area());
+            page = chunks[0].height.saturating_sub(3).max(1) as usize;
+
+            let available = chunks[0].width.saturating_sub(2) as usize;
+            if column < first_column {
+                first_column = column;
+            }
+            while column >= first_column + visible_columns(&widths, first_column, available) {
+                first_column += 1;
+            }
+            let count = visible_columns(&widths, first_column, available);
+            let shown = first_column..first_column + count;
+
+            let header = Row::new(result.columns[shown.clone()].to_vec()).style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            );
+            let rows = result
+                .rows
+                .iter()
+                .map(|values| Row::new(values[shown.clone()].iter().map(display)));
+            let constraints = widths[shown.clone()]
+                .iter()
+                .map(|&w| Constraint::Length(w as u16));
+
+            let table = Table::new(rows, constraints)
+                .header(header)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" Select a cell (Enter to drill down, Esc to cancel) "),
+                )
+                .row_highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                .cell_highlight_style(Style::default().fg(Color::Black).bg(Color::Green));
+
+            let mut state = TableState::default();
+            state.select_cell(Some((row, column - first_column)));
+            f.render_stateful_widget(table, chunks[0], &mut state);
+
+            f.render_widget(
+                Paragraph::new(format!(
+                    "Row {}/{} | {} = {} | Arrows: Move | PgUp/PgDn: Page | Enter: Select | Esc: Cancel",
+                    row + 1,
+                    result.rows.len(),
+                    result.columns[column],
+                    display(&result.rows[row][column])
+                )),
+                chunks[1],
+            );
+        })?;
+
+        if let Event::Key(key) = event::read()? {
+            let last_row = result.rows.len() - 1;
+            let last_column = result.columns.len() - 1;
+            match (key.code, key.modifiers) {
+                (KeyCode::Enter, _) => break Some((row, column)),
+                (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => break None,
+                (KeyCode::Up | KeyCode::Char('k'), _) => row = row.saturating_sub(1),
+                (KeyCode::Down | KeyCode::Char('j'), _) => row = (row + 1).min(last_row),
+                (KeyCode::Left | KeyCode::Char('h'), _) => column = column.saturating_sub(1),
+                (KeyCode::Right | KeyCode::Char('l'), _) => column = (column + 1).min(last_column),
+                (KeyCode::PageUp, _) => row = row.saturating_sub(page),
+                (KeyCode::PageDown, _) => row = (row + page).min(last_row),
+                (KeyCode::Home, _) => row = 0,
+                (KeyCode::End, _) => row = last_row,
+                _ => {}
+            }
+        }
+    };
+
+    terminal::disable_raw_mode()?;
+    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+
+    Ok(selected)
+}
+
+enum Typing {
+    Off,
+    Filter(Option<usize>, String),
+}
+
+fn toggle_sort(sort: Option<(usize, bool)>, column: usize) -> Option<(usize, bool)> {
+    match sort {
+        Some((c, false)) if c == column => Some((column, true)),
+        Some((c, true)) if c == column => None,
+        _ => Some((column, false)),
+    }
+}
+
+pub fn explore(result: &ResultSet, state: &mut ViewState) -> Result<bool> {
+    if result.columns.is_empty() {
+        return Ok(false);
+    }
+    if accessibility::enabled() {
+        println!("The result explorer needs a full-screen terminal; use \\filter, \\sort and \\cols instead.");
+        return Ok(false);
+    }
+
+    terminal::enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let all: Vec<usize> = (0..result.columns.len()).collect();
+    let mut order = state.columns.clone().unwrap_or_else(|| all.clone());
+    let mut typing = Typing::Off;
+    let (mut row, mut column, mut first_column, mut pinned) = (0, 0, 0, 0);
+    let mut page = 1;
+
+    loop {
+        state.columns = (order != all).then(|| order.clone());
+        let view = state.apply(result);
+        let widths: Vec<usize> = column_widths(&view).iter().map(|w| w + 2).collect();
+        let last_row = view.rows.len().saturating_sub(1);
+        row = row.min(last_row);
+        column = column.min(order.len() - 1);
+        pinned = pinned.min(order.len());
+
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .constraints([Constraint::Min(3), Constraint::Length(1)])
+                .split(f.area());
+            page = chunks[0].height.saturating_sub(3).max(1) as usize;
+
+            let pinned_width: usize = widths[..pinned].iter().map(|w| w + 1).sum();
+            let available = (chunks[0].width.saturating_sub(2) as usize).saturating_sub(pinned_width);
+            first_column = first_column.max(pinned);
+            if column >= pinned {
+                if column < first_column {
+                    first_column = column;
+                }
+                while column >= first_column + visible_columns(&widths, first_column, available) {
+                    first_column += 1;
+                }
+            }
+            let count = visible_columns(&widths, first_column, available);
+            let shown: Vec<usize> = (0..pinned)
+                .chain(first_column..first_column + count)
+                .collect();
+
+            let header = Row::new(shown.iter().map(|&i| match state.sort {
+                Some((c, false)) if c == order[i] => format!("{} ▲", view.columns[i]),
+                Some((c, true)) if c == order[i] => format!("{} ▼", view.columns[i]),
+                _ => view.columns[i].clone(),
+            }))
+            .style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            );
+            let rows = view
+                .rows
+                .iter()
+                .map(|values| Row::new(shown.iter().map(|&i| display(&values[i]))));
+            let constraints = shown.iter().map(|&i| Constraint::Length(widths[i] as u16));
+
+            let title = match &state.filter {
+                Some((Some(c), needle)) => format!(
+                    " {} of {} rows where {} contains '{}' ",
+                    view.rows.len(),
+                    result.rows.len(),
+                    result.columns[*c],
+                    needle
+                ),
+                Some((None, needle)) => format!(
+                    " {} of {} rows containing '{}' ",
+                    view.rows.len(),
+                    result.rows.len(),
+                    needle
+                ),
+                None => format!(" {} rows ", view.rows.len()),
+            };
+            let table = Table::new(rows, constraints)
+                .header(header)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .row_highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                .cell_highlight_style(Style::default().fg(Color::Black).bg(Color::Green));
+
+            let mut table_state = TableState::default();
+            if !view.rows.is_empty() {
+                table_state.select_cell(Some((row, shown.iter().position(|&i| i == column).unwrap_or(0))));
+            }
+            f.render_stateful_widget(table, chunks[0], &mut table_state);
+
+            let status = match &typing {
+                Typing::Filter(target, needle) => format!(
+                    "Filter {}: {}_ | Enter: Keep | Esc: Clear",
+                    target.map_or("all columns", |c| &result.columns[c]),
+                    needle
+                ),
+                Typing::Off => format!(
+                    "Row {}/{} | s: Sort | /: Filter | f: Filter column | p: Pin | x: Hide | </>: Move | r: Reset | q: Done",
+                    (row + 1).min(view.rows.len()),
+                    view.rows.len()
+                ),
+            };
+            f.render_widget(Paragraph::new(status), chunks[1]);
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if let Typing::Filter(target, needle) = &mut typing {
+            let done = match key.code {
+                KeyCode::Enter => true,
+                KeyCode::Esc => {
+                    needle.clear();
+                    true
+                }
+                KeyCode::Backspace => {
+                    needle.pop();
+                    false
+                }
+                KeyCode::Char(c) => {
+                    needle.push(c);
+                    false
+                }
+                _ => false,
+            };
+            state.filter = (!needle.is_empty()).then(|| (*target, needle.clone()));
+            if done {
+                typing = Typing::Off;
+            }
+            continue;
+        }
+
+        let last_column = order.len() - 1;
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => break,
+            (KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q'), _) => break,
+            (KeyCode::Up | KeyCode::Char('k'), _) => row = row.saturating_sub(1),
+            (KeyCode::Down | KeyCode::Char('j'), _) => row = (row + 1).min(last_row),
+            (KeyCode::Left | KeyCode::Char('h'), _) => column = column.saturating_sub(1),
+            (KeyCode::Right | KeyCode::Char('l'), _) => column = (column + 1).min(last_column),
+            (KeyCode::PageUp, _) => row = row.saturating_sub(page),
+            (KeyCode::PageDown, _) => row = (row + page).min(last_row),
+            (KeyCode::Home, _) => row = 0,
+            (KeyCode::End, _) => row = last_row,
+            (KeyCode::Char('s'), _) => state.sort = toggle_sort(state.sort, order[column]),
+            (KeyCode::Char('/'), _) => typing = Typing::Filter(None, String::new()),
+            (KeyCode::Char('f'), _) => typing = Typing::Filter(Some(order[column]), String::new()),
+            (KeyCode::Char('x'), _) if order.len() > 1 => {
+                order.remove(column);
+            }
+            (KeyCode::Char('<'), _) if column > 0 => {
+                order.swap(column, column - 1);
+                column -= 1;
+            }
+            (KeyCode::Char('>'), _) if column < last_column => {
+                order.swap(column, column + 1);
+                column += 1;
+            }
+            (KeyCode::Char('p'), _) => pinned = if pinned == column + 1 { 0 } else { column + 1 },
+            (KeyCode::Char('r'), _) => {
+                *state = ViewState::default();
+                order = all.clone();
+                pinned = 0;
+            }
+            _ => {}
+        }
+    }
+
+    terminal::disable_raw_mode()?;
+    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+
+    Ok(true)
+}
+
+fn sql_literal(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("'{}'", value.replace('\'', "''")),
+        None => "NULL".to_string(),
+    }
+}
+
+pub fn drill_down_context(
+    result: &ResultSet,
+    (row, column): (usize, usize),
+    sql: Option<&str>,
+    schema: &Schema,
+) -> String {
+    let values = &result.rows[row];
+    let mut context = String::from(DRILL_DOWN_HEADER);
+    match sql {
+        Some(sql) => context.push_str(&format!(", produced by:\n{}\n", sql.trim())),
+        None => context.push_str(".\n"),
+    }
+    context.push_str(&format!(
+        "Selected cell: {} = {}\n",
+        result.columns[column],
+        sql_literal(&values[column])
+    ));
+
+    let words: Vec<String> = sql
+        .unwrap_or_default()
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+        .map(str::to_lowercase)
+        .collect();
+    for table in &schema.tables {
+        let short = table.name.rsplit('.').next().unwrap_or(&table.name);
+        if !words.contains(&table.name.to_lowercase()) && !words.contains(&short.to_lowercase()) {
+            continue;
+        }
+        let Some(primary_key) = &table.primary_key else {
+            continue;
+        };
+        let key: Option<Vec<String>> = primary_key
+            .iter()
+            .map(|pk| {
+                let i = result.column_index(pk)?;
+                Some(format!("{} = {}", pk, sql_literal(&values[i])))
+            })
+            .collect();
+        if let Some(key) = key {
+            context.push_str(&format!(
+                "Row key in {}: {}\n",
+                table.name,
+                key.join(" AND ")
+            ));
+        }
+    }
+
+    let row: Vec<String> = result
+        .columns
+        .iter()
+        .zip(values)
+        .map(|(name, value)| format!("{} = {}", name, sql_literal(value)))
+        .collect();
+    context.push_str(&format!("Full row: {}\n", row.join(", ")));
+    context.push_str("Filter on these key values so the query is about exactly this row.");
+    context
+}
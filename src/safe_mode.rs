@@ -0,0 +1,31 @@
+use crate::psql::split_statements;
+
+pub const DEFAULT_ROW_LIMIT: u64 = 1_000;
+
+pub fn is_destructive(sql: &str) -> bool {
+    split_statements(sql).iter().any(|statement| {
+        let statement_upper = statement.trim().to_uppercase();
+        let words: Vec<&str> = statement_upper.split_whitespace().collect();
+
+        match words.first() {
+            Some(&"DROP") | Some(&"TRUNCATE") => true,
+            Some(&"ALTER") => words.contains(&"DROP") || words.contains(&"RENAME"),
+            _ => false,
+        }
+    })
+}
+
+pub fn affected_rows(output: &str) -> u64 {
+    output
+        .lines()
+        .filter_map(|line| {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["INSERT", _, count] | ["UPDATE" | "DELETE" | "MERGE", count] => {
+                    count.parse::<u64>().ok()
+                }
+                _ => None,
+            }
+        })
+        .sum()
+}
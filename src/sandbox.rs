@@ -0,0 +1,292 @@
+use crate::psql::PsqlConnection;
+use crate::quoting::quote;
+use crate::result::ResultSet;
+use crate::schema::{Schema, Table};
+use anyhow::{Context, Result};
+use sqlparser::ast::{
+    visit_relations_mut, Ident, ObjectName, ObjectType, Query, Statement, Visit, Visitor,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::hash::{BuildHasher, Hasher};
+use std::ops::ControlFlow;
+
+pub const DEFAULT_SANDBOX_ROWS: u64 = 1000;
+
+const RESET_SEQUENCES: &str = r#"
+DO $$
+DECLARE c record;
+BEGIN
+    FOR c IN
+        SELECT a.attrelid::regclass::text AS tbl, a.attname,
+               pg_get_serial_sequence(a.attrelid::regclass::text, a.attname) AS seq
+        FROM pg_attribute a
+        JOIN pg_class r ON r.oid = a.attrelid
+        WHERE r.relnamespace = '{schema}'::regnamespace
+          AND r.relkind = 'r'
+          AND a.attnum > 0
+          AND NOT a.attisdropped
+    LOOP
+        IF c.seq IS NOT NULL THEN
+            EXECUTE format('SELECT setval(%L, coalesce(max(%I), 0) + 1, false) FROM %s', c.seq, c.attname, c.tbl);
+        END IF;
+    END LOOP;
+END $$;
+"#;
+
+#[derive(Default)]
+struct CteNames(HashSet<String>);
+
+impl Visitor for CteNames {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                self.0.insert(cte.alias.name.value.to_lowercase());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+pub struct Change {
+    pub table: String,
+    pub before: Option<u64>,
+    pub after: Option<u64>,
+}
+
+pub struct Outcome {
+    pub schema: String,
+    pub copied: Vec<String>,
+    pub success: bool,
+    pub output: String,
+    pub error: String,
+    pub changes: Vec<Change>,
+}
+
+fn sandbox_name() -> String {
+    let suffix = RandomState::new().build_hasher().finish() & 0xffff_ffff;
+    format!("psqlm_sandbox_{}_{:08x}", std::process::id(), suffix)
+}
+
+fn redirect_name(name: &mut ObjectName, sandbox: &str) {
+    if let Some(table) = name.0.last().cloned() {
+        *name = ObjectName(vec![Ident::new(sandbox), table]);
+    }
+}
+
+fn redirect(sql: &str, sandbox: &str) -> Result<(String, Vec<String>)> {
+    let dialect = PostgreSqlDialect {};
+    let mut statements = Parser::parse_sql(&dialect, sql)
+        .context("The statement could not be parsed, so it cannot be redirected")?;
+
+    let mut ctes = CteNames::default();
+    let _ = statements.visit(&mut ctes);
+
+    let mut relations = Vec::new();
+    for statement in &mut statements {
+        match statement {
+            Statement::Query(_)
+            | Statement::Insert(_)
+            | Statement::Update { .. }
+            | Statement::Delete(_)
+            | Statement::Merge { .. }
+            | Statement::Truncate { .. }
+            | Statement::CreateTable(_)
+            | Statement::AlterTable { .. }
+            | Statement::CreateIndex(_) => {}
+            Statement::Drop {
+                object_type: ObjectType::Table,
+                names,
+                ..
+            } => {
+                for name in names {
+                    relations.push(name.to_string());
+                    redirect_name(name, sandbox);
+                }
+            }
+            other => {
+                let keyword = other.to_string();
+                let keyword = keyword.split_whitespace().take(2).collect::<Vec<_>>();
+                anyhow::bail!("{} statements cannot run in the sandbox", keyword.join(" "));
+            }
+        }
+    }
+
+    let _ = visit_relations_mut(&mut statements, |name| {
+        let is_cte = name.0.len() == 1 && ctes.0.contains(&name.0[0].value.to_lowercase());
+        if !is_cte {
+            relations.push(name.to_string());
+            redirect_name(name, sandbox);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    let sql = statements
+        .iter()
+        .map(|statement| format!("{};", statement))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok((sql, relations))
+}
+
+fn qualified(table: &Table) -> String {
+    table
+        .name
+        .split('.')
+        .map(quote)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn short_name(table: &Table) -> &str {
+    table.name.rsplit('.').next().unwrap_or(&table.name)
+}
+
+fn setup_sql(sandbox: &str, tables: &[&Table], rows: u64) -> String {
+    let schema = quote(sandbox);
+    let mut sql = vec![format!("CREATE SCHEMA {};", schema)];
+    for table in tables {
+        let copy = format!("{}.{}", schema, quote(short_name(table)));
+        sql.push(format!(
+            "CREATE TABLE {} (LIKE {} INCLUDING ALL EXCLUDING GENERATED);",
+            copy,
+            qualified(table)
+        ));
+        for column in &table.columns {
+            let uses_sequence = column
+                .default
+                .as_deref()
+                .is_some_and(|default| default.contains("nextval("));
+            if !uses_sequence {
+                continue;
+            }
+            let sequence = format!(
+                "{}.{}",
+                schema,
+                quote(&format!("{}_{}_seq", short_name(table), column.name))
+            );
+            sql.push(format!(
+                "CREATE SEQUENCE {} OWNED BY {}.{};",
+                sequence,
+                copy,
+                quote(&column.name)
+            ));
+            sql.push(format!(
+                "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT nextval('{}'::regclass);",
+                copy,
+                quote(&column.name),
+                sequence.replace('\'', "''")
+            ));
+        }
+        sql.push(format!(
+            "INSERT INTO {} OVERRIDING SYSTEM VALUE SELECT * FROM {} LIMIT {};",
+            copy,
+            qualified(table),
+            rows
+        ));
+    }
+    sql.push(RESET_SEQUENCES.replace("{schema}", &sandbox.replace('\'', "''")));
+    sql.join("\n")
+}
+
+fn count_sql(sandbox: &str) -> String {
+    format!(
+        "SELECT tablename, (xpath('/row/rows/text()', query_to_xml(format('SELECT count(*) AS rows FROM %I.%I', schemaname, tablename), false, true, '')))[1]::text AS rows \
+         FROM pg_tables WHERE schemaname = '{}' ORDER BY 1",
+        sandbox.replace('\'', "''")
+    )
+}
+
+fn row_counts(csv: &str) -> Result<BTreeMap<String, u64>> {
+    let result = ResultSet::from_csv(csv)?;
+    Ok(result
+        .rows
+        .iter()
+        .filter_map(|row| match (&row[0], &row[1]) {
+            (Some(table), Some(rows)) => Some((table.clone(), rows.parse().ok()?)),
+            _ => None,
+        })
+        .collect())
+}
+
+fn changes(before: &BTreeMap<String, u64>, after: &BTreeMap<String, u64>) -> Vec<Change> {
+    let tables: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    tables
+        .into_iter()
+        .map(|table| Change {
+            table: table.clone(),
+            before: before.get(table).copied(),
+            after: after.get(table).copied(),
+        })
+        .collect()
+}
+
+pub fn run(psql: &PsqlConnection, schema: &Schema, sql: &str, rows: u64) -> Result<Outcome> {
+    let sandbox = sandbox_name();
+    let (redirected, relations) = redirect(sql, &sandbox)?;
+
+    let mut tables: Vec<&Table> = Vec::new();
+    for relation in &relations {
+        let Some(table) = schema.find_table(relation) else {
+            continue;
+        };
+        if tables.iter().any(|t| t.name == table.name) {
+            continue;
+        }
+        if let Some(other) = tables.iter().find(|t| short_name(t) == short_name(table)) {
+            anyhow::bail!(
+                "{} and {} share a name and cannot both be copied into the sandbox",
+                other.name,
+                table.name
+            );
+        }
+        tables.push(table);
+    }
+
+    let marker = |stage: &str| format!("-- {} {}", sandbox, stage);
+    let (before_marker, statement_marker, after_marker) =
+        (marker("before"), marker("statement"), marker("after"));
+    let echo = |marker: &str| format!("\\echo '{}'", marker);
+    let steps = [
+        "\\set QUIET on".to_string(),
+        setup_sql(&sandbox, &tables, rows),
+        echo(&before_marker),
+        "\\pset format csv".to_string(),
+        count_sql(&sandbox),
+        echo(&statement_marker),
+        "\\pset format aligned".to_string(),
+        "\\set QUIET off".to_string(),
+        redirected,
+        "\\set QUIET on".to_string(),
+        echo(&after_marker),
+        "\\pset format csv".to_string(),
+        count_sql(&sandbox),
+    ];
+    let (_, stdout, stderr) = psql.execute_rolled_back(&steps)?;
+
+    let Some((_, rest)) = stdout.split_once(&format!("{}\n", before_marker)) else {
+        anyhow::bail!("Could not set up the sandbox: {}", stderr.trim());
+    };
+    let (before, rest) = rest
+        .split_once(&format!("{}\n", statement_marker))
+        .unwrap_or((rest, ""));
+    let before = row_counts(before)?;
+    let (output, after) = match rest.split_once(&format!("{}\n", after_marker)) {
+        Some((output, after)) => (output, Some(row_counts(after)?)),
+        None => (rest, None),
+    };
+    let success = after.is_some();
+
+    Ok(Outcome {
+        schema: sandbox,
+        copied: tables.iter().map(|t| t.name.clone()).collect(),
+        success,
+        output: output.to_string(),
+        error: if success { String::new() } else { stderr },
+        changes: changes(&before, after.as_ref().unwrap_or(&before)),
+    })
+}
@@ -1,8 +1,25 @@
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Schema {
     pub tables: Vec<Table>,
+
+    #[serde(default)]
+    pub materialized_views: Vec<MaterializedView>,
+
+    #[serde(default)]
+    pub views: Vec<View>,
+
+    #[serde(default)]
+    pub enums: Vec<EnumType>,
+
+    #[serde(default)]
+    pub composite_types: Vec<CompositeType>,
+
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +29,9 @@ pub struct Table {
     pub primary_key: Option<Vec<String>>,
     pub foreign_keys: Vec<ForeignKey>,
     pub indexes: Vec<Index>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +40,35 @@ pub struct Column {
     pub data_type: String,
     pub is_nullable: bool,
     pub default: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<Identity>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Identity {
+    Always,
+    ByDefault,
+}
+
+impl Column {
+    fn generation(&self) -> Option<String> {
+        match (&self.generated, self.identity) {
+            (Some(expression), _) => Some(format!("GENERATED ALWAYS AS ({}) STORED", expression)),
+            (None, Some(Identity::Always)) => Some("GENERATED ALWAYS AS IDENTITY".to_string()),
+            (None, Some(Identity::ByDefault)) => {
+                Some("GENERATED BY DEFAULT AS IDENTITY".to_string())
+            }
+            (None, None) => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,12 +85,163 @@ pub struct Index {
     pub is_unique: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterializedView {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub is_populated: bool,
+    pub last_refresh: Option<String>,
+    pub has_unique_index: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct View {
+    pub name: String,
+    pub columns: Vec<Column>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnumType {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeType {
+    pub name: String,
+    pub fields: Vec<Column>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Extension {
+    pub name: String,
+    pub version: String,
+}
+
+fn extension_hint(name: &str) -> Option<&'static str> {
+    match name {
+        "vector" => Some(
+            "pgvector: vector columns support <-> (L2 distance), <=> (cosine distance) and <#> (negative inner product); \
+             for nearest-neighbour search ORDER BY the distance operator with a LIMIT so vector indexes are used",
+        ),
+        "postgis" => Some(
+            "PostGIS: geometry/geography columns work with ST_DWithin, ST_Distance, ST_Intersects, ST_Contains, \
+             ST_MakePoint and ST_SetSRID; filter by radius with ST_DWithin rather than ST_Distance so spatial indexes are used",
+        ),
+        "timescaledb" => Some(
+            "TimescaleDB: group time series with time_bucket(interval, time_column) and use first()/last() \
+             for values at the edges of each bucket",
+        ),
+        "pg_trgm" => Some(
+            "pg_trgm: fuzzy text matching with similarity(a, b), word_similarity(a, b), the % operator \
+             and <-> trigram distance for ranking closest matches",
+        ),
+        "fuzzystrmatch" => Some("fuzzystrmatch: levenshtein(a, b), soundex(a) and dmetaphone(a)"),
+        "citext" => Some("citext: case-insensitive text columns compare without lower()"),
+        "hstore" => Some("hstore: key/value columns with -> lookup, ? key existence and @> containment"),
+        "ltree" => Some("ltree: hierarchical labels with @> / <@ ancestry and ~ lquery matching"),
+        _ => None,
+    }
+}
+
+pub fn name_pattern(pattern: &str) -> Result<Regex> {
+    if let Some(regex) = pattern
+        .strip_prefix('/')
+        .and_then(|p| p.strip_suffix('/'))
+        .filter(|p| !p.is_empty())
+    {
+        return RegexBuilder::new(regex)
+            .case_insensitive(true)
+            .build()
+            .with_context(|| format!("Invalid regular expression: {}", regex));
+    }
+
+    let mut glob = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => glob.push_str(".*"),
+            '?' => glob.push('.'),
+            c => glob.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    glob.push('$');
+    Ok(RegexBuilder::new(&glob).case_insensitive(true).build()?)
+}
+
 impl Schema {
+    pub fn matching(&self, pattern: &str) -> Result<Schema> {
+        let regex = name_pattern(pattern)?;
+        let matches = |name: &str| {
+            regex.is_match(name) || regex.is_match(name.rsplit('.').next().unwrap_or(name))
+        };
+
+        Ok(Schema {
+            tables: self
+                .tables
+                .iter()
+                .filter(|t| matches(&t.name))
+                .cloned()
+                .collect(),
+            materialized_views: self
+                .materialized_views
+                .iter()
+                .filter(|v| matches(&v.name))
+                .cloned()
+                .collect(),
+            views: self
+                .views
+                .iter()
+                .filter(|v| matches(&v.name))
+                .cloned()
+                .collect(),
+            enums: Vec::new(),
+            composite_types: Vec::new(),
+            extensions: Vec::new(),
+        })
+    }
+
+    pub fn relation_count(&self) -> usize {
+        self.tables.len() + self.materialized_views.len() + self.views.len()
+    }
+
+    pub fn find_materialized_view(&self, name: &str) -> Option<&MaterializedView> {
+        let name = name.replace('"', "");
+        let qualified = if name.contains('.') {
+            name
+        } else {
+            format!("public.{}", name)
+        };
+        self.materialized_views.iter().find(|v| v.name == qualified)
+    }
+
+    pub fn find_table(&self, name: &str) -> Option<&Table> {
+        let name = name.replace('"', "");
+        if name.contains('.') {
+            return self.tables.iter().find(|t| t.name == name);
+        }
+
+        let qualified = format!("public.{}", name);
+        self.tables
+            .iter()
+            .find(|t| t.name == qualified)
+            .or_else(|| {
+                self.tables
+                    .iter()
+                    .find(|t| t.name.rsplit('.').next() == Some(name.as_str()))
+            })
+    }
+
     pub fn to_prompt_string(&self) -> String {
         let mut output = String::new();
 
         for table in &self.tables {
             output.push_str(&format!("Table: {}\n", table.name));
+            if let Some(comment) = &table.comment {
+                output.push_str(&format!("  Description: {}\n", comment));
+            }
 
             output.push_str("  Columns:\n");
             for col in &table.columns {
@@ -51,9 +251,18 @@ impl Schema {
                     .as_ref()
                     .map(|d| format!(" DEFAULT {}", d))
                     .unwrap_or_default();
+                let generation = col
+                    .generation()
+                    .map(|g| format!(" {}", g))
+                    .unwrap_or_default();
+                let comment = col
+                    .comment
+                    .as_ref()
+                    .map(|c| format!(" -- {}", c))
+                    .unwrap_or_default();
                 output.push_str(&format!(
-                    "    - {} {} {}{}\n",
-                    col.name, col.data_type, nullable, default
+                    "    - {} {} {}{}{}{}\n",
+                    col.name, col.data_type, nullable, default, generation, comment
                 ));
             }
 
@@ -89,6 +298,100 @@ impl Schema {
             output.push('\n');
         }
 
+        for view in &self.materialized_views {
+            let freshness = match (&view.last_refresh, view.is_populated) {
+                (_, false) => "not populated".to_string(),
+                (Some(at), true) => format!("potentially stale, last refreshed {}", at),
+                (None, true) => "potentially stale, last refresh unknown".to_string(),
+            };
+            output.push_str(&format!(
+                "Materialized View: {} ({})\n",
+                view.name, freshness
+            ));
+
+            output.push_str("  Columns:\n");
+            for col in &view.columns {
+                output.push_str(&format!("    - {} {}\n", col.name, col.data_type));
+            }
+
+            output.push('\n');
+        }
+
+        for view in &self.views {
+            output.push_str(&format!("View: {}\n", view.name));
+            output.push_str("  Columns:\n");
+            for col in &view.columns {
+                output.push_str(&format!("    - {} {}\n", col.name, col.data_type));
+            }
+
+            output.push('\n');
+        }
+
+        if !self.enums.is_empty() {
+            output.push_str("Enum Types:\n");
+            for enum_type in &self.enums {
+                let values: Vec<String> = enum_type
+                    .values
+                    .iter()
+                    .map(|v| format!("'{}'", v.replace('\'', "''")))
+                    .collect();
+                output.push_str(&format!("  - {}: {}\n", enum_type.name, values.join(", ")));
+            }
+            output.push_str(
+                "  Compare and assign enum columns only with these exact, case-sensitive values\n\n",
+            );
+        }
+
+        if !self.composite_types.is_empty() {
+            output.push_str("Composite Types:\n");
+            for composite in &self.composite_types {
+                let fields: Vec<String> = composite
+                    .fields
+                    .iter()
+                    .map(|f| format!("{} {}", f.name, f.data_type))
+                    .collect();
+                output.push_str(&format!("  - {} ({})\n", composite.name, fields.join(", ")));
+            }
+            output.push_str("  Read a field with (column).field, including the parentheses\n\n");
+        }
+
+        if !self.extensions.is_empty() {
+            output.push_str("Extensions:\n");
+            for extension in &self.extensions {
+                output.push_str(&format!("  - {} {}\n", extension.name, extension.version));
+            }
+            let hints: Vec<&str> = self
+                .extensions
+                .iter()
+                .filter_map(|e| extension_hint(&e.name))
+                .collect();
+            if !hints.is_empty() {
+                output.push_str("  Prefer extension features over vanilla SQL where they fit:\n");
+                for hint in hints {
+                    output.push_str(&format!("  - {}\n", hint));
+                }
+            }
+            output.push('\n');
+        }
+
         output
     }
+    pub fn view_definitions(&self) -> Option<String> {
+        let views: Vec<(&str, &str)> = self
+            .views
+            .iter()
+            .filter_map(|v| Some((v.name.as_str(), v.definition.as_deref()?)))
+            .collect();
+        if views.is_empty() {
+            return None;
+        }
+
+        let mut output = String::from(
+            "\nVIEW DEFINITIONS:\nWhen a view already joins or aggregates what the question needs, select from the view instead of repeating its query\n",
+        );
+        for (name, definition) in views {
+            output.push_str(&format!("- {}: {}\n", name, definition));
+        }
+        Some(output)
+    }
 }
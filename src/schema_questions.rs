@@ -0,0 +1,143 @@
+use crate::schema::Schema;
+use std::collections::HashSet;
+
+const QUESTION_STARTS: &[&str] = &[
+    "which", "what", "where", "how", "does", "do", "is", "are", "describe", "explain", "list",
+    "tell",
+];
+
+const SCHEMA_TERMS: &[&str] = &[
+    "table",
+    "tables",
+    "column",
+    "columns",
+    "schema",
+    "key",
+    "keys",
+    "index",
+    "indexes",
+    "indices",
+    "constraint",
+    "constraints",
+    "relationship",
+    "relationships",
+    "nullable",
+];
+
+const RELATION_TERMS: &[&str] = &["reference", "references", "referenced", "referencing"];
+
+const FILLER: &[&str] = &[
+    "s", "are", "is", "the", "a", "an", "all", "any", "there", "of", "me", "about", "does", "do",
+    "have", "has", "primary", "foreign", "unique", "in", "on", "for",
+];
+
+const COUNTABLE_TERMS: &[&str] = &[
+    "tables",
+    "columns",
+    "indexes",
+    "indices",
+    "constraints",
+    "keys",
+    "relationships",
+];
+
+fn words(question: &str) -> Vec<String> {
+    question
+        .to_lowercase()
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_string())
+        .collect()
+}
+
+fn mentions(words: &[String], name: &str) -> bool {
+    let name = name.to_lowercase();
+    words
+        .iter()
+        .any(|w| *w == name || format!("{}s", w) == name || *w == format!("{}s", name))
+}
+
+pub fn is_schema_question(question: &str) -> bool {
+    let words = words(question);
+    let Some(first) = words.first() else {
+        return false;
+    };
+    if !QUESTION_STARTS.contains(&first.as_str()) {
+        return false;
+    }
+
+    if first == "how" && matches!(words.get(1).map(String::as_str), Some("many" | "much")) {
+        return words
+            .get(2)
+            .is_some_and(|w| COUNTABLE_TERMS.contains(&w.as_str()));
+    }
+
+    // The schema term has to be what the question asks about ("which tables...",
+    // "what is the primary key of...", "is orders.email nullable"), optionally after
+    // one object name, not a word somewhere inside a data question.
+    let lowered = question.to_lowercase();
+    let mut rest = lowered
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+        .map(|w| w.trim_matches('.'))
+        .filter(|w| !w.is_empty())
+        .skip(1)
+        .skip_while(|w| FILLER.contains(w));
+    match rest.next() {
+        Some(w) if SCHEMA_TERMS.contains(&w) || RELATION_TERMS.contains(&w) => true,
+        Some(_) => rest
+            .find(|w| !FILLER.contains(w))
+            .is_some_and(|w| SCHEMA_TERMS.contains(&w)),
+        None => false,
+    }
+}
+
+pub fn mentioned_tables(question: &str, schema: &Schema) -> HashSet<String> {
+    let words = words(question);
+    let short_name = |name: &str| name.rsplit('.').next().unwrap_or(name).to_string();
+
+    schema
+        .tables
+        .iter()
+        .map(|t| &t.name)
+        .chain(schema.materialized_views.iter().map(|v| &v.name))
+        .chain(schema.views.iter().map(|v| &v.name))
+        .filter(|name| mentions(&words, &short_name(name)))
+        .cloned()
+        .collect()
+}
+
+pub fn relevant_schema(question: &str, schema: &Schema) -> Schema {
+    let mentioned = mentioned_tables(question, schema);
+    if mentioned.is_empty() {
+        return schema.clone();
+    }
+
+    Schema {
+        tables: schema
+            .tables
+            .iter()
+            .filter(|t| {
+                mentioned.contains(&t.name)
+                    || t.foreign_keys
+                        .iter()
+                        .any(|fk| mentioned.contains(&fk.references_table))
+            })
+            .cloned()
+            .collect(),
+        materialized_views: schema
+            .materialized_views
+            .iter()
+            .filter(|v| mentioned.contains(&v.name))
+            .cloned()
+            .collect(),
+        views: schema
+            .views
+            .iter()
+            .filter(|v| mentioned.contains(&v.name))
+            .cloned()
+            .collect(),
+        enums: schema.enums.clone(),
+        composite_types: schema.composite_types.clone(),
+        extensions: schema.extensions.clone(),
+    }
+}
@@ -0,0 +1,260 @@
+use crate::claude::ConversationTurn;
+use crate::metrics;
+use crate::psql::PsqlConnection;
+use crate::schema::Schema;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+
+const IDLE_TIMEOUT: Duration = Duration::from_secs(8 * 60 * 60);
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionState {
+    pub schema: Schema,
+    pub history: Vec<ConversationTurn>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Activity {
+    pid: u32,
+    statement: String,
+    started: u64,
+}
+
+impl Activity {
+    pub fn describe(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        format!(
+            "Another psqlm attached to this session (pid {}) has been running this for {}s:\n{}",
+            self.pid,
+            now.saturating_sub(self.started),
+            self.statement
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    Attach,
+    Update(SessionState, metrics::Snapshot),
+    Begin(Activity),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Turn {
+    Granted(Vec<ConversationTurn>),
+    Busy(Activity),
+}
+
+struct Daemon {
+    state: SessionState,
+    busy: Option<Activity>,
+}
+
+pub struct Session {
+    path: PathBuf,
+}
+
+pub struct Lease {
+    _stream: UnixStream,
+}
+
+fn socket_path(psql: &PsqlConnection) -> Result<PathBuf> {
+    let dir = dirs::runtime_dir()
+        .or_else(dirs::data_dir)
+        .context("Could not determine runtime directory")?
+        .join("psqlm")
+        .join("sessions");
+    Ok(dir.join(format!("{}.sock", psql.storage_key())))
+}
+
+async fn exchange<T: DeserializeOwned>(
+    path: &PathBuf,
+    request: &Request,
+) -> Result<(UnixStream, T)> {
+    let mut stream = UnixStream::connect(path).await?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await?;
+
+    let mut response = String::new();
+    BufReader::new(&mut stream).read_line(&mut response).await?;
+    Ok((stream, serde_json::from_str(&response)?))
+}
+
+async fn send(path: &PathBuf, request: &Request) -> Result<Option<SessionState>> {
+    Ok(exchange(path, request).await?.1)
+}
+
+fn spawn_daemon(psql: &PsqlConnection, profile: Option<&str>) -> Result<()> {
+    let exe = std::env::current_exe().context("Could not locate the psqlm executable")?;
+    let mut cmd = Command::new(exe);
+    cmd.arg("daemon");
+    match profile {
+        Some(profile) => cmd.args(["--profile", profile]),
+        None => cmd
+            .args(["-h", &psql.host])
+            .args(["-p", &psql.port])
+            .args(["-U", &psql.user])
+            .args(["-d", &psql.database]),
+    };
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    if let Some(pw) = psql.current_password() {
+        cmd.env("PGPASSWORD", pw);
+    }
+
+    cmd.process_group(0);
+
+    cmd.spawn().context("Failed to start session daemon")?;
+    Ok(())
+}
+
+impl Session {
+    pub async fn attach(
+        psql: &PsqlConnection,
+        profile: Option<&str>,
+    ) -> Result<(Self, SessionState)> {
+        let path = socket_path(psql)?;
+
+        if let Ok(Some(state)) = send(&path, &Request::Attach).await {
+            return Ok((Self { path }, state));
+        }
+
+        println!("Starting session daemon for {}...", psql.database);
+        spawn_daemon(psql, profile)?;
+
+        let started = std::time::Instant::now();
+        loop {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if let Ok(Some(state)) = send(&path, &Request::Attach).await {
+                return Ok((Self { path }, state));
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                anyhow::bail!("Session daemon did not start within {:?}", STARTUP_TIMEOUT);
+            }
+        }
+    }
+
+    pub async fn update(&self, schema: &Schema, history: &[ConversationTurn]) -> Result<()> {
+        let state = SessionState {
+            schema: schema.clone(),
+            history: history.to_vec(),
+        };
+        send(&self.path, &Request::Update(state, metrics::take())).await?;
+        Ok(())
+    }
+
+    pub async fn begin(
+        &self,
+        statement: &str,
+    ) -> Result<std::result::Result<(Lease, Vec<ConversationTurn>), Activity>> {
+        let activity = Activity {
+            pid: std::process::id(),
+            statement: statement.to_string(),
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        };
+        let (stream, turn) = exchange(&self.path, &Request::Begin(activity)).await?;
+        Ok(match turn {
+            Turn::Granted(history) => Ok((Lease { _stream: stream }, history)),
+            Turn::Busy(holder) => Err(holder),
+        })
+    }
+}
+
+async fn respond<T: Serialize>(writer: &mut OwnedWriteHalf, response: &T) -> Result<()> {
+    let mut line = serde_json::to_string(response)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+async fn handle(stream: UnixStream, daemon: Arc<Mutex<Daemon>>) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+
+    match serde_json::from_str(&line)? {
+        Request::Attach => {
+            let state = daemon.lock().await.state.clone();
+            respond(&mut writer, &Some(state)).await
+        }
+        Request::Update(update, snapshot) => {
+            metrics::add(&snapshot);
+            daemon.lock().await.state = update;
+            respond(&mut writer, &None::<SessionState>).await
+        }
+        Request::Begin(activity) => {
+            let turn = {
+                let mut daemon = daemon.lock().await;
+                match &daemon.busy {
+                    Some(holder) => Turn::Busy(holder.clone()),
+                    None => {
+                        daemon.busy = Some(activity);
+                        Turn::Granted(daemon.state.history.clone())
+                    }
+                }
+            };
+            respond(&mut writer, &turn).await?;
+            if matches!(turn, Turn::Granted(_)) {
+                let _ = reader.read_line(&mut String::new()).await;
+                daemon.lock().await.busy = None;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub async fn serve(psql: PsqlConnection) -> Result<()> {
+    let path = socket_path(&psql)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    if UnixStream::connect(&path).await.is_ok() {
+        anyhow::bail!("A session daemon is already running for {}", psql.database);
+    }
+    let _ = std::fs::remove_file(&path);
+
+    let schema = psql.introspect_schema()?;
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to bind session socket: {:?}", path))?;
+    let daemon = Arc::new(Mutex::new(Daemon {
+        state: SessionState {
+            schema,
+            history: Vec::new(),
+        },
+        busy: None,
+    }));
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                tokio::spawn(handle(stream, daemon.clone()));
+            }
+            _ = tokio::time::sleep(IDLE_TIMEOUT) => break,
+        }
+    }
+
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
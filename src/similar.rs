@@ -0,0 +1,188 @@
+use crate::psql::PsqlConnection;
+use crate::result::{ColumnType, ResultSet};
+use anyhow::Result;
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use std::collections::HashSet;
+
+const MIN_SIMILARITY: f64 = 0.5;
+const MAX_RESULTS: usize = 5;
+const MAX_QUERY_WIDTH: usize = 100;
+const MAX_DIGEST_QUERY_WIDTH: usize = 500;
+const CATALOG_MARKERS: &[&str] = &[
+    "information_schema",
+    "pg_catalog",
+    "pg_stat",
+    "pg_class",
+    "pg_attribute",
+    "pg_namespace",
+    "pg_index",
+    "pg_constraint",
+    "pg_extension",
+    "pg_database",
+    "pg_roles",
+    "pg_settings",
+    "pg_matviews",
+];
+
+fn normalized_tokens(sql: &str) -> Vec<String> {
+    let dialect = PostgreSqlDialect {};
+    let Ok(tokens) = Tokenizer::new(&dialect, sql).tokenize() else {
+        return sql.split_whitespace().map(|w| w.to_lowercase()).collect();
+    };
+
+    tokens
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Whitespace(_) | Token::SemiColon => None,
+            Token::Number(..)
+            | Token::SingleQuotedString(_)
+            | Token::EscapedStringLiteral(_)
+            | Token::DollarQuotedString(_)
+            | Token::Placeholder(_) => Some("?".to_string()),
+            Token::Word(word) if word.quote_style.is_none() => Some(word.value.to_lowercase()),
+            other => Some(other.to_string()),
+        })
+        .collect()
+}
+
+fn shingles(sql: &str) -> HashSet<String> {
+    let tokens = normalized_tokens(sql);
+    if tokens.len() < 2 {
+        return tokens.into_iter().collect();
+    }
+    tokens.windows(2).map(|pair| pair.join(" ")).collect()
+}
+
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+fn one_line(sql: &str, width: usize) -> String {
+    let line = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() > width {
+        let truncated: String = line.chars().take(width - 1).collect();
+        format!("{}…", truncated)
+    } else {
+        line
+    }
+}
+
+fn statement_stats(psql: &PsqlConnection, filter: &str) -> Result<ResultSet> {
+    let stats_query = |time_suffix: &str| {
+        format!(
+            r#"
+            SELECT
+                query,
+                calls,
+                round(mean_{0}::numeric, 2) AS mean_ms,
+                round(total_{0}::numeric, 2) AS total_ms
+            FROM pg_stat_statements
+            WHERE dbid = (SELECT oid FROM pg_database WHERE datname = current_database())
+            {1}
+            ORDER BY total_{0} DESC
+            LIMIT 1000
+            "#,
+            time_suffix, filter
+        )
+    };
+
+    let (mut success, mut stdout, mut stderr) = psql.execute_csv(&stats_query("exec_time"))?;
+    if !success {
+        (success, stdout, stderr) = psql.execute_csv(&stats_query("time"))?;
+    }
+    if !success {
+        anyhow::bail!("Failed to read pg_stat_statements: {}", stderr.trim());
+    }
+
+    ResultSet::from_csv(&stdout)
+}
+
+fn ensure_installed(psql: &PsqlConnection) -> Result<()> {
+    let installed =
+        psql.query("SELECT 1 FROM pg_extension WHERE extname = 'pg_stat_statements'")?;
+    if installed.trim().is_empty() {
+        anyhow::bail!("pg_stat_statements is not installed in this database");
+    }
+    Ok(())
+}
+
+pub fn find_similar(psql: &PsqlConnection, sql: &str) -> Result<ResultSet> {
+    ensure_installed(psql)?;
+    let stats = statement_stats(psql, "")?;
+    let target = shingles(sql);
+
+    let mut matches: Vec<(f64, &Vec<Option<String>>)> = stats
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let query = row[0].as_deref()?;
+            let score = similarity(&target, &shingles(query));
+            (score >= MIN_SIMILARITY).then_some((score, row))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+    matches.truncate(MAX_RESULTS);
+
+    Ok(ResultSet {
+        columns: ["similarity", "calls", "mean_ms", "total_ms", "query"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+        types: vec![
+            ColumnType::Text,
+            ColumnType::Number,
+            ColumnType::Number,
+            ColumnType::Number,
+            ColumnType::Text,
+        ],
+        rows: matches
+            .into_iter()
+            .map(|(score, row)| {
+                vec![
+                    Some(format!("{:.0}%", score * 100.0)),
+                    row[1].clone(),
+                    row[2].clone(),
+                    row[3].clone(),
+                    row[0].as_deref().map(|q| one_line(q, MAX_QUERY_WIDTH)),
+                ]
+            })
+            .collect(),
+    })
+}
+
+pub fn recent_statements(psql: &PsqlConnection, limit: usize) -> Result<String> {
+    ensure_installed(psql)?;
+    let stats = statement_stats(
+        psql,
+        "AND userid = (SELECT oid FROM pg_roles WHERE rolname = current_user)",
+    )?;
+
+    let mut digest = String::new();
+    for row in stats
+        .rows
+        .iter()
+        .filter(|row| {
+            row[0]
+                .as_deref()
+                .is_some_and(|q| !CATALOG_MARKERS.iter().any(|m| q.contains(m)))
+        })
+        .take(limit)
+    {
+        digest.push_str(&format!(
+            "- {} calls, {} ms mean, {} ms total: {}\n",
+            row[1].as_deref().unwrap_or("?"),
+            row[2].as_deref().unwrap_or("?"),
+            row[3].as_deref().unwrap_or("?"),
+            row[0]
+                .as_deref()
+                .map(|q| one_line(q, MAX_DIGEST_QUERY_WIDTH))
+                .unwrap_or_default()
+        ));
+    }
+    Ok(digest)
+}
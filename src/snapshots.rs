@@ -0,0 +1,394 @@
+use crate::config;
+use crate::psql::PsqlConnection;
+use crate::schema::{Column, ForeignKey, Index, MaterializedView, Schema, Table};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub name: Option<String>,
+    pub timestamp: u64,
+    pub schema: Schema,
+}
+
+impl Snapshot {
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} UTC  {}", format_timestamp(self.timestamp), name),
+            None => format!("{} UTC", format_timestamp(self.timestamp)),
+        }
+    }
+}
+
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn for_database(psql: &PsqlConnection) -> Result<Self> {
+        let dir = dirs::data_dir()
+            .context("Could not determine data directory")?
+            .join("psqlm")
+            .join("snapshots")
+            .join(psql.storage_key());
+        Ok(Self { dir })
+    }
+
+    pub fn save(&self, name: Option<String>, schema: &Schema) -> Result<Snapshot> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let snapshot = Snapshot {
+            name,
+            timestamp: now.as_secs(),
+            schema: schema.clone(),
+        };
+
+        let path = self.dir.join(format!("{}.json", now.as_nanos()));
+        config::write_atomic(&path, &serde_json::to_string(&snapshot)?)
+            .with_context(|| format!("Failed to write snapshot: {:?}", path))?;
+        Ok(snapshot)
+    }
+
+    pub fn list(&self) -> Vec<Snapshot> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut snapshots: Vec<Snapshot> = entries
+            .filter_map(|entry| std::fs::read_to_string(entry.ok()?.path()).ok())
+            .filter_map(|contents| serde_json::from_str(&contents).ok())
+            .collect();
+        snapshots.sort_by_key(|s| s.timestamp);
+        snapshots
+    }
+
+    pub fn find(&self, reference: &str) -> Result<Snapshot> {
+        let snapshots = self.list();
+
+        if let Some(index) = reference
+            .strip_prefix('#')
+            .and_then(|i| i.parse::<usize>().ok())
+        {
+            return index
+                .checked_sub(1)
+                .and_then(|i| snapshots.get(i).cloned())
+                .with_context(|| format!("No snapshot #{}", index));
+        }
+
+        snapshots
+            .iter()
+            .rev()
+            .find(|s| s.name.as_deref() == Some(reference))
+            .or_else(|| {
+                snapshots
+                    .iter()
+                    .rev()
+                    .find(|s| format_timestamp(s.timestamp).starts_with(reference))
+            })
+            .cloned()
+            .with_context(|| format!("No snapshot matches '{}'", reference))
+    }
+}
+
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+pub fn plain(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+fn column_definition(column: &Column) -> String {
+    let mut definition = format!("{} {}", column.name, column.data_type);
+    if !column.is_nullable {
+        definition.push_str(" NOT NULL");
+    }
+    if let Some(default) = &column.default {
+        definition.push_str(&format!(" DEFAULT {}", default));
+    }
+    definition
+}
+
+fn index_definition(table: &str, index: &Index) -> String {
+    let unique = if index.is_unique { "UNIQUE " } else { "" };
+    format!(
+        "CREATE {}INDEX {} ON {} ({})",
+        unique,
+        index.name,
+        table,
+        index.columns.join(", ")
+    )
+}
+
+fn fk_definition(fk: &ForeignKey) -> String {
+    format!(
+        "FOREIGN KEY ({}) REFERENCES {} ({})",
+        fk.columns.join(", "),
+        fk.references_table,
+        fk.references_columns.join(", ")
+    )
+}
+
+fn diff_columns(table: &str, old: &[Column], new: &[Column], changes: &mut Vec<String>) {
+    for column in new {
+        let Some(before) = old.iter().find(|c| c.name == column.name) else {
+            changes.push(format!(
+                "{GREEN}ALTER TABLE {} ADD COLUMN {}{RESET}",
+                table,
+                column_definition(column)
+            ));
+            continue;
+        };
+
+        let alter = format!("ALTER TABLE {} ALTER COLUMN {}", table, column.name);
+        if before.data_type != column.data_type {
+            changes.push(format!(
+                "{YELLOW}{} TYPE {}{RESET} (was {})",
+                alter, column.data_type, before.data_type
+            ));
+        }
+        if before.is_nullable != column.is_nullable {
+            let action = if column.is_nullable {
+                "DROP NOT NULL"
+            } else {
+                "SET NOT NULL"
+            };
+            changes.push(format!("{YELLOW}{} {}{RESET}", alter, action));
+        }
+        if before.default != column.default {
+            match &column.default {
+                Some(default) => {
+                    changes.push(format!("{YELLOW}{} SET DEFAULT {}{RESET}", alter, default))
+                }
+                None => changes.push(format!("{YELLOW}{} DROP DEFAULT{RESET}", alter)),
+            }
+        }
+        if before.comment != column.comment {
+            changes.push(format!(
+                "{YELLOW}COMMENT ON COLUMN {}.{} IS {}{RESET}",
+                table,
+                column.name,
+                comment_literal(&column.comment)
+            ));
+        }
+    }
+
+    for column in old {
+        if !new.iter().any(|c| c.name == column.name) {
+            changes.push(format!(
+                "{RED}ALTER TABLE {} DROP COLUMN {}{RESET}",
+                table, column.name
+            ));
+        }
+    }
+}
+
+fn comment_literal(comment: &Option<String>) -> String {
+    match comment {
+        Some(comment) => format!("'{}'", comment.replace('\'', "''")),
+        None => "NULL".to_string(),
+    }
+}
+
+fn diff_table(old: &Table, new: &Table, changes: &mut Vec<String>) {
+    let name = &new.name;
+    diff_columns(name, &old.columns, &new.columns, changes);
+
+    if old.primary_key != new.primary_key {
+        if old.primary_key.is_some() {
+            changes.push(format!("{RED}ALTER TABLE {} DROP PRIMARY KEY{RESET}", name));
+        }
+        if let Some(pk) = &new.primary_key {
+            changes.push(format!(
+                "{GREEN}ALTER TABLE {} ADD PRIMARY KEY ({}){RESET}",
+                name,
+                pk.join(", ")
+            ));
+        }
+    }
+
+    let old_fks: Vec<String> = old.foreign_keys.iter().map(fk_definition).collect();
+    let new_fks: Vec<String> = new.foreign_keys.iter().map(fk_definition).collect();
+    for fk in new_fks.iter().filter(|fk| !old_fks.contains(fk)) {
+        changes.push(format!("{GREEN}ALTER TABLE {} ADD {}{RESET}", name, fk));
+    }
+    for fk in old_fks.iter().filter(|fk| !new_fks.contains(fk)) {
+        changes.push(format!("{RED}ALTER TABLE {} DROP {}{RESET}", name, fk));
+    }
+
+    for index in by_name(&new.indexes, |i| &i.name) {
+        match old.indexes.iter().find(|i| i.name == index.name) {
+            None => changes.push(format!("{GREEN}{}{RESET}", index_definition(name, index))),
+            Some(before)
+                if before.columns != index.columns || before.is_unique != index.is_unique =>
+            {
+                changes.push(format!("{RED}DROP INDEX {}{RESET}", before.name));
+                changes.push(format!("{GREEN}{}{RESET}", index_definition(name, index)));
+            }
+            Some(_) => {}
+        }
+    }
+    for index in by_name(&old.indexes, |i| &i.name) {
+        if !new.indexes.iter().any(|i| i.name == index.name) {
+            changes.push(format!("{RED}DROP INDEX {}{RESET}", index.name));
+        }
+    }
+
+    if old.comment != new.comment {
+        changes.push(format!(
+            "{YELLOW}COMMENT ON TABLE {} IS {}{RESET}",
+            name,
+            comment_literal(&new.comment)
+        ));
+    }
+}
+
+fn create_table(table: &Table) -> Vec<String> {
+    let mut lines = vec![format!("{GREEN}CREATE TABLE {} ({RESET}", table.name)];
+    let mut definitions: Vec<String> = table.columns.iter().map(column_definition).collect();
+    if let Some(pk) = &table.primary_key {
+        definitions.push(format!("PRIMARY KEY ({})", pk.join(", ")));
+    }
+    definitions.extend(table.foreign_keys.iter().map(fk_definition));
+
+    let last = definitions.len().saturating_sub(1);
+    for (i, definition) in definitions.iter().enumerate() {
+        let comma = if i == last { "" } else { "," };
+        lines.push(format!("{GREEN}    {}{}{RESET}", definition, comma));
+    }
+    lines.push(format!("{GREEN}){RESET}"));
+
+    for index in &table.indexes {
+        lines.push(format!(
+            "{GREEN}{}{RESET}",
+            index_definition(&table.name, index)
+        ));
+    }
+    lines
+}
+
+fn diff_view(old: &MaterializedView, new: &MaterializedView, changes: &mut Vec<String>) {
+    let columns = |view: &MaterializedView| -> Vec<String> {
+        view.columns
+            .iter()
+            .map(|c| format!("{} {}", c.name, c.data_type))
+            .collect()
+    };
+    if columns(old) != columns(new) {
+        changes.push(format!(
+            "{YELLOW}-- materialized view {} columns changed: ({}) -> ({}){RESET}",
+            new.name,
+            columns(old).join(", "),
+            columns(new).join(", ")
+        ));
+    }
+}
+
+fn by_name<T>(items: &[T], name: impl Fn(&T) -> &str) -> Vec<&T> {
+    let mut sorted: Vec<&T> = items.iter().collect();
+    sorted.sort_by(|a, b| name(a).cmp(name(b)));
+    sorted
+}
+
+pub fn diff(old: &Schema, new: &Schema) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for table in by_name(&new.tables, |t| &t.name) {
+        match old.tables.iter().find(|t| t.name == table.name) {
+            Some(before) => diff_table(before, table, &mut changes),
+            None => changes.extend(create_table(table)),
+        }
+    }
+    for table in by_name(&old.tables, |t| &t.name) {
+        if !new.tables.iter().any(|t| t.name == table.name) {
+            changes.push(format!("{RED}DROP TABLE {}{RESET}", table.name));
+        }
+    }
+
+    for view in by_name(&new.materialized_views, |v| &v.name) {
+        match old.materialized_views.iter().find(|v| v.name == view.name) {
+            Some(before) => diff_view(before, view, &mut changes),
+            None => changes.push(format!(
+                "{GREEN}CREATE MATERIALIZED VIEW {}{RESET}",
+                view.name
+            )),
+        }
+    }
+    for view in by_name(&old.materialized_views, |v| &v.name) {
+        if !new.materialized_views.iter().any(|v| v.name == view.name) {
+            changes.push(format!("{RED}DROP MATERIALIZED VIEW {}{RESET}", view.name));
+        }
+    }
+
+    for view in by_name(&new.views, |v| &v.name) {
+        if !old.views.iter().any(|v| v.name == view.name) {
+            changes.push(format!("{GREEN}CREATE VIEW {}{RESET}", view.name));
+        }
+    }
+    for view in by_name(&old.views, |v| &v.name) {
+        if !new.views.iter().any(|v| v.name == view.name) {
+            changes.push(format!("{RED}DROP VIEW {}{RESET}", view.name));
+        }
+    }
+
+    for extension in by_name(&new.extensions, |e| &e.name) {
+        match old.extensions.iter().find(|e| e.name == extension.name) {
+            Some(before) if before.version != extension.version => changes.push(format!(
+                "{YELLOW}ALTER EXTENSION {} UPDATE TO '{}'{RESET} (was {})",
+                extension.name, extension.version, before.version
+            )),
+            Some(_) => {}
+            None => changes.push(format!(
+                "{GREEN}CREATE EXTENSION {} VERSION '{}'{RESET}",
+                extension.name, extension.version
+            )),
+        }
+    }
+    for extension in by_name(&old.extensions, |e| &e.name) {
+        if !new.extensions.iter().any(|e| e.name == extension.name) {
+            changes.push(format!("{RED}DROP EXTENSION {}{RESET}", extension.name));
+        }
+    }
+
+    changes
+}
@@ -0,0 +1,72 @@
+use crate::accessibility;
+use anyhow::Result;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+pub struct Spinner {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    pub fn start(message: &str) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        if accessibility::enabled() {
+            eprintln!("{}", message);
+            return Self { stop, handle: None };
+        }
+        let flag = stop.clone();
+        let message = message.to_string();
+
+        let handle = thread::spawn(move || {
+            let started = Instant::now();
+            let mut stderr = io::stderr();
+
+            for frame in FRAMES.iter().cycle() {
+                if flag.load(Ordering::Relaxed) {
+                    break;
+                }
+                let _ = write!(
+                    stderr,
+                    "\r{} {} ({:.1}s)\x1b[K",
+                    frame,
+                    message,
+                    started.elapsed().as_secs_f32()
+                );
+                let _ = stderr.flush();
+                thread::sleep(Duration::from_millis(80));
+            }
+
+            let _ = write!(stderr, "\r\x1b[K");
+            let _ = stderr.flush();
+        });
+
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+pub async fn run_blocking<T, F>(message: &str, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let _spinner = Spinner::start(message);
+    tokio::task::spawn_blocking(f).await?
+}
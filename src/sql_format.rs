@@ -0,0 +1,129 @@
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+
+const CLAUSES: &[Keyword] = &[
+    Keyword::SELECT,
+    Keyword::FROM,
+    Keyword::WHERE,
+    Keyword::GROUP,
+    Keyword::HAVING,
+    Keyword::WINDOW,
+    Keyword::ORDER,
+    Keyword::LIMIT,
+    Keyword::OFFSET,
+    Keyword::UNION,
+    Keyword::INTERSECT,
+    Keyword::EXCEPT,
+    Keyword::SET,
+    Keyword::VALUES,
+    Keyword::RETURNING,
+];
+
+const JOIN_PREFIXES: &[Keyword] = &[
+    Keyword::LEFT,
+    Keyword::RIGHT,
+    Keyword::INNER,
+    Keyword::FULL,
+    Keyword::CROSS,
+    Keyword::NATURAL,
+    Keyword::OUTER,
+];
+
+pub fn needs_formatting(sql: &str, width: usize) -> bool {
+    sql.lines().any(|line| line.chars().count() > width)
+}
+
+fn break_line(output: &mut String, indent: &str) {
+    let trimmed = output.trim_end().len();
+    output.truncate(trimmed);
+    if !output.is_empty() {
+        output.push('\n');
+        output.push_str(indent);
+    }
+}
+
+pub fn format(sql: &str) -> String {
+    let dialect = PostgreSqlDialect {};
+    let formatted = layout(sql);
+    match Parser::parse_sql(&dialect, sql) {
+        Ok(original)
+            if Parser::parse_sql(&dialect, &formatted).ok().as_ref() != Some(&original) =>
+        {
+            sql.to_string()
+        }
+        _ => formatted,
+    }
+}
+
+fn layout(sql: &str) -> String {
+    let dialect = PostgreSqlDialect {};
+    let Ok(tokens) = Tokenizer::new(&dialect, sql)
+        .with_unescape(false)
+        .tokenize()
+    else {
+        return sql.to_string();
+    };
+
+    let mut output = String::new();
+    let mut depth = 0usize;
+    let mut clause = Keyword::NoKeyword;
+    let mut previous = Keyword::NoKeyword;
+    let mut in_between = false;
+    let mut skip_whitespace = false;
+
+    for token in &tokens {
+        match token {
+            Token::Whitespace(Whitespace::Space | Whitespace::Tab | Whitespace::Newline) => {
+                if !skip_whitespace && !output.ends_with(' ') && !output.ends_with('\n') {
+                    output.push(' ');
+                }
+                continue;
+            }
+            Token::LParen => depth += 1,
+            Token::RParen => depth = depth.saturating_sub(1),
+            Token::Comma if depth == 0 && clause == Keyword::SELECT => {
+                output.push(',');
+                break_line(&mut output, "  ");
+                skip_whitespace = true;
+                previous = Keyword::NoKeyword;
+                continue;
+            }
+            Token::Word(word) if depth == 0 && word.quote_style.is_none() => {
+                let keyword = word.keyword;
+                let joins = JOIN_PREFIXES.contains(&keyword) || keyword == Keyword::JOIN;
+
+                if joins && !JOIN_PREFIXES.contains(&previous) {
+                    break_line(&mut output, "");
+                    clause = Keyword::FROM;
+                } else if CLAUSES.contains(&keyword) {
+                    break_line(&mut output, "");
+                    clause = keyword;
+                } else if keyword == Keyword::ON && clause == Keyword::FROM {
+                    break_line(&mut output, "  ");
+                } else if keyword == Keyword::BETWEEN {
+                    in_between = true;
+                } else if matches!(keyword, Keyword::AND | Keyword::OR)
+                    && matches!(clause, Keyword::WHERE | Keyword::HAVING)
+                {
+                    if in_between && keyword == Keyword::AND {
+                        in_between = false;
+                    } else {
+                        break_line(&mut output, "  ");
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        previous = match token {
+            Token::Word(word) => word.keyword,
+            _ => Keyword::NoKeyword,
+        };
+        skip_whitespace = false;
+        output.push_str(&token.to_string());
+    }
+
+    output.trim().to_string()
+}
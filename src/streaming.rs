@@ -0,0 +1,120 @@
+use crate::psql::{split_statements, PsqlConnection};
+use crate::result::{ResultSet, TableLayout};
+use crate::units;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_THRESHOLD: u64 = 10_000;
+pub const FETCH_COUNT: usize = 1_000;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Streamed {
+    pub success: bool,
+    pub stopped: bool,
+    pub rows: usize,
+    pub first_batch: Option<ResultSet>,
+    pub stderr: String,
+}
+
+pub struct Copied {
+    pub success: bool,
+    pub stopped: bool,
+    pub bytes: u64,
+    pub stderr: String,
+}
+
+pub fn is_copy_to_stdout(sql: &str) -> bool {
+    let sql_upper = sql.to_uppercase();
+    let words: Vec<&str> = sql_upper.split_whitespace().collect();
+    words.first() == Some(&"COPY")
+        && words
+            .windows(2)
+            .any(|pair| pair[0] == "TO" && pair[1].trim_end_matches(';') == "STDOUT")
+}
+
+pub fn is_streamable(sql: &str) -> bool {
+    let trimmed = sql.trim().trim_end_matches(';');
+    let sql_upper = trimmed.to_uppercase();
+    let first_word = sql_upper.split_whitespace().next().unwrap_or("");
+
+    split_statements(trimmed).len() == 1
+        && matches!(first_word, "SELECT" | "WITH" | "TABLE" | "VALUES")
+}
+
+pub async fn stream(psql: &PsqlConnection, sql: &str, layout: TableLayout) -> Result<Streamed> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let (conn, sql, flag) = (psql.clone(), sql.to_string(), stop.clone());
+
+    let mut task = tokio::task::spawn_blocking(move || {
+        let mut first_batch: Option<ResultSet> = None;
+        let (success, rows, stderr) = conn.stream_csv(&sql, FETCH_COUNT, &flag, |batch| {
+            print!("{}", batch.render_rows(&layout, first_batch.is_none()));
+            let _ = io::stdout().flush();
+            first_batch.get_or_insert(batch);
+        })?;
+
+        Ok::<_, anyhow::Error>(Streamed {
+            success,
+            stopped: false,
+            rows,
+            first_batch,
+            stderr,
+        })
+    });
+
+    let result = tokio::select! {
+        result = &mut task => result,
+        _ = tokio::signal::ctrl_c() => {
+            stop.store(true, Ordering::Relaxed);
+            task.await
+        }
+    };
+
+    let mut streamed = result??;
+    streamed.stopped = stop.load(Ordering::Relaxed);
+    Ok(streamed)
+}
+
+pub async fn copy_to_file(psql: &PsqlConnection, sql: &str, path: &Path) -> Result<Copied> {
+    let file = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let (conn, sql, flag) = (psql.clone(), sql.to_string(), stop.clone());
+
+    let mut task = tokio::task::spawn_blocking(move || {
+        let mut writer = BufWriter::new(file);
+        let mut last_report = Instant::now();
+        let (success, bytes, stderr) = conn.copy_out(&sql, &mut writer, &flag, |bytes| {
+            if last_report.elapsed() >= PROGRESS_INTERVAL {
+                print!("\r\x1b[2KWrote {}...", units::format_bytes(bytes));
+                let _ = io::stdout().flush();
+                last_report = Instant::now();
+            }
+        })?;
+        print!("\r\x1b[2K");
+        let _ = io::stdout().flush();
+
+        Ok::<_, anyhow::Error>(Copied {
+            success,
+            stopped: false,
+            bytes,
+            stderr,
+        })
+    });
+
+    let result = tokio::select! {
+        result = &mut task => result,
+        _ = tokio::signal::ctrl_c() => {
+            stop.store(true, Ordering::Relaxed);
+            task.await
+        }
+    };
+
+    let mut copied = result??;
+    copied.stopped = stop.load(Ordering::Relaxed);
+    Ok(copied)
+}
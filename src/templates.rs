@@ -0,0 +1,81 @@
+use crate::config::Template;
+use crate::params::{bind, find_placeholders};
+use crate::schema::Schema;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Deserialize)]
+struct TemplateChoice {
+    template: Option<String>,
+
+    #[serde(default)]
+    params: HashMap<String, Value>,
+}
+
+pub fn to_prompt_string(templates: &BTreeMap<String, Template>) -> String {
+    let mut output = String::new();
+
+    for (name, template) in templates {
+        output.push_str(&format!("Template: {}\n", name));
+        output.push_str(&format!("  Description: {}\n", template.description));
+        output.push_str(&format!("  SQL: {}\n", template.sql.trim()));
+        if !template.params.is_empty() {
+            output.push_str("  Parameters:\n");
+            for (param, description) in &template.params {
+                output.push_str(&format!("    - {}: {}\n", param, description));
+            }
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+fn parse_choice(response: &str) -> Result<TemplateChoice> {
+    let start = response.find('{').context("Template choice is not JSON")?;
+    let end = response.rfind('}').context("Template choice is not JSON")?;
+    serde_json::from_str(&response[start..=end]).context("Failed to parse template choice")
+}
+
+fn param_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+pub fn fill(
+    templates: &BTreeMap<String, Template>,
+    response: &str,
+    schema: &Schema,
+) -> Result<Option<(String, String)>> {
+    let choice = parse_choice(response)?;
+    let Some(name) = choice.template else {
+        return Ok(None);
+    };
+    let template = templates
+        .get(&name)
+        .with_context(|| format!("Model chose unknown template {}", name))?;
+
+    let mut values = HashMap::new();
+    for placeholder in find_placeholders(&template.sql, schema) {
+        let key = placeholder.name.trim_start_matches(':');
+        let value = choice
+            .params
+            .get(key)
+            .or_else(|| choice.params.get(&placeholder.name))
+            .with_context(|| {
+                format!(
+                    "Template {} needs parameter {} but none was provided",
+                    name, placeholder.name
+                )
+            })?;
+        values.insert(placeholder.name.clone(), param_value(value));
+    }
+
+    let sql = bind(&template.sql, &values)?;
+    Ok(Some((name, sql)))
+}
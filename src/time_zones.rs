@@ -0,0 +1,68 @@
+use crate::psql::PsqlConnection;
+use crate::schema::Schema;
+use anyhow::Result;
+use regex::Regex;
+
+const TIMESTAMPTZ: &str = "timestamp with time zone";
+
+fn mentions(sql: &str, name: &str) -> bool {
+    Regex::new(&format!(r"\b{}\b", regex::escape(&name.to_lowercase())))
+        .is_ok_and(|pattern| pattern.is_match(sql))
+}
+
+pub fn timestamptz_columns(sql: &str, schema: &Schema) -> Vec<String> {
+    let sql = sql.to_lowercase();
+    schema
+        .tables
+        .iter()
+        .filter(|t| mentions(&sql, t.name.rsplit('.').next().unwrap_or(&t.name)))
+        .flat_map(|t| {
+            t.columns
+                .iter()
+                .filter(|c| c.data_type == TIMESTAMPTZ && mentions(&sql, &c.name))
+                .map(|c| format!("{}.{}", t.name, c.name))
+        })
+        .collect()
+}
+
+pub fn names_zone(sql: &str) -> bool {
+    sql.to_lowercase().contains("at time zone")
+}
+
+pub fn annotate(sql: &str, zone: &str) -> String {
+    if sql.contains("Time zone:") {
+        return sql.to_string();
+    }
+    let sql = sql.trim_end();
+    match sql.strip_suffix(';') {
+        Some(body) => format!("{} /* Time zone: {} */;", body.trim_end(), zone),
+        None => format!("{} /* Time zone: {} */", sql, zone),
+    }
+}
+
+pub fn session(psql: &PsqlConnection) -> Result<String> {
+    Ok(psql.query("SHOW TimeZone")?.trim().to_string())
+}
+
+pub fn local() -> Option<String> {
+    if let Ok(zone) = std::env::var("TZ") {
+        let zone = zone.trim_start_matches(':');
+        if !zone.is_empty() {
+            return Some(zone.to_string());
+        }
+    }
+    let target = std::fs::read_link("/etc/localtime").ok()?;
+    let target = target.to_string_lossy();
+    target
+        .split_once("zoneinfo/")
+        .map(|(_, zone)| zone.to_string())
+}
+
+pub fn is_known(psql: &PsqlConnection, zone: &str) -> Result<bool> {
+    let rows = psql.query(&format!(
+        "SELECT 1 FROM pg_timezone_names WHERE lower(name) = lower('{}') UNION ALL SELECT 1 FROM pg_timezone_abbrevs WHERE lower(abbrev) = lower('{}')",
+        zone.replace('\'', "''"),
+        zone.replace('\'', "''")
+    ))?;
+    Ok(!rows.trim().is_empty())
+}
@@ -0,0 +1,62 @@
+use crate::claude::ConversationTurn;
+use crate::schema::Schema;
+use crate::schema_questions::mentioned_tables;
+use sqlparser::ast::visit_relations;
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+
+const RECENT_TURNS: usize = 2;
+
+const FOLLOW_UP_WORDS: &[&str] = &[
+    "it", "its", "that", "those", "these", "them", "they", "their", "same", "also", "instead",
+    "again", "previous", "above", "too", "only", "except", "more", "fewer", "now",
+];
+
+fn short_name(name: &str) -> String {
+    name.rsplit('.')
+        .next()
+        .unwrap_or(name)
+        .trim_matches('"')
+        .to_lowercase()
+}
+
+fn tables_in_sql(sql: &str) -> HashSet<String> {
+    let dialect = PostgreSqlDialect {};
+    let Ok(statements) = Parser::parse_sql(&dialect, sql) else {
+        return HashSet::new();
+    };
+
+    let mut tables = HashSet::new();
+    let _ = visit_relations(&statements, |relation| {
+        if let Some(ident) = relation.0.last() {
+            tables.insert(ident.value.to_lowercase());
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    tables
+}
+
+pub fn is_new_topic(question: &str, history: &[ConversationTurn], schema: &Schema) -> bool {
+    let refers_back = question
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| FOLLOW_UP_WORDS.contains(&word));
+    if refers_back {
+        return false;
+    }
+
+    let asked: HashSet<String> = mentioned_tables(question, schema)
+        .iter()
+        .map(|name| short_name(name))
+        .collect();
+    let recent: HashSet<String> = history
+        .iter()
+        .rev()
+        .take(RECENT_TURNS)
+        .flat_map(|turn| tables_in_sql(&turn.sql))
+        .collect();
+
+    !asked.is_empty() && !recent.is_empty() && asked.is_disjoint(&recent)
+}
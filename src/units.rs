@@ -0,0 +1,128 @@
+use crate::config::{DisplayRule, UnitFormat};
+use crate::result::{ColumnType, ResultSet};
+use crate::schema::name_pattern;
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::sync::OnceLock;
+
+const DEFAULT_RULES: &[(&str, UnitFormat)] = &[
+    ("*_bytes", UnitFormat::Bytes),
+    ("bytes", UnitFormat::Bytes),
+    ("*_cents", UnitFormat::Cents),
+    ("cents", UnitFormat::Cents),
+    ("*_epoch_ms", UnitFormat::EpochMillis),
+    ("*_epoch", UnitFormat::Epoch),
+    ("epoch", UnitFormat::Epoch),
+];
+
+static RULES: OnceLock<Vec<(Regex, UnitFormat)>> = OnceLock::new();
+
+pub fn configure(rules: Option<&[DisplayRule]>) -> Result<()> {
+    let compiled = match rules {
+        Some(rules) => rules
+            .iter()
+            .map(|rule| {
+                name_pattern(&rule.column)
+                    .map(|pattern| (pattern, rule.format))
+                    .with_context(|| format!("Invalid display rule for {}", rule.column))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => DEFAULT_RULES
+            .iter()
+            .map(|(column, format)| Ok((name_pattern(column)?, *format)))
+            .collect::<Result<Vec<_>>>()?,
+    };
+    let _ = RULES.set(compiled);
+    Ok(())
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    let units = ["bytes", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < units.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+fn format_cents(cents: f64) -> String {
+    let sign = if cents < 0.0 { "-" } else { "" };
+    format!("{}${:.2}", sign, cents.abs() / 100.0)
+}
+
+fn format_epoch(seconds: i64) -> String {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+fn format_value(value: &str, format: UnitFormat) -> Option<String> {
+    let number: f64 = value.parse().ok()?;
+    match format {
+        UnitFormat::Bytes if number >= 0.0 => Some(format_bytes(number as u64)),
+        UnitFormat::Bytes => None,
+        UnitFormat::Cents => Some(format_cents(number)),
+        UnitFormat::Epoch => Some(format_epoch(number as i64)),
+        UnitFormat::EpochMillis => Some(format_epoch((number / 1_000.0) as i64)),
+    }
+}
+
+pub fn apply(result: &ResultSet) -> ResultSet {
+    let rules = RULES.get().map(Vec::as_slice).unwrap_or_default();
+    let formats: Vec<Option<UnitFormat>> = result
+        .columns
+        .iter()
+        .zip(&result.types)
+        .map(|(column, &column_type)| {
+            (column_type == ColumnType::Number)
+                .then(|| {
+                    rules
+                        .iter()
+                        .find(|(pattern, _)| pattern.is_match(column))
+                        .map(|(_, format)| *format)
+                })
+                .flatten()
+        })
+        .collect();
+    if formats.iter().all(Option::is_none) {
+        return result.clone();
+    }
+
+    let mut formatted = result.clone();
+    for row in &mut formatted.rows {
+        for (value, format) in row.iter_mut().zip(&formats) {
+            if let (Some(text), Some(format)) = (value.as_deref(), format) {
+                if let Some(display) = format_value(text, *format) {
+                    *value = Some(display);
+                }
+            }
+        }
+    }
+    formatted
+}
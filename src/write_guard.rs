@@ -0,0 +1,322 @@
+use crate::config::WriteGuard;
+use crate::psql::is_read_only_function;
+use anyhow::Result;
+use sqlparser::ast::{
+    CopySource, Expr, FromTable, ObjectName, ObjectType, Query, SetExpr, Statement, TableFactor,
+    Visit, Visitor,
+};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use std::ops::ControlFlow;
+
+const WRITE_KEYWORDS: &[Keyword] = &[
+    Keyword::INSERT,
+    Keyword::UPDATE,
+    Keyword::DELETE,
+    Keyword::MERGE,
+    Keyword::TRUNCATE,
+    Keyword::ALTER,
+    Keyword::DROP,
+    Keyword::COPY,
+    Keyword::CALL,
+    Keyword::DO,
+];
+
+fn relation_name(factor: &TableFactor) -> Option<&ObjectName> {
+    match factor {
+        TableFactor::Table { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+fn query_targets(query: &Query, targets: &mut Vec<ObjectName>) {
+    if let Some(with) = &query.with {
+        for cte in &with.cte_tables {
+            query_targets(&cte.query, targets);
+        }
+    }
+    set_expr_targets(&query.body, targets);
+}
+
+fn set_expr_targets(expr: &SetExpr, targets: &mut Vec<ObjectName>) {
+    match expr {
+        SetExpr::Insert(statement) | SetExpr::Update(statement) => {
+            statement_targets(statement, targets)
+        }
+        SetExpr::Query(query) => query_targets(query, targets),
+        SetExpr::SetOperation { left, right, .. } => {
+            set_expr_targets(left, targets);
+            set_expr_targets(right, targets);
+        }
+        _ => {}
+    }
+}
+
+fn statement_targets(statement: &Statement, targets: &mut Vec<ObjectName>) {
+    match statement {
+        Statement::Insert(insert) => targets.push(insert.table_name.clone()),
+        Statement::Update { table, .. } => targets.extend(relation_name(&table.relation).cloned()),
+        Statement::Delete(delete) => {
+            targets.extend(delete.tables.iter().cloned());
+            let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) = &delete.from;
+            targets.extend(
+                from.iter()
+                    .filter_map(|t| relation_name(&t.relation).cloned()),
+            );
+        }
+        Statement::Merge { table, .. } => targets.extend(relation_name(table).cloned()),
+        Statement::Truncate { table_names, .. } => {
+            targets.extend(table_names.iter().map(|t| t.name.clone()))
+        }
+        Statement::AlterTable { name, .. } => targets.push(name.clone()),
+        Statement::Drop {
+            object_type: ObjectType::Table,
+            names,
+            ..
+        } => targets.extend(names.iter().cloned()),
+        Statement::CreateIndex(index) => targets.push(index.table_name.clone()),
+        Statement::Copy {
+            source: CopySource::Table { table_name, .. },
+            to: false,
+            ..
+        } => targets.push(table_name.clone()),
+        Statement::Query(query) => query_targets(query, targets),
+        _ => {}
+    }
+}
+
+pub fn target_tables(sql: &str) -> Option<Vec<String>> {
+    let dialect = PostgreSqlDialect {};
+    let statements = Parser::parse_sql(&dialect, sql).ok()?;
+
+    let mut targets = Vec::new();
+    for statement in &statements {
+        statement_targets(statement, &mut targets);
+    }
+
+    let mut names: Vec<String> = targets
+        .iter()
+        .map(|name| name.to_string().replace('"', ""))
+        .collect();
+    names.dedup();
+    Some(names)
+}
+
+const SEQUENCE_FUNCTIONS: &[&str] = &["nextval", "setval"];
+
+fn may_write_tables(function: &ObjectName) -> bool {
+    let sequence = match function.0.as_slice() {
+        [name] => Some(name),
+        [schema, name] if schema.value.eq_ignore_ascii_case("pg_catalog") => Some(name),
+        _ => None,
+    }
+    .is_some_and(|name| SEQUENCE_FUNCTIONS.contains(&name.value.to_lowercase().as_str()));
+    !sequence && !is_read_only_function(function)
+}
+
+struct OpaqueCalls;
+
+impl Visitor for OpaqueCalls {
+    type Break = ObjectName;
+
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<ObjectName> {
+        match statement {
+            Statement::Call(function) => ControlFlow::Break(function.name.clone()),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+
+    fn pre_visit_table_factor(&mut self, factor: &TableFactor) -> ControlFlow<ObjectName> {
+        match factor {
+            TableFactor::Table {
+                name,
+                args: Some(_),
+                ..
+            } if may_write_tables(name) => ControlFlow::Break(name.clone()),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<ObjectName> {
+        match expr {
+            Expr::Function(function) if may_write_tables(&function.name) => {
+                ControlFlow::Break(function.name.clone())
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+// Procedures, and functions other than read-only built-ins and the sequence functions,
+// can write to any table without naming it in the statement.
+fn opaque_call(sql: &str) -> Option<String> {
+    let dialect = PostgreSqlDialect {};
+    let statements = Parser::parse_sql(&dialect, sql).ok()?;
+    match statements.visit(&mut OpaqueCalls) {
+        ControlFlow::Break(name) => Some(name.to_string()),
+        ControlFlow::Continue(()) => None,
+    }
+}
+
+fn mentions_write(sql: &str) -> bool {
+    let dialect = PostgreSqlDialect {};
+    let Ok(tokens) = Tokenizer::new(&dialect, sql).tokenize() else {
+        return true;
+    };
+
+    tokens.iter().any(|token| {
+        matches!(token, Token::Word(word) if word.quote_style.is_none() && WRITE_KEYWORDS.contains(&word.keyword))
+    })
+}
+
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+    let mut parts = pattern.split('*');
+
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+fn matches_any(patterns: &[String], table: &str) -> bool {
+    let short = table.rsplit('.').next().unwrap_or(table);
+    patterns
+        .iter()
+        .any(|p| glob_match(p, table) || glob_match(p, short))
+}
+
+pub fn check(guard: &WriteGuard, sql: &str) -> Result<()> {
+    if guard.allow.is_empty() && guard.deny.is_empty() {
+        return Ok(());
+    }
+
+    let Some(targets) = target_tables(sql) else {
+        if mentions_write(sql) {
+            anyhow::bail!("Write guard: could not determine which tables this statement modifies");
+        }
+        return Ok(());
+    };
+    if let Some(function) = opaque_call(sql) {
+        anyhow::bail!(
+            "Write guard: could not determine which tables {}() modifies",
+            function
+        );
+    }
+
+    for table in &targets {
+        if matches_any(&guard.deny, table) {
+            anyhow::bail!("Write guard: {} is on the deny list", table);
+        }
+        if !guard.allow.is_empty() && !matches_any(&guard.allow, table) {
+            anyhow::bail!("Write guard: {} is not on the allow list", table);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(allow: &[&str], deny: &[&str]) -> WriteGuard {
+        WriteGuard {
+            allow: allow.iter().map(|p| p.to_string()).collect(),
+            deny: deny.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn finds_writes_wrapped_in_ctes() {
+        let sql = "WITH closed AS (UPDATE orders SET status = 'closed' RETURNING id) SELECT count(*) FROM closed";
+        assert_eq!(target_tables(sql), Some(vec!["orders".to_string()]));
+        assert!(check(&guard(&[], &["orders"]), sql).is_err());
+        assert!(check(&guard(&[], &["customers"]), sql).is_ok());
+
+        let sql = "WITH recent AS (SELECT * FROM orders) INSERT INTO archive SELECT * FROM recent";
+        assert_eq!(target_tables(sql), Some(vec!["archive".to_string()]));
+        assert!(check(&guard(&["archive"], &[]), sql).is_ok());
+    }
+
+    #[test]
+    fn refuses_cte_writes_it_cannot_parse() {
+        let sql = "WITH gone AS (DELETE FROM orders RETURNING id) SELECT count(*) FROM gone";
+        assert_eq!(target_tables(sql), None);
+        assert!(check(&guard(&[], &["customers"]), sql).is_err());
+    }
+
+    #[test]
+    fn delete_using_targets_only_the_deleted_table() {
+        let sql = "DELETE FROM orders USING customers WHERE orders.customer_id = customers.id AND customers.banned";
+        assert_eq!(target_tables(sql), Some(vec!["orders".to_string()]));
+        assert!(check(&guard(&[], &["customers"]), sql).is_ok());
+        assert!(check(&guard(&[], &["orders"]), sql).is_err());
+    }
+
+    #[test]
+    fn copy_from_writes_the_table_and_copy_to_does_not() {
+        assert_eq!(
+            target_tables("COPY orders FROM '/tmp/orders.csv' WITH (FORMAT csv)"),
+            Some(vec!["orders".to_string()])
+        );
+        assert_eq!(target_tables("COPY orders TO STDOUT"), Some(vec![]));
+        assert!(check(&guard(&[], &["orders"]), "COPY orders FROM STDIN").is_err());
+        assert!(check(&guard(&[], &["orders"]), "COPY orders TO STDOUT").is_ok());
+    }
+
+    #[test]
+    fn refuses_calls_it_cannot_see_into() {
+        let allow = guard(&["orders"], &[]);
+        let error = check(&allow, "CALL archive_orders()").unwrap_err();
+        assert!(error.to_string().contains("archive_orders()"));
+        assert!(check(&allow, "SELECT purge_orders()").is_err());
+        assert!(check(&allow, "SELECT lower(name) FROM customers").is_ok());
+        assert!(check(&allow, "SELECT nextval('orders_id_seq')").is_ok());
+    }
+
+    #[test]
+    fn refuses_unparseable_do_blocks() {
+        let sql = "DO $$ BEGIN DELETE FROM orders; END $$";
+        let error = check(&guard(&[], &["customers"]), sql).unwrap_err();
+        assert!(error.to_string().contains("could not determine"));
+    }
+
+    #[test]
+    fn matches_qualified_and_quoted_names() {
+        let deny = guard(&[], &["orders"]);
+        assert!(check(&deny, "UPDATE public.orders SET total = 0").is_err());
+        assert!(check(&deny, r#"UPDATE "public"."orders" SET total = 0"#).is_err());
+        assert!(check(&deny, "UPDATE public.orders_archive SET total = 0").is_ok());
+
+        let schema = guard(&["staging.*"], &[]);
+        assert!(check(&schema, "DELETE FROM staging.orders").is_ok());
+        assert!(check(&schema, r#"DELETE FROM "staging"."Orders""#).is_ok());
+        assert!(check(&schema, "DELETE FROM public.orders").is_err());
+        assert!(check(&schema, "DELETE FROM orders").is_err());
+
+        let prefix = guard(&[], &["tmp_*"]);
+        assert!(check(&prefix, "TRUNCATE analytics.tmp_load").is_err());
+        assert!(check(&prefix, "TRUNCATE analytics.load_tmp").is_ok());
+    }
+
+    #[test]
+    fn allows_everything_without_patterns() {
+        assert!(check(&guard(&[], &[]), "DROP TABLE orders").is_ok());
+    }
+}
@@ -0,0 +1,41 @@
+CREATE TABLE customers (
+    id serial PRIMARY KEY,
+    name text NOT NULL,
+    tier text NOT NULL DEFAULT 'silver',
+    balance numeric(10, 2) NOT NULL DEFAULT 0,
+    note text
+);
+
+CREATE TABLE orders (
+    id serial PRIMARY KEY,
+    customer_id integer NOT NULL REFERENCES customers (id),
+    total numeric(10, 2) NOT NULL,
+    placed_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX orders_customer_id_idx ON orders (customer_id);
+
+INSERT INTO customers (name, tier, balance, note) VALUES
+    ('Ada', 'silver', 120.50, NULL),
+    ('Grace', 'gold', 8.00, 'vip');
+
+INSERT INTO orders (customer_id, total) VALUES
+    (1, 30.00),
+    (1, 12.25),
+    (2, 99.99);
+
+CREATE MATERIALIZED VIEW customer_totals AS
+    SELECT customer_id, sum(total) AS total
+    FROM orders
+    GROUP BY customer_id;
+
+CREATE UNIQUE INDEX customer_totals_customer_id_idx ON customer_totals (customer_id);
+
+CREATE VIEW gold_customers AS
+    SELECT id, name, balance
+    FROM customers
+    WHERE tier = 'gold';
+
+CREATE TYPE shipping_speed AS ENUM ('standard', 'express', 'same day');
+
+CREATE TYPE address AS (street text, city text);